@@ -0,0 +1,168 @@
+//! Assert 设备：客户程序断言期望值与实际值相等，不相等时带着失败信息结束模拟，用于自检测试程序
+//!
+//! 寄存器映射（相对于设备基址）:
+//! - 0x00: 信息寄存器（4/8 字节，可读写），客户内存中以 NUL 结尾的失败信息的地址
+//! - 0x08: 期望值寄存器（4/8 字节，可读写）
+//! - 0x10: 实际值寄存器（4/8 字节，可读写）
+//! - 0x18: 检查寄存器（4 字节，只写），写入任意值即比较期望值与实际值，
+//!   不相等时请求以退出码 1 结束模拟，并由模拟器取走断言内容
+use mmio_trait::{AssertionRequest, DeviceError, MmioDevice, Register, RegisterFile};
+
+const MESSAGE_REG: u64 = 0x00;
+const EXPECTED_REG: u64 = 0x08;
+const ACTUAL_REG: u64 = 0x10;
+const CHECK_REG: u64 = 0x18;
+
+/// 断言失败时请求的退出码
+pub const ASSERT_EXIT_CODE: u8 = 1;
+
+/// 4 字节访问只修改低 32 位
+fn merge(old: u64, value: u64, size: usize) -> u64 {
+    match size {
+        8 => value,
+        _ => (old & !0xffff_ffff) | value,
+    }
+}
+
+/// Assert 寄存器表
+const ASSERT_REGISTERS: RegisterFile<Assert> = RegisterFile::new(
+    "Assert",
+    &[
+        Register {
+            name: "信息",
+            offset: MESSAGE_REG,
+            widths: &[4, 8],
+            read: Some(|assert, _| assert.message_ptr),
+            write: Some(|assert, value, size| {
+                assert.message_ptr = merge(assert.message_ptr, value, size);
+                Ok(())
+            }),
+        },
+        Register {
+            name: "期望值",
+            offset: EXPECTED_REG,
+            widths: &[4, 8],
+            read: Some(|assert, _| assert.expected),
+            write: Some(|assert, value, size| {
+                assert.expected = merge(assert.expected, value, size);
+                Ok(())
+            }),
+        },
+        Register {
+            name: "实际值",
+            offset: ACTUAL_REG,
+            widths: &[4, 8],
+            read: Some(|assert, _| assert.actual),
+            write: Some(|assert, value, size| {
+                assert.actual = merge(assert.actual, value, size);
+                Ok(())
+            }),
+        },
+        Register {
+            name: "检查",
+            offset: CHECK_REG,
+            widths: &[4],
+            read: None,
+            write: Some(|assert, _, _| {
+                assert.check();
+                Ok(())
+            }),
+        },
+    ],
+);
+
+/// Assert 设备：断言失败后由模拟器取走退出请求与断言内容
+pub struct Assert {
+    name: String,
+    message_ptr: u64,
+    expected: u64,
+    actual: u64,
+    /// 尚未被模拟器取走的断言失败
+    failure: Option<AssertionRequest>,
+}
+
+impl Assert {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            message_ptr: 0,
+            expected: 0,
+            actual: 0,
+            failure: None,
+        }
+    }
+
+    fn check(&mut self) {
+        if self.expected != self.actual {
+            self.failure = Some(AssertionRequest {
+                message_ptr: self.message_ptr,
+                expected: self.expected,
+                actual: self.actual,
+            });
+        }
+    }
+}
+
+impl Default for Assert {
+    fn default() -> Self {
+        Self::new("assert".to_string())
+    }
+}
+
+impl MmioDevice for Assert {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        ASSERT_REGISTERS.read(self, offset, size)
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        ASSERT_REGISTERS.write(self, offset, data)
+    }
+
+    fn take_exit_code(&mut self) -> Option<u8> {
+        self.failure.map(|_| ASSERT_EXIT_CODE)
+    }
+
+    fn take_assertion_failure(&mut self) -> Option<AssertionRequest> {
+        self.failure.take()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatch_requests_exit_with_assertion() {
+        let mut a = Assert::new("a".to_string());
+        a.write(MESSAGE_REG, &0x8000_1000u64.to_le_bytes()).unwrap();
+        a.write(EXPECTED_REG, &7u64.to_le_bytes()).unwrap();
+        a.write(ACTUAL_REG, &7u32.to_le_bytes()).unwrap();
+        a.write(CHECK_REG, &1u32.to_le_bytes()).unwrap();
+        assert_eq!(a.take_exit_code(), None);
+        assert_eq!(a.take_assertion_failure(), None);
+
+        a.write(ACTUAL_REG, &9u64.to_le_bytes()).unwrap();
+        a.write(CHECK_REG, &1u32.to_le_bytes()).unwrap();
+        assert_eq!(a.take_exit_code(), Some(ASSERT_EXIT_CODE));
+        assert_eq!(
+            a.take_assertion_failure(),
+            Some(AssertionRequest {
+                message_ptr: 0x8000_1000,
+                expected: 7,
+                actual: 9,
+            })
+        );
+        assert_eq!(a.take_exit_code(), None);
+        assert_eq!(a.take_assertion_failure(), None);
+    }
+
+    #[test]
+    fn check_register_is_write_only() {
+        let mut a = Assert::new("a".to_string());
+        assert!(a.read(CHECK_REG, 4).is_err());
+    }
+}
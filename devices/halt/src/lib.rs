@@ -0,0 +1,90 @@
+//! Halt 设备：客户程序写入即结束模拟
+//!
+//! 寄存器映射（相对于设备基址）:
+//! - 0x00: 退出寄存器（写入任意值即请求退出，写入值的低 8 位作为退出码；读返回 0）
+use mmio_trait::{DeviceError, MmioDevice};
+
+const EXIT_REG: u64 = 0x00;
+
+/// 简化的 Halt 设备实现：写入退出寄存器后由模拟器取走退出请求
+pub struct Halt {
+    name: String,
+    /// 尚未被模拟器取走的退出码
+    exit_code: Option<u8>,
+}
+
+impl Halt {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            exit_code: None,
+        }
+    }
+}
+
+impl Default for Halt {
+    fn default() -> Self {
+        Self::new("halt".to_string())
+    }
+}
+
+impl MmioDevice for Halt {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        match (offset, size) {
+            (EXIT_REG, 1 | 2 | 4) => Ok(vec![0u8; size]),
+            (EXIT_REG, _) => Err(DeviceError::Unsupported(
+                "退出寄存器只支持 1/2/4 字节访问".to_string(),
+            )),
+            _ => Err(DeviceError::Access(format!(
+                "Halt 不支持的寄存器偏移: {:#x}",
+                offset
+            ))),
+        }
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        match (offset, data.len()) {
+            (EXIT_REG, 1 | 2 | 4) => {
+                // 小端序，低字节即为退出码
+                self.exit_code = Some(data[0]);
+                Ok(())
+            }
+            (EXIT_REG, _) => Err(DeviceError::Unsupported(
+                "退出寄存器只支持 1/2/4 字节访问".to_string(),
+            )),
+            _ => Err(DeviceError::Access(format!(
+                "Halt 不支持的寄存器偏移: {:#x}",
+                offset
+            ))),
+        }
+    }
+
+    fn take_exit_code(&mut self) -> Option<u8> {
+        self.exit_code.take()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_requests_exit_once() {
+        let mut h = Halt::new("h".to_string());
+        assert_eq!(h.take_exit_code(), None);
+        h.write(EXIT_REG, &7u32.to_le_bytes()).unwrap();
+        assert_eq!(h.take_exit_code(), Some(7));
+        assert_eq!(h.take_exit_code(), None);
+    }
+
+    #[test]
+    fn invalid_offset_is_rejected() {
+        let mut h = Halt::new("h".to_string());
+        assert!(h.write(0x4, &[0]).is_err());
+        assert!(h.read(0x4, 4).is_err());
+    }
+}
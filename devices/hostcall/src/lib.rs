@@ -0,0 +1,143 @@
+//! Hostcall 设备：客户程序向宿主报告进度或断言，用于自检测试程序
+//!
+//! 寄存器映射（相对于设备基址）:
+//! - 0x00: 参数寄存器（4/8 字节，可读写）
+//! - 0x08: 值寄存器（4/8 字节，可读写）
+//! - 0x10: 命令寄存器（4 字节，只写），写入即以当前的参数与值发出一次请求，由模拟器取走并处理
+//!
+//! 命令:
+//! - CMD_MARKER (1): 打印标记，值为标记内容
+//! - CMD_CHECKPOINT (2): 记录检查点，值为检查点编号
+//! - CMD_ASSERT_REG (3): 断言寄存器 x\[参数\] 等于值
+use mmio_trait::{DeviceError, HostRequest, MmioDevice, Register, RegisterFile};
+
+const ARG_REG: u64 = 0x00;
+const VALUE_REG: u64 = 0x08;
+const CMD_REG: u64 = 0x10;
+
+pub const CMD_MARKER: u32 = 1;
+pub const CMD_CHECKPOINT: u32 = 2;
+pub const CMD_ASSERT_REG: u32 = 3;
+
+/// 4 字节访问只修改低 32 位
+fn merge(old: u64, value: u64, size: usize) -> u64 {
+    match size {
+        8 => value,
+        _ => (old & !0xffff_ffff) | value,
+    }
+}
+
+/// Hostcall 寄存器表
+const HOSTCALL_REGISTERS: RegisterFile<Hostcall> = RegisterFile::new(
+    "Hostcall",
+    &[
+        Register {
+            name: "参数",
+            offset: ARG_REG,
+            widths: &[4, 8],
+            read: Some(|hostcall, _| hostcall.arg),
+            write: Some(|hostcall, value, size| {
+                hostcall.arg = merge(hostcall.arg, value, size);
+                Ok(())
+            }),
+        },
+        Register {
+            name: "值",
+            offset: VALUE_REG,
+            widths: &[4, 8],
+            read: Some(|hostcall, _| hostcall.value),
+            write: Some(|hostcall, value, size| {
+                hostcall.value = merge(hostcall.value, value, size);
+                Ok(())
+            }),
+        },
+        Register {
+            name: "命令",
+            offset: CMD_REG,
+            widths: &[4],
+            read: None,
+            write: Some(|hostcall, command, _| {
+                hostcall.request = Some(HostRequest {
+                    command: command as u32,
+                    arg: hostcall.arg,
+                    value: hostcall.value,
+                });
+                Ok(())
+            }),
+        },
+    ],
+);
+
+/// Hostcall 设备：写入命令寄存器后由模拟器取走请求
+pub struct Hostcall {
+    name: String,
+    arg: u64,
+    value: u64,
+    /// 尚未被模拟器取走的请求
+    request: Option<HostRequest>,
+}
+
+impl Hostcall {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            arg: 0,
+            value: 0,
+            request: None,
+        }
+    }
+}
+
+impl Default for Hostcall {
+    fn default() -> Self {
+        Self::new("hostcall".to_string())
+    }
+}
+
+impl MmioDevice for Hostcall {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        HOSTCALL_REGISTERS.read(self, offset, size)
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        HOSTCALL_REGISTERS.write(self, offset, data)
+    }
+
+    fn take_host_request(&mut self) -> Option<HostRequest> {
+        self.request.take()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_write_issues_request_once() {
+        let mut h = Hostcall::new("h".to_string());
+        h.write(ARG_REG, &5u64.to_le_bytes()).unwrap();
+        h.write(VALUE_REG, &0x1234u32.to_le_bytes()).unwrap();
+        assert_eq!(h.take_host_request(), None);
+
+        h.write(CMD_REG, &CMD_ASSERT_REG.to_le_bytes()).unwrap();
+        assert_eq!(
+            h.take_host_request(),
+            Some(HostRequest {
+                command: CMD_ASSERT_REG,
+                arg: 5,
+                value: 0x1234,
+            })
+        );
+        assert_eq!(h.take_host_request(), None);
+    }
+
+    #[test]
+    fn command_register_is_write_only() {
+        let mut h = Hostcall::new("h".to_string());
+        assert!(h.read(CMD_REG, 4).is_err());
+    }
+}
@@ -2,6 +2,10 @@
 
 use thiserror::Error;
 
+pub mod register_file;
+
+pub use register_file::{Register, RegisterFile};
+
 /// 设备错误类型
 #[derive(Debug, Error)]
 pub enum DeviceError {
@@ -13,6 +17,23 @@ pub enum DeviceError {
     Internal(String),
 }
 
+/// 客户程序通过设备发给宿主的请求（见 hostcall 设备）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostRequest {
+    pub command: u32,
+    pub arg: u64,
+    pub value: u64,
+}
+
+/// 客户程序通过设备报告的断言失败（见 assert 设备）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssertionRequest {
+    /// 客户内存中以 NUL 结尾的失败信息的地址，0 表示没有信息
+    pub message_ptr: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
 /// MMIO 设备 trait
 /// 所有 MMIO 设备都必须实现此 trait
 pub trait MmioDevice: Send + Sync {
@@ -39,6 +60,35 @@ pub trait MmioDevice: Send + Sync {
     /// - cycles: 经过的周期数
     fn tick(&mut self, _cycles: u64) {}
 
+    /// 冻结/恢复设备时间（可选）
+    ///
+    /// 冻结期间基于时间的设备应保持当前值不变，恢复后从冻结时的值继续计时
+    fn set_time_frozen(&mut self, _frozen: bool) {}
+
+    /// 取走设备发出的退出请求（可选）
+    ///
+    /// # 返回
+    /// 如果客户程序通过该设备请求结束模拟，返回退出码；取走后清除
+    fn take_exit_code(&mut self) -> Option<u8> {
+        None
+    }
+
+    /// 取走设备发给宿主的请求（可选）
+    ///
+    /// # 返回
+    /// 如果客户程序通过该设备发出了请求，返回该请求；取走后清除
+    fn take_host_request(&mut self) -> Option<HostRequest> {
+        None
+    }
+
+    /// 取走设备报告的断言失败（可选）
+    ///
+    /// # 返回
+    /// 如果客户程序通过该设备的断言未通过，返回断言内容；取走后清除
+    fn take_assertion_failure(&mut self) -> Option<AssertionRequest> {
+        None
+    }
+
     /// 检查是否有中断挂起（可选）
     /// 
     /// # 返回
@@ -47,6 +97,12 @@ pub trait MmioDevice: Send + Sync {
         None
     }
 
+    /// 接收其它设备的中断线电平（可选，供 PLIC 等中断控制器实现）
+    ///
+    /// # 参数
+    /// - sources: 第 n 位对应中断源 n，置位表示该中断源的设备有中断挂起
+    fn set_irq_sources(&mut self, _sources: u64) {}
+
     /// 获取设备名称（用于调试）
     fn name(&self) -> &str {
         "unknown"
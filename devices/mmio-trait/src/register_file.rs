@@ -0,0 +1,141 @@
+//! 寄存器表辅助工具
+//!
+//! 设备以静态表的形式声明寄存器（偏移、允许的访问宽度、读写函数），
+//! 由 [`RegisterFile`] 统一完成偏移查找、宽度检查、读写权限检查与小端字节转换
+
+use crate::DeviceError;
+
+/// 寄存器读函数：参数为访问宽度（字节），返回值按宽度截断
+pub type ReadFn<D> = fn(&mut D, usize) -> u64;
+
+/// 寄存器写函数：参数为写入值（已按小端拼接）与访问宽度（字节）
+pub type WriteFn<D> = fn(&mut D, u64, usize) -> Result<(), DeviceError>;
+
+/// 单个寄存器的描述
+pub struct Register<D> {
+    /// 寄存器名称（用于错误信息）
+    pub name: &'static str,
+    /// 相对于设备基址的偏移
+    pub offset: u64,
+    /// 允许的访问宽度（字节）
+    pub widths: &'static [usize],
+    /// 读函数，None 表示只写
+    pub read: Option<ReadFn<D>>,
+    /// 写函数，None 表示只读
+    pub write: Option<WriteFn<D>>,
+}
+
+/// 设备的寄存器表
+pub struct RegisterFile<D: 'static> {
+    device: &'static str,
+    registers: &'static [Register<D>],
+}
+
+impl<D: 'static> RegisterFile<D> {
+    /// device 为设备类型名称，用于错误信息
+    pub const fn new(device: &'static str, registers: &'static [Register<D>]) -> Self {
+        Self { device, registers }
+    }
+
+    /// 查找 offset 处的寄存器并检查访问宽度
+    fn lookup(&self, offset: u64, size: usize) -> Result<&Register<D>, DeviceError> {
+        let register = self
+            .registers
+            .iter()
+            .find(|register| register.offset == offset)
+            .ok_or_else(|| {
+                DeviceError::Access(format!("{} 不支持的寄存器偏移: {:#x}", self.device, offset))
+            })?;
+        if !register.widths.contains(&size) {
+            return Err(DeviceError::Unsupported(format!(
+                "{} {} 寄存器不支持 {} 字节访问（支持 {:?}）",
+                self.device, register.name, size, register.widths
+            )));
+        }
+        Ok(register)
+    }
+
+    /// 读取寄存器，返回小端字节序的数据
+    pub fn read(&self, device: &mut D, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        let register = self.lookup(offset, size)?;
+        let read = register.read.ok_or_else(|| {
+            DeviceError::Unsupported(format!("{} {} 寄存器是只写的", self.device, register.name))
+        })?;
+        Ok(read(device, size).to_le_bytes()[..size].to_vec())
+    }
+
+    /// 写入寄存器，data 按小端序解释
+    pub fn write(&self, device: &mut D, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        let register = self.lookup(offset, data.len())?;
+        let write = register.write.ok_or_else(|| {
+            DeviceError::Unsupported(format!("{} {} 寄存器是只读的", self.device, register.name))
+        })?;
+        let mut bytes = [0u8; 8];
+        bytes[..data.len()].copy_from_slice(data);
+        write(device, u64::from_le_bytes(bytes), data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Dummy {
+        value: u64,
+    }
+
+    const REGISTERS: RegisterFile<Dummy> = RegisterFile::new(
+        "Dummy",
+        &[
+            Register {
+                name: "VALUE",
+                offset: 0x00,
+                widths: &[4, 8],
+                read: Some(|dummy, _| dummy.value),
+                write: Some(|dummy, value, _| {
+                    dummy.value = value;
+                    Ok(())
+                }),
+            },
+            Register {
+                name: "ID",
+                offset: 0x08,
+                widths: &[4],
+                read: Some(|_, _| 0x1234_5678),
+                write: None,
+            },
+        ],
+    );
+
+    #[test]
+    fn read_write_round_trip() {
+        let mut dummy = Dummy::default();
+        REGISTERS.write(&mut dummy, 0x00, &0xdead_beef_u32.to_le_bytes()).unwrap();
+        assert_eq!(dummy.value, 0xdead_beef);
+        assert_eq!(REGISTERS.read(&mut dummy, 0x00, 8).unwrap(), 0xdead_beef_u64.to_le_bytes());
+        assert_eq!(REGISTERS.read(&mut dummy, 0x08, 4).unwrap(), 0x1234_5678_u32.to_le_bytes());
+    }
+
+    #[test]
+    fn out_of_range_offset() {
+        let mut dummy = Dummy::default();
+        let err = REGISTERS.read(&mut dummy, 0x10, 4).unwrap_err();
+        assert!(matches!(err, DeviceError::Access(msg) if msg.contains("0x10")));
+    }
+
+    #[test]
+    fn wrong_width_access() {
+        let mut dummy = Dummy::default();
+        let err = REGISTERS.write(&mut dummy, 0x00, &[1, 2]).unwrap_err();
+        assert!(matches!(err, DeviceError::Unsupported(msg) if msg.contains("VALUE")));
+        assert_eq!(dummy.value, 0);
+    }
+
+    #[test]
+    fn read_only_register_write() {
+        let mut dummy = Dummy::default();
+        let err = REGISTERS.write(&mut dummy, 0x08, &[0; 4]).unwrap_err();
+        assert!(matches!(err, DeviceError::Unsupported(msg) if msg.contains("只读")));
+    }
+}
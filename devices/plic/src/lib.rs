@@ -0,0 +1,221 @@
+//! PLIC 设备：平台级中断控制器，只有一个上下文（hart 0 的 M 模式）
+//!
+//! 寄存器映射（相对于设备基址，均为 4 字节访问）:
+//! - 0x000000 + 4*n: 中断源 n 的优先级（0 表示屏蔽），n 为 1..64
+//! - 0x001000 / 0x001004: 挂起位（只读），第 n 位对应中断源 n
+//! - 0x002000 / 0x002004: 上下文 0 的使能位
+//! - 0x200000: 上下文 0 的优先级阈值，只有优先级高于阈值的中断源才会被送出
+//! - 0x200004: 上下文 0 的 claim/complete：读取返回优先级最高的挂起中断源并清除其挂起位，
+//!   处理完成后写回该中断源号
+//!
+//! 中断源按电平触发：中断线保持有效时，complete 之后会再次挂起
+use mmio_trait::{DeviceError, MmioDevice};
+
+/// 支持的中断源数量（中断源 0 保留）
+pub const NUM_SOURCES: u64 = 64;
+
+/// 存在可送出的中断时挂起的中断号（mip 中的 M 模式外部中断位）
+pub const PLIC_IRQ: u32 = 11;
+
+/// 优先级寄存器从偏移 0 开始，每个中断源 4 字节
+const PRIORITY_END: u64 = 4 * NUM_SOURCES;
+const PENDING_BASE: u64 = 0x00_1000;
+const ENABLE_BASE: u64 = 0x00_2000;
+const THRESHOLD_REG: u64 = 0x20_0000;
+const CLAIM_REG: u64 = 0x20_0004;
+
+/// 优先级寄存器的有效位
+const PRIORITY_MASK: u32 = 0x7;
+
+pub struct Plic {
+    name: String,
+    priority: [u32; NUM_SOURCES as usize],
+    /// 挂起位
+    pending: u64,
+    /// 上下文 0 的使能位
+    enable: u64,
+    threshold: u32,
+    /// 已被 claim 但尚未 complete 的中断源
+    claimed: u64,
+    /// 最近一次收到的中断线电平
+    lines: u64,
+}
+
+impl Plic {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            priority: [0; NUM_SOURCES as usize],
+            pending: 0,
+            enable: 0,
+            threshold: 0,
+            claimed: 0,
+            lines: 0,
+        }
+    }
+
+    /// 优先级最高的可送出中断源，同优先级时中断源号小者优先
+    fn best_source(&self) -> Option<u32> {
+        let candidates = self.pending & self.enable;
+        (1..NUM_SOURCES as u32)
+            .filter(|&source| candidates & (1 << source) != 0)
+            .filter(|&source| self.priority[source as usize] > self.threshold)
+            .max_by_key(|&source| (self.priority[source as usize], std::cmp::Reverse(source)))
+    }
+
+    fn claim(&mut self) -> u32 {
+        let Some(source) = self.best_source() else {
+            return 0;
+        };
+        self.pending &= !(1 << source);
+        self.claimed |= 1 << source;
+        source
+    }
+
+    fn complete(&mut self, source: u32) {
+        if (source as u64) < NUM_SOURCES {
+            self.claimed &= !(1 << source);
+            self.update_pending();
+        }
+    }
+
+    /// 网关：有效且未被 claim 的中断线置起挂起位
+    fn update_pending(&mut self) {
+        self.pending |= self.lines & !self.claimed & !1;
+    }
+
+    /// 读取 64 位位图中 offset 所在的 32 位
+    fn bitmap_word(bits: u64, offset: u64) -> u32 {
+        (bits >> (32 * (offset / 4))) as u32
+    }
+
+    fn set_bitmap_word(bits: &mut u64, offset: u64, value: u32) {
+        let shift = 32 * (offset / 4);
+        *bits = (*bits & !(0xffff_ffff << shift)) | ((value as u64) << shift);
+    }
+
+    fn read_reg(&mut self, offset: u64) -> Result<u32, DeviceError> {
+        Ok(match offset {
+            o if o < PRIORITY_END => self.priority[(o / 4) as usize],
+            o if (PENDING_BASE..PENDING_BASE + 8).contains(&o) => {
+                Self::bitmap_word(self.pending, o - PENDING_BASE)
+            }
+            o if (ENABLE_BASE..ENABLE_BASE + 8).contains(&o) => {
+                Self::bitmap_word(self.enable, o - ENABLE_BASE)
+            }
+            THRESHOLD_REG => self.threshold,
+            CLAIM_REG => self.claim(),
+            _ => return Err(DeviceError::Access(format!("PLIC 不支持的寄存器偏移: {:#x}", offset))),
+        })
+    }
+
+    fn write_reg(&mut self, offset: u64, value: u32) -> Result<(), DeviceError> {
+        match offset {
+            o if o < PRIORITY_END => {
+                // 中断源 0 保留，优先级恒为 0
+                if o >= 4 {
+                    self.priority[(o / 4) as usize] = value & PRIORITY_MASK;
+                }
+            }
+            o if (PENDING_BASE..PENDING_BASE + 8).contains(&o) => {
+                return Err(DeviceError::Unsupported("PLIC 挂起寄存器是只读的".to_string()));
+            }
+            o if (ENABLE_BASE..ENABLE_BASE + 8).contains(&o) => {
+                Self::set_bitmap_word(&mut self.enable, o - ENABLE_BASE, value);
+                self.enable &= !1;
+            }
+            THRESHOLD_REG => self.threshold = value & PRIORITY_MASK,
+            CLAIM_REG => self.complete(value),
+            _ => return Err(DeviceError::Access(format!("PLIC 不支持的寄存器偏移: {:#x}", offset))),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Plic {
+    fn default() -> Self {
+        Self::new("plic".to_string())
+    }
+}
+
+impl MmioDevice for Plic {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        if size != 4 || !offset.is_multiple_of(4) {
+            return Err(DeviceError::Unsupported(format!(
+                "PLIC 只支持对齐的 4 字节访问: 偏移 {:#x}, 大小 {}",
+                offset, size
+            )));
+        }
+        Ok(self.read_reg(offset)?.to_le_bytes().to_vec())
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        let Ok(bytes) = <[u8; 4]>::try_from(data) else {
+            return Err(DeviceError::Unsupported(format!(
+                "PLIC 只支持对齐的 4 字节访问: 偏移 {:#x}, 大小 {}",
+                offset,
+                data.len()
+            )));
+        };
+        if !offset.is_multiple_of(4) {
+            return Err(DeviceError::Unsupported(format!("PLIC 访问未对齐: 偏移 {:#x}", offset)));
+        }
+        self.write_reg(offset, u32::from_le_bytes(bytes))
+    }
+
+    fn set_irq_sources(&mut self, sources: u64) {
+        self.lines = sources;
+        self.update_pending();
+    }
+
+    fn irq_pending(&self) -> Option<u32> {
+        self.best_source().map(|_| PLIC_IRQ)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(plic: &mut Plic, offset: u64) -> u32 {
+        u32::from_le_bytes(plic.read(offset, 4).unwrap().try_into().unwrap())
+    }
+
+    fn write_u32(plic: &mut Plic, offset: u64, value: u32) {
+        plic.write(offset, &value.to_le_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_claim_complete() {
+        let mut plic = Plic::default();
+        write_u32(&mut plic, 4 * 3, 1);
+        write_u32(&mut plic, 4 * 5, 2);
+        write_u32(&mut plic, ENABLE_BASE, (1 << 3) | (1 << 5));
+
+        plic.set_irq_sources((1 << 3) | (1 << 5));
+        assert_eq!(read_u32(&mut plic, PENDING_BASE), (1 << 3) | (1 << 5));
+        assert_eq!(plic.irq_pending(), Some(PLIC_IRQ));
+
+        // 优先级高的先被 claim，claim 后挂起位清除，线仍有效也不会重新挂起
+        assert_eq!(read_u32(&mut plic, CLAIM_REG), 5);
+        assert_eq!(read_u32(&mut plic, CLAIM_REG), 3);
+        assert_eq!(read_u32(&mut plic, CLAIM_REG), 0);
+        assert_eq!(plic.irq_pending(), None);
+
+        // 源 3 的线撤销后 complete，不再挂起；源 5 的线仍有效，complete 后再次挂起
+        plic.set_irq_sources(1 << 5);
+        write_u32(&mut plic, CLAIM_REG, 3);
+        write_u32(&mut plic, CLAIM_REG, 5);
+        assert_eq!(read_u32(&mut plic, PENDING_BASE), 1 << 5);
+
+        // 阈值不低于优先级时不送出
+        write_u32(&mut plic, THRESHOLD_REG, 2);
+        assert_eq!(plic.irq_pending(), None);
+        write_u32(&mut plic, THRESHOLD_REG, 1);
+        assert_eq!(plic.irq_pending(), Some(PLIC_IRQ));
+    }
+}
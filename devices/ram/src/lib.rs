@@ -0,0 +1,166 @@
+//! RAM/ROM 设备：以 `Vec<u8>` 为后备存储的一段内存，可放在主内存以外的任意地址，
+//! 用于放置只读的启动 ROM 或额外的 RAM 区域
+//!
+//! 整个设备区域按字节寻址，支持 1/2/4/8 字节访问；ROM 拒绝一切写入
+use mmio_trait::{DeviceError, MmioDevice};
+
+/// RAM 与 ROM 共用的后备存储
+struct Storage {
+    name: String,
+    data: Vec<u8>,
+}
+
+impl Storage {
+    fn new(name: String, size: usize) -> Self {
+        Self {
+            name,
+            data: vec![0; size],
+        }
+    }
+
+    /// 将 image 复制到存储开头
+    fn load(&mut self, image: &[u8]) -> Result<(), DeviceError> {
+        if image.len() > self.data.len() {
+            return Err(DeviceError::Internal(format!(
+                "{} 的初始内容 ({} 字节) 超出设备大小 ({} 字节)",
+                self.name,
+                image.len(),
+                self.data.len()
+            )));
+        }
+        self.data[..image.len()].copy_from_slice(image);
+        Ok(())
+    }
+
+    /// 检查访问宽度与范围，返回对应的字节区间
+    fn range(&self, offset: u64, size: usize) -> Result<std::ops::Range<usize>, DeviceError> {
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return Err(DeviceError::Unsupported(format!(
+                "{} 不支持 {} 字节访问",
+                self.name, size
+            )));
+        }
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| Some(start..start.checked_add(size)?))
+            .filter(|range| range.end <= self.data.len())
+            .ok_or_else(|| {
+                DeviceError::Access(format!(
+                    "{} 访问越界: 偏移 {:#x}, 大小 {}",
+                    self.name, offset, size
+                ))
+            })
+    }
+
+    fn read(&self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        Ok(self.data[self.range(offset, size)?].to_vec())
+    }
+}
+
+/// 可读写的 RAM 设备
+pub struct Ram {
+    storage: Storage,
+}
+
+impl Ram {
+    /// 创建 size 字节、内容全为 0 的 RAM
+    pub fn new(name: String, size: usize) -> Self {
+        Self {
+            storage: Storage::new(name, size),
+        }
+    }
+
+    /// 以 image 作为开头的初始内容，image 超出设备大小时返回错误
+    pub fn with_image(mut self, image: &[u8]) -> Result<Self, DeviceError> {
+        self.storage.load(image)?;
+        Ok(self)
+    }
+}
+
+impl MmioDevice for Ram {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        self.storage.read(offset, size)
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        let range = self.storage.range(offset, data.len())?;
+        self.storage.data[range].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.storage.name
+    }
+}
+
+/// 只读的 ROM 设备，内容在创建时确定
+pub struct Rom {
+    storage: Storage,
+}
+
+impl Rom {
+    /// 创建 size 字节的 ROM，image 为开头的内容，其余字节为 0；image 超出设备大小时返回错误
+    pub fn new(name: String, size: usize, image: &[u8]) -> Result<Self, DeviceError> {
+        let mut storage = Storage::new(name, size);
+        storage.load(image)?;
+        Ok(Self { storage })
+    }
+}
+
+impl MmioDevice for Rom {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        self.storage.read(offset, size)
+    }
+
+    fn write(&mut self, offset: u64, _data: &[u8]) -> Result<(), DeviceError> {
+        Err(DeviceError::Unsupported(format!(
+            "{} 只读, 不能写入偏移 {:#x}",
+            self.storage.name, offset
+        )))
+    }
+
+    fn name(&self) -> &str {
+        &self.storage.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ram_read_back_and_bounds() {
+        let mut ram = Ram::new("ram".to_string(), 16)
+            .with_image(&[1, 2, 3])
+            .unwrap();
+        assert_eq!(ram.read(0, 4).unwrap(), vec![1, 2, 3, 0]);
+        ram.write(8, &0x1122_3344_5566_7788u64.to_le_bytes())
+            .unwrap();
+        assert_eq!(
+            ram.read(8, 8).unwrap(),
+            0x1122_3344_5566_7788u64.to_le_bytes()
+        );
+        assert!(ram.read(12, 8).is_err());
+        assert!(ram.write(16, &[0]).is_err());
+        assert!(ram.read(0, 3).is_err());
+        assert!(Ram::new("ram".to_string(), 2).with_image(&[0; 3]).is_err());
+    }
+
+    #[test]
+    fn rom_reads_preloaded_image() {
+        let mut rom = Rom::new("rom".to_string(), 8, &[0x13, 0x00, 0x00, 0x00, 0xaa]).unwrap();
+        assert_eq!(rom.read(0, 4).unwrap(), 0x13u32.to_le_bytes());
+        assert_eq!(rom.read(4, 2).unwrap(), vec![0xaa, 0]);
+        assert!(rom.read(8, 1).is_err());
+    }
+
+    #[test]
+    fn rom_rejects_writes() {
+        let mut rom = Rom::new("rom".to_string(), 8, &[7]).unwrap();
+        assert!(matches!(
+            rom.write(0, &[1]),
+            Err(DeviceError::Unsupported(_))
+        ));
+        assert_eq!(rom.read(0, 1).unwrap(), vec![7]);
+    }
+}
@@ -5,13 +5,19 @@
 //! - 0x04: 保留（与 0x00 同步）
 //! - 0x08: 保留（与 0x00 同步）
 //! - 0x0C: 控制寄存器（保留）
-use mmio_trait::{DeviceError, MmioDevice};
+//! - 0x10: 比较寄存器（8 字节，或按 4 字节分别访问 0x10/0x14），计数值不小于它时挂起定时器中断
+use mmio_trait::{DeviceError, MmioDevice, Register, RegisterFile};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 const CNT0_REG: u64 = 0x00;
 const CNT1_REG: u64 = 0x04;
 const CNT2_REG: u64 = 0x08;
 const CTRL_REG: u64 = 0x0c;
+const CMP_LO_REG: u64 = 0x10;
+const CMP_HI_REG: u64 = 0x14;
+
+/// 比较值到期时挂起的中断号（mip 中的 M 模式定时器中断位）
+pub const TIMER_IRQ: u32 = 7;
 
 fn current_time_us() -> u64 {
     let d = SystemTime::now().duration_since(UNIX_EPOCH);
@@ -27,11 +33,28 @@ fn current_time_us() -> u64 {
 /// 简化的 Timer 设备实现：读出系统时间（us）
 pub struct Timer {
     name: String,
+    /// 累计的冻结时长，读出值 = 系统时间 - offset
+    offset: u64,
+    /// 冻结时保持的计数值
+    held: Option<u64>,
+    /// 比较值，默认为最大值（不会到期）
+    cmp: u64,
 }
 
 impl Timer {
     pub fn new(name: String) -> Self {
-        Self { name }
+        Self {
+            name,
+            offset: 0,
+            held: None,
+            cmp: u64::MAX,
+        }
+    }
+
+    /// 当前计数值（微秒）
+    fn now(&self) -> u64 {
+        self.held
+            .unwrap_or_else(|| current_time_us().wrapping_sub(self.offset))
     }
 }
 
@@ -41,59 +64,84 @@ impl Default for Timer {
     }
 }
 
+/// 计数器寄存器：支持 1/2/4/8 字节读取，返回当前时间（微秒）的低位
+const fn counter(name: &'static str, offset: u64) -> Register<Timer> {
+    Register {
+        name,
+        offset,
+        widths: &[1, 2, 4, 8],
+        read: Some(|timer, _| timer.now()),
+        write: None,
+    }
+}
+
+/// Timer 寄存器表
+const TIMER_REGISTERS: RegisterFile<Timer> = RegisterFile::new(
+    "Timer",
+    &[
+        counter("计数器", CNT0_REG),
+        counter("计数器", CNT1_REG),
+        counter("计数器", CNT2_REG),
+        Register {
+            // 控制寄存器暂不实现，返回 0
+            name: "控制",
+            offset: CTRL_REG,
+            widths: &[1, 4],
+            read: Some(|_, _| 0),
+            write: None,
+        },
+        Register {
+            // 8 字节访问整个比较值，4 字节访问低 32 位
+            name: "比较",
+            offset: CMP_LO_REG,
+            widths: &[4, 8],
+            read: Some(|timer, _| timer.cmp),
+            write: Some(|timer, value, size| {
+                timer.cmp = match size {
+                    8 => value,
+                    _ => (timer.cmp & !0xffff_ffff) | value,
+                };
+                Ok(())
+            }),
+        },
+        Register {
+            name: "比较（高位）",
+            offset: CMP_HI_REG,
+            widths: &[4],
+            read: Some(|timer, _| timer.cmp >> 32),
+            write: Some(|timer, value, _| {
+                timer.cmp = (timer.cmp & 0xffff_ffff) | (value << 32);
+                Ok(())
+            }),
+        },
+    ],
+);
+
 impl MmioDevice for Timer {
     fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
-        match offset {
-            CNT0_REG | CNT1_REG | CNT2_REG => {
-                // 支持 1/2/4/8 字节读取，返回当前系统时间（微秒）的小端字节序
-                match size {
-                    1 | 2 | 4 | 8 => {
-                        let t = current_time_us();
-                        let bytes = t.to_le_bytes(); // 8 字节
-                        let mut out = Vec::new();
-                        // 根据 size 返回低位的 size 字节
-                        out.extend_from_slice(&bytes[0..size]);
-                        Ok(out)
-                    }
-                    _ => Err(DeviceError::Unsupported(
-                        "计数器只支持 1/2/4/8 字节读取".to_string(),
-                    )),
-                }
-            }
-            CTRL_REG => {
-                // 控制寄存器暂不实现，返回 0
-                if size == 1 {
-                    Ok(vec![0u8])
-                } else if size == 4 {
-                    Ok(vec![0u8, 0u8, 0u8, 0u8])
-                } else {
-                    Err(DeviceError::Unsupported(
-                        "控制寄存器只支持 1 或 4 字节访问".to_string(),
-                    ))
-                }
-            }
-            _ => Err(DeviceError::Access(format!(
-                "Timer 不支持的寄存器偏移: {:#x}",
-                offset
-            ))),
-        }
+        TIMER_REGISTERS.read(self, offset, size)
     }
 
-    fn write(&mut self, offset: u64, _data: &[u8]) -> Result<(), DeviceError> {
-        match offset {
-            CNT0_REG | CNT1_REG | CNT2_REG | CTRL_REG => {
-                // 写操作对该设备无效或被忽略（只读设备）
-                Err(DeviceError::Unsupported(
-                    "Timer 为只读设备（读系统时间）".to_string(),
-                ))
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        TIMER_REGISTERS.write(self, offset, data)
+    }
+
+    fn set_time_frozen(&mut self, frozen: bool) {
+        match (frozen, self.held) {
+            (true, None) => self.held = Some(self.now()),
+            (false, Some(held)) => {
+                // 跳过冻结期间流逝的时间
+                self.offset = current_time_us().wrapping_sub(held);
+                self.held = None;
             }
-            _ => Err(DeviceError::Access(format!(
-                "Timer 不支持的寄存器偏移: {:#x}",
-                offset
-            ))),
+            _ => (),
         }
     }
 
+    fn irq_pending(&self) -> Option<u32> {
+        (self.now() >= self.cmp).then_some(TIMER_IRQ)
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -118,4 +166,43 @@ mod tests {
         let ts = u64::from_le_bytes([r[0], r[1], r[2], r[3], r[4], r[5], r[6], r[7]]);
         assert!(ts > 0);
     }
+
+    fn read_u64(t: &mut Timer) -> u64 {
+        let r = t.read(CNT0_REG, 8).unwrap();
+        u64::from_le_bytes(r.try_into().unwrap())
+    }
+
+    #[test]
+    fn frozen_time_holds_value() {
+        let mut t = Timer::new("t".to_string());
+        t.set_time_frozen(true);
+        let held = read_u64(&mut t);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(read_u64(&mut t), held);
+
+        // 恢复后从冻结值继续，而不是跳到当前系统时间
+        t.set_time_frozen(false);
+        let resumed = read_u64(&mut t);
+        assert!(resumed >= held);
+        assert!(resumed - held < 1_000);
+    }
+
+    #[test]
+    fn compare_raises_irq() {
+        let mut t = Timer::new("t".to_string());
+        assert_eq!(t.irq_pending(), None);
+
+        t.set_time_frozen(true);
+        let now = read_u64(&mut t);
+        t.write(CMP_LO_REG, &(now + 1).to_le_bytes()).unwrap();
+        assert_eq!(t.irq_pending(), None);
+        t.write(CMP_LO_REG, &(now as u32).to_le_bytes()).unwrap();
+        t.write(CMP_HI_REG, &((now >> 32) as u32).to_le_bytes()).unwrap();
+        assert_eq!(t.irq_pending(), Some(TIMER_IRQ));
+        assert_eq!(t.read(CMP_LO_REG, 8).unwrap(), now.to_le_bytes());
+
+        // 写入更大的比较值后中断撤销
+        t.write(CMP_LO_REG, &u64::MAX.to_le_bytes()).unwrap();
+        assert_eq!(t.irq_pending(), None);
+    }
 }
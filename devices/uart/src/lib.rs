@@ -1,7 +1,13 @@
 //! UART 设备实现
+//!
+//! 接收缓冲为空时，读取状态或数据寄存器会以非阻塞方式从输入源取一个字节。
+//! 默认没有输入源，可用 [`Uart::with_input`] 指定；[`Stdin`] 以非阻塞方式读取标准输入。
+//! 发送的字节默认输出到 stderr，可用 [`Uart::with_output`] 改为其他目标
 
-use mmio_trait::{DeviceError, MmioDevice};
-use std::io::{self, Write};
+use mmio_trait::{DeviceError, MmioDevice, Register, RegisterFile};
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Mutex, OnceLock};
 
 /// UART 寄存器偏移
 const UART_DATA_REG: u64 = 0x00;  // 数据寄存器
@@ -12,120 +18,199 @@ const UART_CTRL_REG: u64 = 0x08;   // 控制寄存器
 const UART_STATUS_TX_READY: u32 = 0x01;  // 发送就绪
 const UART_STATUS_RX_VALID: u32 = 0x02;  // 接收有效
 
+/// UART 控制位
+const UART_CTRL_RX_IRQ_ENABLE: u32 = 0x01; // 接收中断使能
+
+/// 默认的接收中断号（mip 中的 M 模式外部中断位）；连接到 PLIC 时只关心是否挂起
+pub const UART_RX_IRQ: u32 = 11;
+
+/// 非阻塞的标准输入：没有可读数据时返回 `WouldBlock`
+///
+/// 首次读取时启动一个进程内唯一的后台线程阻塞读取 stdin，字节经通道转交给 UART
+pub struct Stdin;
+
+impl Read for Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        static RX: OnceLock<Mutex<Receiver<u8>>> = OnceLock::new();
+        let rx = RX.get_or_init(|| {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                for byte in io::stdin().lock().bytes() {
+                    let Ok(byte) = byte else { break };
+                    if tx.send(byte).is_err() {
+                        break;
+                    }
+                }
+            });
+            Mutex::new(rx)
+        });
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match rx.lock().unwrap().try_recv() {
+            Ok(byte) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            Err(TryRecvError::Empty) => Err(ErrorKind::WouldBlock.into()),
+            Err(TryRecvError::Disconnected) => Ok(0),
+        }
+    }
+}
+
 /// UART 设备
 pub struct Uart {
     name: String,
     tx_ready: bool,
     rx_buffer: Option<u8>,
+    /// 控制寄存器
+    ctrl: u32,
+    /// 接收中断号
+    irq: u32,
+    /// 接收数据的来源，读到文件尾或出错后置为 None
+    input: Option<Mutex<Box<dyn Read + Send>>>,
+    /// 发送数据的去向
+    output: Mutex<Box<dyn Write + Send>>,
 }
 
 impl Uart {
-    /// 创建新的 UART 设备
+    /// 创建新的 UART 设备，没有输入源，接收缓冲始终为空
     pub fn new(name: String) -> Self {
         Self {
             name,
             tx_ready: true,
             rx_buffer: None,
+            ctrl: 0,
+            irq: UART_RX_IRQ,
+            input: None,
+            output: Mutex::new(Box::new(io::stderr())),
         }
     }
+
+    /// 创建从 reader 接收数据的 UART 设备
+    ///
+    /// reader 没有可读数据时应返回 `WouldBlock`，而不是阻塞
+    pub fn with_input(name: String, reader: Box<dyn Read + Send>) -> Self {
+        Self {
+            input: Some(Mutex::new(reader)),
+            ..Self::new(name)
+        }
+    }
+
+    /// 设置发送数据的去向，每写入一个字节都会刷新
+    pub fn with_output(mut self, writer: Box<dyn Write + Send>) -> Self {
+        self.output = Mutex::new(writer);
+        self
+    }
+
+    /// 设置接收中断号
+    pub fn with_irq(mut self, irq: u32) -> Self {
+        self.irq = irq;
+        self
+    }
+
+    /// 接收缓冲为空时尝试从输入源取一个字节
+    fn poll_input(&mut self) {
+        if self.rx_buffer.is_some() {
+            return;
+        }
+        let Some(input) = &mut self.input else {
+            return;
+        };
+        let mut byte = [0u8];
+        match input.get_mut().unwrap().read(&mut byte) {
+            Ok(1) => self.rx_buffer = Some(byte[0]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => (),
+            // 文件尾或读取错误：不再接收
+            _ => self.input = None,
+        }
+    }
+}
+
+/// UART 寄存器表
+const UART_REGISTERS: RegisterFile<Uart> = RegisterFile::new(
+    "UART",
+    &[
+        Register {
+            name: "数据",
+            offset: UART_DATA_REG,
+            widths: &[1],
+            read: Some(Uart::read_data),
+            write: Some(Uart::write_data),
+        },
+        Register {
+            name: "状态",
+            offset: UART_STATUS_REG,
+            widths: &[4],
+            read: Some(Uart::read_status),
+            write: None,
+        },
+        Register {
+            // 目前只有接收中断使能位，其余位保留
+            name: "控制",
+            offset: UART_CTRL_REG,
+            widths: &[4],
+            read: Some(|uart, _| uart.ctrl as u64),
+            write: Some(|uart, value, _| {
+                uart.ctrl = value as u32 & UART_CTRL_RX_IRQ_ENABLE;
+                Ok(())
+            }),
+        },
+    ],
+);
+
+impl Uart {
+    /// 读取数据寄存器，读取后清空接收缓冲
+    fn read_data(&mut self, _size: usize) -> u64 {
+        self.poll_input();
+        self.rx_buffer.take().unwrap_or(0) as u64
+    }
+
+    /// 写入数据寄存器，将字节送往输出目标
+    fn write_data(&mut self, value: u64, _size: usize) -> Result<(), DeviceError> {
+        let output = self.output.get_mut().unwrap();
+        output
+            .write_all(&[value as u8])
+            .map_err(|e| DeviceError::Internal(format!("UART 输出错误: {}", e)))?;
+        output
+            .flush()
+            .map_err(|e| DeviceError::Internal(format!("UART 刷新错误: {}", e)))
+    }
+
+    fn read_status(&mut self, _size: usize) -> u64 {
+        self.poll_input();
+        let mut status = 0u32;
+        if self.tx_ready {
+            status |= UART_STATUS_TX_READY;
+        }
+        if self.rx_buffer.is_some() {
+            status |= UART_STATUS_RX_VALID;
+        }
+        status as u64
+    }
 }
 
 impl MmioDevice for Uart {
     fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
-        match offset {
-            UART_DATA_REG => {
-                // 读取数据寄存器
-                if size != 1 {
-                    return Err(DeviceError::Unsupported(
-                        "UART 数据寄存器只支持字节访问".to_string(),
-                    ));
-                }
-                let data = self.rx_buffer.unwrap_or(0);
-                self.rx_buffer = None; // 读取后清空
-                Ok(vec![data])
-            }
-            UART_STATUS_REG => {
-                // 读取状态寄存器
-                if size != 4 {
-                    return Err(DeviceError::Unsupported(
-                        "UART 状态寄存器只支持32位访问".to_string(),
-                    ));
-                }
-                let mut status = 0u32;
-                if self.tx_ready {
-                    status |= UART_STATUS_TX_READY;
-                }
-                if self.rx_buffer.is_some() {
-                    status |= UART_STATUS_RX_VALID;
-                }
-                Ok(status.to_le_bytes().to_vec())
-            }
-            UART_CTRL_REG => {
-                // 读取控制寄存器（暂时返回0）
-                if size != 4 {
-                    return Err(DeviceError::Unsupported(
-                        "UART 控制寄存器只支持32位访问".to_string(),
-                    ));
-                }
-                Ok(vec![0; 4])
-            }
-            _ => Err(DeviceError::Access(format!(
-                "UART 不支持的寄存器偏移: {:#x}",
-                offset
-            ))),
-        }
+        UART_REGISTERS.read(self, offset, size)
     }
 
     fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
-        match offset {
-            UART_DATA_REG => {
-                // 写入数据寄存器
-                if data.len() != 1 {
-                    return Err(DeviceError::Unsupported(
-                        "UART 数据寄存器只支持字节访问".to_string(),
-                    ));
-                }
-                
-                // 将字节输出到 stderr
-                let byte = data[0];
-                if let Err(e) = io::stderr().write_all(&[byte]) {
-                    return Err(DeviceError::Internal(format!(
-                        "UART 输出错误: {}",
-                        e
-                    )));
-                }
-                if let Err(e) = io::stderr().flush() {
-                    return Err(DeviceError::Internal(format!(
-                        "UART 刷新错误: {}",
-                        e
-                    )));
-                }
-                
-                Ok(())
-            }
-            UART_STATUS_REG => {
-                // 状态寄存器是只读的
-                Err(DeviceError::Unsupported(
-                    "UART 状态寄存器是只读的".to_string(),
-                ))
-            }
-            UART_CTRL_REG => {
-                // 写入控制寄存器（暂时忽略）
-                if data.len() != 4 {
-                    return Err(DeviceError::Unsupported(
-                        "UART 控制寄存器只支持32位访问".to_string(),
-                    ));
-                }
-                // 可以在这里实现控制逻辑，比如波特率设置等
-                Ok(())
-            }
-            _ => Err(DeviceError::Access(format!(
-                "UART 不支持的寄存器偏移: {:#x}",
-                offset
-            ))),
+        UART_REGISTERS.write(self, offset, data)
+    }
+
+    /// 配置了输入源时周期性地接收输入，使中断驱动的客户程序无需轮询状态寄存器
+    fn tick(&mut self, _cycles: u64) {
+        if self.input.is_some() {
+            self.poll_input();
         }
     }
 
+    /// 接收缓冲非空且使能了接收中断时挂起，读取数据寄存器后撤销
+    fn irq_pending(&self) -> Option<u32> {
+        (self.rx_buffer.is_some() && self.ctrl & UART_CTRL_RX_IRQ_ENABLE != 0).then_some(self.irq)
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -150,12 +235,82 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::byte_char_slices)]
     fn test_uart_data_write() {
         let mut uart = Uart::new("test".to_string());
         let result = uart.write(UART_DATA_REG, &[b'A']);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_uart_custom_output() {
+        /// 可在写入后查看内容的共享缓冲
+        #[derive(Clone, Default)]
+        struct SharedBuf(std::sync::Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = SharedBuf::default();
+        let mut uart = Uart::new("test".to_string()).with_output(Box::new(buf.clone()));
+        uart.write(UART_DATA_REG, b"O").unwrap();
+        uart.write(UART_DATA_REG, b"K").unwrap();
+        assert_eq!(*buf.0.lock().unwrap(), b"OK");
+    }
+
+    #[test]
+    fn test_uart_receives_injected_input() {
+        let mut uart = Uart::with_input("test".to_string(), Box::new(io::Cursor::new(b"Hi".to_vec())));
+        let status = uart.read(UART_STATUS_REG, 4).unwrap();
+        assert_eq!(status[0] as u32 & UART_STATUS_RX_VALID, UART_STATUS_RX_VALID);
+        assert_eq!(uart.read(UART_DATA_REG, 1).unwrap(), vec![b'H']);
+        assert_eq!(uart.read(UART_DATA_REG, 1).unwrap(), vec![b'i']);
+
+        // 输入耗尽后接收无效
+        let status = uart.read(UART_STATUS_REG, 4).unwrap();
+        assert_eq!(status[0] as u32 & UART_STATUS_RX_VALID, 0);
+        assert_eq!(uart.read(UART_DATA_REG, 1).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_uart_rx_irq() {
+        let input = Box::new(io::Cursor::new(b"ab".to_vec()));
+        let mut uart = Uart::with_input("test".to_string(), input).with_irq(5);
+
+        // 未使能时不挂起
+        uart.tick(1);
+        assert_eq!(uart.irq_pending(), None);
+
+        uart.write(UART_CTRL_REG, &UART_CTRL_RX_IRQ_ENABLE.to_le_bytes()).unwrap();
+        assert_eq!(uart.read(UART_CTRL_REG, 4).unwrap(), UART_CTRL_RX_IRQ_ENABLE.to_le_bytes());
+        assert_eq!(uart.irq_pending(), Some(5));
+
+        // 读取数据后撤销，下一个字节到达时再次挂起
+        assert_eq!(uart.read(UART_DATA_REG, 1).unwrap(), vec![b'a']);
+        assert_eq!(uart.irq_pending(), None);
+        uart.tick(1);
+        assert_eq!(uart.irq_pending(), Some(5));
+
+        uart.write(UART_CTRL_REG, &0u32.to_le_bytes()).unwrap();
+        assert_eq!(uart.irq_pending(), None);
+    }
+
+    #[test]
+    fn test_uart_without_input() {
+        let mut uart = Uart::new("test".to_string());
+        uart.tick(1);
+        let status = uart.read(UART_STATUS_REG, 4).unwrap();
+        assert_eq!(status[0] as u32 & UART_STATUS_RX_VALID, 0);
+        assert_eq!(uart.read(UART_DATA_REG, 1).unwrap(), vec![0]);
+    }
+
     #[test]
     fn test_invalid_register() {
         let mut uart = Uart::new("test".to_string());
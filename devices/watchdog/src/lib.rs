@@ -0,0 +1,160 @@
+//! Watchdog 设备：超时未被喂狗则请求结束模拟
+//!
+//! 寄存器映射（相对于设备基址）:
+//! - 0x00: 超时寄存器（周期数，写入后重新开始倒计时，0 表示禁用；读返回当前设置）
+//! - 0x04: 喂狗寄存器（写入任意值重新开始倒计时；读返回 0）
+//! - 0x08: 计数寄存器（只读，距离超时剩余的周期数）
+use mmio_trait::{DeviceError, MmioDevice};
+
+const TIMEOUT_REG: u64 = 0x00;
+const KICK_REG: u64 = 0x04;
+const COUNT_REG: u64 = 0x08;
+
+/// 超时时请求的退出码
+pub const TIMEOUT_EXIT_CODE: u8 = 0xff;
+
+/// 简化的 Watchdog 设备实现：由 tick 驱动倒计时，超时后发出退出请求
+pub struct Watchdog {
+    name: String,
+    /// 超时周期数，0 表示禁用
+    timeout: u32,
+    /// 剩余周期数
+    remaining: u64,
+    /// 是否已超时（超时后不再重复触发，直到重新设置或喂狗）
+    expired: bool,
+    /// 尚未被模拟器取走的退出码
+    exit_code: Option<u8>,
+}
+
+impl Watchdog {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            timeout: 0,
+            remaining: 0,
+            expired: false,
+            exit_code: None,
+        }
+    }
+
+    /// 重新开始倒计时
+    fn reload(&mut self) {
+        self.remaining = self.timeout as u64;
+        self.expired = false;
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new("watchdog".to_string())
+    }
+}
+
+impl MmioDevice for Watchdog {
+    fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, DeviceError> {
+        if size != 4 {
+            return Err(DeviceError::Unsupported(
+                "Watchdog 寄存器只支持 4 字节访问".to_string(),
+            ));
+        }
+        let value = match offset {
+            TIMEOUT_REG => self.timeout,
+            KICK_REG => 0,
+            COUNT_REG => self.remaining.min(u32::MAX as u64) as u32,
+            _ => {
+                return Err(DeviceError::Access(format!(
+                    "Watchdog 不支持的寄存器偏移: {:#x}",
+                    offset
+                )))
+            }
+        };
+        Ok(value.to_le_bytes().to_vec())
+    }
+
+    fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), DeviceError> {
+        let Ok(bytes) = <[u8; 4]>::try_from(data) else {
+            return Err(DeviceError::Unsupported(
+                "Watchdog 寄存器只支持 4 字节访问".to_string(),
+            ));
+        };
+        match offset {
+            TIMEOUT_REG => {
+                self.timeout = u32::from_le_bytes(bytes);
+                self.reload();
+                Ok(())
+            }
+            KICK_REG => {
+                self.reload();
+                Ok(())
+            }
+            COUNT_REG => Err(DeviceError::Unsupported(
+                "Watchdog 计数寄存器为只读".to_string(),
+            )),
+            _ => Err(DeviceError::Access(format!(
+                "Watchdog 不支持的寄存器偏移: {:#x}",
+                offset
+            ))),
+        }
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        if self.timeout == 0 || self.expired {
+            return;
+        }
+        self.remaining = self.remaining.saturating_sub(cycles);
+        if self.remaining == 0 {
+            self.expired = true;
+            self.exit_code = Some(TIMEOUT_EXIT_CODE);
+        }
+    }
+
+    fn take_exit_code(&mut self) -> Option<u8> {
+        self.exit_code.take()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn armed(timeout: u32) -> Watchdog {
+        let mut w = Watchdog::new("wdt".to_string());
+        w.write(TIMEOUT_REG, &timeout.to_le_bytes()).unwrap();
+        w
+    }
+
+    #[test]
+    fn timeout_without_kick_fires() {
+        let mut w = armed(100);
+        w.tick(60);
+        assert_eq!(w.take_exit_code(), None);
+        w.tick(60);
+        assert_eq!(w.take_exit_code(), Some(TIMEOUT_EXIT_CODE));
+        // 只触发一次
+        w.tick(200);
+        assert_eq!(w.take_exit_code(), None);
+    }
+
+    #[test]
+    fn timely_kicks_prevent_timeout() {
+        let mut w = armed(100);
+        for _ in 0..10 {
+            w.tick(60);
+            w.write(KICK_REG, &0u32.to_le_bytes()).unwrap();
+        }
+        assert_eq!(w.take_exit_code(), None);
+        let count = w.read(COUNT_REG, 4).unwrap();
+        assert_eq!(u32::from_le_bytes(count.try_into().unwrap()), 100);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut w = Watchdog::new("wdt".to_string());
+        w.tick(u64::MAX);
+        assert_eq!(w.take_exit_code(), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! 译码表哈希函数对比：恒等哈希（nohash）与 FxHash，分别以 opcode 和完整指令字为键
+//!
+//! 运行：cargo bench --bench decode_hasher
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use nohash_hasher::BuildNoHashHasher;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+/// RV64IMAC 常见的主 opcode
+const OPCODES: [u32; 13] = [
+    0x03, 0x0f, 0x13, 0x17, 0x1b, 0x23, 0x2f, 0x33, 0x37, 0x3b, 0x63, 0x67, 0x6f,
+];
+
+/// 模拟程序中出现的指令字：opcode 取自常见集合，其余字段伪随机
+fn instruction_words(n: usize) -> Vec<u32> {
+    let mut seed = 0x1234_5678u32;
+    (0..n)
+        .map(|i| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed & !0x7f) | OPCODES[i % OPCODES.len()]
+        })
+        .collect()
+}
+
+fn build_map<S: BuildHasher + Default>(keys: &[u32]) -> HashMap<u32, u32, S> {
+    let mut map = HashMap::with_hasher(S::default());
+    for (i, &key) in keys.iter().enumerate() {
+        map.insert(key, i as u32);
+    }
+    map
+}
+
+fn bench_keys(c: &mut Criterion, group_name: &str, keys: &[u32]) {
+    let mut group = c.benchmark_group(group_name);
+    let nohash = build_map::<BuildNoHashHasher<u32>>(keys);
+    group.bench_with_input(BenchmarkId::new("nohash", keys.len()), keys, |b, keys| {
+        b.iter(|| keys.iter().filter_map(|k| nohash.get(black_box(k))).sum::<u32>())
+    });
+    let fx: FxHashMap<u32, u32> = build_map(keys);
+    group.bench_with_input(BenchmarkId::new("fx", keys.len()), keys, |b, keys| {
+        b.iter(|| keys.iter().filter_map(|k| fx.get(black_box(k))).sum::<u32>())
+    });
+    group.finish();
+}
+
+fn decode_hasher(c: &mut Criterion) {
+    bench_keys(c, "opcode_key", &OPCODES);
+    bench_keys(c, "instruction_word_key", &instruction_words(4096));
+}
+
+criterion_group!(benches, decode_hasher);
+criterion_main!(benches);
@@ -1,33 +1,163 @@
 use anyhow::{self, Context};
 use serde::Deserialize;
+use std::fmt;
 use std::path::Path;
 
-/// 主配置中保留的内存项（仅含 boot_pc）
+/// 主配置中保留的内存项（boot_pc 与可选的栈保护）
 #[derive(Deserialize, Debug)]
 pub struct MemoryConfig {
     pub boot_pc: u64,
+    /// 栈保护区配置，未配置时不启用
+    #[serde(default)]
+    pub stack_guard: Option<StackGuardConfig>,
+    /// 主内存初始填充方式，默认全零
+    #[serde(default)]
+    pub fill: MemoryFill,
+}
+
+/// 主内存初始填充方式
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryFill {
+    #[default]
+    Zero,
+    /// 每个字节填充 [`MemoryFill::POISON_BYTE`]，使未初始化读取容易辨认
+    Poison,
+    /// 第 i 个字节填充 i 的低8位
+    Incrementing,
+}
+
+impl MemoryFill {
+    pub const POISON_BYTE: u8 = 0xAA;
+}
+
+/// 栈保护区配置：栈向下增长，[stack_base - guard_size, stack_base) 被设为只读
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct StackGuardConfig {
+    /// 栈的最低合法地址
+    pub stack_base: u64,
+    /// 保护区大小（字节）
+    #[serde(default = "default_stack_guard_size")]
+    pub guard_size: u64,
+}
+
+fn default_stack_guard_size() -> u64 {
+    0x1000
+}
+
+/// 通用寄存器宽度
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(try_from = "u32")]
+pub enum Xlen {
+    X32,
+    #[default]
+    X64,
+}
+
+impl Xlen {
+    pub fn bits(self) -> u32 {
+        match self {
+            Xlen::X32 => 32,
+            Xlen::X64 => 64,
+        }
+    }
+}
+
+impl TryFrom<u32> for Xlen {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            32 => Ok(Xlen::X32),
+            64 => Ok(Xlen::X64),
+            _ => Err(format!("不支持的 xlen: {}，仅支持 32 或 64", value)),
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct InstSetConfig {
+    /// 寄存器宽度（32 或 64），默认64
+    #[serde(default)]
+    pub xlen: Xlen,
     #[serde(default)]
     pub m_ext: bool,
     #[serde(default)]
     pub a_ext: bool,
     #[serde(default)]
     pub c_ext: bool,
+    /// F 扩展（单精度浮点）
+    #[serde(default)]
+    pub f_ext: bool,
+    /// D 扩展（双精度浮点），需同时启用 f_ext
+    #[serde(default)]
+    pub d_ext: bool,
+    /// Zicsr 扩展（csrrw/csrrs/csrrc 及立即数形式）
+    #[serde(default)]
+    pub zicsr: bool,
+    /// 特权指令 mret/wfi
+    #[serde(default)]
+    pub privileged: bool,
+    /// S 模式特权指令 sret，需同时启用 privileged
+    #[serde(default)]
+    pub s_mode: bool,
+    /// 允许作为空操作执行的未实现指令（名称见 instructions/stubs.rs）
+    #[serde(default)]
+    pub stub_instructions: Vec<String>,
+    /// 译码表使用的哈希函数，默认 FxHash
+    #[serde(default)]
+    pub decode_hasher: DecodeHasher,
+}
+
+/// 译码表使用的哈希函数
+///
+/// 基准测试见 benches/decode_hasher.rs，无论以 opcode 还是完整指令字为键 FxHash 都更快：
+/// hashbrown 用哈希值的高 7 位做组内标签，恒等哈希下小整数键的标签全为 0，每次查找都要逐个比较组内的键；
+/// 以指令字为键时，低位又集中在少数几种 opcode 上，桶冲突严重
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeHasher {
+    /// 恒等哈希（nohash-hasher）
+    NoHash,
+    /// FxHash（rustc-hash）
+    #[default]
+    Fx,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct DebugConfig {
     pub event_list_size: usize,
+    /// 陷入日志条目数，0 表示不记录
+    #[serde(default = "default_trap_log_size")]
+    pub trap_log_size: usize,
+    /// MMIO 访问日志条目数，0 表示不记录
+    #[serde(default)]
+    pub mmio_log_size: usize,
+    /// 标记区域访问日志条目数，0 表示不记录
+    #[serde(default)]
+    pub tag_log_size: usize,
     #[cfg(feature = "tracer")]
     pub instruction_tracer_list_size: usize,
 }
 
+fn default_trap_log_size() -> usize {
+    64
+}
+
 #[derive(Deserialize, Debug)]
 pub struct OthersConfig {
+    /// 译码缓存条目数（向上取整为2的幂），0 表示禁用
     pub decoder_cache_size: usize,
+    /// 取指缓存条目数（向上取整为2的幂），0 表示禁用
+    #[serde(default)]
+    pub fetch_cache_size: usize,
+    /// 每执行多少条指令批量驱动一次设备的 tick（一条指令计为一个周期），1 表示逐条驱动
+    #[serde(default = "default_device_tick_interval")]
+    pub device_tick_interval: u64,
+}
+
+fn default_device_tick_interval() -> u64 {
+    1024
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -39,6 +169,34 @@ pub struct DeviceConfig {
     pub size: u64,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// 设备寄存器字节序，默认小端
+    #[serde(default)]
+    pub endianness: Endianness,
+    /// 连接到 PLIC 的中断源号（1..64）；未设置时设备挂起的中断号直接作为 mip 位
+    #[serde(default)]
+    pub irq: Option<u32>,
+    /// 每次访问设备寄存器额外计入的周期数
+    #[serde(default)]
+    pub latency: u64,
+    /// 设备专有参数，例如 UART 的 `output`；各设备类型支持的参数见 `DeviceFactory`
+    #[serde(default)]
+    pub params: toml::Table,
+}
+
+impl DeviceConfig {
+    /// 是否允许从设备区域取指（RAM/ROM 设备）
+    pub fn is_executable(&self) -> bool {
+        matches!(self.device_type.as_str(), "ram" | "rom")
+    }
+}
+
+/// 设备寄存器字节序
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
 }
 
 fn default_true() -> bool {
@@ -63,13 +221,124 @@ impl EmuConfig {
             .with_context(|| format!("无法解析主配置文件: {:?}", &path.as_ref().as_os_str()))?;
         anyhow::Ok(config)
     }
+
+    /// 检查主配置与设备配置之间的约束，一次性报告所有问题
+    ///
+    /// 解析阶段只能发现单个字段的格式错误，这里检查跨字段的约束：
+    /// boot_pc 落在主内存或 RAM/ROM 设备中、设备区间互不重叠、扩展组合合法、各缓冲区大小非零
+    pub fn validate(&self, device_file: &DeviceFile) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
+
+        let mem_base = device_file.memory.memory_base as u128;
+        let mem_end = mem_base + device_file.memory.memory_size as u128 * 1024 * 1024;
+        if device_file.memory.memory_size == 0 {
+            errors.push("memory.memory_size: 主内存大小不能为0".to_string());
+        }
+
+        let devices: Vec<_> = device_file.devices.iter().filter(|d| d.enabled).collect();
+        let boot_pc = self.memory.boot_pc as u128;
+        if let Some(device) = devices
+            .iter()
+            .find(|d| boot_pc >= d.base as u128 && boot_pc < d.base as u128 + d.size as u128)
+        {
+            if !device.is_executable() {
+                errors.push(format!(
+                    "memory.boot_pc: {:#x} 位于设备 '{}' 的区域 [{:#x}, {:#x}) 内",
+                    self.memory.boot_pc,
+                    device.name,
+                    device.base,
+                    device.base as u128 + device.size as u128
+                ));
+            }
+        } else if boot_pc < mem_base || boot_pc >= mem_end {
+            errors.push(format!(
+                "memory.boot_pc: {:#x} 不在主内存 [{:#x}, {:#x}) 内",
+                self.memory.boot_pc, mem_base, mem_end
+            ));
+        }
+
+        for (i, device) in devices.iter().enumerate() {
+            let base = device.base as u128;
+            let end = base + device.size as u128;
+            if device.size == 0 {
+                errors.push(format!("devices.{}.size: 设备区域大小不能为0", device.name));
+                continue;
+            }
+            if base < mem_end && end > mem_base {
+                errors.push(format!(
+                    "devices.{}: 区域 [{:#x}, {:#x}) 与主内存 [{:#x}, {:#x}) 重叠",
+                    device.name, base, end, mem_base, mem_end
+                ));
+            }
+            if let Some(irq) = device.irq {
+                if !(1..64).contains(&irq) {
+                    errors.push(format!("devices.{}.irq: 中断源号 {} 超出范围 1..64", device.name, irq));
+                } else if !devices.iter().any(|d| d.device_type == "plic") {
+                    errors.push(format!("devices.{}.irq: 未配置 PLIC 设备", device.name));
+                }
+            }
+            for other in &devices[i + 1..] {
+                let other_base = other.base as u128;
+                let other_end = other_base + other.size as u128;
+                if base < other_end && end > other_base {
+                    errors.push(format!(
+                        "devices.{}: 区域 [{:#x}, {:#x}) 与设备 '{}' [{:#x}, {:#x}) 重叠",
+                        device.name, base, end, other.name, other_base, other_end
+                    ));
+                }
+            }
+        }
+
+        let inst_set = &self.inst_set;
+        if inst_set.d_ext && !inst_set.f_ext {
+            errors.push("inst_set.d_ext: D 扩展需要同时启用 f_ext".to_string());
+        }
+        if inst_set.s_mode && !inst_set.privileged {
+            errors.push("inst_set.s_mode: S 模式指令需要同时启用 privileged".to_string());
+        }
+        if inst_set.c_ext && inst_set.xlen == Xlen::X32 {
+            errors.push("inst_set.c_ext: xlen = 32 时暂不支持 C 扩展".to_string());
+        }
+
+        if self.debug.event_list_size == 0 {
+            errors.push("debug.event_list_size: 事件列表大小必须大于0".to_string());
+        }
+        #[cfg(feature = "tracer")]
+        if self.debug.instruction_tracer_list_size == 0 {
+            errors.push("debug.instruction_tracer_list_size: 指令追踪列表大小必须大于0".to_string());
+        }
+        if self.others.device_tick_interval == 0 {
+            errors.push("others.device_tick_interval: 设备 tick 间隔必须大于0".to_string());
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(ConfigErrors(errors)) }
+    }
 }
 
+/// 配置校验发现的所有问题，每条消息以出错的字段路径开头
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "配置校验失败，共 {} 个问题:", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
 /// 从设备配置文件中读取的结构（devices/profile/device.toml）
 #[derive(Deserialize, Debug)]
 pub struct DeviceFileMemory {
     pub memory_base: u64,
     pub memory_size: usize,
+    /// 每次读写主内存额外计入的周期数（取指不计）
+    #[serde(default)]
+    pub latency: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -79,12 +348,73 @@ pub struct DeviceFile {
     pub devices: Vec<DeviceConfig>,
 }
 
+/// 内置的默认设备布局：128MB 主内存 + 一个 UART + 一个定时器 + 一个 PLIC，与 devices/profile/device.toml 一致
+const DEFAULT_DEVICE_CONFIG: &str = r#"
+[memory]
+memory_base = 0x8000_0000
+memory_size = 128
+
+[[devices]]
+name = "uart0"
+type = "uart"
+base = 0x1000_0000
+size = 0x100
+
+[[devices]]
+name = "timer0"
+type = "timer"
+base = 0x1000_0100
+size = 0x100
+
+[[devices]]
+name = "plic0"
+type = "plic"
+base = 0x0c00_0000
+size = 0x400_0000
+"#;
+
 impl DeviceFile {
+    /// 读取设备配置文件；文件不存在时使用内置默认布局
     pub fn new(path: impl AsRef<Path>) -> anyhow::Result<DeviceFile> {
+        if !path.as_ref().exists() {
+            tracing::warn!(
+                path = ?path.as_ref().as_os_str(),
+                "设备配置文件不存在，使用内置默认设备布局"
+            );
+            return Ok(Self::builtin());
+        }
         let toml_str = std::fs::read_to_string(&path)
             .with_context(|| format!("无法读取设备配置文件: {:?}", &path.as_ref().as_os_str()))?;
         let profile: DeviceFile = toml::from_str(&toml_str)
             .with_context(|| format!("无法解析设备配置文件: {:?}", &path.as_ref().as_os_str()))?;
         anyhow::Ok(profile)
     }
+
+    /// 内置的默认设备布局
+    pub fn builtin() -> DeviceFile {
+        toml::from_str(DEFAULT_DEVICE_CONFIG).expect("内置设备配置无效")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_config, test_device};
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let (mut config, mut device_file) = test_config();
+        assert_eq!(config.validate(&device_file), Ok(()));
+
+        config.memory.boot_pc = 0x4000_0000;
+        device_file.devices.push(test_device("uart0", "uart", 0x1000_0000, 0x100));
+        device_file.devices.push(test_device("timer0", "timer", 0x1000_0080, 0x100));
+
+        let errors = config.validate(&device_file).unwrap_err();
+        assert_eq!(errors.0.len(), 2, "{errors}");
+        assert!(errors.0[0].starts_with("memory.boot_pc:"), "{errors}");
+        assert!(errors.0[1].starts_with("devices.uart0:") && errors.0[1].contains("timer0"), "{errors}");
+        let msg = errors.to_string();
+        assert!(msg.contains("0x40000000") && msg.contains("0x10000080"), "{msg}");
+    }
 }
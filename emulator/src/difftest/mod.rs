@@ -6,6 +6,7 @@ use rv64emu::{
 };
 
 use crate::emulator::Emulator;
+use crate::utils::reg_file;
 
 pub enum DiffMode {
     Dut,
@@ -28,6 +29,13 @@ impl Display for DiffState {
     }
 }
 
+impl DiffState {
+    /// 生成参考模型（self）与被测模型之间的差异报告
+    pub fn mismatch_report(&self, dut: &DiffState) -> String {
+        reg_file::mismatch_report(self.pc, &self.reg, dut.pc, &dut.reg)
+    }
+}
+
 #[allow(unused)]
 pub trait Difftest {
     fn init(&mut self);
@@ -36,6 +44,7 @@ pub trait Difftest {
     fn step(&mut self) -> bool;
     fn set_regs(&mut self, regs: &[u64; 32]);
     fn set_pc(&mut self, pc: u64);
+    fn set_csr(&mut self, csr: u16, value: u64);
     fn get_mem(&mut self, addr: u64, size: usize) -> u64;
     fn set_mem(&mut self, addr: u64, data: u64, len: usize);
 }
@@ -69,11 +78,14 @@ impl Difftest for Emulator {
         self.sync_pc();
     }
 
+    fn set_csr(&mut self, csr: u16, value: u64) {
+        let _ = Emulator::set_csr(self, csr, value);
+    }
+
     fn get_mem(&mut self, addr: u64, size: usize) -> u64 {
+        // read_memory 返回的切片从 addr 开始，取低 size 字节后零扩展
         let mut data = 0u64.to_le_bytes();
-        data[..size].copy_from_slice(
-            &self.read_memory(addr, size).unwrap()[addr as usize..addr as usize + size],
-        );
+        data[..size].copy_from_slice(&self.read_memory(addr, size).unwrap()[..size]);
         u64::from_le_bytes(data)
     }
 
@@ -118,6 +130,10 @@ impl Difftest for CpuCore {
         self.npc = pc;
     }
 
+    fn set_csr(&mut self, csr: u16, value: u64) {
+        <CpuCore as rv64emu::difftest::difftest_trait::Difftest>::set_csr(self, csr as u64, value);
+    }
+
     fn get_mem(&mut self, addr: u64, size: usize) -> u64 {
         <CpuCore as rv64emu::difftest::difftest_trait::Difftest>::get_mem(self, addr, size)
     }
@@ -126,3 +142,31 @@ impl Difftest for CpuCore {
         <CpuCore as rv64emu::difftest::difftest_trait::Difftest>::set_mem(self, addr, data, len);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+
+    #[test]
+    fn test_set_mem_get_mem_round_trip() {
+        let mut emu = emu_with_program(&[]);
+        let value = 0x8877_6655_4433_2211u64;
+        for len in [1, 2, 4, 8] {
+            let addr = TEST_BASE + 0x100 + len as u64 * 16;
+            emu.set_mem(addr, value, len);
+            let expected = if len == 8 { value } else { value & ((1 << (len * 8)) - 1) };
+            assert_eq!(emu.get_mem(addr, len), expected, "len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_set_mem_writes_only_len_bytes() {
+        let mut emu = emu_with_program(&[]);
+        let addr = TEST_BASE + 0x200;
+        emu.set_mem(addr, u64::MAX, 8);
+        emu.set_mem(addr, 0, 2);
+        assert_eq!(emu.get_mem(addr, 8), 0xffff_ffff_ffff_0000);
+        assert_eq!(emu.get_mem(addr + 2, 2), 0xffff);
+    }
+}
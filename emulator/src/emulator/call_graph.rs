@@ -0,0 +1,180 @@
+//! 调用图记录
+//! 根据 jal/jalr 的链接寄存器约定识别函数调用与返回，用影子栈维护当前所在函数，
+//! 统计 调用者 → 被调用者 的次数，并输出 Graphviz DOT 格式
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use anyhow::Result;
+
+use super::Emulator;
+use super::instructions::is_compressed;
+use crate::utils::bit_utils::BitSlice;
+use crate::utils::SymbolTable;
+
+/// 链接寄存器 ra 与备用链接寄存器 t0
+#[inline(always)]
+fn is_link(reg: u64) -> bool {
+    reg == 1 || reg == 5
+}
+
+/// 控制流转移的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Transfer {
+    Call,
+    Return,
+}
+
+/// 按链接寄存器约定对跳转指令分类，其他指令返回 None
+pub(super) fn classify(inst: u32) -> Option<Transfer> {
+    if is_compressed(inst) {
+        // c.jr/c.jalr：象限 10，funct3 为 100，rs2 为0，rs1 非0
+        let inst = inst as u64;
+        if inst & 0b11 != 0b10 || inst.bit_range(13..16) != 0b100 || inst.bit_range(2..7) != 0 {
+            return None;
+        }
+        let rs1 = inst.bit_range(7..12);
+        return match (rs1, inst.bit(12)) {
+            (0, _) => None,
+            (_, true) => Some(Transfer::Call),
+            (rs1, false) if is_link(rs1) => Some(Transfer::Return),
+            _ => None,
+        };
+    }
+    let rd = inst.bit_range(7..12);
+    match inst & 0x7F {
+        0b1101111 if is_link(rd) => Some(Transfer::Call),
+        0b1100111 if is_link(rd) => Some(Transfer::Call),
+        0b1100111 if rd == 0 && is_link(inst.bit_range(15..20)) => Some(Transfer::Return),
+        _ => None,
+    }
+}
+
+/// 调用图：节点为函数入口地址，边上记录调用次数
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    /// 影子调用栈，栈顶为当前函数的入口；第一条被记录的指令视为根函数入口
+    stack: Vec<u64>,
+    /// (调用者入口, 被调用者入口) -> 调用次数
+    edges: BTreeMap<(u64, u64), u64>,
+}
+
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一条已提交的指令，target 为其后继 PC
+    #[inline(always)]
+    pub fn on_inst(&mut self, pc: u64, inst: u32, target: u64) {
+        if self.stack.is_empty() {
+            self.stack.push(pc);
+        }
+        match classify(inst) {
+            Some(Transfer::Call) => {
+                let caller = *self.stack.last().unwrap();
+                *self.edges.entry((caller, target)).or_insert(0) += 1;
+                self.stack.push(target);
+            }
+            // 保留根函数，避免不成对的返回清空影子栈
+            Some(Transfer::Return) if self.stack.len() > 1 => {
+                self.stack.pop();
+            }
+            _ => (),
+        }
+    }
+
+    /// 按 (调用者, 被调用者) 排序的调用边及其次数
+    pub fn edges(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.edges.iter().map(|(&(caller, callee), &count)| (caller, callee, count))
+    }
+
+    /// 以 Graphviz DOT 格式写出调用图，节点以 symbols 中的函数名标注，找不到符号时使用入口地址
+    pub fn write_dot(&self, symbols: &SymbolTable, mut writer: impl Write) -> Result<()> {
+        writeln!(writer, "digraph call_graph {{")?;
+        writeln!(writer, "    node [shape=box];")?;
+        let nodes: BTreeSet<u64> =
+            self.edges.keys().flat_map(|&(caller, callee)| [caller, callee]).collect();
+        for addr in nodes {
+            match symbols.function_at(addr) {
+                Some(func) => writeln!(writer, "    \"{:#x}\" [label=\"{}\"];", addr, func.name)?,
+                None => writeln!(writer, "    \"{:#x}\";", addr)?,
+            }
+        }
+        for (caller, callee, count) in self.edges() {
+            writeln!(
+                writer,
+                "    \"{:#x}\" -> \"{:#x}\" [label=\"{}\"];",
+                caller, callee, count
+            )?;
+        }
+        writeln!(writer, "}}")?;
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// 开始记录调用图，已有的记录会被清空
+    pub fn enable_call_graph(&mut self) {
+        self.call_graph = Some(CallGraph::new());
+    }
+
+    /// 已记录的调用图，未启用时返回 None
+    pub fn call_graph(&self) -> Option<&CallGraph> {
+        self.call_graph.as_ref()
+    }
+
+    /// 以 Graphviz DOT 格式写出调用图，未启用时返回错误
+    pub fn write_call_graph_dot(&self, writer: impl Write) -> Result<()> {
+        self.call_graph
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("未启用调用图记录"))?
+            .write_dot(&self.symbols, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+    use crate::utils::FuncSymbol;
+
+    #[test]
+    fn test_call_graph_dot() {
+        let mut emu = emu_with_program(&[
+            0x010000ef, // jal ra, foo
+            0x00c000ef, // jal ra, foo
+            0x018000ef, // jal ra, bar
+            0x00100073, // ebreak
+            0x00008313, // foo: mv t1, ra
+            0x00c000ef, // jal ra, bar
+            0x00030093, // mv ra, t1
+            0x00008067, // ret
+            0x00008067, // bar: ret
+        ]);
+        emu.enable_call_graph();
+        emu.symbols = SymbolTable::new(vec![FuncSymbol {
+            name: "foo".to_string(),
+            start: TEST_BASE + 0x10,
+            end: TEST_BASE + 0x20,
+        }]);
+        emu.steps(100).unwrap();
+
+        let main = TEST_BASE;
+        let foo = TEST_BASE + 0x10;
+        let bar = TEST_BASE + 0x20;
+        let edges: Vec<_> = emu.call_graph().unwrap().edges().collect();
+        assert_eq!(edges, vec![(main, foo, 2), (main, bar, 1), (foo, bar, 2)]);
+
+        let mut dot = Vec::new();
+        emu.write_call_graph_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph call_graph {"));
+        assert!(dot.contains("\"0x80000000\" -> \"0x80000010\" [label=\"2\"];"));
+        assert!(dot.contains("\"0x80000010\" -> \"0x80000020\" [label=\"2\"];"));
+        assert!(dot.contains("\"0x80000000\" -> \"0x80000020\" [label=\"1\"];"));
+        // 有符号的节点以函数名标注，没有符号的保留地址
+        assert!(dot.contains("\"0x80000010\" [label=\"foo\"];"));
+        assert!(dot.contains("\"0x80000020\";"));
+    }
+}
@@ -0,0 +1,262 @@
+//! 断点与观察点
+//! GDB 存根与嵌入方共用同一份断点/观察点表，便于监控命令列出当前设置；
+//! 断点在取指前检查，观察点由内存在每次访问时检查，命中后本步产生对应事件。
+//! 观察点在触发访问的指令执行完成后才报告，继续执行时从下一条指令开始，
+//! 同一次访问不会重复触发；循环中再次执行该指令属于新的访问，会再次触发
+
+use std::collections::BTreeSet;
+
+use super::Emulator;
+
+/// 观察点类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WatchKind {
+    Write,
+    Read,
+    ReadWrite,
+}
+
+/// 一个观察点：监视 [addr, addr + len)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Watchpoint {
+    pub addr: u64,
+    pub len: u64,
+    pub kind: WatchKind,
+}
+
+/// 断点与观察点表
+#[derive(Debug, Default)]
+pub struct DebugPoints {
+    breakpoints: BTreeSet<u64>,
+    /// 按 (起始地址, 长度, 类型) 区分的观察点，GDB 可在同一地址设置多个不同的观察点
+    watchpoints: BTreeSet<Watchpoint>,
+    /// 上一步因断点停在此地址，下一步从这里恢复时不再触发
+    resume_from: Option<u64>,
+}
+
+impl DebugPoints {
+    /// 即将执行 pc 处的指令时调用，命中断点时返回 true
+    ///
+    /// 停在断点处后再次执行同一地址视为恢复执行，不重复触发，
+    /// 因此每次到达断点只触发一次
+    #[inline(always)]
+    pub(super) fn check_breakpoint(&mut self, pc: u64) -> bool {
+        let resume_from = self.resume_from.take();
+        if self.breakpoints.is_empty() || resume_from == Some(pc) {
+            return false;
+        }
+        let hit = self.breakpoints.contains(&pc);
+        if hit {
+            self.resume_from = Some(pc);
+        }
+        hit
+    }
+
+    /// 忘记上一次停下的断点，使下一次到达时重新触发
+    pub(super) fn clear_resume(&mut self) {
+        self.resume_from = None;
+    }
+
+    /// 主内存搬移后按 relocate 平移断点与观察点的地址
+    pub(super) fn relocate(&mut self, relocate: impl Fn(u64) -> u64) {
+        self.breakpoints = self.breakpoints.iter().map(|&addr| relocate(addr)).collect();
+        self.watchpoints = self
+            .watchpoints
+            .iter()
+            .map(|watch| Watchpoint { addr: relocate(watch.addr), ..*watch })
+            .collect();
+        self.resume_from = None;
+    }
+}
+
+impl Emulator {
+    /// 在 addr 处设置断点，已存在时返回 false
+    pub fn add_breakpoint(&mut self, addr: u64) -> bool {
+        self.debug_points.breakpoints.insert(addr)
+    }
+
+    /// 删除 addr 处的断点，不存在时返回 false
+    pub fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        self.debug_points.breakpoints.remove(&addr)
+    }
+
+    /// 设置观察点，起始地址、长度与类型都相同的观察点只保留一个
+    pub fn add_watchpoint(&mut self, addr: u64, len: u64, kind: WatchKind) {
+        self.debug_points
+            .watchpoints
+            .insert(Watchpoint { addr, len, kind });
+        self.sync_watchpoints();
+    }
+
+    /// 删除起始地址、长度与类型都匹配的观察点，不存在时返回 false
+    pub fn remove_watchpoint(&mut self, addr: u64, len: u64, kind: WatchKind) -> bool {
+        let removed = self.debug_points.watchpoints.remove(&Watchpoint { addr, len, kind });
+        if removed {
+            self.sync_watchpoints();
+        }
+        removed
+    }
+
+    /// 访存时由内存检查观察点，观察点表变化后同步过去
+    pub(super) fn sync_watchpoints(&mut self) {
+        let watchpoints = self.debug_points.watchpoints.iter().copied().collect();
+        self.state.memory.set_watchpoints(watchpoints);
+    }
+
+    /// 当前设置的所有断点，按地址升序
+    pub fn list_breakpoints(&self) -> Vec<u64> {
+        self.debug_points.breakpoints.iter().copied().collect()
+    }
+
+    /// 当前设置的所有观察点的 (起始地址, 类型)，按地址升序
+    pub fn list_watchpoints(&self) -> Vec<(u64, WatchKind)> {
+        self.debug_points
+            .watchpoints
+            .iter()
+            .map(|watch| (watch.addr, watch.kind))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{Event, StepOutcome};
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+
+    #[test]
+    fn test_list_breakpoints_and_watchpoints() {
+        let mut emu = emu_with_program(&[]);
+        assert!(emu.add_breakpoint(0x8000_0010));
+        assert!(emu.add_breakpoint(0x8000_0004));
+        assert!(!emu.add_breakpoint(0x8000_0004));
+        emu.add_watchpoint(0x8000_1000, 8, WatchKind::Write);
+        emu.add_watchpoint(0x8000_0800, 4, WatchKind::ReadWrite);
+
+        assert_eq!(emu.list_breakpoints(), vec![0x8000_0004, 0x8000_0010]);
+        assert_eq!(
+            emu.list_watchpoints(),
+            vec![(0x8000_0800, WatchKind::ReadWrite), (0x8000_1000, WatchKind::Write)]
+        );
+
+        // 同一地址上类型不同的观察点互不覆盖
+        emu.add_watchpoint(0x8000_1000, 8, WatchKind::Read);
+        assert_eq!(
+            emu.list_watchpoints(),
+            vec![
+                (0x8000_0800, WatchKind::ReadWrite),
+                (0x8000_1000, WatchKind::Write),
+                (0x8000_1000, WatchKind::Read)
+            ]
+        );
+
+        assert!(emu.remove_breakpoint(0x8000_0010));
+        assert!(!emu.remove_watchpoint(0x8000_1000, 4, WatchKind::Read));
+        assert!(emu.remove_watchpoint(0x8000_1000, 8, WatchKind::Write));
+        assert!(emu.remove_watchpoint(0x8000_1000, 8, WatchKind::Read));
+        assert_eq!(emu.list_breakpoints(), vec![0x8000_0004]);
+        assert_eq!(emu.list_watchpoints(), vec![(0x8000_0800, WatchKind::ReadWrite)]);
+    }
+
+    #[test]
+    fn test_breakpoint_fires_once_per_arrival() {
+        let mut emu = emu_with_program(&[
+            0x00300593, // li a1, 3
+            0xfff58593, // loop: addi a1, a1, -1
+            0xfe059ee3, // bnez a1, loop
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.add_breakpoint(TEST_BASE + 4);
+
+        let mut arrivals = Vec::new();
+        for _ in 0..100 {
+            match emu.step_outcome().unwrap() {
+                StepOutcome::Event(Event::Break) => {
+                    // 停在断点处时该指令尚未执行
+                    assert_eq!(emu.get_pc(), TEST_BASE + 4);
+                    arrivals.push(emu.get_reg(11).unwrap());
+                }
+                StepOutcome::Halted => break,
+                _ => (),
+            }
+        }
+        assert_eq!(arrivals, vec![3, 2, 1]);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_steps_stops_at_breakpoint() {
+        let mut emu = emu_with_program(&[
+            0x00100593, // li a1, 1
+            0x00200593, // li a1, 2
+            0x00100073, // ebreak
+        ]);
+        emu.add_breakpoint(TEST_BASE + 4);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_cur_event(), Event::Break);
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+
+        // 继续执行时越过断点
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 2);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_write_watchpoint_triggers_on_store() {
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x1002b583, // ld a1, 0x100(t0)
+            0x10b2b423, // sd a1, 0x108(t0)
+            0x00100073, // ebreak
+        ]);
+        emu.add_watchpoint(TEST_BASE + 0x10c, 4, WatchKind::Write);
+        emu.add_watchpoint(TEST_BASE + 0x100, 8, WatchKind::Write);
+
+        // 读取只有写观察点的地址不触发
+        assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Continued);
+        assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Continued);
+        assert_eq!(
+            emu.step_outcome().unwrap(),
+            StepOutcome::Event(Event::WatchWrite(TEST_BASE + 0x10c))
+        );
+        assert_eq!(emu.get_pc(), TEST_BASE + 8);
+
+        emu.remove_watchpoint(TEST_BASE + 0x100, 8, WatchKind::Write);
+        emu.add_watchpoint(TEST_BASE + 0x100, 8, WatchKind::Read);
+        emu.set_npc(TEST_BASE + 4);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_cur_event(), Event::WatchRead(TEST_BASE + 0x100));
+        assert_eq!(emu.get_pc(), TEST_BASE + 4);
+    }
+
+    #[test]
+    fn test_continue_past_watchpoint_makes_progress() {
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x00300593, // li a1, 3
+            0x10b2b023, // loop: sd a1, 0x100(t0)
+            0xfff58593, // addi a1, a1, -1
+            0xfe059ce3, // bnez a1, loop
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.add_watchpoint(TEST_BASE + 0x100, 8, WatchKind::Write);
+
+        // 每次继续执行都越过已报告的存储，下一次停下是循环中的下一次存储
+        let mut stored = Vec::new();
+        for _ in 0..10 {
+            if emu.exit_code().is_some() {
+                break;
+            }
+            emu.steps(100).unwrap();
+            if emu.get_cur_event() == Event::WatchWrite(TEST_BASE + 0x100) {
+                assert_eq!(emu.get_pc(), TEST_BASE + 8);
+                stored.push(emu.get_reg(11).unwrap());
+            }
+        }
+        assert_eq!(stored, vec![3, 2, 1]);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+}
@@ -1,6 +1,8 @@
 //! 设备管理模块
 //! 负责根据配置文件创建和管理 MMIO 设备
 
+use std::fs::File;
+use std::io::{self, Read, Write};
 use std::sync::{Arc, Mutex};
 use mmio_trait::MmioDevice;
 use crate::const_values::DeviceConfig;
@@ -19,20 +21,130 @@ pub enum DeviceError {
 pub struct DeviceFactory;
 
 impl DeviceFactory {
-    /// 根据配置创建设备
+    /// 根据配置创建设备，同一类型可以创建多个实例，以 `config.name` 区分
+    ///
+    /// 设备专有参数取自 `config.params`：
+    /// - uart 的 `output`: 发送数据的去向，`"stderr"`（默认）、`"stdout"` 或文件路径
+    /// - uart 的 `input`: 接收数据的来源，`"stdin"` 或文件路径，未设置时没有输入
+    /// - uart 的 `irq`: 接收中断在 mip 中的位号，默认为 M 模式外部中断
+    /// - ram/rom 的 `image`: 作为初始内容加载到设备开头的文件路径
     pub fn create_device(config: &DeviceConfig) -> Result<Arc<Mutex<dyn MmioDevice>>, DeviceError> {
-            match config.device_type.as_str() {
+        let supported: &[&str] = match config.device_type.as_str() {
+            "uart" => &["output", "input", "irq"],
+            "ram" | "rom" => &["image"],
+            _ => &[],
+        };
+        if let Some(key) = config.params.keys().find(|key| !supported.contains(&key.as_str())) {
+            return Err(DeviceError::CreationFailed(format!(
+                "{} 设备不支持参数 '{}'",
+                config.device_type, key
+            )));
+        }
+
+        match config.device_type.as_str() {
             "uart" => {
-                let uart = uart::Uart::new(config.name.clone());
+                let uart = match Self::uart_input(config)? {
+                    Some(input) => uart::Uart::with_input(config.name.clone(), input),
+                    None => uart::Uart::new(config.name.clone()),
+                }
+                .with_output(Self::uart_output(config)?);
+                let uart = match config.params.get("irq") {
+                    None => uart,
+                    Some(toml::Value::Integer(irq)) if (0..64).contains(irq) => uart.with_irq(*irq as u32),
+                    Some(_) => {
+                        return Err(DeviceError::CreationFailed(
+                            "参数 'irq' 必须是 0..64 内的整数".to_string(),
+                        ));
+                    }
+                };
                 Ok(Arc::new(Mutex::new(uart)))
             }
             "timer" => {
                 let timer = timer::Timer::new(config.name.clone());
                 Ok(Arc::new(Mutex::new(timer)))
             }
+            "halt" => {
+                let halt = halt::Halt::new(config.name.clone());
+                Ok(Arc::new(Mutex::new(halt)))
+            }
+            "watchdog" => {
+                let watchdog = watchdog::Watchdog::new(config.name.clone());
+                Ok(Arc::new(Mutex::new(watchdog)))
+            }
+            "hostcall" => {
+                let hostcall = hostcall::Hostcall::new(config.name.clone());
+                Ok(Arc::new(Mutex::new(hostcall)))
+            }
+            "assert" => {
+                let assert = assertion::Assert::new(config.name.clone());
+                Ok(Arc::new(Mutex::new(assert)))
+            }
+            "plic" => {
+                let plic = plic::Plic::new(config.name.clone());
+                Ok(Arc::new(Mutex::new(plic)))
+            }
+            "ram" => {
+                let ram = ram::Ram::new(config.name.clone(), config.size as usize)
+                    .with_image(&Self::image(config)?)
+                    .map_err(|e| DeviceError::CreationFailed(e.to_string()))?;
+                Ok(Arc::new(Mutex::new(ram)))
+            }
+            "rom" => {
+                let image = Self::image(config)?;
+                let rom = ram::Rom::new(config.name.clone(), config.size as usize, &image)
+                    .map_err(|e| DeviceError::CreationFailed(e.to_string()))?;
+                Ok(Arc::new(Mutex::new(rom)))
+            }
             _ => Err(DeviceError::UnknownDeviceType(config.device_type.clone())),
         }
     }
+
+    /// 读取 `image` 参数指定的文件，未设置时返回空内容
+    fn image(config: &DeviceConfig) -> Result<Vec<u8>, DeviceError> {
+        match config.params.get("image") {
+            None => Ok(Vec::new()),
+            Some(toml::Value::String(path)) => std::fs::read(path).map_err(|e| {
+                DeviceError::CreationFailed(format!("无法读取初始内容文件 '{}': {}", path, e))
+            }),
+            Some(_) => Err(DeviceError::CreationFailed(
+                "参数 'image' 必须是字符串".to_string(),
+            )),
+        }
+    }
+
+    /// 由 `input` 参数打开 UART 的输入源，未设置时返回 None
+    fn uart_input(config: &DeviceConfig) -> Result<Option<Box<dyn Read + Send>>, DeviceError> {
+        match config.params.get("input") {
+            None => Ok(None),
+            Some(toml::Value::String(input)) if input == "stdin" => Ok(Some(Box::new(uart::Stdin))),
+            Some(toml::Value::String(path)) => Ok(Some(Box::new(File::open(path).map_err(|e| {
+                DeviceError::CreationFailed(format!("无法打开 UART 输入文件 '{}': {}", path, e))
+            })?))),
+            Some(_) => Err(DeviceError::CreationFailed(
+                "参数 'input' 必须是字符串".to_string(),
+            )),
+        }
+    }
+
+    /// 由 `output` 参数打开 UART 的输出目标
+    fn uart_output(config: &DeviceConfig) -> Result<Box<dyn Write + Send>, DeviceError> {
+        let output = match config.params.get("output") {
+            None => return Ok(Box::new(io::stderr())),
+            Some(toml::Value::String(output)) => output,
+            Some(_) => {
+                return Err(DeviceError::CreationFailed(
+                    "参数 'output' 必须是字符串".to_string(),
+                ));
+            }
+        };
+        Ok(match output.as_str() {
+            "stderr" => Box::new(io::stderr()),
+            "stdout" => Box::new(io::stdout()),
+            path => Box::new(File::create(path).map_err(|e| {
+                DeviceError::CreationFailed(format!("无法创建 UART 输出文件 '{}': {}", path, e))
+            })?),
+        })
+    }
 }
 
 /// 设备管理器
@@ -56,12 +168,23 @@ impl DeviceManager {
             let device = DeviceFactory::create_device(config)
                 .map_err(|e| format!("创建设备 {} 失败: {}", config.name, e))?;
 
-            memory.map_mmio(
+            memory.map_mmio_with_endianness(
                 config.base,
                 config.size,
                 device,
                 config.name.clone(),
+                config.endianness,
             ).map_err(|e| format!("映射设备 {} 失败: {}", config.name, e))?;
+
+            if let Some(source) = config.irq {
+                memory.route_irq(&config.name, source)?;
+            }
+            if config.latency != 0 {
+                memory.set_device_latency(&config.name, config.latency)?;
+            }
+            if config.is_executable() {
+                memory.set_device_executable(&config.name)?;
+            }
         }
 
         memory.sort_mmio_regions();
@@ -69,3 +192,142 @@ impl DeviceManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::const_values::DeviceConfig;
+    use crate::emulator::{Emulator, ExecState};
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config, test_device};
+
+    /// 只含一个参数的设备参数表
+    fn params(key: &str, value: impl Into<toml::Value>) -> toml::Table {
+        toml::Table::from_iter([(key.to_string(), value.into())])
+    }
+
+    #[test]
+    fn test_two_uarts_from_device_list() {
+        let path = std::env::temp_dir().join(format!("dolphin-uart1-{}.txt", std::process::id()));
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("console", "uart", 0x1000_0000, 0x100));
+        device_file.devices.push(DeviceConfig {
+            params: params("output", path.display().to_string()),
+            ..test_device("log", "uart", 0x1000_1000, 0x100)
+        });
+        let mut emu = emu_from(config, &device_file);
+
+        let memory = &emu.get_state_ref().memory;
+        assert_eq!(memory.mmio_region_at(0x1000_0000).unwrap().0, "console");
+        assert_eq!(memory.mmio_region_at(0x1000_1000).unwrap().0, "log");
+
+        load_program(&mut emu, &[
+            0x100012b7, // lui t0, 0x10001
+            0x05800593, // li a1, 'X'
+            0x00b28023, // sb a1, 0(t0)
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        let output = std::fs::read(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(output.unwrap(), b"X");
+    }
+
+    #[test]
+    fn test_uart_input_from_file() {
+        let path = std::env::temp_dir().join(format!("dolphin-uart-in-{}.txt", std::process::id()));
+        std::fs::write(&path, b"Z").unwrap();
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(DeviceConfig {
+            params: params("input", path.display().to_string()),
+            ..test_device("console", "uart", 0x1000_0000, 0x100)
+        });
+        let mut emu = emu_from(config, &device_file);
+        std::fs::remove_file(&path).ok();
+
+        load_program(&mut emu, &[
+            0x100002b7, // lui t0, 0x10000
+            0x0002c503, // lbu a0, 0(t0)
+            0x00100073, // ebreak
+        ]);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.exit_code(), Some(b'Z'));
+    }
+
+    #[test]
+    fn test_uart_irq_param() {
+        let path = std::env::temp_dir().join(format!("dolphin-uart-irq-{}.txt", std::process::id()));
+        std::fs::write(&path, b"Z").unwrap();
+        let mut uart = test_device("console", "uart", 0x1000_0000, 0x100);
+        uart.params = params("input", path.display().to_string());
+        uart.params.insert("irq".to_string(), 5.into());
+        let device = super::DeviceFactory::create_device(&uart);
+        std::fs::remove_file(&path).ok();
+        let device = device.unwrap();
+        let mut device = device.lock().unwrap();
+
+        device.write(0x08, &1u32.to_le_bytes()).unwrap(); // 使能接收中断
+        device.tick(1);
+        assert_eq!(device.irq_pending(), Some(5));
+
+        uart.params = params("irq", 64);
+        assert!(super::DeviceFactory::create_device(&uart).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_device_param_rejected() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(DeviceConfig {
+            params: params("output", "stdout"),
+            ..test_device("timer0", "timer", 0x1000_0100, 0x100)
+        });
+        let err = Emulator::from_config(config, &device_file).err().unwrap();
+        assert!(format!("{err:#}").contains("'output'"), "{err:#}");
+    }
+
+    #[test]
+    fn test_run_from_rom_image() {
+        let path = std::env::temp_dir().join(format!("dolphin-rom-{}.bin", std::process::id()));
+        let rom: Vec<u8> = [
+            0x00158513u32, // addi a0, a1, 1
+            0x00100073,    // ebreak
+        ]
+        .iter()
+        .flat_map(|inst| inst.to_le_bytes())
+        .collect();
+        std::fs::write(&path, &rom).unwrap();
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(DeviceConfig {
+            params: params("image", path.display().to_string()),
+            ..test_device("bootrom", "rom", 0x2000_0000, 0x100)
+        });
+        let emu = Emulator::from_config(config, &device_file);
+        let (mut boot_config, _) = test_config();
+        boot_config.memory.boot_pc = 0x2000_0000;
+        let boot_emu = Emulator::from_config(boot_config, &device_file);
+        std::fs::remove_file(&path).ok();
+        let mut emu = emu.unwrap();
+
+        // 预加载的内容可以读回，ROM 拒绝写入
+        assert_eq!(emu.state.read_memory(0x2000_0000, 8).unwrap(), rom);
+        assert!(emu.state.write_memory(0x2000_0000, &[0]).is_err());
+
+        // 从主内存跳入 ROM 执行，开启可执行范围检查时 ROM 同样可以取指
+        load_program(&mut emu, &[
+            0x02900593, // li a1, 41
+            0x200002b7, // lui t0, 0x20000
+            0x00028067, // jr t0
+        ]);
+        emu.add_exec_region(TEST_BASE..TEST_BASE + 12);
+        emu.enable_exec_check();
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(42));
+
+        // boot_pc 位于 ROM 中时直接从 ROM 启动
+        let mut boot_emu = boot_emu.unwrap();
+        boot_emu.steps(10).unwrap();
+        assert_eq!(boot_emu.get_exec_state(), ExecState::End);
+        assert_eq!(boot_emu.exit_code(), Some(1));
+    }
+}
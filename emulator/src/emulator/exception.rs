@@ -2,13 +2,24 @@
 
 use thiserror::Error;
 
+use super::instructions::insts::*;
+
 #[derive(Debug, Error)]
 pub enum Exception {
     #[error("取指未对齐地址: {addr:#x}")]
     InstructionAddressMisaligned { addr: u64 },
 
-    #[error("访问错误: {addr:#x}")]
-    AccessFault { addr: u64 },
+    #[error("加载地址未对齐: {addr:#x}")]
+    LoadAddressMisaligned { addr: u64 },
+
+    #[error("存储地址未对齐: {addr:#x}")]
+    StoreAddressMisaligned { addr: u64 },
+
+    #[error("加载访问错误: {addr:#x}")]
+    LoadAccessFault { addr: u64 },
+
+    #[error("存储访问错误: {addr:#x}")]
+    StoreAccessFault { addr: u64 },
 
     #[error("指令错误: {addr:#x}")]
     InstructionFault { addr: u64 },
@@ -23,21 +34,58 @@ pub enum Exception {
     Breakpoint,
 }
 
-// 特权级别
-// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-// pub enum PrivilegeLevel {
-//     User = 0,
-//     Supervisor = 1,
-//     Machine = 3,
-// }
-
-// impl PrivilegeLevel {
-//     pub fn from_u64(value: u64) -> Option<Self> {
-//         match value {
-//             0 => Some(PrivilegeLevel::User),
-//             1 => Some(PrivilegeLevel::Supervisor),
-//             3 => Some(PrivilegeLevel::Machine),
-//             _ => None,
-//         }
-//     }
-// }
+impl Exception {
+    /// 异常对应的 mcause 编码，环境调用的编码取决于当前特权级
+    pub fn cause(&self, privilege: PrivilegeLevel) -> u64 {
+        let code = match self {
+            Exception::InstructionAddressMisaligned { .. } => CAUSE_MISALIGNED_FETCH,
+            Exception::LoadAddressMisaligned { .. } => CAUSE_MISALIGNED_LOAD,
+            Exception::StoreAddressMisaligned { .. } => CAUSE_MISALIGNED_STORE,
+            Exception::LoadAccessFault { .. } => CAUSE_LOAD_ACCESS,
+            Exception::StoreAccessFault { .. } => CAUSE_STORE_ACCESS,
+            Exception::InstructionFault { .. } => CAUSE_FETCH_ACCESS,
+            Exception::IllegalInstruction { .. } => CAUSE_ILLEGAL_INSTRUCTION,
+            Exception::EnvironmentCall => match privilege {
+                PrivilegeLevel::User => CAUSE_USER_ECALL,
+                PrivilegeLevel::Supervisor => CAUSE_SUPERVISOR_ECALL,
+                PrivilegeLevel::Machine => CAUSE_MACHINE_ECALL,
+            },
+            Exception::Breakpoint => CAUSE_BREAKPOINT,
+        };
+        code as u64
+    }
+
+    /// 异常对应的 mtval 值
+    pub fn tval(&self) -> u64 {
+        match self {
+            Exception::InstructionAddressMisaligned { addr }
+            | Exception::LoadAddressMisaligned { addr }
+            | Exception::StoreAddressMisaligned { addr }
+            | Exception::LoadAccessFault { addr }
+            | Exception::StoreAccessFault { addr }
+            | Exception::InstructionFault { addr } => *addr,
+            Exception::IllegalInstruction { instruction, .. } => *instruction as u64,
+            Exception::EnvironmentCall | Exception::Breakpoint => 0,
+        }
+    }
+}
+
+/// 特权级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum PrivilegeLevel {
+    User = 0,
+    Supervisor = 1,
+    #[default]
+    Machine = 3,
+}
+
+impl PrivilegeLevel {
+    pub fn from_u64(value: u64) -> Option<Self> {
+        match value {
+            0 => Some(PrivilegeLevel::User),
+            1 => Some(PrivilegeLevel::Supervisor),
+            3 => Some(PrivilegeLevel::Machine),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! 取指缓存模块
+//! 按 PC 缓存已取出的指令字，独立于译码；写入覆盖到的 PC 时使缓存项失效
+
+/// 无效条目的标记（合法的 PC 至少按2字节对齐，不会等于该值）
+const INVALID_TAG: u64 = u64::MAX;
+
+/// 直接映射的取指缓存
+#[derive(Debug)]
+pub struct FetchCache {
+    /// (PC, 指令字)
+    entries: Vec<(u64, u32)>,
+    mask: usize,
+}
+
+impl FetchCache {
+    /// 创建取指缓存，条目数向上取整为2的幂
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+        Self {
+            entries: vec![(INVALID_TAG, 0); size],
+            mask: size - 1,
+        }
+    }
+
+    #[inline(always)]
+    fn index(&self, pc: u64) -> usize {
+        // PC 至少按2字节对齐，去掉最低位以充分利用条目
+        (pc >> 1) as usize & self.mask
+    }
+
+    /// 查找 PC 对应的指令字
+    #[inline(always)]
+    pub fn lookup(&self, pc: u64) -> Option<u32> {
+        let (tag, word) = self.entries[self.index(pc)];
+        (tag == pc).then_some(word)
+    }
+
+    /// 填充缓存
+    #[inline(always)]
+    pub fn insert(&mut self, pc: u64, word: u32) {
+        let index = self.index(pc);
+        self.entries[index] = (pc, word);
+    }
+
+    /// 使与写入区间 [addr, addr + len) 重叠的缓存项失效
+    ///
+    /// 每个缓存项覆盖 [pc, pc + 4)，因此 PC 落在 (addr - 4, addr + len) 内的条目都需要失效
+    pub fn invalidate(&mut self, addr: u64, len: usize) {
+        if len / 2 >= self.entries.len() {
+            self.clear();
+            return;
+        }
+        let end = addr.saturating_add(len as u64);
+        let mut pc = addr.saturating_sub(3) & !1;
+        while pc < end {
+            let index = self.index(pc);
+            if self.entries[index].0 == pc {
+                self.entries[index].0 = INVALID_TAG;
+            }
+            pc += 2;
+        }
+    }
+
+    /// 清空缓存
+    pub fn clear(&mut self) {
+        self.entries.fill((INVALID_TAG, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_insert() {
+        let mut cache = FetchCache::new(16);
+        assert_eq!(cache.lookup(0x8000_0000), None);
+        cache.insert(0x8000_0000, 0x0000_0013);
+        assert_eq!(cache.lookup(0x8000_0000), Some(0x0000_0013));
+        // 同一条目的不同 PC 不会误命中
+        assert_eq!(cache.lookup(0x8000_0000 + 32), None);
+    }
+
+    #[test]
+    fn test_invalidate_overlapping_writes() {
+        let mut cache = FetchCache::new(64);
+        cache.insert(0x100, 1);
+        cache.insert(0x104, 2);
+        cache.insert(0x108, 3);
+
+        // 写入 0x103 处的1个字节，只覆盖 PC 0x100 的指令
+        cache.invalidate(0x103, 1);
+        assert_eq!(cache.lookup(0x100), None);
+        assert_eq!(cache.lookup(0x104), Some(2));
+
+        // 写入跨越 0x106..0x10a 的4字节，覆盖 0x104 和 0x108
+        cache.invalidate(0x106, 4);
+        assert_eq!(cache.lookup(0x104), None);
+        assert_eq!(cache.lookup(0x108), None);
+    }
+}
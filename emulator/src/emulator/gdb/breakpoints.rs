@@ -1,4 +1,4 @@
-use crate::emulator::Emulator;
+use crate::emulator::{Emulator, WatchKind};
 use gdbstub::target;
 
 impl target::ext::breakpoints::Breakpoints for Emulator {
@@ -23,7 +23,7 @@ impl target::ext::breakpoints::SwBreakpoint for Emulator {
         addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
         _kind: <Self::Arch as gdbstub::arch::Arch>::BreakpointKind,
     ) -> target::TargetResult<bool, Self> {
-        self.gdb_data.breakpoints.insert(addr);
+        self.add_breakpoint(addr);
         Ok(true)
     }
 
@@ -32,7 +32,16 @@ impl target::ext::breakpoints::SwBreakpoint for Emulator {
         addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
         _kind: <Self::Arch as gdbstub::arch::Arch>::BreakpointKind,
     ) -> target::TargetResult<bool, Self> {
-        Ok(self.gdb_data.breakpoints.remove(&addr))
+        Ok(self.remove_breakpoint(addr))
+    }
+}
+
+/// GDB 的观察点类型转换为模拟器的观察点类型
+fn watch_kind(kind: target::ext::breakpoints::WatchKind) -> WatchKind {
+    match kind {
+        target::ext::breakpoints::WatchKind::Write => WatchKind::Write,
+        target::ext::breakpoints::WatchKind::Read => WatchKind::Read,
+        target::ext::breakpoints::WatchKind::ReadWrite => WatchKind::ReadWrite,
     }
 }
 
@@ -41,11 +50,9 @@ impl target::ext::breakpoints::HwWatchpoint for Emulator {
         &mut self,
         addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
         len: <Self::Arch as gdbstub::arch::Arch>::Usize,
-        _kind: target::ext::breakpoints::WatchKind,
+        kind: target::ext::breakpoints::WatchKind,
     ) -> target::TargetResult<bool, Self> {
-        for addr in addr..(addr + len) {
-            self.gdb_data.watchpoints.insert(addr);
-        }
+        self.add_watchpoint(addr, len, watch_kind(kind));
         Ok(true)
     }
 
@@ -53,13 +60,8 @@ impl target::ext::breakpoints::HwWatchpoint for Emulator {
         &mut self,
         addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
         len: <Self::Arch as gdbstub::arch::Arch>::Usize,
-        _kind: target::ext::breakpoints::WatchKind,
+        kind: target::ext::breakpoints::WatchKind,
     ) -> target::TargetResult<bool, Self> {
-        for addr in addr..(addr + len) {
-            if !self.gdb_data.watchpoints.remove(&addr) {
-                return Ok(false);
-            }
-        }
-        Ok(true)
+        Ok(self.remove_watchpoint(addr, len, watch_kind(kind)))
     }
 }
@@ -1,16 +1,18 @@
 mod breakpoints;
+mod monitor;
+mod rv32;
+
+pub use rv32::{EmuGdbEventLoop32, Rv32Target};
 
 use crate::emulator::Emulator;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use gdbstub::target::ext::base::single_register_access::SingleRegisterAccess;
 use gdbstub::target::ext::base::singlethread::{
     SingleThreadBase, SingleThreadRangeStepping, SingleThreadResume, SingleThreadSingleStep,
 };
 use gdbstub::target::{self, Target};
 use gdbstub_arch::riscv::reg::id::RiscvRegId;
-use nohash_hasher::{self, BuildNoHashHasher};
-use std::collections::HashSet;
-use std::net::{TcpListener, TcpStream};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
 use tracing::info;
 
 use super::state::{Event, ExecMode, ExecState};
@@ -19,22 +21,6 @@ use gdbstub::conn::{Connection, ConnectionExt};
 use gdbstub::stub::{SingleThreadStopReason, run_blocking};
 use gdbstub::target::ext::breakpoints::WatchKind;
 
-type NoHashHashSet<T> = HashSet<T, BuildNoHashHasher<T>>;
-
-pub struct GdbData {
-    pub breakpoints: NoHashHashSet<u64>,
-    pub watchpoints: NoHashHashSet<u64>,
-}
-
-impl GdbData {
-    pub fn new() -> Self {
-        Self {
-            breakpoints: NoHashHashSet::default(),
-            watchpoints: NoHashHashSet::default(),
-        }
-    }
-}
-
 pub enum EmuGdbEventLoop {}
 
 impl run_blocking::BlockingEventLoop for EmuGdbEventLoop {
@@ -54,99 +40,143 @@ impl run_blocking::BlockingEventLoop for EmuGdbEventLoop {
             <Self::Connection as Connection>::Error,
         >,
     > {
-        let mode = target.get_exec_mode();
-        let mut cnt = match mode {
-            ExecMode::Step => 1,
-            ExecMode::Continue => usize::MAX,
-            ExecMode::RangeStep(start, end) => {
-                if target.get_state_ref().get_pc() >= end {
+        wait_for_stop(target, conn, |addr| addr)
+    }
+
+    fn on_interrupt(
+        _target: &mut Self::Target,
+    ) -> std::result::Result<Option<Self::StopReason>, <Self::Target as Target>::Error> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
+
+/// 执行到下一个停止原因，RV32 与 RV64 的事件循环共用；to_usize 将观察点地址转换为目标架构的地址宽度
+fn wait_for_stop<U>(
+    target: &mut Emulator,
+    conn: &mut Box<dyn ConnectionExt<Error = std::io::Error>>,
+    to_usize: fn(u64) -> U,
+) -> std::result::Result<
+    run_blocking::Event<SingleThreadStopReason<U>>,
+    run_blocking::WaitForStopReasonError<String, std::io::Error>,
+> {
+    let mode = target.get_exec_mode();
+    let mut cnt = match mode {
+        ExecMode::Step => 1,
+        ExecMode::Continue => target.gdb_continue_quantum.unwrap_or(usize::MAX),
+        ExecMode::RangeStep(start, end) => {
+            if target.get_state_ref().get_pc() >= end {
+                return Ok(run_blocking::Event::TargetStopped(
+                    SingleThreadStopReason::Exited(0),
+                ));
+            }
+            (end - start) as usize
+        }
+        _ => 1, // 默认单步执行
+    };
+    let mut delay_cycles = 0;
+    while target.get_exec_state() != ExecState::End {
+        if delay_cycles >= 1000
+            && conn
+                .peek()
+                .map_err(run_blocking::WaitForStopReasonError::Connection)?
+                .is_some()
+        {
+            let byte = conn
+                .read()
+                .map_err(run_blocking::WaitForStopReasonError::Connection)?;
+            return Ok(run_blocking::Event::IncomingData(byte));
+        }
+
+        match target.step() {
+            Ok(_) => match target.event {
+                Event::None => (),
+                Event::Halted(x) | Event::Exited(x) => {
                     return Ok(run_blocking::Event::TargetStopped(
-                        SingleThreadStopReason::Exited(0),
+                        SingleThreadStopReason::Exited(x),
                     ));
                 }
-                (end - start) as usize
-            }
-            _ => 1, // 默认单步执行
-        };
-        let mut delay_cycles = 0;
-        while target.get_exec_state() != ExecState::End {
-            if delay_cycles >= 1000 && conn.peek().is_ok() {
-                let byte = conn
-                    .read()
-                    .map_err(run_blocking::WaitForStopReasonError::Connection)?;
-                return Ok(run_blocking::Event::IncomingData(byte));
-            }
-
-            match target.step() {
-                Ok(_) => match target.event {
-                    Event::None => (),
-                    Event::Halted(x) => {
-                        return Ok(run_blocking::Event::TargetStopped(
-                            SingleThreadStopReason::Exited(x),
-                        ));
-                    }
-                    Event::Break => {
-                        return Ok(run_blocking::Event::TargetStopped(
-                            SingleThreadStopReason::Terminated(Signal::SIGSTOP),
-                        ));
-                    }
-                    Event::WatchWrite(addr) => {
-                        return Ok(run_blocking::Event::TargetStopped(
-                            SingleThreadStopReason::Watch {
-                                tid: (),
-                                kind: WatchKind::Write,
-                                addr,
-                            },
-                        ));
-                    }
-                    Event::WatchRead(addr) => {
-                        return Ok(run_blocking::Event::TargetStopped(
-                            SingleThreadStopReason::Watch {
-                                tid: (),
-                                kind: WatchKind::Read,
-                                addr,
-                            },
-                        ));
-                    }
-                },
-                Err(e) => {
-                    let error_msg = format!("gdb调试过程中出现执行错误: {}", e);
-                    // 打印错误信息
-                    tracing::error!("{}", error_msg);
-                    tracing::error!("CPU状态:\n{}", target.get_state_ref());
-                    return Err(run_blocking::WaitForStopReasonError::Target(error_msg));
+                // 命中断点时程序仍可继续，不能报告为已终止
+                Event::Break => {
+                    return Ok(run_blocking::Event::TargetStopped(
+                        SingleThreadStopReason::SwBreak(()),
+                    ));
                 }
-            }
-            if mode != ExecMode::Continue {
-                cnt -= 1;
-                if cnt == 0 {
+                Event::WatchWrite(addr) => {
+                    return Ok(run_blocking::Event::TargetStopped(
+                        SingleThreadStopReason::Watch {
+                            tid: (),
+                            kind: WatchKind::Write,
+                            addr: to_usize(addr),
+                        },
+                    ));
+                }
+                Event::WatchRead(addr) => {
                     return Ok(run_blocking::Event::TargetStopped(
-                        SingleThreadStopReason::DoneStep,
+                        SingleThreadStopReason::Watch {
+                            tid: (),
+                            kind: WatchKind::Read,
+                            addr: to_usize(addr),
+                        },
                     ));
                 }
+            },
+            Err(e) => {
+                let error_msg = format!("gdb调试过程中出现执行错误: {}", e);
+                // 打印错误信息
+                tracing::error!("{}", error_msg);
+                tracing::error!("CPU状态:\n{}", target.get_state_ref());
+                return Err(run_blocking::WaitForStopReasonError::Target(error_msg));
             }
-            if delay_cycles >= 1000 {
-                delay_cycles = 0; // 重置延迟计数器
-            } else {
-                delay_cycles += 1;
+        }
+        cnt -= 1;
+        if cnt == 0 {
+            if mode == ExecMode::Continue {
+                // 连续执行达到上限，暂停以便 GDB 会话保持响应
+                info!(
+                    pc = format_args!("{:#x}", target.get_npc()),
+                    "continue 已执行 {} 条指令，暂停执行",
+                    target.gdb_continue_quantum.unwrap_or(usize::MAX)
+                );
+                return Ok(run_blocking::Event::TargetStopped(
+                    SingleThreadStopReason::Signal(Signal::SIGINT),
+                ));
             }
+            return Ok(run_blocking::Event::TargetStopped(
+                SingleThreadStopReason::DoneStep,
+            ));
+        }
+        if delay_cycles >= 1000 {
+            delay_cycles = 0; // 重置延迟计数器
+        } else {
+            delay_cycles += 1;
         }
-        Ok(run_blocking::Event::TargetStopped(
-            SingleThreadStopReason::DoneStep,
-        ))
     }
+    Ok(run_blocking::Event::TargetStopped(
+        SingleThreadStopReason::DoneStep,
+    ))
+}
 
-    fn on_interrupt(
-        _target: &mut Self::Target,
-    ) -> std::result::Result<Option<Self::StopReason>, <Self::Target as Target>::Error> {
-        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+impl Emulator {
+    /// 设置 GDB 每次 continue 最多连续执行的指令数，None 表示不限制
+    ///
+    /// 达到上限后像 Ctrl-C 一样以 SIGINT 停止并报告当前位置，再次 continue 即可继续执行
+    pub fn set_gdb_continue_quantum(&mut self, quantum: Option<usize>) {
+        self.gdb_continue_quantum = quantum.filter(|&n| n > 0);
     }
 }
 
-pub fn wait_for_tcp(port: u16) -> Result<TcpStream> {
-    let sock_addr = format!("localhost:{}", port);
-    info!(port, "等待TCP连接: {}", sock_addr);
-    let sock = TcpListener::bind(sock_addr)?;
+/// 在 addr:port 上监听 GDB 连接，addr 须为 IP 地址（如 127.0.0.1 或 0.0.0.0）
+pub fn bind_gdb_listener(addr: &str, port: u16) -> Result<TcpListener> {
+    let ip: IpAddr = addr
+        .parse()
+        .with_context(|| format!("无效的 GDB 监听地址 '{}'", addr))?;
+    let sock_addr = SocketAddr::new(ip, port);
+    TcpListener::bind(sock_addr).with_context(|| format!("无法在 {} 上监听 GDB 连接", sock_addr))
+}
+
+pub fn wait_for_tcp(addr: &str, port: u16) -> Result<TcpStream> {
+    let sock = bind_gdb_listener(addr, port)?;
+    info!(port, "等待TCP连接: {}", sock.local_addr()?);
     let (stream, addr) = sock.accept()?;
     info!(?addr, "TCP连接已建立");
     Ok(stream)
@@ -167,6 +197,11 @@ impl Target for Emulator {
     ) -> Option<target::ext::breakpoints::BreakpointsOps<'_, Self>> {
         Some(self)
     }
+
+    #[inline(always)]
+    fn support_monitor_cmd(&mut self) -> Option<target::ext::monitor_cmd::MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
 }
 
 impl SingleThreadBase for Emulator {
@@ -249,6 +284,11 @@ impl SingleRegisterAccess<()> for Emulator {
                 buf.copy_from_slice(&reg_value.to_le_bytes());
                 Ok(buf.len())
             }
+            RiscvRegId::Csr(csr) => {
+                // 未实现的 CSR 读作0
+                buf.copy_from_slice(&self.csr_or_zero(csr).to_le_bytes());
+                Ok(buf.len())
+            }
             _ => {
                 // 其他寄存器暂不支持
                 Err(target::TargetError::NonFatal)
@@ -278,6 +318,13 @@ impl SingleRegisterAccess<()> for Emulator {
                     .map_err(|_| target::TargetError::NonFatal)?;
                 Ok(())
             }
+            RiscvRegId::Csr(csr) => {
+                let csr_value =
+                    u64::from_le_bytes(val.try_into().map_err(|_| target::TargetError::NonFatal)?);
+                self.write_csr(csr, csr_value)
+                    .map_err(|_| target::TargetError::NonFatal)?;
+                Ok(())
+            }
             _ => {
                 // 其他寄存器暂不支持
                 Err(target::TargetError::NonFatal)
@@ -339,3 +386,114 @@ impl SingleThreadResume for Emulator {
         Some(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::emu_with_program;
+    use run_blocking::BlockingEventLoop;
+
+    /// 没有输入数据的 GDB 连接
+    struct IdleConnection;
+
+    impl Connection for IdleConnection {
+        type Error = std::io::Error;
+
+        fn write(&mut self, _byte: u8) -> std::result::Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> std::result::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl ConnectionExt for IdleConnection {
+        fn read(&mut self) -> std::result::Result<u8, Self::Error> {
+            Err(std::io::ErrorKind::WouldBlock.into())
+        }
+
+        fn peek(&mut self) -> std::result::Result<Option<u8>, Self::Error> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_single_step_reports_stop_reason() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00158593, // addi a1, a1, 1
+            0x00300513, // li a0, 3
+            0x00100073, // ebreak
+        ]);
+        emu.set_exec_mode(ExecMode::Step);
+        let mut conn: Box<dyn ConnectionExt<Error = std::io::Error>> = Box::new(IdleConnection);
+        let mut step =
+            |emu: &mut Emulator| match EmuGdbEventLoop::wait_for_stop_reason(emu, &mut conn) {
+                Ok(run_blocking::Event::TargetStopped(reason)) => reason,
+                _ => panic!("单步未报告停止原因"),
+            };
+
+        assert!(matches!(step(&mut emu), SingleThreadStopReason::DoneStep));
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+        assert!(matches!(step(&mut emu), SingleThreadStopReason::DoneStep));
+        assert_eq!(emu.get_npc(), base + 8);
+        assert!(matches!(step(&mut emu), SingleThreadStopReason::Exited(3)));
+    }
+
+    #[test]
+    fn test_csr_register_access() {
+        use crate::emulator::instructions::insts::{CSR_MEPC, CSR_MSCRATCH};
+
+        let mut emu = emu_with_program(&[]);
+        emu.state.set_csr(CSR_MEPC, 0x8000_1234).unwrap();
+        let mut buf = [0u8; 8];
+        let len = emu
+            .read_register((), RiscvRegId::Csr(CSR_MEPC), &mut buf)
+            .unwrap();
+        assert_eq!(len, 8);
+        assert_eq!(buf, 0x8000_1234u64.to_le_bytes());
+
+        emu.write_register((), RiscvRegId::Csr(CSR_MSCRATCH), &7u64.to_le_bytes())
+            .unwrap();
+        assert_eq!(emu.state.get_csr(CSR_MSCRATCH).unwrap(), 7);
+
+        // 未实现的 CSR 读作0
+        emu.read_register((), RiscvRegId::Csr(0x7ff), &mut buf).unwrap();
+        assert_eq!(buf, [0; 8]);
+    }
+
+    #[test]
+    fn test_bind_gdb_listener() {
+        let listener = bind_gdb_listener("127.0.0.1", 0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.ip().is_loopback());
+        let client = std::net::TcpStream::connect(addr).unwrap();
+        let (_, peer) = listener.accept().unwrap();
+        assert_eq!(peer, client.local_addr().unwrap());
+
+        let err = bind_gdb_listener("localhost:1234", 0).unwrap_err();
+        assert!(err.to_string().contains("无效的 GDB 监听地址"), "{err}");
+    }
+
+    #[test]
+    fn test_continue_returns_after_quantum() {
+        let mut emu = emu_with_program(&[
+            0x00158593, // loop: addi a1, a1, 1
+            0xffdff06f, // j loop
+        ]);
+        emu.set_gdb_continue_quantum(Some(5000));
+        emu.set_exec_mode(ExecMode::Continue);
+        let mut conn: Box<dyn ConnectionExt<Error = std::io::Error>> = Box::new(IdleConnection);
+
+        for round in 1..=2 {
+            let Ok(run_blocking::Event::TargetStopped(reason)) =
+                EmuGdbEventLoop::wait_for_stop_reason(&mut emu, &mut conn)
+            else {
+                panic!("continue 未在上限内停止");
+            };
+            assert!(matches!(reason, SingleThreadStopReason::Signal(Signal::SIGINT)));
+            assert_eq!(emu.get_reg(11).unwrap(), 2500 * round);
+        }
+    }
+}
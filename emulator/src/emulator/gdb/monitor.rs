@@ -0,0 +1,95 @@
+//! GDB monitor 命令（qRcmd），如 `monitor regs`
+use std::fmt::Write;
+
+use crate::emulator::Emulator;
+use gdbstub::target;
+use gdbstub::target::ext::monitor_cmd::ConsoleOutput;
+
+const HELP: &str = "\
+可用的 monitor 命令:
+  regs    打印 CPU 状态
+  reset   复位寄存器与 CSR，从 boot_pc 重新执行
+  events  打印最近的事件
+  break   列出断点与观察点
+  help    打印本帮助
+";
+
+impl Emulator {
+    /// 执行一条 monitor 命令，返回输出到 GDB 控制台的文本
+    pub fn monitor_command(&mut self, cmd: &str) -> String {
+        let mut out = String::new();
+        match cmd.trim() {
+            "regs" => out = self.state.to_string(),
+            "reset" => {
+                self.reset();
+                let _ = writeln!(out, "已复位, PC 为 {:#x}", self.state.get_pc());
+            }
+            "events" => {
+                if self.event_list.is_empty() {
+                    out.push_str("没有事件\n");
+                }
+                for event in self.event_list.iter() {
+                    let _ = writeln!(out, "{:?}", event);
+                }
+            }
+            "break" => {
+                for addr in self.list_breakpoints() {
+                    let _ = writeln!(out, "断点 {:#x}", addr);
+                }
+                for (addr, kind) in self.list_watchpoints() {
+                    let _ = writeln!(out, "观察点 {:#x} ({:?})", addr, kind);
+                }
+                if out.is_empty() {
+                    out.push_str("没有断点或观察点\n");
+                }
+            }
+            "" | "help" => out.push_str(HELP),
+            other => {
+                let _ = writeln!(out, "未知的 monitor 命令 '{}'", other);
+                out.push_str(HELP);
+            }
+        }
+        out
+    }
+}
+
+impl target::ext::monitor_cmd::MonitorCmd for Emulator {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let output = self.monitor_command(&String::from_utf8_lossy(cmd));
+        out.write_raw(output.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+
+    #[test]
+    fn test_monitor_commands() {
+        let mut emu = emu_with_program(&[
+            0x00100593, // li a1, 1
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.steps(2).unwrap();
+        assert_eq!(emu.get_pc(), TEST_BASE + 4);
+
+        let regs = emu.monitor_command("regs");
+        assert!(regs.contains("PC:"), "{regs}");
+
+        emu.add_breakpoint(TEST_BASE + 8);
+        assert_eq!(emu.monitor_command("break"), "断点 0x80000008\n");
+
+        emu.monitor_command(" reset ");
+        assert_eq!(emu.get_pc(), TEST_BASE);
+        assert!(
+            emu.monitor_command("frobnicate")
+                .starts_with("未知的 monitor 命令")
+        );
+    }
+}
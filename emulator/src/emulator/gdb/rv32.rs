@@ -0,0 +1,286 @@
+//! RV32 模式下的 GDB 目标
+//! gdbstub 的目标架构在编译期确定，RV32 程序通过 Rv32Target 包装模拟器并以 Riscv32 架构调试，
+//! 寄存器与地址在 32 位与模拟器内部的 64 位表示之间转换，其余操作转发给 Riscv64 目标的实现
+use gdbstub::common::Signal;
+use gdbstub::conn::{Connection, ConnectionExt};
+use gdbstub::stub::{SingleThreadStopReason, run_blocking};
+use gdbstub::target::ext::base::single_register_access::SingleRegisterAccess;
+use gdbstub::target::ext::base::singlethread::{
+    SingleThreadBase, SingleThreadRangeStepping, SingleThreadResume, SingleThreadSingleStep,
+};
+use gdbstub::target::ext::breakpoints::{Breakpoints, HwWatchpoint, SwBreakpoint};
+use gdbstub::target::ext::monitor_cmd::{ConsoleOutput, MonitorCmd};
+use gdbstub::target::{self, Target};
+use gdbstub_arch::riscv::reg::id::RiscvRegId;
+
+use crate::emulator::Emulator;
+
+/// 以 Riscv32 架构调试的模拟器
+pub struct Rv32Target(pub Emulator);
+
+pub enum EmuGdbEventLoop32 {}
+
+impl run_blocking::BlockingEventLoop for EmuGdbEventLoop32 {
+    type Target = Rv32Target;
+
+    type Connection = Box<dyn ConnectionExt<Error = std::io::Error>>;
+
+    type StopReason = SingleThreadStopReason<u32>;
+
+    fn wait_for_stop_reason(
+        target: &mut Self::Target,
+        conn: &mut Self::Connection,
+    ) -> std::result::Result<
+        run_blocking::Event<Self::StopReason>,
+        run_blocking::WaitForStopReasonError<
+            <Self::Target as Target>::Error,
+            <Self::Connection as Connection>::Error,
+        >,
+    > {
+        super::wait_for_stop(&mut target.0, conn, |addr| addr as u32)
+    }
+
+    fn on_interrupt(
+        _target: &mut Self::Target,
+    ) -> std::result::Result<Option<Self::StopReason>, <Self::Target as Target>::Error> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
+
+/// 32 位的寄存器编号转换为 Riscv64 目标使用的寄存器编号
+fn reg_id64(reg_id: RiscvRegId<u32>) -> Option<RiscvRegId<u64>> {
+    match reg_id {
+        RiscvRegId::Gpr(reg) => Some(RiscvRegId::Gpr(reg)),
+        RiscvRegId::Fpr(reg) => Some(RiscvRegId::Fpr(reg)),
+        RiscvRegId::Pc => Some(RiscvRegId::Pc),
+        RiscvRegId::Csr(csr) => Some(RiscvRegId::Csr(csr)),
+        RiscvRegId::Priv => Some(RiscvRegId::Priv),
+        _ => None,
+    }
+}
+
+impl Target for Rv32Target {
+    type Arch = gdbstub_arch::riscv::Riscv32;
+    type Error = String;
+
+    #[inline(always)]
+    fn base_ops(&mut self) -> target::ext::base::BaseOps<'_, Self::Arch, Self::Error> {
+        target::ext::base::BaseOps::SingleThread(self)
+    }
+
+    #[inline(always)]
+    fn support_breakpoints(
+        &mut self,
+    ) -> Option<target::ext::breakpoints::BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_monitor_cmd(&mut self) -> Option<target::ext::monitor_cmd::MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadBase for Rv32Target {
+    fn read_registers(
+        &mut self,
+        regs: &mut <Self::Arch as gdbstub::arch::Arch>::Registers,
+    ) -> target::TargetResult<(), Self> {
+        let state = self.0.get_state_ref();
+        regs.pc = state.get_pc() as u32;
+        for (dst, &src) in regs.x.iter_mut().zip(state.get_regs()) {
+            *dst = src as u32;
+        }
+        Ok(())
+    }
+
+    fn write_registers(
+        &mut self,
+        regs: &<Self::Arch as gdbstub::arch::Arch>::Registers,
+    ) -> target::TargetResult<(), Self> {
+        let mut regs64 = gdbstub_arch::riscv::reg::RiscvCoreRegs::<u64> {
+            pc: regs.pc.into(),
+            ..Default::default()
+        };
+        for (dst, &src) in regs64.x.iter_mut().zip(regs.x.iter()) {
+            *dst = src.into();
+        }
+        self.0.write_registers(&regs64)
+    }
+
+    fn read_addrs(
+        &mut self,
+        start_addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        data: &mut [u8],
+    ) -> target::TargetResult<usize, Self> {
+        self.0.read_addrs(start_addr.into(), data)
+    }
+
+    fn write_addrs(
+        &mut self,
+        start_addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        data: &[u8],
+    ) -> target::TargetResult<(), Self> {
+        self.0.write_addrs(start_addr.into(), data)
+    }
+
+    #[inline(always)]
+    fn support_resume(
+        &mut self,
+    ) -> Option<target::ext::base::singlethread::SingleThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleRegisterAccess<()> for Rv32Target {
+    fn read_register(
+        &mut self,
+        tid: (),
+        reg_id: <Self::Arch as gdbstub::arch::Arch>::RegId,
+        buf: &mut [u8],
+    ) -> target::TargetResult<usize, Self> {
+        let reg_id = reg_id64(reg_id).ok_or(target::TargetError::NonFatal)?;
+        let mut value = [0u8; 8];
+        self.0.read_register(tid, reg_id, &mut value)?;
+        // 只报告低32位
+        buf.copy_from_slice(&value[..4]);
+        Ok(buf.len())
+    }
+
+    fn write_register(
+        &mut self,
+        tid: (),
+        reg_id: <Self::Arch as gdbstub::arch::Arch>::RegId,
+        val: &[u8],
+    ) -> target::TargetResult<(), Self> {
+        let reg_id = reg_id64(reg_id).ok_or(target::TargetError::NonFatal)?;
+        let value = u32::from_le_bytes(val.try_into().map_err(|_| target::TargetError::NonFatal)?);
+        self.0.write_register(tid, reg_id, &u64::from(value).to_le_bytes())
+    }
+}
+
+impl SingleThreadSingleStep for Rv32Target {
+    fn step(&mut self, signal: Option<Signal>) -> std::result::Result<(), Self::Error> {
+        SingleThreadSingleStep::step(&mut self.0, signal)
+    }
+}
+
+impl SingleThreadRangeStepping for Rv32Target {
+    fn resume_range_step(
+        &mut self,
+        start: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        end: <Self::Arch as gdbstub::arch::Arch>::Usize,
+    ) -> std::result::Result<(), Self::Error> {
+        self.0.resume_range_step(start.into(), end.into())
+    }
+}
+
+impl SingleThreadResume for Rv32Target {
+    fn resume(&mut self, signal: Option<Signal>) -> std::result::Result<(), Self::Error> {
+        self.0.resume(signal)
+    }
+
+    #[inline(always)]
+    fn support_single_step(
+        &mut self,
+    ) -> Option<target::ext::base::singlethread::SingleThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_range_step(
+        &mut self,
+    ) -> Option<target::ext::base::singlethread::SingleThreadRangeSteppingOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl Breakpoints for Rv32Target {
+    #[inline(always)]
+    fn support_sw_breakpoint(
+        &mut self,
+    ) -> Option<target::ext::breakpoints::SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    #[inline(always)]
+    fn support_hw_watchpoint(
+        &mut self,
+    ) -> Option<target::ext::breakpoints::HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for Rv32Target {
+    fn add_sw_breakpoint(
+        &mut self,
+        addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        kind: <Self::Arch as gdbstub::arch::Arch>::BreakpointKind,
+    ) -> target::TargetResult<bool, Self> {
+        self.0.add_sw_breakpoint(addr.into(), kind)
+    }
+
+    fn remove_sw_breakpoint(
+        &mut self,
+        addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        kind: <Self::Arch as gdbstub::arch::Arch>::BreakpointKind,
+    ) -> target::TargetResult<bool, Self> {
+        self.0.remove_sw_breakpoint(addr.into(), kind)
+    }
+}
+
+impl HwWatchpoint for Rv32Target {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        len: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        kind: target::ext::breakpoints::WatchKind,
+    ) -> target::TargetResult<bool, Self> {
+        self.0.add_hw_watchpoint(addr.into(), len.into(), kind)
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        len: <Self::Arch as gdbstub::arch::Arch>::Usize,
+        kind: target::ext::breakpoints::WatchKind,
+    ) -> target::TargetResult<bool, Self> {
+        self.0.remove_hw_watchpoint(addr.into(), len.into(), kind)
+    }
+}
+
+impl MonitorCmd for Rv32Target {
+    fn handle_monitor_cmd(&mut self, cmd: &[u8], out: ConsoleOutput<'_>) -> Result<(), Self::Error> {
+        self.0.handle_monitor_cmd(cmd, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::const_values::Xlen;
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config};
+
+    #[test]
+    fn test_rv32_register_access() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.xlen = Xlen::X32;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &[0xfff00593]); // li a1, -1
+        emu.step().unwrap();
+        let mut target = Rv32Target(emu);
+
+        let mut regs = Default::default();
+        target.read_registers(&mut regs).unwrap();
+        assert_eq!(regs.x[11], 0xffff_ffff);
+        assert_eq!(regs.pc, TEST_BASE as u32);
+
+        let mut buf = [0u8; 4];
+        assert_eq!(target.read_register((), RiscvRegId::Gpr(11), &mut buf).unwrap(), 4);
+        assert_eq!(buf, 0xffff_ffffu32.to_le_bytes());
+
+        // 写入的32位值在模拟器内部按符号扩展保存
+        target.write_register((), RiscvRegId::Gpr(12), &0x8000_0000u32.to_le_bytes()).unwrap();
+        assert_eq!(target.0.get_reg(12).unwrap(), 0xffff_ffff_8000_0000);
+    }
+}
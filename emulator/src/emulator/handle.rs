@@ -0,0 +1,288 @@
+//! 后台运行模块
+//! 在独立线程上运行模拟器，通过命令通道控制运行/暂停/单步/停止，并可随时查询状态。
+//! Emulator 内部使用 Rc/RefCell，不能跨线程移动，因此模拟器在后台线程上创建并始终留在该线程
+
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+use super::{Emulator, Event, ExecState, StepOutcome};
+
+/// 运行状态下每批执行的指令数，批与批之间处理命令
+const RUN_BATCH: usize = 1024;
+
+/// 控制命令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorCommand {
+    /// 连续运行，直到收到 Pause/Step、遇到事件或程序结束
+    Run,
+    /// 暂停运行
+    Pause,
+    /// 暂停并执行一条指令
+    Step,
+    /// 结束后台线程
+    Stop,
+}
+
+/// 后台模拟器的状态快照
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmulatorStatus {
+    pub exec_state: ExecState,
+    /// 是否处于连续运行中
+    pub running: bool,
+    pub pc: u64,
+    pub registers: [u64; 32],
+    /// 最近一次使运行停下的事件（断点、观察点等）
+    pub last_event: Option<Event>,
+    pub exit_code: Option<u8>,
+    /// 执行出错时的错误信息，出错后不再继续运行
+    pub error: Option<String>,
+}
+
+enum Request {
+    Command(EmulatorCommand),
+    Query(Sender<EmulatorStatus>),
+}
+
+/// 在后台线程上运行的模拟器
+pub struct EmulatorHandle {
+    requests: Sender<Request>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EmulatorHandle {
+    /// 启动后台线程，并在该线程上调用 build 创建模拟器
+    pub fn spawn<F>(build: F) -> Result<Self>
+    where
+        F: FnOnce() -> Result<Emulator> + Send + 'static,
+    {
+        let (requests, receiver) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("emulator".to_string())
+            .spawn(move || match build() {
+                Ok(emu) => {
+                    let _ = ready_tx.send(Ok(()));
+                    Worker::new(emu).run(receiver);
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(format!("{:#}", e)));
+                }
+            })?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                requests,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(anyhow::anyhow!("后台模拟器创建失败: {}", e)),
+            Err(_) => Err(anyhow::anyhow!("后台模拟器线程意外退出")),
+        }
+    }
+
+    /// 发送控制命令
+    pub fn send(&self, command: EmulatorCommand) -> Result<()> {
+        self.requests
+            .send(Request::Command(command))
+            .map_err(|_| anyhow::anyhow!("后台模拟器线程已结束"))
+    }
+
+    /// 查询当前状态；此前发送的命令都已处理完毕
+    pub fn status(&self) -> Result<EmulatorStatus> {
+        let (tx, rx) = mpsc::channel();
+        self.requests
+            .send(Request::Query(tx))
+            .map_err(|_| anyhow::anyhow!("后台模拟器线程已结束"))?;
+        rx.recv().map_err(|_| anyhow::anyhow!("后台模拟器线程已结束"))
+    }
+
+    /// 停止后台线程并等待其结束
+    pub fn join(mut self) -> Result<()> {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        let _ = self.requests.send(Request::Command(EmulatorCommand::Stop));
+        thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("后台模拟器线程发生 panic"))
+    }
+}
+
+impl Drop for EmulatorHandle {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// 后台线程上的执行循环
+struct Worker {
+    emu: Emulator,
+    running: bool,
+    last_event: Option<Event>,
+    error: Option<String>,
+}
+
+impl Worker {
+    fn new(emu: Emulator) -> Self {
+        Self {
+            emu,
+            running: false,
+            last_event: None,
+            error: None,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<Request>) {
+        loop {
+            // 运行中不阻塞地检查命令，暂停时阻塞等待
+            let request = if self.running {
+                match receiver.try_recv() {
+                    Ok(request) => Some(request),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            } else {
+                match receiver.recv() {
+                    Ok(request) => Some(request),
+                    Err(_) => return,
+                }
+            };
+
+            match request {
+                Some(Request::Command(EmulatorCommand::Run)) => {
+                    self.running = self.can_execute();
+                }
+                Some(Request::Command(EmulatorCommand::Pause)) => self.running = false,
+                Some(Request::Command(EmulatorCommand::Step)) => {
+                    self.running = false;
+                    if self.can_execute() {
+                        self.execute(1);
+                    }
+                }
+                Some(Request::Command(EmulatorCommand::Stop)) => return,
+                Some(Request::Query(reply)) => {
+                    let _ = reply.send(self.status());
+                }
+                None => (),
+            }
+
+            if self.running {
+                self.execute(RUN_BATCH);
+            }
+        }
+    }
+
+    fn can_execute(&self) -> bool {
+        self.error.is_none() && self.emu.get_exec_state() != ExecState::End
+    }
+
+    /// 最多执行 n 条指令，遇到事件、程序结束或出错时停止运行
+    fn execute(&mut self, n: usize) {
+        for _ in 0..n {
+            match self.emu.step_outcome() {
+                Ok(StepOutcome::Continued | StepOutcome::Interrupt(_) | StepOutcome::Branch) => (),
+                Ok(StepOutcome::Event(event)) => {
+                    self.last_event = Some(event);
+                    self.running = false;
+                    return;
+                }
+                Ok(StepOutcome::Halted) => {
+                    self.running = false;
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!("后台模拟器执行出错: {:#}", e);
+                    self.error = Some(format!("{:#}", e));
+                    self.running = false;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn status(&self) -> EmulatorStatus {
+        EmulatorStatus {
+            exec_state: self.emu.get_exec_state(),
+            running: self.running,
+            pc: self.emu.get_pc(),
+            registers: *self.emu.get_regs(),
+            last_event: self.last_event,
+            exit_code: self.emu.exit_code(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+
+    #[test]
+    fn test_run_pause_step_over_channel() {
+        let handle = EmulatorHandle::spawn(|| {
+            Ok(emu_with_program(&[
+                0x00158593, // addi a1, a1, 1
+                0xffdff06f, // j -4
+            ]))
+        })
+        .unwrap();
+
+        let status = handle.status().unwrap();
+        assert!(!status.running);
+        assert_eq!(status.registers[11], 0);
+
+        handle.send(EmulatorCommand::Step).unwrap();
+        let status = handle.status().unwrap();
+        assert_eq!(status.registers[11], 1);
+        assert_eq!(status.pc, TEST_BASE);
+
+        handle.send(EmulatorCommand::Run).unwrap();
+        assert!(handle.status().unwrap().running);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        handle.send(EmulatorCommand::Pause).unwrap();
+        let paused = handle.status().unwrap();
+        assert!(!paused.running);
+        assert_eq!(paused.exec_state, ExecState::Idle);
+        assert!(paused.registers[11] > 1);
+
+        // 暂停期间状态保持不变
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(handle.status().unwrap(), paused);
+
+        handle.send(EmulatorCommand::Step).unwrap();
+        assert_ne!(handle.status().unwrap().pc, paused.pc);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_run_to_completion() {
+        let handle = EmulatorHandle::spawn(|| {
+            Ok(emu_with_program(&[
+                0x00300513, // li a0, 3
+                0x00100073, // ebreak
+            ]))
+        })
+        .unwrap();
+        handle.send(EmulatorCommand::Run).unwrap();
+        let status = loop {
+            let status = handle.status().unwrap();
+            if !status.running {
+                break status;
+            }
+        };
+        assert_eq!(status.exec_state, ExecState::End);
+        assert_eq!(status.exit_code, Some(3));
+        assert!(status.error.is_none());
+    }
+
+    #[test]
+    fn test_spawn_reports_build_error() {
+        let result = EmulatorHandle::spawn(|| Err(anyhow::anyhow!("配置无效")));
+        assert!(result.err().unwrap().to_string().contains("配置无效"));
+    }
+}
@@ -0,0 +1,210 @@
+//! 指令直方图：按编码格式与指令名统计执行的指令条数，并可与其他运行统计一起导出为 JSON
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use super::Emulator;
+use super::instructions::is_compressed;
+
+/// RISC-V 指令编码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InstFormat {
+    R,
+    /// 浮点融合乘加使用的四操作数格式
+    R4,
+    I,
+    S,
+    B,
+    U,
+    J,
+    /// 16 位压缩指令
+    Compressed,
+}
+
+/// 由 opcode 推导指令的编码格式，未知 opcode 返回 None
+pub fn inst_format(inst: u32) -> Option<InstFormat> {
+    if is_compressed(inst) {
+        return Some(InstFormat::Compressed);
+    }
+    Some(match inst & 0x7f {
+        // op, op-32, amo, op-fp
+        0b0110011 | 0b0111011 | 0b0101111 | 0b1010011 => InstFormat::R,
+        // fmadd/fmsub/fnmsub/fnmadd
+        0b1000011 | 0b1000111 | 0b1001011 | 0b1001111 => InstFormat::R4,
+        // load, load-fp, misc-mem, op-imm, op-imm-32, jalr, system
+        0b0000011 | 0b0000111 | 0b0001111 | 0b0010011 | 0b0011011 | 0b1100111 | 0b1110011 => {
+            InstFormat::I
+        }
+        // store, store-fp
+        0b0100011 | 0b0100111 => InstFormat::S,
+        0b1100011 => InstFormat::B,
+        // lui, auipc
+        0b0110111 | 0b0010111 => InstFormat::U,
+        0b1101111 => InstFormat::J,
+        _ => return None,
+    })
+}
+
+/// 按指令名统计的执行条数，供 `Emulator::enable_opcode_histogram` 与指令频率追踪器共用
+#[derive(Debug, Clone, Default)]
+pub struct OpcodeHistogram {
+    counts: HashMap<&'static str, u64>,
+}
+
+impl OpcodeHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次执行
+    #[inline(always)]
+    pub fn record(&mut self, name: &'static str) {
+        *self.counts.entry(name).or_default() += 1;
+    }
+
+    /// 指令名 -> 执行条数
+    pub fn counts(&self) -> &HashMap<&'static str, u64> {
+        &self.counts
+    }
+
+    /// 按执行次数降序排列的直方图，次数相同时按指令名排序
+    pub fn sorted(&self) -> Vec<(&'static str, u64)> {
+        let mut histogram: Vec<_> = self.counts.iter().map(|(&name, &count)| (name, count)).collect();
+        histogram.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        histogram
+    }
+}
+
+/// 导出为 JSON 的运行统计
+#[derive(Serialize)]
+struct RunStats {
+    /// 已退休的指令数
+    minstret: u64,
+    mcycle: u64,
+    decode_hit_rate: f64,
+    exit_code: Option<u8>,
+    /// 指令名 -> 执行条数
+    opcodes: BTreeMap<&'static str, u64>,
+    /// 编码格式 -> 执行条数
+    formats: BTreeMap<String, u64>,
+}
+
+impl Emulator {
+    /// 开始按格式统计执行的指令，已有的统计会被清空
+    pub fn enable_format_histogram(&mut self) {
+        self.format_histogram = Some(HashMap::new());
+    }
+
+    /// 各格式已执行的指令条数，未启用时返回空表
+    pub fn format_histogram(&self) -> HashMap<InstFormat, u64> {
+        self.format_histogram.clone().unwrap_or_default()
+    }
+
+    /// 开始按指令名统计执行的指令，已有的统计会被清空
+    pub fn enable_opcode_histogram(&mut self) {
+        self.opcode_histogram = Some(OpcodeHistogram::new());
+    }
+
+    /// 各指令已执行的条数，未启用时返回空表
+    pub fn opcode_histogram(&self) -> HashMap<&'static str, u64> {
+        self.opcode_histogram
+            .as_ref()
+            .map(|histogram| histogram.counts().clone())
+            .unwrap_or_default()
+    }
+
+    /// 将运行统计序列化为 JSON，供 CI 等工具读取；直方图未启用时对应的表为空
+    pub fn stats_json(&self) -> String {
+        let stats = RunStats {
+            minstret: self.instret(),
+            mcycle: self.cycles(),
+            decode_hit_rate: self.get_hit_rate(),
+            exit_code: self.exit_code(),
+            opcodes: self.opcode_histogram().into_iter().collect(),
+            formats: self
+                .format_histogram()
+                .into_iter()
+                .map(|(format, count)| (format!("{:?}", format), count))
+                .collect(),
+        };
+        serde_json::to_string_pretty(&stats).expect("运行统计序列化失败")
+    }
+
+    /// 记录一条已译码指令的名称
+    #[inline(always)]
+    pub(super) fn record_opcode(&mut self, name: &'static str) {
+        if let Some(histogram) = &mut self.opcode_histogram {
+            histogram.record(name);
+        }
+    }
+
+    /// 记录一条已译码的指令
+    #[inline(always)]
+    pub(super) fn record_format(&mut self, inst: u32) {
+        if let Some(histogram) = &mut self.format_histogram
+            && let Some(format) = inst_format(inst)
+        {
+            *histogram.entry(format).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::emu_with_program;
+
+    #[test]
+    fn test_format_histogram() {
+        let mut emu = emu_with_program(&[
+            0x00100593, // li a1, 1
+            0x00200613, // li a2, 2
+            0x00c586b3, // add a3, a1, a2
+            0x02c58733, // mul a4, a1, a2
+            0x00000797, // auipc a5, 0
+            0x04d7b023, // sd a3, 64(a5)
+            0x00058463, // beqz a1, 8
+            0x0080006f, // j 8
+            0x00000013, // nop (跳过)
+            0x00100073, // ebreak
+        ]);
+        emu.enable_format_histogram();
+        emu.steps(100).unwrap();
+
+        let histogram = emu.format_histogram();
+        assert_eq!(histogram.get(&InstFormat::I), Some(&3));
+        assert_eq!(histogram.get(&InstFormat::R), Some(&2));
+        assert_eq!(histogram.get(&InstFormat::U), Some(&1));
+        assert_eq!(histogram.get(&InstFormat::S), Some(&1));
+        assert_eq!(histogram.get(&InstFormat::B), Some(&1));
+        assert_eq!(histogram.get(&InstFormat::J), Some(&1));
+        assert_eq!(histogram.get(&InstFormat::Compressed), None);
+    }
+
+    #[test]
+    fn test_stats_json() {
+        let mut emu = emu_with_program(&[
+            0x00100593, // li a1, 1
+            0x00200613, // li a2, 2
+            0x00c586b3, // add a3, a1, a2
+            0x02c58733, // mul a4, a1, a2
+            0x00058463, // beqz a1, 8
+            0x0080006f, // j 8
+            0x00000013, // nop (跳过)
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.enable_opcode_histogram();
+        emu.steps(100).unwrap();
+
+        let stats: serde_json::Value = serde_json::from_str(&emu.stats_json()).unwrap();
+        assert_eq!(stats["minstret"], 8);
+        assert_eq!(stats["mcycle"], 8);
+        assert_eq!(stats["exit_code"], 0);
+        assert_eq!(stats["opcodes"]["addi"], 3);
+        assert_eq!(stats["opcodes"]["mul"], 1);
+        assert_eq!(stats["opcodes"]["ebreak"], 1);
+        assert_eq!(stats["formats"], serde_json::json!({}));
+    }
+}
@@ -0,0 +1,271 @@
+//! 宿主调用模块
+//! 客户程序通过 hostcall 设备向宿主报告标记、检查点或断言，结果按顺序收集，
+//! 供自检测试程序的宿主侧检查；assert 设备报告的断言失败会带着失败信息结束运行
+
+use anyhow::Result;
+use ::hostcall::{CMD_ASSERT_REG, CMD_CHECKPOINT, CMD_MARKER};
+use mmio_trait::{AssertionRequest, HostRequest};
+
+use super::Emulator;
+
+/// 一次宿主调用的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hostcall {
+    /// 打印标记
+    Marker(u64),
+    /// 检查点
+    Checkpoint(u64),
+    /// 断言寄存器 x[reg] 等于 expected，actual 为发出请求时的实际值
+    AssertReg { reg: u64, expected: u64, actual: u64 },
+    /// 断言的寄存器编号无效，视为断言失败
+    AssertInvalidReg { reg: u64, expected: u64 },
+    /// 未知命令
+    Unknown(HostRequest),
+}
+
+/// 宿主调用结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostcallResult {
+    /// 发出请求的指令地址
+    pub pc: u64,
+    pub call: Hostcall,
+}
+
+impl HostcallResult {
+    /// 断言是否成立；其他调用总是视为成立
+    pub fn passed(&self) -> bool {
+        match self.call {
+            Hostcall::AssertReg {
+                expected, actual, ..
+            } => expected == actual,
+            Hostcall::AssertInvalidReg { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+/// 失败信息的最大长度（字节），超出部分被截断
+const MAX_ASSERT_MESSAGE_LEN: usize = 256;
+
+/// 客户程序通过 assert 设备报告的断言失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionFailure {
+    /// 触发检查的指令地址
+    pub pc: u64,
+    pub message: String,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl Emulator {
+    /// 最近一次断言失败，没有断言失败时返回 None
+    pub fn last_assertion_failure(&self) -> Option<&AssertionFailure> {
+        self.last_assertion_failure.as_ref()
+    }
+
+    /// 按发生顺序返回所有宿主调用结果
+    pub fn hostcall_results(&self) -> &[HostcallResult] {
+        &self.hostcall_results
+    }
+
+    /// 处理设备转交的宿主请求
+    pub(super) fn handle_host_request(&mut self, pc: u64, request: HostRequest) -> Result<()> {
+        let call = match request.command {
+            CMD_MARKER => {
+                tracing::info!("PC {:#x} 宿主标记: {:#x}", pc, request.value);
+                Hostcall::Marker(request.value)
+            }
+            CMD_CHECKPOINT => {
+                tracing::info!("PC {:#x} 到达检查点 {}", pc, request.value);
+                Hostcall::Checkpoint(request.value)
+            }
+            CMD_ASSERT_REG => {
+                // 寄存器编号由客户程序给出，无效时记为失败的调用而不是宿主错误
+                let Ok(actual) = self.state.get_reg(request.arg) else {
+                    tracing::error!("PC {:#x} 断言的寄存器编号无效: x{}", pc, request.arg);
+                    self.hostcall_results.push(HostcallResult {
+                        pc,
+                        call: Hostcall::AssertInvalidReg {
+                            reg: request.arg,
+                            expected: request.value,
+                        },
+                    });
+                    return Ok(());
+                };
+                if actual != request.value {
+                    tracing::error!(
+                        "PC {:#x} 断言失败: x{} = {:#x}，期望 {:#x}",
+                        pc,
+                        request.arg,
+                        actual,
+                        request.value
+                    );
+                }
+                Hostcall::AssertReg {
+                    reg: request.arg,
+                    expected: request.value,
+                    actual,
+                }
+            }
+            _ => {
+                tracing::warn!("PC {:#x} 未知的宿主调用命令: {}", pc, request.command);
+                Hostcall::Unknown(request)
+            }
+        };
+        self.hostcall_results.push(HostcallResult { pc, call });
+        Ok(())
+    }
+
+    /// 记录设备报告的断言失败，结束运行由设备的退出请求完成
+    pub(super) fn handle_assertion_failure(&mut self, pc: u64, request: AssertionRequest) {
+        let message = self.read_guest_string(request.message_ptr);
+        tracing::error!(
+            "PC {:#x} 断言失败: {}（期望 {:#x}，实际 {:#x}）",
+            pc,
+            message,
+            request.expected,
+            request.actual
+        );
+        self.last_assertion_failure = Some(AssertionFailure {
+            pc,
+            message,
+            expected: request.expected,
+            actual: request.actual,
+        });
+    }
+
+    /// 读取主内存中以 NUL 结尾的字符串，地址为0或不在主内存中时返回空串，到达主内存末尾时截止
+    ///
+    /// 不经过总线读取，指向设备寄存器的地址不会触发设备的读副作用
+    fn read_guest_string(&self, addr: u64) -> String {
+        let memory = &self.state.memory;
+        if addr == 0 || !memory.is_mem_region(addr) {
+            return String::new();
+        }
+        let bytes: Vec<u8> = memory.ram()[(addr - memory.memory_base()) as usize..]
+            .iter()
+            .take(MAX_ASSERT_MESSAGE_LEN)
+            .take_while(|&&byte| byte != 0)
+            .copied()
+            .collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config, test_device};
+
+    #[test]
+    fn test_guest_checkpoint_and_assert() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("hostcall0", "hostcall", 0x1000_0400, 0x20));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x40028293, // addi t0, t0, 0x400
+                0x02a00313, // li t1, 42
+                0x0062b423, // sd t1, 8(t0)       值 = 42
+                0x00200393, // li t2, 2
+                0x0072a823, // sw t2, 16(t0)      检查点 42
+                0x00600393, // li t2, 6
+                0x0072b023, // sd t2, 0(t0)       参数 = x6
+                0x00300393, // li t2, 3
+                0x0072a823, // sw t2, 16(t0)      断言 x6 == 42
+                0x02800393, // li t2, 40
+                0x0072b023, // sd t2, 0(t0)       参数 = x40
+                0x00300393, // li t2, 3
+                0x0072a823, // sw t2, 16(t0)      断言无效寄存器
+                0x00100073, // ebreak
+            ],
+        );
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), crate::emulator::ExecState::End);
+
+        let results = emu.hostcall_results();
+        assert_eq!(
+            results[0],
+            HostcallResult {
+                pc: TEST_BASE + 20,
+                call: Hostcall::Checkpoint(42),
+            }
+        );
+        assert_eq!(
+            results[1].call,
+            Hostcall::AssertReg {
+                reg: 6,
+                expected: 42,
+                actual: 42,
+            }
+        );
+        assert!(results[..2].iter().all(HostcallResult::passed));
+        assert_eq!(
+            results[2].call,
+            Hostcall::AssertInvalidReg {
+                reg: 40,
+                expected: 42,
+            }
+        );
+        assert!(!results[2].passed());
+    }
+
+    #[test]
+    fn test_failed_assertion_halts_with_message() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("assert0", "assert", 0x1000_0500, 0x20));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x00000517, // auipc a0, 0
+                0x04050513, // addi a0, a0, 64   信息地址
+                0x100002b7, // lui t0, 0x10000
+                0x50028293, // addi t0, t0, 0x500
+                0x00a2b023, // sd a0, 0(t0)
+                0x00300313, // li t1, 3
+                0x0062b423, // sd t1, 8(t0)      期望值 = 3
+                0x00400313, // li t1, 4
+                0x0062b823, // sd t1, 16(t0)     实际值 = 4
+                0x0062ac23, // sw t1, 24(t0)     检查
+                0x00900393, // li t2, 9          不会执行
+                0x00100073, // ebreak
+            ],
+        );
+        emu.write_memory(TEST_BASE + 64, b"counter mismatch\0").unwrap();
+        emu.steps(100).unwrap();
+
+        assert_eq!(emu.get_exec_state(), crate::emulator::ExecState::End);
+        assert_eq!(emu.exit_code(), Some(assertion::ASSERT_EXIT_CODE));
+        assert_eq!(emu.get_reg(7).unwrap(), 0);
+        assert_eq!(
+            emu.last_assertion_failure(),
+            Some(&AssertionFailure {
+                pc: TEST_BASE + 36,
+                message: "counter mismatch".to_string(),
+                expected: 3,
+                actual: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_assertion_message_is_read_from_main_memory_only() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("hostcall0", "hostcall", 0x1000_0400, 0x20));
+        let mut emu = emu_from(config, &device_file);
+        emu.write_memory(TEST_BASE, b"ok\0").unwrap();
+        assert_eq!(emu.read_guest_string(TEST_BASE), "ok");
+
+        // 设备地址与主内存之外的地址都不读取
+        assert_eq!(emu.read_guest_string(0x1000_0400), "");
+        assert_eq!(emu.read_guest_string(0x10), "");
+
+        // 没有结尾 NUL 时在主内存末尾截止
+        let end = TEST_BASE + emu.state.memory.ram().len() as u64;
+        emu.write_memory(end - 3, b"end").unwrap();
+        assert_eq!(emu.read_guest_string(end - 3), "end");
+    }
+}
@@ -0,0 +1,39 @@
+//! 以 u32 为键的译码表，哈希函数在运行时由 [`DecodeHasher`] 选择
+
+use nohash_hasher::BuildNoHashHasher;
+use rustc_hash::FxHashMap;
+use std::collections::HashMap;
+
+use crate::const_values::DecodeHasher;
+
+pub enum DecodeMap<V> {
+    NoHash(HashMap<u32, V, BuildNoHashHasher<u32>>),
+    Fx(FxHashMap<u32, V>),
+}
+
+impl<V> DecodeMap<V> {
+    pub fn new(hasher: DecodeHasher) -> Self {
+        match hasher {
+            DecodeHasher::NoHash => DecodeMap::NoHash(HashMap::default()),
+            DecodeHasher::Fx => DecodeMap::Fx(FxHashMap::default()),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: u32) -> Option<&V> {
+        match self {
+            DecodeMap::NoHash(map) => map.get(&key),
+            DecodeMap::Fx(map) => map.get(&key),
+        }
+    }
+
+    pub fn entry_or_default(&mut self, key: u32) -> &mut V
+    where
+        V: Default,
+    {
+        match self {
+            DecodeMap::NoHash(map) => map.entry(key).or_default(),
+            DecodeMap::Fx(map) => map.entry(key).or_default(),
+        }
+    }
+}
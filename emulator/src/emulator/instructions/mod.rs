@@ -1,17 +1,25 @@
-mod insts;
+mod decode_map;
+pub(crate) mod insts;
+mod privileged;
+mod rv32;
 mod rv64a;
+mod rv64c;
+mod rv64d;
+mod rv64f;
 mod rv64i;
 mod rv64m;
+mod softfloat;
+mod stubs;
+mod zicsr;
 // clock_cache removed: instruction cache not needed
 
 use anyhow::{Ok, Result};
-use nohash_hasher::BuildNoHashHasher;
-use std::collections::HashMap;
 use std::rc::Rc;
 
-use crate::const_values::EmuConfig;
+use crate::const_values::{EmuConfig, Xlen};
 use crate::emulator::Emulator;
 use crate::utils::bit_utils::{BitSlice, sign_extend_64};
+use decode_map::DecodeMap;
 
 #[derive(Debug, Clone, Copy, Hash)]
 pub struct Instruction {
@@ -26,7 +34,11 @@ pub struct InstDecoder {
     compressed_instructions: Vec<Instruction>,
     #[allow(unused)]
     config: Rc<EmuConfig>,
-    opcode_map: HashMap<u32, Vec<&'static Instruction>, BuildNoHashHasher<u32>>,
+    opcode_map: DecodeMap<Vec<&'static Instruction>>,
+    /// 直接映射的译码缓存：(指令字, 译码结果)，为空表示禁用
+    cache: Vec<Option<(u32, Instruction)>>,
+    cache_hits: u64,
+    cache_misses: u64,
 }
 
 const MASK_OPCODE: u32 = 0x7F;
@@ -36,16 +48,68 @@ pub fn is_compressed(inst: u32) -> bool {
     inst & 0b11 != 0b11
 }
 
+/// 跳转目标是否未对齐：启用 C 扩展时按 2 字节对齐，否则按 4 字节对齐
 #[inline(always)]
-pub fn is_inst_addr_misaligned(pc: u64) -> bool {
-    pc & 0b11 != 0
+pub fn is_inst_addr_misaligned(emu: &Emulator, pc: u64) -> bool {
+    let mask = if emu.config.inst_set.c_ext { 0b1 } else { 0b11 };
+    pc & mask != 0
+}
+
+/// 指令是否写内存（store、AMO 与压缩 store），用于区分加载与存储访问错误
+///
+/// lr 只读内存，其余 AMO 按规范报告存储访问错误
+#[inline(always)]
+pub fn is_store(inst: u32) -> bool {
+    if is_compressed(inst) {
+        // c.fsd/c.sw/c.sd 与 c.fsdsp/c.swsp/c.sdsp：象限 00/10 的 funct3 为 101/110/111
+        return inst & 0b11 != 0b01 && inst.bit_range(13..16) >= 0b101;
+    }
+    match inst & MASK_OPCODE {
+        0b0100011 | 0b0100111 => true,
+        0b0101111 => inst.bit_range(27..32) != 0b00010,
+        _ => false,
+    }
+}
+
+/// 按 opcode 划分的指令类别，用作性能分析 span 的字段
+#[cfg(feature = "inst-spans")]
+pub fn opcode_class(inst: u32) -> &'static str {
+    if is_compressed(inst) {
+        return "compressed";
+    }
+    match inst & MASK_OPCODE {
+        0b0000011 => "load",
+        0b0000111 => "load-fp",
+        0b0001111 => "misc-mem",
+        0b0010011 => "op-imm",
+        0b0010111 => "auipc",
+        0b0011011 => "op-imm-32",
+        0b0100011 => "store",
+        0b0100111 => "store-fp",
+        0b0101111 => "amo",
+        0b0110011 => "op",
+        0b0110111 => "lui",
+        0b0111011 => "op-32",
+        0b1000011 => "madd",
+        0b1000111 => "msub",
+        0b1001011 => "nmsub",
+        0b1001111 => "nmadd",
+        0b1010011 => "op-fp",
+        0b1100011 => "branch",
+        0b1100111 => "jalr",
+        0b1101111 => "jal",
+        0b1110011 => "system",
+        _ => "unknown",
+    }
 }
 
 impl InstDecoder {
-    pub fn new(config: Rc<EmuConfig>) -> Self {
+    /// 按指令集配置构建译码器，扩展之间的依赖由 `EmuConfig::validate` 检查
+    pub fn new(config: Rc<EmuConfig>) -> Result<Self> {
         let mut instructions_set: Vec<&'static Instruction> = vec![];
-        let compressed_instructions = vec![];
-        let mut opcode_map = HashMap::with_hasher(BuildNoHashHasher::default());
+        let mut compressed_instructions = vec![];
+        let mut opcode_map: DecodeMap<Vec<&'static Instruction>> =
+            DecodeMap::new(config.inst_set.decode_hasher);
 
         instructions_set.extend(rv64i::RV_I);
         if config.inst_set.m_ext {
@@ -54,26 +118,56 @@ impl InstDecoder {
         if config.inst_set.a_ext {
             instructions_set.extend(rv64a::RV_A);
         }
+        if config.inst_set.f_ext {
+            instructions_set.extend(rv64f::RV_F);
+            if config.inst_set.d_ext {
+                instructions_set.extend(rv64d::RV_D);
+            }
+        }
+        if config.inst_set.zicsr {
+            instructions_set.extend(zicsr::RV_ZICSR);
+        }
+        if config.inst_set.privileged {
+            instructions_set.extend(privileged::RV_PRIV);
+            if config.inst_set.s_mode {
+                instructions_set.extend(privileged::RV_S);
+            }
+        }
 
         if config.inst_set.c_ext {
-            todo!("Implement compressed instructions");
+            compressed_instructions.extend_from_slice(rv64c::RV_C);
+            if config.inst_set.d_ext {
+                compressed_instructions.extend_from_slice(rv64c::RV_C_D);
+            }
+        }
+
+        stubs::apply_stubs(&mut instructions_set, &config.inst_set.stub_instructions);
+
+        if config.inst_set.xlen == Xlen::X32 {
+            rv32::apply_rv32(&mut instructions_set);
         }
 
         for inst in &instructions_set {
             let opcode = inst.identifier & MASK_OPCODE;
-            let entry: &mut Vec<&'static Instruction> = opcode_map.entry(opcode).or_default();
-            entry.push(inst);
+            opcode_map.entry_or_default(opcode).push(inst);
         }
-        InstDecoder {
+        let cache = match config.others.decoder_cache_size {
+            0 => Vec::new(),
+            n => vec![None; n.next_power_of_two()],
+        };
+        Ok(InstDecoder {
             instructions_set,
             compressed_instructions,
             config,
             opcode_map,
-        }
+            cache,
+            cache_hits: 0,
+            cache_misses: 0,
+        })
     }
 
     #[inline]
-    pub fn slow_path(&mut self, inst: u32) -> Result<&Instruction> {
+    pub fn slow_path(&self, inst: u32) -> Result<&Instruction> {
         if is_compressed(inst) {
             self.compressed_instructions
                 .iter()
@@ -84,7 +178,7 @@ impl InstDecoder {
             let opcode = inst & MASK_OPCODE;
 
             // 尝试在优化过的 opcode_map 中查找
-            let maybe_instruction = self.opcode_map.get(&opcode).and_then(|instructions| {
+            let maybe_instruction = self.opcode_map.get(opcode).and_then(|instructions| {
                 instructions
                     .iter()
                     .find(|&&x| x.mask & inst == x.identifier)
@@ -120,12 +214,48 @@ impl InstDecoder {
         }
     }
 
+    /// 指令字在译码缓存中的位置：取乘法散列的高位，使各个字段都参与索引
+    #[inline(always)]
+    fn cache_index(&self, inst: u32) -> usize {
+        ((inst as u64 * 0x9e37_79b9) >> 32) as usize & (self.cache.len() - 1)
+    }
+
     #[inline(always)]
     pub fn fast_path(&mut self, inst: u32) -> Result<&Instruction> {
-        // instruction cache removed: always use slow_path
-        self.slow_path(inst)
+        if self.cache.is_empty() {
+            return self.slow_path(inst);
+        }
+        let index = self.cache_index(inst);
+        if let Some((word, _)) = self.cache[index]
+            && word == inst
+        {
+            self.cache_hits += 1;
+        } else {
+            self.cache_misses += 1;
+            let found = *self.slow_path(inst)?;
+            self.cache[index] = Some((inst, found));
+        }
+        Ok(&self.cache[index].as_ref().unwrap().1)
+    }
+
+    /// 译码缓存的命中率（命中次数 / 查找次数），禁用缓存或尚未查找时返回 0.0
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.cache_hits as f64 / lookups as f64
     }
 
+    /// 译码缓存的累计 (命中次数, 未命中次数)
+    pub fn cache_stats(&self) -> (u64, u64) {
+        (self.cache_hits, self.cache_misses)
+    }
+
+    /// 清空译码缓存中的条目，累计的命中与未命中次数保留
+    pub fn clear_cache(&mut self) {
+        self.cache.fill(None);
+    }
 }
 
 struct FormatR {
@@ -235,3 +365,96 @@ fn parse_format_j(inst: u32) -> FormatJ {
     let imm = sign_extend_64(imm, 21);
     FormatJ { rd, imm }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::const_values::DecodeHasher;
+    use crate::test_utils::test_config;
+
+    fn full_decoder(hasher: DecodeHasher) -> InstDecoder {
+        let (mut config, _) = test_config();
+        config.inst_set.m_ext = true;
+        config.inst_set.a_ext = true;
+        config.inst_set.c_ext = true;
+        config.inst_set.f_ext = true;
+        config.inst_set.d_ext = true;
+        config.inst_set.zicsr = true;
+        config.inst_set.privileged = true;
+        config.inst_set.s_mode = true;
+        config.inst_set.decode_hasher = hasher;
+        InstDecoder::new(Rc::new(config)).unwrap()
+    }
+
+    #[test]
+    fn test_lookups_agree_under_both_hashers() {
+        let mut nohash = full_decoder(DecodeHasher::NoHash);
+        let mut fx = full_decoder(DecodeHasher::Fx);
+        let encodings: Vec<u32> = nohash
+            .instructions_set
+            .iter()
+            .copied()
+            .chain(nohash.compressed_instructions.iter())
+            .map(|inst| inst.identifier)
+            .collect();
+        assert!(encodings.len() > 80);
+
+        for code in encodings {
+            let a = nohash.fast_path(code).unwrap();
+            assert_eq!(a.mask & code, a.identifier, "{} 与 {:#010x} 不匹配", a.name, code);
+            let b = fx.fast_path(code).unwrap();
+            assert_eq!(a.name, b.name, "{:#010x} 的译码结果不一致", code);
+        }
+        assert!(nohash.fast_path(0xffff_ffff).is_err());
+        assert!(fx.fast_path(0xffff_ffff).is_err());
+    }
+
+    #[test]
+    fn test_srai_srli_distinguished_by_mask() {
+        let mut decoder = full_decoder(DecodeHasher::NoHash);
+        let srli = 0x03f55693; // srli a3, a0, 63
+        let srai = 0x43f55713; // srai a4, a0, 63
+        assert_eq!(decoder.fast_path(srli).unwrap().name, "srli");
+        assert_eq!(decoder.fast_path(srai).unwrap().name, "srai");
+
+        // funct6 会进入 imm，执行时必须只取低6位
+        assert_eq!(parse_format_i(srli).imm, 63);
+        assert_eq!(parse_format_i(srai).imm, 0x43f);
+        assert_eq!(parse_format_i(srai).imm & 0x3F, 63);
+    }
+
+    #[test]
+    fn test_decode_cache_hit_rate() {
+        let mut decoder = full_decoder(DecodeHasher::Fx);
+        assert_eq!(decoder.hit_rate(), 0.0);
+        let add = 0x00c586b3; // add a3, a1, a2
+        let srai = 0x43f55713; // srai a4, a0, 63
+        for _ in 0..5 {
+            assert_eq!(decoder.fast_path(add).unwrap().name, "add");
+            assert_eq!(decoder.fast_path(srai).unwrap().name, "srai");
+        }
+        // 每条指令首次译码未命中，其余均命中
+        assert!((decoder.hit_rate() - 0.8).abs() < 1e-9, "{}", decoder.hit_rate());
+        // 未知指令不会填充缓存
+        assert!(decoder.fast_path(0xffff_ffff).is_err());
+        assert!(decoder.fast_path(0xffff_ffff).is_err());
+
+        let (mut config, _) = test_config();
+        config.others.decoder_cache_size = 0;
+        let mut uncached = InstDecoder::new(Rc::new(config)).unwrap();
+        uncached.fast_path(add).unwrap();
+        uncached.fast_path(add).unwrap();
+        assert_eq!(uncached.hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_is_store() {
+        assert!(is_store(0x00a5a023)); // sw a0, 0(a1)
+        assert!(!is_store(0x0005a503)); // lw a0, 0(a1)
+        assert!(is_store(0x0cc5a52f)); // amoswap.w a0, a2, (a1)
+        assert!(!is_store(0x1005a52f)); // lr.w a0, (a1)
+        assert!(is_store(0xe188)); // c.sd a0, 0(a1)
+        assert!(!is_store(0x6188)); // c.ld a0, 0(a1)
+        assert!(!is_store(0xa001)); // c.j 0
+    }
+}
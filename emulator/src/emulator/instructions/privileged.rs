@@ -0,0 +1,161 @@
+//! 特权指令：mret/sret/wfi
+//!
+//! sstatus 是 mstatus 的子集视图，这里直接读写 mstatus 中的 S 模式字段
+
+use crate::emulator::trap::{MSTATUS_MIE, MSTATUS_MPIE, MSTATUS_MPP};
+use crate::emulator::{Emulator, Exception::*, PrivilegeLevel};
+use crate::utils::bit_utils::BitSlice;
+
+use super::Instruction;
+use super::insts::*;
+
+/// mstatus.SIE
+pub const MSTATUS_SIE: usize = 1;
+/// mstatus.SPIE
+pub const MSTATUS_SPIE: usize = 5;
+/// mstatus.SPP
+pub const MSTATUS_SPP: usize = 8;
+/// mstatus.MPRV
+pub const MSTATUS_MPRV: usize = 17;
+
+/// 当前特权级低于 required 时记录非法指令异常，返回是否可以继续执行
+#[inline(always)]
+fn check_privilege(emu: &mut Emulator, required: PrivilegeLevel, inst: u32, pc: u64) -> bool {
+    if (emu.privilege() as u64) < required as u64 {
+        emu.execption = Some(IllegalInstruction { instruction: inst, addr: pc });
+        return false;
+    }
+    true
+}
+
+pub const RV_PRIV: &[Instruction] = &[
+    Instruction {
+        mask: MASK_MRET,
+        identifier: MATCH_MRET,
+        name: "mret",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if !check_privilege(emu, PrivilegeLevel::Machine, inst, pc) {
+                return Ok(());
+            }
+            let mut mstatus = emu.csr_or_zero(CSR_MSTATUS);
+            let mpp = PrivilegeLevel::from_u64(mstatus.bit_range(MSTATUS_MPP))
+                .unwrap_or(PrivilegeLevel::User);
+            mstatus.set_bit(MSTATUS_MIE, mstatus.bit(MSTATUS_MPIE));
+            mstatus.set_bit(MSTATUS_MPIE, true);
+            mstatus.set_bit_range(MSTATUS_MPP, PrivilegeLevel::User as u64);
+            if mpp != PrivilegeLevel::Machine {
+                mstatus.set_bit(MSTATUS_MPRV, false);
+            }
+            emu.state.set_csr(CSR_MSTATUS, mstatus)?;
+            emu.privilege = mpp;
+            emu.set_npc(emu.csr_or_zero(CSR_MEPC));
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_WFI,
+        identifier: MATCH_WFI,
+        name: "wfi",
+        execute: |_emu: &mut Emulator, _inst: u32, _pc: u64| {
+            // 不等待中断，直接执行下一条指令；挂起的中断在下一个指令边界响应
+            Ok(())
+        },
+    },
+];
+
+pub const RV_S: &[Instruction] = &[Instruction {
+    mask: MASK_SRET,
+    identifier: MATCH_SRET,
+    name: "sret",
+    execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+        if !check_privilege(emu, PrivilegeLevel::Supervisor, inst, pc) {
+            return Ok(());
+        }
+        let mut mstatus = emu.csr_or_zero(CSR_MSTATUS);
+        let spp = if mstatus.bit(MSTATUS_SPP) {
+            PrivilegeLevel::Supervisor
+        } else {
+            PrivilegeLevel::User
+        };
+        mstatus.set_bit(MSTATUS_SIE, mstatus.bit(MSTATUS_SPIE));
+        mstatus.set_bit(MSTATUS_SPIE, true);
+        mstatus.set_bit(MSTATUS_SPP, false);
+        mstatus.set_bit(MSTATUS_MPRV, false);
+        emu.state.set_csr(CSR_MSTATUS, mstatus)?;
+        emu.privilege = spp;
+        emu.set_npc(emu.csr_or_zero(CSR_SEPC));
+        Ok(())
+    },
+}];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config};
+
+    #[test]
+    fn test_trap_handler_mret_returns_past_ecall() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.zicsr = true;
+        config.inst_set.privileged = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x00000073, // ecall
+                0x00158593, // addi a1, a1, 1
+                0x00100073, // ebreak
+                0x00000013, // nop
+                0x341022f3, // handler: csrr t0, mepc
+                0x00428293, // addi t0, t0, 4
+                0x34129073, // csrw mepc, t0
+                0x30200073, // mret
+            ],
+        );
+        emu.state.set_csr(CSR_MTVEC, TEST_BASE + 0x10).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << MSTATUS_MIE).unwrap();
+
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mepc, TEST_BASE);
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 0x10);
+        assert!(!emu.trap_state().mstatus.bit(MSTATUS_MIE));
+
+        emu.steps(4).unwrap();
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 4);
+        assert_eq!(emu.privilege(), PrivilegeLevel::Machine);
+        let mstatus = emu.trap_state().mstatus;
+        assert!(mstatus.bit(MSTATUS_MIE));
+        assert!(mstatus.bit(MSTATUS_MPIE));
+        assert_eq!(mstatus.bit_range(MSTATUS_MPP), PrivilegeLevel::User as u64);
+
+        emu.step().unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_mret_to_user_then_mret_is_illegal() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.privileged = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x30200073, // mret               MPP = U，返回到 mepc
+                0x30200073, // mret               U 模式下非法
+            ],
+        );
+        emu.state.set_csr(CSR_MTVEC, TEST_BASE + 0x40).unwrap();
+        emu.state.set_csr(CSR_MEPC, TEST_BASE + 4).unwrap();
+
+        emu.step().unwrap();
+        assert_eq!(emu.privilege(), PrivilegeLevel::User);
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 4);
+
+        emu.step().unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_ILLEGAL_INSTRUCTION as u64);
+        assert_eq!(view.mepc, TEST_BASE + 4);
+        assert_eq!(view.privilege, PrivilegeLevel::Machine);
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 0x40);
+    }
+}
@@ -0,0 +1,285 @@
+//! RV32 模式下的指令替换表
+//!
+//! RV32 的寄存器以符号扩展后的形式存放在64位寄存器中（由 `State::set_reg` 保证），
+//! 因此大部分 RV64I 指令的低32位结果天然正确。本文件只提供语义与 XLEN 相关的指令：
+//! 访存/跳转地址截断为32位、移位量为5位、逻辑右移与乘除法的高位按32位计算
+
+use crate::emulator::{Emulator, Exception::*};
+
+use super::insts::*;
+use super::*;
+
+/// RV32 中不存在的 RV64 专有指令
+pub const RV64_ONLY: &[&str] = &[
+    "addiw", "slliw", "srliw", "sraiw", "addw", "subw", "sllw", "srlw", "sraw", "ld", "lwu", "sd",
+    "mulw", "divw", "divuw", "remw", "remuw", "lr.d", "sc.d", "amoswap.d", "amoadd.d", "amoxor.d",
+    "amoand.d", "amoor.d", "amomin.d", "amomax.d", "amominu.d", "amomaxu.d", "fcvt.l.s",
+    "fcvt.lu.s", "fcvt.s.l", "fcvt.s.lu", "fcvt.l.d", "fcvt.lu.d", "fcvt.d.l", "fcvt.d.lu",
+    "fmv.x.d", "fmv.d.x",
+];
+
+/// 计算32位有效地址
+#[inline(always)]
+fn addr32(base: u64, imm: u64) -> u64 {
+    base.wrapping_add(imm) & 0xFFFF_FFFF
+}
+
+/// RV32 下立即数移位指令的 shamt[5] 必须为0
+#[inline(always)]
+fn check_shamt(emu: &mut Emulator, inst: u32, pc: u64) -> bool {
+    if inst.bit(25) {
+        emu.execption = Some(IllegalInstruction { instruction: inst, addr: pc });
+        return false;
+    }
+    true
+}
+
+/// 按名称替换 RV_I/RV_M 中的同名指令
+pub const RV32_OVERRIDES: &[Instruction] = &[
+    Instruction {
+        mask: MASK_JALR,
+        identifier: MATCH_JALR,
+        name: "jalr",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let i = parse_format_i(inst);
+            let target = addr32(emu.get_reg(i.rs1)?, i.imm) & !1u64;
+            if is_inst_addr_misaligned(emu, target) {
+                emu.execption = Some(InstructionAddressMisaligned { addr: target });
+                return Ok(());
+            }
+            emu.set_npc(target);
+            emu.set_reg(i.rd, pc.wrapping_add(4))
+        },
+    },
+    Instruction {
+        mask: MASK_LB,
+        identifier: MATCH_LB,
+        name: "lb",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let i = parse_format_i(inst);
+            let addr = addr32(emu.get_reg(i.rs1)?, i.imm);
+            let raw = emu.state.memory.read_byte(addr)?;
+            emu.set_reg(i.rd, sign_extend_64(raw as u64, 8))
+        },
+    },
+    Instruction {
+        mask: MASK_LH,
+        identifier: MATCH_LH,
+        name: "lh",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let i = parse_format_i(inst);
+            let addr = addr32(emu.get_reg(i.rs1)?, i.imm);
+            let raw = emu.state.memory.read_halfword(addr)?;
+            emu.set_reg(i.rd, sign_extend_64(raw as u64, 16))
+        },
+    },
+    Instruction {
+        mask: MASK_LW,
+        identifier: MATCH_LW,
+        name: "lw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let i = parse_format_i(inst);
+            let addr = addr32(emu.get_reg(i.rs1)?, i.imm);
+            let raw = emu.state.memory.read_word(addr)?;
+            emu.set_reg(i.rd, raw as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_LBU,
+        identifier: MATCH_LBU,
+        name: "lbu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let i = parse_format_i(inst);
+            let addr = addr32(emu.get_reg(i.rs1)?, i.imm);
+            let raw = emu.state.memory.read_byte(addr)?;
+            emu.set_reg(i.rd, raw as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_LHU,
+        identifier: MATCH_LHU,
+        name: "lhu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let i = parse_format_i(inst);
+            let addr = addr32(emu.get_reg(i.rs1)?, i.imm);
+            let raw = emu.state.memory.read_halfword(addr)?;
+            emu.set_reg(i.rd, raw as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SB,
+        identifier: MATCH_SB,
+        name: "sb",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let s = parse_format_s(inst);
+            let addr = addr32(emu.get_reg(s.rs1)?, s.imm);
+            let value = emu.get_reg(s.rs2)?;
+            emu.state.memory.write_byte(addr, value as u8)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_SH,
+        identifier: MATCH_SH,
+        name: "sh",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let s = parse_format_s(inst);
+            let addr = addr32(emu.get_reg(s.rs1)?, s.imm);
+            let value = emu.get_reg(s.rs2)?;
+            emu.state.memory.write_halfword(addr, value as u16)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_SW,
+        identifier: MATCH_SW,
+        name: "sw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let s = parse_format_s(inst);
+            let addr = addr32(emu.get_reg(s.rs1)?, s.imm);
+            let value = emu.get_reg(s.rs2)?;
+            emu.state.memory.write_word(addr, value as u32)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_SLLI,
+        identifier: MATCH_SLLI,
+        name: "slli",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if !check_shamt(emu, inst, pc) {
+                return Ok(());
+            }
+            let i = parse_format_i(inst);
+            let lhs = emu.get_reg(i.rs1)? as u32;
+            emu.set_reg(i.rd, (lhs << (i.imm & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SRLI,
+        identifier: MATCH_SRLI,
+        name: "srli",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if !check_shamt(emu, inst, pc) {
+                return Ok(());
+            }
+            let i = parse_format_i(inst);
+            let lhs = emu.get_reg(i.rs1)? as u32;
+            emu.set_reg(i.rd, (lhs >> (i.imm & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SRAI,
+        identifier: MATCH_SRAI,
+        name: "srai",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if !check_shamt(emu, inst, pc) {
+                return Ok(());
+            }
+            let i = parse_format_i(inst);
+            let lhs = emu.get_reg(i.rs1)? as i32;
+            emu.set_reg(i.rd, (lhs >> (i.imm & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SLL,
+        identifier: MATCH_SLL,
+        name: "sll",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as u32;
+            let rhs = emu.get_reg(r.rs2)?;
+            emu.set_reg(r.rd, (lhs << (rhs & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SRL,
+        identifier: MATCH_SRL,
+        name: "srl",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as u32;
+            let rhs = emu.get_reg(r.rs2)?;
+            emu.set_reg(r.rd, (lhs >> (rhs & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_SRA,
+        identifier: MATCH_SRA,
+        name: "sra",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as i32;
+            let rhs = emu.get_reg(r.rs2)?;
+            emu.set_reg(r.rd, (lhs >> (rhs & 0x1F)) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_MULH,
+        identifier: MATCH_MULH,
+        name: "mulh",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as i32 as i64;
+            let rhs = emu.get_reg(r.rs2)? as i32 as i64;
+            emu.set_reg(r.rd, (lhs.wrapping_mul(rhs) >> 32) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_MULHSU,
+        identifier: MATCH_MULHSU,
+        name: "mulhsu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as i32 as i64;
+            let rhs = emu.get_reg(r.rs2)? as u32 as i64;
+            emu.set_reg(r.rd, (lhs.wrapping_mul(rhs) >> 32) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_MULHU,
+        identifier: MATCH_MULHU,
+        name: "mulhu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as u32 as u64;
+            let rhs = emu.get_reg(r.rs2)? as u32 as u64;
+            emu.set_reg(r.rd, (lhs * rhs) >> 32)
+        },
+    },
+    Instruction {
+        mask: MASK_DIVU,
+        identifier: MATCH_DIVU,
+        name: "divu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as u32;
+            let rhs = emu.get_reg(r.rs2)? as u32;
+            let res = lhs.checked_div(rhs).unwrap_or(u32::MAX);
+            emu.set_reg(r.rd, res as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_REMU,
+        identifier: MATCH_REMU,
+        name: "remu",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let lhs = emu.get_reg(r.rs1)? as u32;
+            let rhs = emu.get_reg(r.rs2)? as u32;
+            let res = lhs.checked_rem(rhs).unwrap_or(lhs);
+            emu.set_reg(r.rd, res as u64)
+        },
+    },
+];
+
+/// 将 RV64 指令集转换为 RV32 指令集：移除 RV64 专有指令并替换 XLEN 相关的实现
+pub fn apply_rv32(instructions_set: &mut Vec<&'static Instruction>) {
+    instructions_set.retain(|inst| !RV64_ONLY.contains(&inst.name));
+    for inst in instructions_set.iter_mut() {
+        if let Some(replacement) = RV32_OVERRIDES.iter().find(|o| o.name == inst.name) {
+            *inst = replacement;
+        }
+    }
+}
+
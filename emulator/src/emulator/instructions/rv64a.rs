@@ -1,13 +1,305 @@
-use crate::emulator::Emulator;
+//! RV64A 原子指令：lr/sc 与 amo*
+//!
+//! 模拟器只有一个 hart，读-改-写序列天然是原子的；aq/rl 位不影响执行。
+//! lr 在 Memory 中建立保留集，之后对保留集的任何写入（包括 sc 本身）都会使其失效
+
+use crate::emulator::{Emulator, Exception::*};
 
-use super::Instruction;
 use super::insts::*;
+use super::*;
+
+/// 计算原子指令的地址，RV32 下截断为32位；未按 size 对齐时记录地址未对齐异常并返回 None
+#[inline(always)]
+fn amo_addr(emu: &mut Emulator, rs1: u64, size: u64, is_store: bool) -> Result<Option<u64>> {
+    let mut addr = emu.get_reg(rs1)?;
+    if emu.state.xlen == Xlen::X32 {
+        addr &= 0xFFFF_FFFF;
+    }
+    if addr & (size - 1) != 0 {
+        emu.execption = Some(if is_store {
+            StoreAddressMisaligned { addr }
+        } else {
+            LoadAddressMisaligned { addr }
+        });
+        return Ok(None);
+    }
+    Ok(Some(addr))
+}
+
+/// 32位 AMO：rd 得到符号扩展后的旧值，内存写入 op(旧值, rs2)
+#[inline(always)]
+fn amo_w(emu: &mut Emulator, inst: u32, op: fn(u32, u32) -> u32) -> Result<()> {
+    let r = parse_format_r(inst);
+    let Some(addr) = amo_addr(emu, r.rs1, 4, true)? else {
+        return Ok(());
+    };
+    let src = emu.get_reg(r.rs2)? as u32;
+    let old = emu.state.memory.read_word(addr)?;
+    emu.state.memory.write_word(addr, op(old, src))?;
+    emu.set_reg(r.rd, sign_extend_64(old as u64, 32))
+}
 
-pub const RV_A: &[Instruction] = &[Instruction {
-    mask: MASK_MUL,
-    identifier: MATCH_MUL,
-    name: "todo!",
-    execute: |_emu: &mut Emulator, _inst: u32, _pc: u64| {
-        todo!("Implement MUL instruction execution");
+/// 64位 AMO：rd 得到旧值，内存写入 op(旧值, rs2)
+#[inline(always)]
+fn amo_d(emu: &mut Emulator, inst: u32, op: fn(u64, u64) -> u64) -> Result<()> {
+    let r = parse_format_r(inst);
+    let Some(addr) = amo_addr(emu, r.rs1, 8, true)? else {
+        return Ok(());
+    };
+    let src = emu.get_reg(r.rs2)?;
+    let old = emu.state.memory.read_doubleword(addr)?;
+    emu.state.memory.write_doubleword(addr, op(old, src))?;
+    emu.set_reg(r.rd, old)
+}
+
+pub const RV_A: &[Instruction] = &[
+    Instruction {
+        mask: MASK_LR_W,
+        identifier: MATCH_LR_W,
+        name: "lr.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let Some(addr) = amo_addr(emu, r.rs1, 4, false)? else {
+                return Ok(());
+            };
+            let value = emu.state.memory.read_word(addr)?;
+            emu.state.memory.reserve(addr);
+            emu.set_reg(r.rd, sign_extend_64(value as u64, 32))
+        },
+    },
+    Instruction {
+        mask: MASK_LR_D,
+        identifier: MATCH_LR_D,
+        name: "lr.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let Some(addr) = amo_addr(emu, r.rs1, 8, false)? else {
+                return Ok(());
+            };
+            let value = emu.state.memory.read_doubleword(addr)?;
+            emu.state.memory.reserve(addr);
+            emu.set_reg(r.rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_SC_W,
+        identifier: MATCH_SC_W,
+        name: "sc.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let Some(addr) = amo_addr(emu, r.rs1, 4, true)? else {
+                return Ok(());
+            };
+            // 成功时 rd 为0，失败时为1
+            if !emu.state.memory.take_reservation(addr) {
+                return emu.set_reg(r.rd, 1);
+            }
+            let value = emu.get_reg(r.rs2)? as u32;
+            emu.state.memory.write_word(addr, value)?;
+            emu.set_reg(r.rd, 0)
+        },
+    },
+    Instruction {
+        mask: MASK_SC_D,
+        identifier: MATCH_SC_D,
+        name: "sc.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let Some(addr) = amo_addr(emu, r.rs1, 8, true)? else {
+                return Ok(());
+            };
+            if !emu.state.memory.take_reservation(addr) {
+                return emu.set_reg(r.rd, 1);
+            }
+            let value = emu.get_reg(r.rs2)?;
+            emu.state.memory.write_doubleword(addr, value)?;
+            emu.set_reg(r.rd, 0)
+        },
+    },
+    Instruction {
+        mask: MASK_AMOSWAP_W,
+        identifier: MATCH_AMOSWAP_W,
+        name: "amoswap.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |_, src| src),
+    },
+    Instruction {
+        mask: MASK_AMOADD_W,
+        identifier: MATCH_AMOADD_W,
+        name: "amoadd.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_w(emu, inst, |old, src| old.wrapping_add(src))
+        },
+    },
+    Instruction {
+        mask: MASK_AMOXOR_W,
+        identifier: MATCH_AMOXOR_W,
+        name: "amoxor.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |old, src| old ^ src),
+    },
+    Instruction {
+        mask: MASK_AMOAND_W,
+        identifier: MATCH_AMOAND_W,
+        name: "amoand.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |old, src| old & src),
+    },
+    Instruction {
+        mask: MASK_AMOOR_W,
+        identifier: MATCH_AMOOR_W,
+        name: "amoor.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |old, src| old | src),
+    },
+    Instruction {
+        mask: MASK_AMOMIN_W,
+        identifier: MATCH_AMOMIN_W,
+        name: "amomin.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_w(emu, inst, |old, src| (old as i32).min(src as i32) as u32)
+        },
+    },
+    Instruction {
+        mask: MASK_AMOMAX_W,
+        identifier: MATCH_AMOMAX_W,
+        name: "amomax.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_w(emu, inst, |old, src| (old as i32).max(src as i32) as u32)
+        },
+    },
+    Instruction {
+        mask: MASK_AMOMINU_W,
+        identifier: MATCH_AMOMINU_W,
+        name: "amominu.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |old, src| old.min(src)),
+    },
+    Instruction {
+        mask: MASK_AMOMAXU_W,
+        identifier: MATCH_AMOMAXU_W,
+        name: "amomaxu.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_w(emu, inst, |old, src| old.max(src)),
+    },
+    Instruction {
+        mask: MASK_AMOSWAP_D,
+        identifier: MATCH_AMOSWAP_D,
+        name: "amoswap.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |_, src| src),
     },
-}];
+    Instruction {
+        mask: MASK_AMOADD_D,
+        identifier: MATCH_AMOADD_D,
+        name: "amoadd.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_d(emu, inst, |old, src| old.wrapping_add(src))
+        },
+    },
+    Instruction {
+        mask: MASK_AMOXOR_D,
+        identifier: MATCH_AMOXOR_D,
+        name: "amoxor.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |old, src| old ^ src),
+    },
+    Instruction {
+        mask: MASK_AMOAND_D,
+        identifier: MATCH_AMOAND_D,
+        name: "amoand.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |old, src| old & src),
+    },
+    Instruction {
+        mask: MASK_AMOOR_D,
+        identifier: MATCH_AMOOR_D,
+        name: "amoor.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |old, src| old | src),
+    },
+    Instruction {
+        mask: MASK_AMOMIN_D,
+        identifier: MATCH_AMOMIN_D,
+        name: "amomin.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_d(emu, inst, |old, src| (old as i64).min(src as i64) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_AMOMAX_D,
+        identifier: MATCH_AMOMAX_D,
+        name: "amomax.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            amo_d(emu, inst, |old, src| (old as i64).max(src as i64) as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_AMOMINU_D,
+        identifier: MATCH_AMOMINU_D,
+        name: "amominu.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |old, src| old.min(src)),
+    },
+    Instruction {
+        mask: MASK_AMOMAXU_D,
+        identifier: MATCH_AMOMAXU_D,
+        name: "amomaxu.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| amo_d(emu, inst, |old, src| old.max(src)),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config};
+
+    fn emu_with_a_ext(program: &[u32]) -> crate::emulator::Emulator {
+        let (mut config, device_file) = test_config();
+        config.inst_set.a_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, program);
+        emu
+    }
+
+    #[test]
+    fn test_amoadd_w() {
+        let data = TEST_BASE + 0x100;
+        let mut emu = emu_with_a_ext(&[
+            0x00b5262f, // amoadd.w a2, a1, (a0)
+            0x00b526af, // amoadd.w a3, a1, (a0)
+        ]);
+        emu.write_memory(data, &0x7fff_fffeu32.to_le_bytes()).unwrap();
+        emu.set_reg(10, data).unwrap();
+        emu.set_reg(11, 1).unwrap();
+        emu.steps(2).unwrap();
+
+        assert_eq!(emu.get_reg(12).unwrap(), 0x7fff_fffe);
+        // 旧值按32位符号扩展
+        assert_eq!(emu.get_reg(13).unwrap(), 0x7fff_ffff);
+        assert_eq!(emu.read_memory(data, 4).unwrap(), 0x8000_0000u32.to_le_bytes());
+        // 只修改了一个字
+        assert_eq!(emu.read_memory(data + 4, 4).unwrap(), [0; 4]);
+    }
+
+    #[test]
+    fn test_lr_sc_success_then_fail() {
+        let data = TEST_BASE + 0x100;
+        let mut emu = emu_with_a_ext(&[
+            0x1005362f, // lr.d a2, (a0)
+            0x18b536af, // sc.d a3, a1, (a0)     成功
+            0x18b5372f, // sc.d a4, a1, (a0)     保留集已失效
+            0x1005362f, // lr.d a2, (a0)
+            0x00053423, // sd zero, 8(a0)        写入保留集之外
+            0x18b536af, // sc.d a3, a1, (a0)     成功
+            0x1005362f, // lr.d a2, (a0)
+            0x00053023, // sd zero, 0(a0)        写入保留集
+            0x18b5372f, // sc.d a4, a1, (a0)     失败
+        ]);
+        emu.write_memory(data, &5u64.to_le_bytes()).unwrap();
+        emu.set_reg(10, data).unwrap();
+        emu.set_reg(11, 9).unwrap();
+
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(12).unwrap(), 5);
+        assert_eq!(emu.get_reg(13).unwrap(), 0);
+        assert_eq!(emu.get_reg(14).unwrap(), 1);
+        assert_eq!(emu.read_memory(data, 8).unwrap(), 9u64.to_le_bytes());
+
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(12).unwrap(), 9);
+        assert_eq!(emu.get_reg(13).unwrap(), 0);
+
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(14).unwrap(), 1);
+        assert_eq!(emu.read_memory(data, 8).unwrap(), [0; 8]);
+    }
+}
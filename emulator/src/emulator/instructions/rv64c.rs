@@ -0,0 +1,628 @@
+//! RV64C 压缩指令，双精度浮点访存单独放在 `RV_C_D` 中，仅在启用 D 扩展时加入
+//!
+//! 压缩指令只使用低16位，传入的 inst 高16位可能是下一条指令的内容，解析时一律忽略。
+//! 与 32 位指令不同，链接地址为 pc + 2
+
+use crate::emulator::{Emulator, Exception::*};
+
+use super::insts::*;
+use super::*;
+
+/// 常用寄存器子集 x8~x15 的编号（3位字段）
+#[inline(always)]
+fn creg(inst: u32, lo: usize) -> u64 {
+    8 + inst.bit_range(lo..lo + 3)
+}
+
+/// rd/rs1 完整编号 inst[11:7]
+#[inline(always)]
+fn rd_full(inst: u32) -> u64 {
+    inst.bit_range(7..12)
+}
+
+/// rs2 完整编号 inst[6:2]
+#[inline(always)]
+fn rs2_full(inst: u32) -> u64 {
+    inst.bit_range(2..7)
+}
+
+/// CI 格式的 6 位有符号立即数 imm[5|4:0] = inst[12|6:2]
+#[inline(always)]
+fn ci_imm(inst: u32) -> u64 {
+    sign_extend_64((inst.bit(12) as u64) << 5 | inst.bit_range(2..7), 6)
+}
+
+/// 移位量 shamt[5|4:0] = inst[12|6:2]
+#[inline(always)]
+fn ci_shamt(inst: u32) -> u64 {
+    (inst.bit(12) as u64) << 5 | inst.bit_range(2..7)
+}
+
+/// c.j 的跳转偏移 offset[11|4|9:8|10|6|7|3:1|5]
+#[inline(always)]
+fn cj_offset(inst: u32) -> u64 {
+    let imm = (inst.bit(12) as u64) << 11
+        | (inst.bit(11) as u64) << 4
+        | inst.bit_range(9..11) << 8
+        | (inst.bit(8) as u64) << 10
+        | (inst.bit(7) as u64) << 6
+        | (inst.bit(6) as u64) << 7
+        | inst.bit_range(3..6) << 1
+        | (inst.bit(2) as u64) << 5;
+    sign_extend_64(imm, 12)
+}
+
+/// c.beqz/c.bnez 的跳转偏移 offset[8|4:3] = inst[12|11:10]，offset[7:6|2:1|5] = inst[6:2]
+#[inline(always)]
+fn cb_offset(inst: u32) -> u64 {
+    let imm = (inst.bit(12) as u64) << 8
+        | inst.bit_range(10..12) << 3
+        | inst.bit_range(5..7) << 6
+        | inst.bit_range(3..5) << 1
+        | (inst.bit(2) as u64) << 5;
+    sign_extend_64(imm, 9)
+}
+
+/// c.lw/c.sw 的偏移 uimm[5:3] = inst[12:10]，uimm[2|6] = inst[6:5]
+#[inline(always)]
+fn cl_word_offset(inst: u32) -> u64 {
+    inst.bit_range(10..13) << 3 | (inst.bit(6) as u64) << 2 | (inst.bit(5) as u64) << 6
+}
+
+/// c.ld/c.sd 的偏移 uimm[5:3] = inst[12:10]，uimm[7:6] = inst[6:5]
+#[inline(always)]
+fn cl_double_offset(inst: u32) -> u64 {
+    inst.bit_range(10..13) << 3 | inst.bit_range(5..7) << 6
+}
+
+/// 保留编码按非法指令处理
+#[inline(always)]
+fn illegal(emu: &mut Emulator, inst: u32, pc: u64) -> Result<()> {
+    emu.execption = Some(IllegalInstruction { instruction: inst & 0xFFFF, addr: pc });
+    Ok(())
+}
+
+/// 跳转到 target，未对齐时记录异常
+#[inline(always)]
+fn jump(emu: &mut Emulator, target: u64) {
+    if is_inst_addr_misaligned(emu, target) {
+        emu.execption = Some(InstructionAddressMisaligned { addr: target });
+        return;
+    }
+    emu.set_npc(target);
+}
+
+pub const RV_C: &[Instruction] = &[
+    // CR 格式中 c.ebreak ⊂ c.jalr ⊂ c.add，c.jr ⊂ c.mv，需先匹配更具体的编码
+    Instruction {
+        mask: MASK_C_EBREAK,
+        identifier: MATCH_C_EBREAK,
+        name: "c.ebreak",
+        execute: |emu: &mut Emulator, _inst: u32, _pc: u64| rv64i::exec_ebreak(emu),
+    },
+    Instruction {
+        mask: MASK_C_JALR,
+        identifier: MATCH_C_JALR,
+        name: "c.jalr",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let target = emu.get_reg(rd_full(inst))? & !1u64;
+            if is_inst_addr_misaligned(emu, target) {
+                emu.execption = Some(InstructionAddressMisaligned { addr: target });
+                return Ok(());
+            }
+            emu.set_npc(target);
+            emu.set_reg(1, pc.wrapping_add(2))
+        },
+    },
+    Instruction {
+        mask: MASK_C_ADD,
+        identifier: MATCH_C_ADD,
+        name: "c.add",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = rd_full(inst);
+            let value = emu.get_reg(rd)?.wrapping_add(emu.get_reg(rs2_full(inst))?);
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_JR,
+        identifier: MATCH_C_JR,
+        name: "c.jr",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let rs1 = rd_full(inst);
+            if rs1 == 0 {
+                return illegal(emu, inst, pc);
+            }
+            let target = emu.get_reg(rs1)? & !1u64;
+            jump(emu, target);
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_MV,
+        identifier: MATCH_C_MV,
+        name: "c.mv",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let value = emu.get_reg(rs2_full(inst))?;
+            emu.set_reg(rd_full(inst), value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_ADDI4SPN,
+        identifier: MATCH_C_ADDI4SPN,
+        name: "c.addi4spn",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            // nzuimm[5:4|9:6|2|3] = inst[12:5]
+            let imm = inst.bit_range(11..13) << 4
+                | inst.bit_range(7..11) << 6
+                | (inst.bit(6) as u64) << 2
+                | (inst.bit(5) as u64) << 3;
+            if imm == 0 {
+                return illegal(emu, inst, pc);
+            }
+            let value = emu.get_reg(2)?.wrapping_add(imm);
+            emu.set_reg(creg(inst, 2), value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_LW,
+        identifier: MATCH_C_LW,
+        name: "c.lw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_word_offset(inst));
+            let raw = emu.state.memory.read_word(addr)?;
+            emu.set_reg(creg(inst, 2), sign_extend_64(raw as u64, 32))
+        },
+    },
+    Instruction {
+        mask: MASK_C_LD,
+        identifier: MATCH_C_LD,
+        name: "c.ld",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_double_offset(inst));
+            let raw = emu.state.memory.read_doubleword(addr)?;
+            emu.set_reg(creg(inst, 2), raw)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SW,
+        identifier: MATCH_C_SW,
+        name: "c.sw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_word_offset(inst));
+            let value = emu.get_reg(creg(inst, 2))?;
+            emu.state.memory.write_word(addr, value as u32)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_SD,
+        identifier: MATCH_C_SD,
+        name: "c.sd",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_double_offset(inst));
+            let value = emu.get_reg(creg(inst, 2))?;
+            emu.state.memory.write_doubleword(addr, value)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_ADDI,
+        identifier: MATCH_C_ADDI,
+        name: "c.addi",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            // rd == 0 时即 c.nop
+            let rd = rd_full(inst);
+            let value = emu.get_reg(rd)?.wrapping_add(ci_imm(inst));
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_ADDIW,
+        identifier: MATCH_C_ADDIW,
+        name: "c.addiw",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let rd = rd_full(inst);
+            if rd == 0 {
+                return illegal(emu, inst, pc);
+            }
+            let result = emu.get_reg(rd)?.wrapping_add(ci_imm(inst)).bit_range(0..32);
+            emu.set_reg(rd, sign_extend_64(result, 32))
+        },
+    },
+    Instruction {
+        mask: MASK_C_LI,
+        identifier: MATCH_C_LI,
+        name: "c.li",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| emu.set_reg(rd_full(inst), ci_imm(inst)),
+    },
+    Instruction {
+        mask: MASK_C_ADDI16SP,
+        identifier: MATCH_C_ADDI16SP,
+        name: "c.addi16sp",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            // nzimm[9|4|6|8:7|5] = inst[12|6:2]
+            let imm = (inst.bit(12) as u64) << 9
+                | (inst.bit(6) as u64) << 4
+                | (inst.bit(5) as u64) << 6
+                | inst.bit_range(3..5) << 7
+                | (inst.bit(2) as u64) << 5;
+            if imm == 0 {
+                return illegal(emu, inst, pc);
+            }
+            let value = emu.get_reg(2)?.wrapping_add(sign_extend_64(imm, 10));
+            emu.set_reg(2, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_LUI,
+        identifier: MATCH_C_LUI,
+        name: "c.lui",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let imm = ci_imm(inst);
+            if imm == 0 {
+                return illegal(emu, inst, pc);
+            }
+            emu.set_reg(rd_full(inst), imm << 12)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SRLI,
+        identifier: MATCH_C_SRLI,
+        name: "c.srli",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)? >> ci_shamt(inst);
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SRAI,
+        identifier: MATCH_C_SRAI,
+        name: "c.srai",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = (emu.get_reg(rd)? as i64) >> ci_shamt(inst);
+            emu.set_reg(rd, value as u64)
+        },
+    },
+    Instruction {
+        mask: MASK_C_ANDI,
+        identifier: MATCH_C_ANDI,
+        name: "c.andi",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)? & ci_imm(inst);
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SUB,
+        identifier: MATCH_C_SUB,
+        name: "c.sub",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)?.wrapping_sub(emu.get_reg(creg(inst, 2))?);
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_XOR,
+        identifier: MATCH_C_XOR,
+        name: "c.xor",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)? ^ emu.get_reg(creg(inst, 2))?;
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_OR,
+        identifier: MATCH_C_OR,
+        name: "c.or",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)? | emu.get_reg(creg(inst, 2))?;
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_AND,
+        identifier: MATCH_C_AND,
+        name: "c.and",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let value = emu.get_reg(rd)? & emu.get_reg(creg(inst, 2))?;
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SUBW,
+        identifier: MATCH_C_SUBW,
+        name: "c.subw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let result = emu.get_reg(rd)?.wrapping_sub(emu.get_reg(creg(inst, 2))?);
+            emu.set_reg(rd, sign_extend_64(result.bit_range(0..32), 32))
+        },
+    },
+    Instruction {
+        mask: MASK_C_ADDW,
+        identifier: MATCH_C_ADDW,
+        name: "c.addw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = creg(inst, 7);
+            let result = emu.get_reg(rd)?.wrapping_add(emu.get_reg(creg(inst, 2))?);
+            emu.set_reg(rd, sign_extend_64(result.bit_range(0..32), 32))
+        },
+    },
+    Instruction {
+        mask: MASK_C_J,
+        identifier: MATCH_C_J,
+        name: "c.j",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            jump(emu, pc.wrapping_add(cj_offset(inst)));
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_BEQZ,
+        identifier: MATCH_C_BEQZ,
+        name: "c.beqz",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if emu.get_reg(creg(inst, 7))? == 0 {
+                jump(emu, pc.wrapping_add(cb_offset(inst)));
+            }
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_BNEZ,
+        identifier: MATCH_C_BNEZ,
+        name: "c.bnez",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            if emu.get_reg(creg(inst, 7))? != 0 {
+                jump(emu, pc.wrapping_add(cb_offset(inst)));
+            }
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_SLLI,
+        identifier: MATCH_C_SLLI,
+        name: "c.slli",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let rd = rd_full(inst);
+            let value = emu.get_reg(rd)? << ci_shamt(inst);
+            emu.set_reg(rd, value)
+        },
+    },
+    Instruction {
+        mask: MASK_C_LWSP,
+        identifier: MATCH_C_LWSP,
+        name: "c.lwsp",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let rd = rd_full(inst);
+            if rd == 0 {
+                return illegal(emu, inst, pc);
+            }
+            // uimm[5|4:2|7:6] = inst[12|6:2]
+            let offset =
+                (inst.bit(12) as u64) << 5 | inst.bit_range(4..7) << 2 | inst.bit_range(2..4) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            let raw = emu.state.memory.read_word(addr)?;
+            emu.set_reg(rd, sign_extend_64(raw as u64, 32))
+        },
+    },
+    Instruction {
+        mask: MASK_C_LDSP,
+        identifier: MATCH_C_LDSP,
+        name: "c.ldsp",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let rd = rd_full(inst);
+            if rd == 0 {
+                return illegal(emu, inst, pc);
+            }
+            // uimm[5|4:3|8:6] = inst[12|6:2]
+            let offset =
+                (inst.bit(12) as u64) << 5 | inst.bit_range(5..7) << 3 | inst.bit_range(2..5) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            let raw = emu.state.memory.read_doubleword(addr)?;
+            emu.set_reg(rd, raw)
+        },
+    },
+    Instruction {
+        mask: MASK_C_SWSP,
+        identifier: MATCH_C_SWSP,
+        name: "c.swsp",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            // uimm[5:2|7:6] = inst[12:7]
+            let offset = inst.bit_range(9..13) << 2 | inst.bit_range(7..9) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            let value = emu.get_reg(rs2_full(inst))?;
+            emu.state.memory.write_word(addr, value as u32)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_SDSP,
+        identifier: MATCH_C_SDSP,
+        name: "c.sdsp",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            // uimm[5:3|8:6] = inst[12:7]
+            let offset = inst.bit_range(10..13) << 3 | inst.bit_range(7..10) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            let value = emu.get_reg(rs2_full(inst))?;
+            emu.state.memory.write_doubleword(addr, value)?;
+            Ok(())
+        },
+    },
+];
+
+/// 双精度浮点的压缩访存指令，偏移编码与 c.ld/c.sd/c.ldsp/c.sdsp 相同
+pub const RV_C_D: &[Instruction] = &[
+    Instruction {
+        mask: MASK_C_FLD,
+        identifier: MATCH_C_FLD,
+        name: "c.fld",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_double_offset(inst));
+            emu.state.fregs[creg(inst, 2) as usize] = emu.state.memory.read_doubleword(addr)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_FSD,
+        identifier: MATCH_C_FSD,
+        name: "c.fsd",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let addr = emu.get_reg(creg(inst, 7))?.wrapping_add(cl_double_offset(inst));
+            let value = emu.state.fregs[creg(inst, 2) as usize];
+            emu.state.memory.write_doubleword(addr, value)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_FLDSP,
+        identifier: MATCH_C_FLDSP,
+        name: "c.fldsp",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            // uimm[5|4:3|8:6] = inst[12|6:2]，与 c.ldsp 不同，f0 是合法的目标寄存器
+            let offset =
+                (inst.bit(12) as u64) << 5 | inst.bit_range(5..7) << 3 | inst.bit_range(2..5) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            emu.state.fregs[rd_full(inst) as usize] = emu.state.memory.read_doubleword(addr)?;
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_C_FSDSP,
+        identifier: MATCH_C_FSDSP,
+        name: "c.fsdsp",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            // uimm[5:3|8:6] = inst[12:7]
+            let offset = inst.bit_range(10..13) << 3 | inst.bit_range(7..10) << 6;
+            let addr = emu.get_reg(2)?.wrapping_add(offset);
+            let value = emu.state.fregs[rs2_full(inst) as usize];
+            emu.state.memory.write_doubleword(addr, value)?;
+            Ok(())
+        },
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::InstDecoder;
+    use crate::emulator::{Emulator, Event, ExecState};
+    use crate::test_utils::{TEST_BASE, emu_from, test_config};
+
+    fn emu_with_compressed(program: &[u16]) -> Emulator {
+        let (mut config, device_file) = test_config();
+        config.inst_set.c_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+        emu.write_memory(TEST_BASE, &bytes).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_decode_overlapping_encodings() {
+        let (mut config, _) = test_config();
+        config.inst_set.c_ext = true;
+        let mut decoder = InstDecoder::new(Rc::new(config)).unwrap();
+        for (inst, name) in [
+            (0x9002, "c.ebreak"),
+            (0x9282, "c.jalr"),
+            (0x952e, "c.add"),
+            (0x8082, "c.jr"),
+            (0x85aa, "c.mv"),
+            (0x713d, "c.addi16sp"),
+            (0x62fd, "c.lui"),
+            (0x0001, "c.addi"),
+            (0x357d, "c.addiw"),
+            (0x9405, "c.srai"),
+            (0x9a65, "c.andi"),
+            (0x9d0d, "c.subw"),
+            (0xdfba, "c.swsp"),
+            (0x747e, "c.ldsp"),
+        ] {
+            assert_eq!(decoder.slow_path(inst).unwrap().name, name, "{inst:#06x}");
+        }
+    }
+
+    #[test]
+    fn test_c_ebreak_matches_ebreak() {
+        let mut emu = emu_with_compressed(&[
+            0x4515, // c.li a0, 5
+            0x9002, // c.ebreak
+        ]);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.get_cur_event(), Event::Halted(5));
+        assert_eq!(emu.exit_code(), Some(5));
+        assert_eq!(emu.get_pc(), TEST_BASE + 2);
+    }
+
+    #[test]
+    fn test_compressed_loop_and_stack() {
+        let mut emu = emu_with_compressed(&[
+            0x450d, // c.li a0, 3
+            0x4581, // c.li a1, 0
+            0x0595, // loop: c.addi a1, 5
+            0x157d, // c.addi a0, -1
+            0xfd75, // c.bnez a0, loop
+            0xe82e, // c.sdsp a1, 16(sp)
+            0x6642, // c.ldsp a2, 16(sp)
+            0x0800, // c.addi4spn s0, sp, 16
+            0x6014, // c.ld a3, 0(s0)
+            0x9002, // c.ebreak
+        ]);
+        let sp = TEST_BASE + 0x1000;
+        emu.set_reg(2, sp).unwrap();
+        emu.steps(100).unwrap();
+        assert_eq!(emu.exit_code(), Some(0));
+        assert_eq!(emu.get_reg(11).unwrap(), 15);
+        assert_eq!(emu.get_reg(12).unwrap(), 15);
+        assert_eq!(emu.get_reg(8).unwrap(), sp + 16);
+        assert_eq!(emu.get_reg(13).unwrap(), 15);
+    }
+
+    #[test]
+    fn test_compressed_fp_load_store() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.c_ext = true;
+        config.inst_set.f_ext = true;
+        config.inst_set.d_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        let program: [u16; 5] = [
+            0x2500, // c.fld fs0, 8(a0)
+            0xa900, // c.fsd fs0, 16(a0)
+            0x20e2, // c.fldsp ft1, 24(sp)
+            0xb006, // c.fsdsp ft1, 32(sp)
+            0x9002, // c.ebreak
+        ];
+        let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+        emu.write_memory(TEST_BASE, &bytes).unwrap();
+        let data = TEST_BASE + 0x100;
+        emu.write_memory(data + 8, &1.5f64.to_le_bytes()).unwrap();
+        emu.write_memory(data + 24, &(-2.0f64).to_le_bytes()).unwrap();
+        emu.set_reg(10, data).unwrap();
+        emu.set_reg(2, data).unwrap();
+        emu.steps(10).unwrap();
+
+        assert_eq!(emu.exit_code(), Some(0));
+        assert_eq!(emu.state.fregs[8], 1.5f64.to_bits());
+        assert_eq!(emu.state.fregs[1], (-2.0f64).to_bits());
+        assert_eq!(emu.read_memory(data + 16, 8).unwrap(), 1.5f64.to_le_bytes());
+        assert_eq!(emu.read_memory(data + 32, 8).unwrap(), (-2.0f64).to_le_bytes());
+    }
+
+    #[test]
+    fn test_zero_halfword_is_illegal() {
+        let mut emu = emu_with_compressed(&[0x0000]);
+        let err = emu.steps(1).unwrap_err();
+        assert!(format!("{err:#}").contains("mtvec"), "{err:#}");
+    }
+}
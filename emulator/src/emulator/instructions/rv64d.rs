@@ -0,0 +1,241 @@
+//! RV64D 双精度浮点指令，运算辅助函数与 RV64F 共用（见 rv64f.rs）
+
+use crate::emulator::Emulator;
+
+use super::insts::*;
+use super::rv64f::*;
+use super::*;
+
+pub const RV_D: &[Instruction] = &[
+    Instruction {
+        mask: MASK_FLD,
+        identifier: MATCH_FLD,
+        name: "fld",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_load_d(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FSD,
+        identifier: MATCH_FSD,
+        name: "fsd",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_store_d(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FADD_D,
+        identifier: MATCH_FADD_D,
+        name: "fadd.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f64>(emu, inst, pc, FpOp::Add),
+    },
+    Instruction {
+        mask: MASK_FSUB_D,
+        identifier: MATCH_FSUB_D,
+        name: "fsub.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f64>(emu, inst, pc, FpOp::Sub),
+    },
+    Instruction {
+        mask: MASK_FMUL_D,
+        identifier: MATCH_FMUL_D,
+        name: "fmul.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f64>(emu, inst, pc, FpOp::Mul),
+    },
+    Instruction {
+        mask: MASK_FDIV_D,
+        identifier: MATCH_FDIV_D,
+        name: "fdiv.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f64>(emu, inst, pc, FpOp::Div),
+    },
+    Instruction {
+        mask: MASK_FSQRT_D,
+        identifier: MATCH_FSQRT_D,
+        name: "fsqrt.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_sqrt::<f64>(emu, inst, pc),
+    },
+    Instruction {
+        mask: MASK_FMADD_D,
+        identifier: MATCH_FMADD_D,
+        name: "fmadd.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f64>(emu, inst, pc, false, false),
+    },
+    Instruction {
+        mask: MASK_FMSUB_D,
+        identifier: MATCH_FMSUB_D,
+        name: "fmsub.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f64>(emu, inst, pc, false, true),
+    },
+    Instruction {
+        mask: MASK_FNMSUB_D,
+        identifier: MATCH_FNMSUB_D,
+        name: "fnmsub.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f64>(emu, inst, pc, true, false),
+    },
+    Instruction {
+        mask: MASK_FNMADD_D,
+        identifier: MATCH_FNMADD_D,
+        name: "fnmadd.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f64>(emu, inst, pc, true, true),
+    },
+    Instruction {
+        mask: MASK_FMIN_D,
+        identifier: MATCH_FMIN_D,
+        name: "fmin.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_minmax::<f64>(emu, inst, false),
+    },
+    Instruction {
+        mask: MASK_FMAX_D,
+        identifier: MATCH_FMAX_D,
+        name: "fmax.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_minmax::<f64>(emu, inst, true),
+    },
+    Instruction {
+        mask: MASK_FSGNJ_D,
+        identifier: MATCH_FSGNJ_D,
+        name: "fsgnj.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f64>(emu, inst, Sgnj::Copy),
+    },
+    Instruction {
+        mask: MASK_FSGNJN_D,
+        identifier: MATCH_FSGNJN_D,
+        name: "fsgnjn.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f64>(emu, inst, Sgnj::Negate),
+    },
+    Instruction {
+        mask: MASK_FSGNJX_D,
+        identifier: MATCH_FSGNJX_D,
+        name: "fsgnjx.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f64>(emu, inst, Sgnj::Xor),
+    },
+    Instruction {
+        mask: MASK_FEQ_D,
+        identifier: MATCH_FEQ_D,
+        name: "feq.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f64>(emu, inst, FpCmp::Eq),
+    },
+    Instruction {
+        mask: MASK_FLT_D,
+        identifier: MATCH_FLT_D,
+        name: "flt.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f64>(emu, inst, FpCmp::Lt),
+    },
+    Instruction {
+        mask: MASK_FLE_D,
+        identifier: MATCH_FLE_D,
+        name: "fle.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f64>(emu, inst, FpCmp::Le),
+    },
+    Instruction {
+        mask: MASK_FCLASS_D,
+        identifier: MATCH_FCLASS_D,
+        name: "fclass.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_class::<f64>(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FCVT_W_D,
+        identifier: MATCH_FCVT_W_D,
+        name: "fcvt.w.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f64>(emu, inst, pc, true, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_WU_D,
+        identifier: MATCH_FCVT_WU_D,
+        name: "fcvt.wu.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f64>(emu, inst, pc, false, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_L_D,
+        identifier: MATCH_FCVT_L_D,
+        name: "fcvt.l.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f64>(emu, inst, pc, true, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_LU_D,
+        identifier: MATCH_FCVT_LU_D,
+        name: "fcvt.lu.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f64>(emu, inst, pc, false, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_D_W,
+        identifier: MATCH_FCVT_D_W,
+        name: "fcvt.d.w",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f64>(emu, inst, pc, true, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_D_WU,
+        identifier: MATCH_FCVT_D_WU,
+        name: "fcvt.d.wu",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f64>(emu, inst, pc, false, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_D_L,
+        identifier: MATCH_FCVT_D_L,
+        name: "fcvt.d.l",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f64>(emu, inst, pc, true, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_D_LU,
+        identifier: MATCH_FCVT_D_LU,
+        name: "fcvt.d.lu",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f64>(emu, inst, pc, false, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_S_D,
+        identifier: MATCH_FCVT_S_D,
+        name: "fcvt.s.d",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_convert::<f64, f32>(emu, inst, pc),
+    },
+    Instruction {
+        mask: MASK_FCVT_D_S,
+        identifier: MATCH_FCVT_D_S,
+        name: "fcvt.d.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_convert::<f32, f64>(emu, inst, pc),
+    },
+    Instruction {
+        mask: MASK_FMV_X_D,
+        identifier: MATCH_FMV_X_D,
+        name: "fmv.x.d",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            emu.set_reg(r.rd, emu.state.fregs[r.rs1 as usize])
+        },
+    },
+    Instruction {
+        mask: MASK_FMV_D_X,
+        identifier: MATCH_FMV_D_X,
+        name: "fmv.d.x",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            emu.state.fregs[r.rd as usize] = emu.get_reg(r.rs1)?;
+            Ok(())
+        },
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config};
+
+    #[test]
+    fn test_fadd_d_from_memory() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.f_ext = true;
+        config.inst_set.d_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x00053007, // fld ft0, 0(a0)
+                0x00853087, // fld ft1, 8(a0)
+                0x02107153, // fadd.d ft2, ft0, ft1
+                0xe20105d3, // fmv.x.d a1, ft2
+            ],
+        );
+        let data = TEST_BASE + 0x100;
+        emu.write_memory(data, &1.5f64.to_le_bytes()).unwrap();
+        emu.write_memory(data + 8, &2.25f64.to_le_bytes()).unwrap();
+        emu.set_reg(10, data).unwrap();
+        emu.steps(4).unwrap();
+
+        assert_eq!(emu.state.fregs[2], 0x400e_0000_0000_0000);
+        assert_eq!(emu.get_reg(11).unwrap(), 3.75f64.to_bits());
+        assert_eq!(emu.state.get_csr(CSR_FFLAGS).unwrap(), 0);
+    }
+}
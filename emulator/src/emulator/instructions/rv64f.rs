@@ -0,0 +1,709 @@
+//! RV64F 单精度浮点指令，以及 F/D 共用的浮点辅助函数
+//!
+//! 需要舍入的运算（算术、平方根、乘加与各类转换）由 `softfloat` 按舍入模式
+//! （指令中的 rm 或 fcsr.frm）完成，并在 fflags 中记录 NV/DZ/OF/UF/NX；
+//! 比较、符号注入等不舍入的操作直接使用 Rust 的 f32/f64。
+//! 单精度值以 NaN-boxing 的形式存放在64位浮点寄存器中，mstatus.FS 视为始终开启
+
+use crate::emulator::{Emulator, Exception::*};
+
+use super::insts::*;
+use super::softfloat::{self, Format};
+use super::*;
+
+/// fflags 各位
+pub const FFLAG_NX: u64 = 1 << 0;
+pub const FFLAG_UF: u64 = 1 << 1;
+pub const FFLAG_OF: u64 = 1 << 2;
+pub const FFLAG_DZ: u64 = 1 << 3;
+pub const FFLAG_NV: u64 = 1 << 4;
+
+/// 舍入模式编码（0 为 RNE）
+pub const RM_RTZ: u64 = 1;
+pub const RM_RDN: u64 = 2;
+pub const RM_RUP: u64 = 3;
+pub const RM_RMM: u64 = 4;
+/// 使用 fcsr.frm 中的动态舍入模式
+const RM_DYN: u64 = 7;
+
+/// f32 与 f64 的共同操作
+pub(super) trait Fp: Copy + PartialOrd {
+    /// 软件浮点使用的格式
+    const FORMAT: Format;
+    /// 寄存器中规范 NaN 的位模式
+    const CANONICAL_NAN: u64;
+    /// 符号位
+    const SIGN_BIT: u64;
+    /// 尾数最高位，为1表示 quiet NaN
+    const QUIET_BIT: u64;
+
+    /// 从浮点寄存器读取，单精度值未正确 NaN-boxing 时读出规范 NaN
+    fn read(emu: &Emulator, reg: u64) -> Self;
+    /// 将位模式写入浮点寄存器，单精度值会被 NaN-boxing
+    fn write_bits(emu: &mut Emulator, reg: u64, bits: u64);
+    fn to_bits64(self) -> u64;
+    fn from_bits64(bits: u64) -> Self;
+    fn to_f64(self) -> f64;
+    fn is_nan(self) -> bool;
+    fn is_infinite(self) -> bool;
+    fn is_subnormal(self) -> bool;
+
+    #[inline(always)]
+    fn is_snan(self) -> bool {
+        self.is_nan() && self.to_bits64() & Self::QUIET_BIT == 0
+    }
+
+    #[inline(always)]
+    fn is_sign_negative(self) -> bool {
+        self.to_bits64() & Self::SIGN_BIT != 0
+    }
+
+    #[inline(always)]
+    fn is_zero(self) -> bool {
+        self.to_bits64() & !Self::SIGN_BIT == 0
+    }
+
+    /// 写入运算结果，NaN 统一写为规范 NaN
+    #[inline(always)]
+    fn write(emu: &mut Emulator, reg: u64, value: Self) {
+        let bits = if value.is_nan() { Self::CANONICAL_NAN } else { value.to_bits64() };
+        Self::write_bits(emu, reg, bits);
+    }
+}
+
+impl Fp for f32 {
+    const FORMAT: Format = softfloat::F32;
+    const CANONICAL_NAN: u64 = 0x7fc0_0000;
+    const SIGN_BIT: u64 = 1 << 31;
+    const QUIET_BIT: u64 = 1 << 22;
+
+    #[inline(always)]
+    fn read(emu: &Emulator, reg: u64) -> Self {
+        let raw = emu.state.fregs[reg as usize];
+        if raw >> 32 != 0xFFFF_FFFF {
+            return f32::from_bits(Self::CANONICAL_NAN as u32);
+        }
+        f32::from_bits(raw as u32)
+    }
+    #[inline(always)]
+    fn write_bits(emu: &mut Emulator, reg: u64, bits: u64) {
+        emu.state.fregs[reg as usize] = 0xFFFF_FFFF_0000_0000 | (bits & 0xFFFF_FFFF);
+    }
+    fn to_bits64(self) -> u64 {
+        self.to_bits() as u64
+    }
+    fn from_bits64(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+    fn is_infinite(self) -> bool {
+        f32::is_infinite(self)
+    }
+    fn is_subnormal(self) -> bool {
+        f32::is_subnormal(self)
+    }
+}
+
+impl Fp for f64 {
+    const FORMAT: Format = softfloat::F64;
+    const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+    const SIGN_BIT: u64 = 1 << 63;
+    const QUIET_BIT: u64 = 1 << 51;
+
+    #[inline(always)]
+    fn read(emu: &Emulator, reg: u64) -> Self {
+        f64::from_bits(emu.state.fregs[reg as usize])
+    }
+    #[inline(always)]
+    fn write_bits(emu: &mut Emulator, reg: u64, bits: u64) {
+        emu.state.fregs[reg as usize] = bits;
+    }
+    fn to_bits64(self) -> u64 {
+        self.to_bits()
+    }
+    fn from_bits64(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+    fn is_infinite(self) -> bool {
+        f64::is_infinite(self)
+    }
+    fn is_subnormal(self) -> bool {
+        f64::is_subnormal(self)
+    }
+}
+
+/// 累加异常标志到 fflags
+#[inline(always)]
+fn raise_flags(emu: &mut Emulator, flags: u64) -> Result<()> {
+    if flags != 0 {
+        let fcsr = emu.csr_or_zero(CSR_FCSR);
+        emu.state.set_csr(CSR_FCSR, fcsr | flags)?;
+    }
+    Ok(())
+}
+
+/// 解析指令的舍入模式，非法时记录非法指令异常并返回 None
+#[inline(always)]
+fn rounding_mode(emu: &mut Emulator, inst: u32, pc: u64) -> Option<u64> {
+    let rm = match inst.bit_range(12..15) {
+        RM_DYN => emu.csr_or_zero(CSR_FRM),
+        rm => rm,
+    };
+    if rm > RM_RMM {
+        emu.execption = Some(IllegalInstruction { instruction: inst, addr: pc });
+        return None;
+    }
+    Some(rm)
+}
+
+/// 按舍入模式将 value 舍入为整数值
+#[inline(always)]
+fn round_to_integral(value: f64, rm: u64) -> f64 {
+    match rm {
+        RM_RTZ => value.trunc(),
+        RM_RDN => value.floor(),
+        RM_RUP => value.ceil(),
+        RM_RMM => value.round(),
+        // RNE
+        _ => value.round_ties_even(),
+    }
+}
+
+/// 计算浮点访存地址，RV32 下截断为32位
+#[inline(always)]
+fn fp_addr(emu: &Emulator, base: u64, imm: u64) -> u64 {
+    let addr = base.wrapping_add(imm);
+    match emu.state.xlen {
+        Xlen::X32 => addr & 0xFFFF_FFFF,
+        Xlen::X64 => addr,
+    }
+}
+
+#[inline(always)]
+pub(super) fn fp_load_w(emu: &mut Emulator, inst: u32) -> Result<()> {
+    let i = parse_format_i(inst);
+    let addr = fp_addr(emu, emu.get_reg(i.rs1)?, i.imm);
+    let raw = emu.state.memory.read_word(addr)?;
+    f32::write_bits(emu, i.rd, raw as u64);
+    Ok(())
+}
+
+#[inline(always)]
+pub(super) fn fp_store_w(emu: &mut Emulator, inst: u32) -> Result<()> {
+    let s = parse_format_s(inst);
+    let addr = fp_addr(emu, emu.get_reg(s.rs1)?, s.imm);
+    let raw = emu.state.fregs[s.rs2 as usize] as u32;
+    emu.state.memory.write_word(addr, raw)?;
+    Ok(())
+}
+
+#[inline(always)]
+pub(super) fn fp_load_d(emu: &mut Emulator, inst: u32) -> Result<()> {
+    let i = parse_format_i(inst);
+    let addr = fp_addr(emu, emu.get_reg(i.rs1)?, i.imm);
+    let raw = emu.state.memory.read_doubleword(addr)?;
+    f64::write_bits(emu, i.rd, raw);
+    Ok(())
+}
+
+#[inline(always)]
+pub(super) fn fp_store_d(emu: &mut Emulator, inst: u32) -> Result<()> {
+    let s = parse_format_s(inst);
+    let addr = fp_addr(emu, emu.get_reg(s.rs1)?, s.imm);
+    let raw = emu.state.fregs[s.rs2 as usize];
+    emu.state.memory.write_doubleword(addr, raw)?;
+    Ok(())
+}
+
+/// 双操作数算术运算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FpOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[inline(always)]
+pub(super) fn fp_arith<F: Fp>(emu: &mut Emulator, inst: u32, pc: u64, op: FpOp) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let (a, b) = (F::read(emu, r.rs1).to_bits64(), F::read(emu, r.rs2).to_bits64());
+    let (result, flags) = match op {
+        FpOp::Add => softfloat::add(F::FORMAT, a, b, rm),
+        FpOp::Sub => softfloat::sub(F::FORMAT, a, b, rm),
+        FpOp::Mul => softfloat::mul(F::FORMAT, a, b, rm),
+        FpOp::Div => softfloat::div(F::FORMAT, a, b, rm),
+    };
+    F::write_bits(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+#[inline(always)]
+pub(super) fn fp_sqrt<F: Fp>(emu: &mut Emulator, inst: u32, pc: u64) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let (result, flags) = softfloat::sqrt(F::FORMAT, F::read(emu, r.rs1).to_bits64(), rm);
+    F::write_bits(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+/// 融合乘加：结果为 ±(rs1 * rs2) ± rs3
+#[inline(always)]
+pub(super) fn fp_fma<F: Fp>(
+    emu: &mut Emulator,
+    inst: u32,
+    pc: u64,
+    negate_product: bool,
+    negate_addend: bool,
+) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let a = F::read(emu, r.rs1).to_bits64();
+    let b = F::read(emu, r.rs2).to_bits64();
+    let c = F::read(emu, inst.bit_range(27..32)).to_bits64();
+    let a = if negate_product { a ^ F::SIGN_BIT } else { a };
+    let c = if negate_addend { c ^ F::SIGN_BIT } else { c };
+    let (result, flags) = softfloat::fma(F::FORMAT, a, b, c, rm);
+    F::write_bits(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+/// fmin/fmax：一个操作数为 NaN 时返回另一个，-0 小于 +0
+#[inline(always)]
+pub(super) fn fp_minmax<F: Fp>(emu: &mut Emulator, inst: u32, max: bool) -> Result<()> {
+    let r = parse_format_r(inst);
+    let (a, b) = (F::read(emu, r.rs1), F::read(emu, r.rs2));
+    let flags = if a.is_snan() || b.is_snan() { FFLAG_NV } else { 0 };
+    let result = match (a.is_nan(), b.is_nan()) {
+        (true, true) => F::from_bits64(F::CANONICAL_NAN),
+        (true, false) => b,
+        (false, true) => a,
+        _ if a.is_zero() && b.is_zero() => {
+            // 符号不同的零：min 取负零，max 取正零
+            if a.is_sign_negative() == max { b } else { a }
+        }
+        _ if (a < b) != max => a,
+        _ => b,
+    };
+    F::write(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+/// 符号注入的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Sgnj {
+    /// 取 rs2 的符号
+    Copy,
+    /// 取 rs2 符号的反
+    Negate,
+    /// rs1 与 rs2 符号的异或
+    Xor,
+}
+
+/// 符号注入只操作位模式，NaN 不会被规范化
+#[inline(always)]
+pub(super) fn fp_sgnj<F: Fp>(emu: &mut Emulator, inst: u32, kind: Sgnj) -> Result<()> {
+    let r = parse_format_r(inst);
+    let a = F::read(emu, r.rs1).to_bits64();
+    let b = F::read(emu, r.rs2).to_bits64();
+    let sign = match kind {
+        Sgnj::Copy => b & F::SIGN_BIT,
+        Sgnj::Negate => !b & F::SIGN_BIT,
+        Sgnj::Xor => (a ^ b) & F::SIGN_BIT,
+    };
+    F::write_bits(emu, r.rd, (a & !F::SIGN_BIT) | sign);
+    Ok(())
+}
+
+/// 比较运算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FpCmp {
+    Eq,
+    Lt,
+    Le,
+}
+
+/// 比较结果写入整数寄存器；feq 仅在遇到 signaling NaN 时置 NV，flt/fle 遇到任何 NaN 都置 NV
+#[inline(always)]
+pub(super) fn fp_cmp<F: Fp>(emu: &mut Emulator, inst: u32, cmp: FpCmp) -> Result<()> {
+    let r = parse_format_r(inst);
+    let (a, b) = (F::read(emu, r.rs1), F::read(emu, r.rs2));
+    let (result, invalid) = match cmp {
+        FpCmp::Eq => (a == b, a.is_snan() || b.is_snan()),
+        FpCmp::Lt => (a < b, a.is_nan() || b.is_nan()),
+        FpCmp::Le => (a <= b, a.is_nan() || b.is_nan()),
+    };
+    emu.set_reg(r.rd, result as u64)?;
+    raise_flags(emu, if invalid { FFLAG_NV } else { 0 })
+}
+
+#[inline(always)]
+pub(super) fn fp_class<F: Fp>(emu: &mut Emulator, inst: u32) -> Result<()> {
+    let r = parse_format_r(inst);
+    let a = F::read(emu, r.rs1);
+    let negative = a.is_sign_negative();
+    let bit = if a.is_nan() {
+        if a.is_snan() { 8 } else { 9 }
+    } else if a.is_infinite() {
+        if negative { 0 } else { 7 }
+    } else if a.is_zero() {
+        if negative { 3 } else { 4 }
+    } else if a.is_subnormal() {
+        if negative { 2 } else { 5 }
+    } else if negative {
+        1
+    } else {
+        6
+    };
+    emu.set_reg(r.rd, 1 << bit)
+}
+
+/// 浮点转整数：按舍入模式取整，NaN 与越界值饱和到边界并置 NV，不精确时置 NX；
+/// 32 位结果（包括无符号）按规范符号扩展后写入 rd
+#[inline(always)]
+pub(super) fn fp_to_int<F: Fp>(emu: &mut Emulator, inst: u32, pc: u64, signed: bool, bits: u32) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let value = F::read(emu, r.rs1).to_f64();
+    let (min, max) = if signed {
+        (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+    } else {
+        (0, (1i128 << bits) - 1)
+    };
+    let rounded = round_to_integral(value, rm);
+    let (result, flags) = if value.is_nan() {
+        (max, FFLAG_NV)
+    } else if rounded < min as f64 {
+        (min, FFLAG_NV)
+    } else if rounded > max as f64 {
+        (max, FFLAG_NV)
+    } else {
+        // 2^63 与 2^64 在 f64 中可以精确表示，恰好等于时仍然越界
+        let result = rounded as i128;
+        if result > max {
+            (max, FFLAG_NV)
+        } else {
+            (result, if rounded != value { FFLAG_NX } else { 0 })
+        }
+    };
+    let result = if bits == 32 { result as i32 as u64 } else { result as u64 };
+    emu.set_reg(r.rd, result)?;
+    raise_flags(emu, flags)
+}
+
+/// 整数转浮点
+#[inline(always)]
+pub(super) fn int_to_fp<F: Fp>(emu: &mut Emulator, inst: u32, pc: u64, signed: bool, bits: u32) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let src = emu.get_reg(r.rs1)?;
+    let (negative, magnitude) = match (signed, bits) {
+        (true, 32) => ((src as i32) < 0, (src as i32).unsigned_abs() as u64),
+        (false, 32) => (false, src as u32 as u64),
+        (true, _) => ((src as i64) < 0, (src as i64).unsigned_abs()),
+        (false, _) => (false, src),
+    };
+    let (result, flags) = softfloat::from_int(F::FORMAT, negative, magnitude, rm);
+    F::write_bits(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+/// 浮点格式之间的转换（fcvt.s.d/fcvt.d.s）
+#[inline(always)]
+pub(super) fn fp_convert<From: Fp, To: Fp>(emu: &mut Emulator, inst: u32, pc: u64) -> Result<()> {
+    let Some(rm) = rounding_mode(emu, inst, pc) else {
+        return Ok(());
+    };
+    let r = parse_format_r(inst);
+    let a = From::read(emu, r.rs1).to_bits64();
+    let (result, flags) = softfloat::convert(From::FORMAT, To::FORMAT, a, rm);
+    To::write_bits(emu, r.rd, result);
+    raise_flags(emu, flags)
+}
+
+pub const RV_F: &[Instruction] = &[
+    Instruction {
+        mask: MASK_FLW,
+        identifier: MATCH_FLW,
+        name: "flw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_load_w(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FSW,
+        identifier: MATCH_FSW,
+        name: "fsw",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_store_w(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FADD_S,
+        identifier: MATCH_FADD_S,
+        name: "fadd.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f32>(emu, inst, pc, FpOp::Add),
+    },
+    Instruction {
+        mask: MASK_FSUB_S,
+        identifier: MATCH_FSUB_S,
+        name: "fsub.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f32>(emu, inst, pc, FpOp::Sub),
+    },
+    Instruction {
+        mask: MASK_FMUL_S,
+        identifier: MATCH_FMUL_S,
+        name: "fmul.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f32>(emu, inst, pc, FpOp::Mul),
+    },
+    Instruction {
+        mask: MASK_FDIV_S,
+        identifier: MATCH_FDIV_S,
+        name: "fdiv.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_arith::<f32>(emu, inst, pc, FpOp::Div),
+    },
+    Instruction {
+        mask: MASK_FSQRT_S,
+        identifier: MATCH_FSQRT_S,
+        name: "fsqrt.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_sqrt::<f32>(emu, inst, pc),
+    },
+    Instruction {
+        mask: MASK_FMADD_S,
+        identifier: MATCH_FMADD_S,
+        name: "fmadd.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f32>(emu, inst, pc, false, false),
+    },
+    Instruction {
+        mask: MASK_FMSUB_S,
+        identifier: MATCH_FMSUB_S,
+        name: "fmsub.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f32>(emu, inst, pc, false, true),
+    },
+    Instruction {
+        mask: MASK_FNMSUB_S,
+        identifier: MATCH_FNMSUB_S,
+        name: "fnmsub.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f32>(emu, inst, pc, true, false),
+    },
+    Instruction {
+        mask: MASK_FNMADD_S,
+        identifier: MATCH_FNMADD_S,
+        name: "fnmadd.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_fma::<f32>(emu, inst, pc, true, true),
+    },
+    Instruction {
+        mask: MASK_FMIN_S,
+        identifier: MATCH_FMIN_S,
+        name: "fmin.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_minmax::<f32>(emu, inst, false),
+    },
+    Instruction {
+        mask: MASK_FMAX_S,
+        identifier: MATCH_FMAX_S,
+        name: "fmax.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_minmax::<f32>(emu, inst, true),
+    },
+    Instruction {
+        mask: MASK_FSGNJ_S,
+        identifier: MATCH_FSGNJ_S,
+        name: "fsgnj.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f32>(emu, inst, Sgnj::Copy),
+    },
+    Instruction {
+        mask: MASK_FSGNJN_S,
+        identifier: MATCH_FSGNJN_S,
+        name: "fsgnjn.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f32>(emu, inst, Sgnj::Negate),
+    },
+    Instruction {
+        mask: MASK_FSGNJX_S,
+        identifier: MATCH_FSGNJX_S,
+        name: "fsgnjx.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_sgnj::<f32>(emu, inst, Sgnj::Xor),
+    },
+    Instruction {
+        mask: MASK_FEQ_S,
+        identifier: MATCH_FEQ_S,
+        name: "feq.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f32>(emu, inst, FpCmp::Eq),
+    },
+    Instruction {
+        mask: MASK_FLT_S,
+        identifier: MATCH_FLT_S,
+        name: "flt.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f32>(emu, inst, FpCmp::Lt),
+    },
+    Instruction {
+        mask: MASK_FLE_S,
+        identifier: MATCH_FLE_S,
+        name: "fle.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_cmp::<f32>(emu, inst, FpCmp::Le),
+    },
+    Instruction {
+        mask: MASK_FCLASS_S,
+        identifier: MATCH_FCLASS_S,
+        name: "fclass.s",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| fp_class::<f32>(emu, inst),
+    },
+    Instruction {
+        mask: MASK_FCVT_W_S,
+        identifier: MATCH_FCVT_W_S,
+        name: "fcvt.w.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f32>(emu, inst, pc, true, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_WU_S,
+        identifier: MATCH_FCVT_WU_S,
+        name: "fcvt.wu.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f32>(emu, inst, pc, false, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_L_S,
+        identifier: MATCH_FCVT_L_S,
+        name: "fcvt.l.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f32>(emu, inst, pc, true, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_LU_S,
+        identifier: MATCH_FCVT_LU_S,
+        name: "fcvt.lu.s",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| fp_to_int::<f32>(emu, inst, pc, false, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_S_W,
+        identifier: MATCH_FCVT_S_W,
+        name: "fcvt.s.w",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f32>(emu, inst, pc, true, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_S_WU,
+        identifier: MATCH_FCVT_S_WU,
+        name: "fcvt.s.wu",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f32>(emu, inst, pc, false, 32),
+    },
+    Instruction {
+        mask: MASK_FCVT_S_L,
+        identifier: MATCH_FCVT_S_L,
+        name: "fcvt.s.l",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f32>(emu, inst, pc, true, 64),
+    },
+    Instruction {
+        mask: MASK_FCVT_S_LU,
+        identifier: MATCH_FCVT_S_LU,
+        name: "fcvt.s.lu",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| int_to_fp::<f32>(emu, inst, pc, false, 64),
+    },
+    Instruction {
+        mask: MASK_FMV_X_W,
+        identifier: MATCH_FMV_X_W,
+        name: "fmv.x.w",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let raw = emu.state.fregs[r.rs1 as usize];
+            emu.set_reg(r.rd, sign_extend_64(raw & 0xFFFF_FFFF, 32))
+        },
+    },
+    Instruction {
+        mask: MASK_FMV_W_X,
+        identifier: MATCH_FMV_W_X,
+        name: "fmv.w.x",
+        execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
+            let r = parse_format_r(inst);
+            let raw = emu.get_reg(r.rs1)?;
+            f32::write_bits(emu, r.rd, raw);
+            Ok(())
+        },
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{emu_from, load_program, test_config};
+
+    fn emu_with_f_ext(program: &[u32]) -> Emulator {
+        let (mut config, device_file) = test_config();
+        config.inst_set.f_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, program);
+        emu
+    }
+
+    #[test]
+    fn test_fcvt_w_s_rounding_and_saturation() {
+        let mut emu = emu_with_f_ext(&[
+            0xc0001553, // fcvt.w.s a0, ft0, rtz
+            0xc00035d3, // fcvt.w.s a1, ft0, rup
+            0xc0008653, // fcvt.w.s a2, ft1, rne
+            0xc01006d3, // fcvt.wu.s a3, ft0, rne
+        ]);
+        f32::write_bits(&mut emu, 0, (-2.5f32).to_bits() as u64);
+        f32::write_bits(&mut emu, 1, 3.0e10f32.to_bits() as u64);
+        emu.steps(4).unwrap();
+
+        assert_eq!(emu.get_reg(10).unwrap() as i64, -2);
+        assert_eq!(emu.get_reg(11).unwrap() as i64, -2);
+        assert_eq!(emu.get_reg(12).unwrap(), i32::MAX as u64);
+        assert_eq!(emu.get_reg(13).unwrap(), 0);
+        assert_eq!(emu.state.get_csr(CSR_FFLAGS).unwrap(), FFLAG_NV | FFLAG_NX);
+    }
+
+    #[test]
+    fn test_arith_rounding_modes() {
+        let mut emu = emu_with_f_ext(&[
+            0x18102153, // fdiv.s ft2, ft0, ft1, rdn
+            0x181031d3, // fdiv.s ft3, ft0, ft1, rup
+            0x18107253, // fdiv.s ft4, ft0, ft1, dyn
+            0x106372d3, // fmul.s ft5, ft6, ft6
+        ]);
+        f32::write_bits(&mut emu, 0, 1.0f32.to_bits() as u64);
+        f32::write_bits(&mut emu, 1, 3.0f32.to_bits() as u64);
+        f32::write_bits(&mut emu, 6, 1.0e-30f32.to_bits() as u64);
+        emu.state.set_csr(CSR_FRM, RM_RTZ).unwrap();
+        emu.steps(4).unwrap();
+
+        let down = f32::read(&emu, 2);
+        assert!(down < 1.0 / 3.0);
+        assert_eq!(f32::read(&emu, 3), down.next_up());
+        assert_eq!(f32::read(&emu, 4), down);
+        assert_eq!(f32::read(&emu, 5), 0.0);
+        assert_eq!(emu.state.get_csr(CSR_FFLAGS).unwrap(), FFLAG_NX | FFLAG_UF);
+    }
+
+    #[test]
+    fn test_single_is_nan_boxed() {
+        let mut emu = emu_with_f_ext(&[
+            0xf0050053, // fmv.w.x ft0, a0
+            0x000000d3, // fadd.s ft1, ft0, ft0
+            0xe00085d3, // fmv.x.w a1, ft1
+        ]);
+        emu.set_reg(10, 1.5f32.to_bits() as u64).unwrap();
+        emu.steps(3).unwrap();
+
+        assert_eq!(emu.state.fregs[1], 0xFFFF_FFFF_0000_0000 | 3.0f32.to_bits() as u64);
+        assert_eq!(emu.get_reg(11).unwrap(), 3.0f32.to_bits() as u64);
+
+        // 未正确 NaN-boxing 的值按规范 NaN 读取
+        emu.state.fregs[0] = 1.5f64.to_bits();
+        assert!(f32::read(&emu, 0).is_nan());
+    }
+}
@@ -3,6 +3,13 @@ use crate::emulator::{Emulator, Exception::*, state::Event};
 use super::insts::*;
 use super::*;
 
+/// ebreak 与 c.ebreak 共用的行为：以 a0 作为退出码停止执行
+pub(super) fn exec_ebreak(emu: &mut Emulator) -> Result<()> {
+    emu.event = Event::Halted(emu.get_reg(10)? as u8);
+    tracing::info!("执行 EBREAK 指令, 触发 CPU 停止事件");
+    Ok(())
+}
+
 pub const RV_I: &[Instruction] = &[
     Instruction {
         mask: MASK_LUI,
@@ -30,7 +37,7 @@ pub const RV_I: &[Instruction] = &[
             let j = parse_format_j(inst);
             emu.set_reg(j.rd, pc.wrapping_add(4))?;
             let target = pc.wrapping_add(j.imm);
-            if is_inst_addr_misaligned(target) {
+            if is_inst_addr_misaligned(emu, target) {
                 emu.execption = Some(InstructionAddressMisaligned { addr: target });
                 return Ok(());
             }
@@ -45,7 +52,7 @@ pub const RV_I: &[Instruction] = &[
         execute: |emu: &mut Emulator, inst: u32, pc: u64| {
             let i = parse_format_i(inst);
             let target = (emu.get_reg(i.rs1)?).wrapping_add(i.imm) & !1u64;
-            if is_inst_addr_misaligned(target) {
+            if is_inst_addr_misaligned(emu, target) {
                 emu.execption = Some(InstructionAddressMisaligned { addr: target });
                 return Ok(());
             }
@@ -63,7 +70,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if lhs == rhs {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -82,7 +89,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if lhs != rhs {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -101,7 +108,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if (lhs as i64) < (rhs as i64) {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -120,7 +127,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if (lhs as i64) >= (rhs as i64) {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -139,7 +146,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if lhs < rhs {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -158,7 +165,7 @@ pub const RV_I: &[Instruction] = &[
             let rhs = emu.get_reg(b.rs2)?;
             if lhs >= rhs {
                 let target = pc.wrapping_add(b.imm);
-                if is_inst_addr_misaligned(target) {
+                if is_inst_addr_misaligned(emu, target) {
                     emu.execption = Some(InstructionAddressMisaligned { addr: target });
                     return Ok(());
                 }
@@ -334,7 +341,7 @@ pub const RV_I: &[Instruction] = &[
         execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
             let i = parse_format_i(inst);
             let lhs = emu.get_reg(i.rs1)?;
-            let shamt = (i.imm & 0x3F) as u64; // 确保移位量在0-63范围内
+            let shamt = (i.imm & 0x3F) as u64; // imm 高位为 funct6，只取低6位 shamt
             emu.set_reg(i.rd, lhs << shamt)
         },
     },
@@ -345,7 +352,7 @@ pub const RV_I: &[Instruction] = &[
         execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
             let i = parse_format_i(inst);
             let lhs = emu.get_reg(i.rs1)?;
-            let shamt = (i.imm & 0x3F) as u64; // 确保移位量在0-63范围内
+            let shamt = (i.imm & 0x3F) as u64; // imm 高位为 funct6，只取低6位 shamt
             emu.set_reg(i.rd, lhs >> shamt)
         },
     },
@@ -356,7 +363,7 @@ pub const RV_I: &[Instruction] = &[
         execute: |emu: &mut Emulator, inst: u32, _pc: u64| {
             let i = parse_format_i(inst);
             let lhs = emu.get_reg(i.rs1)?;
-            let shamt = (i.imm & 0x3F) as u64; // 确保移位量在0-63范围内
+            let shamt = (i.imm & 0x3F) as u64; // imm 高位为 funct6，只取低6位 shamt
             emu.set_reg(i.rd, (lhs as i64 >> shamt) as u64)
         },
     },
@@ -480,31 +487,21 @@ pub const RV_I: &[Instruction] = &[
         identifier: MATCH_FENCE,
         name: "fence",
         execute: |_emu: &mut Emulator, _inst: u32, _pc: u64| {
-            // FENCE 指令不做任何操作
-            tracing::warn!("执行FENCE指令, 但是目前不做任何操作");
-            todo!("Implement FENCE handling");
+            // 单核且访存按程序顺序完成，FENCE 无需任何操作
+            Ok(())
         },
     },
     Instruction {
         mask: MASK_ECALL,
         identifier: MATCH_ECALL,
         name: "ecall",
-        execute: |_emu: &mut Emulator, _inst: u32, _pc: u64| {
-            // 处理 ECALL 指令
-            tracing::warn!("执行 ECALL 指令, 但目前未实现系统调用处理");
-            todo!("Implement ECALL handling");
-            // Ok(())
-        },
+        execute: |emu: &mut Emulator, _inst: u32, pc: u64| emu.exec_ecall(pc),
     },
     Instruction {
         mask: MASK_EBREAK,
         identifier: MATCH_EBREAK,
         name: "ebreak",
-        execute: |emu: &mut Emulator, _inst: u32, _pc: u64| {
-            emu.event = Event::Halted(emu.get_reg(10)? as u8);
-            tracing::info!("执行 EBREAK 指令, 触发 CPU 停止事件");
-            Ok(())
-        },
+        execute: |emu: &mut Emulator, _inst: u32, _pc: u64| exec_ebreak(emu),
     },
     Instruction {
         mask: MASK_ADDIW,
@@ -653,3 +650,120 @@ pub const RV_I: &[Instruction] = &[
         },
     },
 ];
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::ExecState;
+    use crate::emulator::instructions::insts::{CAUSE_MISALIGNED_FETCH, CSR_MTVEC};
+    use crate::test_utils::{TEST_BASE, emu_from, emu_with_program, test_config};
+
+    #[test]
+    fn test_jalr_rd_equals_rs1() {
+        let mut emu = emu_with_program(&[
+            0x00000097, // auipc ra, 0
+            0x00c080e7, // jalr ra, 12(ra)    目标由旧的 ra 计算
+            0x00000013, // nop
+            0x00000013, // nop                <- 目标
+        ]);
+        emu.steps(2).unwrap();
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 12);
+        assert_eq!(emu.get_reg(1).unwrap(), TEST_BASE + 8);
+    }
+
+    #[test]
+    fn test_jalr_misaligned_target_does_not_write_rd() {
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x006280e7, // jalr ra, 6(t0)     目标 TEST_BASE + 6 未对齐
+            0x00000013, // nop
+            0x00000013, // nop                <- mtvec
+        ]);
+        emu.state.set_csr(CSR_MTVEC, TEST_BASE + 12).unwrap();
+        emu.steps(2).unwrap();
+
+        // 触发异常的指令不会提交，ra 保持原值
+        assert_eq!(emu.get_reg(1).unwrap(), 0);
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_MISALIGNED_FETCH as u64);
+        assert_eq!(view.mepc, TEST_BASE + 4);
+        assert_eq!(view.mtval, TEST_BASE + 6);
+        assert_eq!(emu.state.get_npc(), TEST_BASE + 12);
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        let mut emu = emu_with_program(&[
+            0x00c000ef, // jal ra, func
+            0x00158593, // addi a1, a1, 1
+            0x00100073, // ebreak
+            0x00560613, // func: addi a2, a2, 5
+            0x00008067, // ret
+        ]);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.get_reg(1).unwrap(), TEST_BASE + 4);
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+        assert_eq!(emu.get_reg(12).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_lui_sign_extends_immediate() {
+        let mut emu = emu_with_program(&[
+            0x800000b7, // lui ra, 0x80000
+            0xfffff137, // lui sp, 0xfffff
+            0x7ffff2b7, // lui t0, 0x7ffff
+        ]);
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(1).unwrap(), 0xffff_ffff_8000_0000);
+        assert_eq!(emu.get_reg(2).unwrap(), 0xffff_ffff_ffff_f000);
+        assert_eq!(emu.get_reg(5).unwrap(), 0x7fff_f000);
+    }
+
+    #[test]
+    fn test_auipc_negative_immediate() {
+        let mut emu = emu_with_program(&[
+            0x80000197, // auipc gp, 0x80000   pc - 0x80000000
+        ]);
+        emu.steps(1).unwrap();
+        assert_eq!(emu.get_reg(3).unwrap(), TEST_BASE.wrapping_sub(0x8000_0000));
+    }
+
+    #[test]
+    fn test_auipc_wraps_at_top_of_address_space() {
+        const HIGH_BASE: u64 = 0xffff_ffff_ffe0_0000;
+        let (mut config, mut device_file) = test_config();
+        config.memory.boot_pc = HIGH_BASE;
+        device_file.memory.memory_base = HIGH_BASE;
+        let mut emu = emu_from(config, &device_file);
+        let program: Vec<u8> = [
+            0x40000217u32, // auipc tp, 0x40000    越过地址空间顶端回绕
+            0x80000197,    // auipc gp, 0x80000
+        ]
+        .iter()
+        .flat_map(|inst| inst.to_le_bytes())
+        .collect();
+        emu.write_memory(HIGH_BASE, &program).unwrap();
+
+        emu.steps(2).unwrap();
+        assert_eq!(emu.get_reg(4).unwrap(), 0x3fe0_0000);
+        assert_eq!(emu.get_reg(3).unwrap(), 0xffff_ffff_7fe0_0004);
+    }
+
+    #[test]
+    fn test_shift_immediate_logical_vs_arithmetic() {
+        let mut emu = emu_with_program(&[
+            0xff100513, // li a0, -15
+            0x00455593, // srli a1, a0, 4
+            0x40455613, // srai a2, a0, 4     与 srli 仅 bit 30 不同
+            0x03f55693, // srli a3, a0, 63
+            0x43f55713, // srai a4, a0, 63
+            0x03f51793, // slli a5, a0, 63
+        ]);
+        emu.steps(6).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 0x0fff_ffff_ffff_ffff);
+        assert_eq!(emu.get_reg(12).unwrap(), 0xffff_ffff_ffff_ffff);
+        assert_eq!(emu.get_reg(13).unwrap(), 1);
+        assert_eq!(emu.get_reg(14).unwrap(), 0xffff_ffff_ffff_ffff);
+        assert_eq!(emu.get_reg(15).unwrap(), 0x8000_0000_0000_0000);
+    }
+}
@@ -0,0 +1,419 @@
+//! 软件浮点：按指定的舍入模式完成单/双精度运算并给出 fflags
+//!
+//! 运算先在整数上求出精确结果（或带粘滞位的足够宽的近似），再统一由 `round_pack` 舍入，
+//! 因此五种舍入模式与 NX/UF/OF 标志都与 IEEE 754 一致；下溢按 RISC-V 的约定在舍入后检测。
+//! 输入与输出都是位模式，NaN 结果总是规范 NaN
+
+use std::cmp::Ordering;
+
+use super::rv64f::{FFLAG_DZ, FFLAG_NV, FFLAG_NX, FFLAG_OF, FFLAG_UF, RM_RDN, RM_RMM, RM_RTZ, RM_RUP};
+
+/// 浮点格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Format {
+    /// 尾数位数（不含隐含的1）
+    mant_bits: u32,
+    exp_bits: u32,
+}
+
+pub(super) const F32: Format = Format { mant_bits: 23, exp_bits: 8 };
+pub(super) const F64: Format = Format { mant_bits: 52, exp_bits: 11 };
+
+impl Format {
+    fn bias(self) -> i32 {
+        (1 << (self.exp_bits - 1)) - 1
+    }
+
+    /// 最小正规数的指数
+    fn emin(self) -> i32 {
+        1 - self.bias()
+    }
+
+    fn exp_field_max(self) -> u64 {
+        (1 << self.exp_bits) - 1
+    }
+
+    fn frac_mask(self) -> u64 {
+        (1 << self.mant_bits) - 1
+    }
+
+    fn sign(self, negative: bool) -> u64 {
+        (negative as u64) << (self.mant_bits + self.exp_bits)
+    }
+
+    fn zero(self, negative: bool) -> u64 {
+        self.sign(negative)
+    }
+
+    fn inf(self, negative: bool) -> u64 {
+        self.sign(negative) | self.exp_field_max() << self.mant_bits
+    }
+
+    fn max_finite(self, negative: bool) -> u64 {
+        self.sign(negative) | (self.exp_field_max() - 1) << self.mant_bits | self.frac_mask()
+    }
+
+    fn canonical_nan(self) -> u64 {
+        self.exp_field_max() << self.mant_bits | 1 << (self.mant_bits - 1)
+    }
+}
+
+/// 运算结果：(位模式, fflags)
+pub(super) type FpResult = (u64, u64);
+
+/// 拆开的浮点值；有限值为 (-1)^negative * sig * 2^exp，零的 sig 为0
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    Nan { signaling: bool },
+    Inf { negative: bool },
+    Num { negative: bool, sig: u128, exp: i32 },
+}
+
+use Value::*;
+
+fn unpack(fmt: Format, bits: u64) -> Value {
+    let negative = bits >> (fmt.mant_bits + fmt.exp_bits) & 1 != 0;
+    let exp_field = bits >> fmt.mant_bits & fmt.exp_field_max();
+    let frac = bits & fmt.frac_mask();
+    let mant = fmt.mant_bits as i32;
+    if exp_field == fmt.exp_field_max() {
+        if frac == 0 {
+            Inf { negative }
+        } else {
+            Nan { signaling: frac >> (fmt.mant_bits - 1) == 0 }
+        }
+    } else if exp_field == 0 {
+        Num { negative, sig: frac as u128, exp: fmt.emin() - mant }
+    } else {
+        let sig = (frac | 1 << fmt.mant_bits) as u128;
+        Num { negative, sig, exp: exp_field as i32 - fmt.bias() - mant }
+    }
+}
+
+fn is_snan(value: Value) -> bool {
+    matches!(value, Nan { signaling: true })
+}
+
+/// 规范 NaN，invalid 时置 NV
+fn nan(fmt: Format, invalid: bool) -> FpResult {
+    (fmt.canonical_nan(), if invalid { FFLAG_NV } else { 0 })
+}
+
+/// 符号相反的两个值精确相消得到的零：只有 RDN 下为 -0
+fn cancelled_zero(fmt: Format, rm: u64) -> u64 {
+    fmt.zero(rm == RM_RDN)
+}
+
+fn msb(sig: u128) -> i32 {
+    127 - sig.leading_zeros() as i32
+}
+
+/// 将 sig 右移 shift 位并按舍入模式舍入，sticky 表示 sig 最低位以下还有非零部分；
+/// 返回 (舍入后的值, 是否不精确)。shift 至少为2，保证 sticky 小于舍入位的一半
+fn round_shift(sig: u128, shift: u32, sticky: bool, negative: bool, rm: u64) -> (u128, bool) {
+    let (kept, rem, half) = match shift {
+        // 移出的部分小于舍入位的一半
+        129.. => (0, sig, u128::MAX),
+        128 => (0, sig, 1 << 127),
+        _ => (sig >> shift, sig & ((1 << shift) - 1), 1 << (shift - 1)),
+    };
+    let inexact = rem != 0 || sticky;
+    let to_half = rem.cmp(&half).then(if sticky { Ordering::Greater } else { Ordering::Equal });
+    let increment = match rm {
+        RM_RTZ => false,
+        RM_RDN => inexact && negative,
+        RM_RUP => inexact && !negative,
+        RM_RMM => to_half != Ordering::Less,
+        // RNE
+        _ => to_half == Ordering::Greater || (to_half == Ordering::Equal && kept & 1 == 1),
+    };
+    (kept + increment as u128, inexact)
+}
+
+/// 将 (-1)^negative * (sig + ε) * 2^exp 舍入到 fmt 并打包，ε ∈ [0, 1) 仅在 sticky 时非零；sig 不能为0
+fn round_pack(fmt: Format, negative: bool, sig: u128, exp: i32, sticky: bool, rm: u64) -> FpResult {
+    // 规范化使最高位位于第125位，舍入时至少移出2位
+    let (sig, exp, sticky) = match msb(sig) {
+        top @ 126.. => {
+            let shift = top - 125;
+            (sig >> shift, exp + shift, sticky || sig & ((1 << shift) - 1) != 0)
+        }
+        top => (sig << (125 - top), exp - (125 - top), sticky),
+    };
+    let mant = fmt.mant_bits as i32;
+    let emin = fmt.emin();
+    // 最高位的指数，以及结果最低位的指数（非正规数的最低位固定）
+    let top = exp + 125;
+    let mut lsb = top.max(emin) - mant;
+    let (mut kept, inexact) = round_shift(sig, (lsb - exp) as u32, sticky, negative, rm);
+    if kept >> (mant + 1) != 0 {
+        kept >>= 1;
+        lsb += 1;
+    }
+
+    let mut flags = if inexact { FFLAG_NX } else { 0 };
+    // 舍入后检测下溢：按无界指数范围舍入的结果仍小于最小正规数
+    if inexact && top < emin {
+        let tiny = top < emin - 1 || {
+            let (wide, _) = round_shift(sig, (top - mant - exp) as u32, sticky, negative, rm);
+            wide >> (mant + 1) == 0
+        };
+        if tiny {
+            flags |= FFLAG_UF;
+        }
+    }
+
+    let exp_field = if kept >> mant == 0 { 0 } else { (lsb + mant + fmt.bias()) as u64 };
+    if exp_field >= fmt.exp_field_max() {
+        let to_inf = match rm {
+            RM_RTZ => false,
+            RM_RDN => negative,
+            RM_RUP => !negative,
+            _ => true,
+        };
+        let bits = if to_inf { fmt.inf(negative) } else { fmt.max_finite(negative) };
+        return (bits, flags | FFLAG_OF | FFLAG_NX);
+    }
+    (fmt.sign(negative) | exp_field << fmt.mant_bits | kept as u64 & fmt.frac_mask(), flags)
+}
+
+/// 将 sig * 2^exp 对齐到指数 to，右移丢弃的非零位记为 sticky
+fn align(sig: u128, exp: i32, to: i32) -> (u128, bool) {
+    match exp - to {
+        shift @ 0.. => (sig << shift, false),
+        shift @ -127..0 => (sig >> -shift, sig & ((1 << -shift) - 1) != 0),
+        _ => (0, sig != 0),
+    }
+}
+
+/// 两个有限值之和，操作数的 sig 不超过 106 位
+fn add_num(fmt: Format, a: (bool, u128, i32), b: (bool, u128, i32), rm: u64) -> FpResult {
+    let ((sa, siga, ea), (sb, sigb, eb)) = (a, b);
+    match (siga, sigb) {
+        (0, 0) => return (if sa == sb { fmt.zero(sa) } else { cancelled_zero(fmt, rm) }, 0),
+        (0, _) => return round_pack(fmt, sb, sigb, eb, false, rm),
+        (_, 0) => return round_pack(fmt, sa, siga, ea, false, rm),
+        _ => (),
+    }
+    // 对齐到公共指数：较大的操作数左移后最高位不超过第124位，只有相差很远的较小操作数需要右移，
+    // 此时相减最多损失1位，粘滞位远低于结果的舍入位
+    let top = (ea + msb(siga)).max(eb + msb(sigb));
+    let to = ea.min(eb).max(top - 124);
+    let (a, sticky_a) = align(siga, ea, to);
+    let (b, sticky_b) = align(sigb, eb, to);
+    if sa == sb {
+        return round_pack(fmt, sa, a + b, to, sticky_a || sticky_b, rm);
+    }
+    // 异号：大减小，带粘滞位的一方必然较小，借位后粘滞位保持
+    match a.cmp(&b) {
+        Ordering::Greater => round_pack(fmt, sa, a - b - sticky_b as u128, to, sticky_b, rm),
+        Ordering::Less => round_pack(fmt, sb, b - a - sticky_a as u128, to, sticky_a, rm),
+        Ordering::Equal => (cancelled_zero(fmt, rm), 0),
+    }
+}
+
+pub(super) fn add(fmt: Format, a: u64, b: u64, rm: u64) -> FpResult {
+    match (unpack(fmt, a), unpack(fmt, b)) {
+        (x @ Nan { .. }, y) | (x, y @ Nan { .. }) => nan(fmt, is_snan(x) || is_snan(y)),
+        (Inf { negative: na }, Inf { negative: nb }) if na != nb => nan(fmt, true),
+        (Inf { negative }, _) | (_, Inf { negative }) => (fmt.inf(negative), 0),
+        (Num { negative: na, sig: siga, exp: ea }, Num { negative: nb, sig: sigb, exp: eb }) => {
+            add_num(fmt, (na, siga, ea), (nb, sigb, eb), rm)
+        }
+    }
+}
+
+pub(super) fn sub(fmt: Format, a: u64, b: u64, rm: u64) -> FpResult {
+    add(fmt, a, b ^ fmt.sign(true), rm)
+}
+
+pub(super) fn mul(fmt: Format, a: u64, b: u64, rm: u64) -> FpResult {
+    match (unpack(fmt, a), unpack(fmt, b)) {
+        (x @ Nan { .. }, y) | (x, y @ Nan { .. }) => nan(fmt, is_snan(x) || is_snan(y)),
+        (Inf { .. }, Num { sig: 0, .. }) | (Num { sig: 0, .. }, Inf { .. }) => nan(fmt, true),
+        (Inf { negative: na }, Inf { negative: nb })
+        | (Inf { negative: na }, Num { negative: nb, .. })
+        | (Num { negative: na, .. }, Inf { negative: nb }) => (fmt.inf(na != nb), 0),
+        (Num { negative: na, sig: siga, exp: ea }, Num { negative: nb, sig: sigb, exp: eb }) => {
+            if siga == 0 || sigb == 0 {
+                return (fmt.zero(na != nb), 0);
+            }
+            round_pack(fmt, na != nb, siga * sigb, ea + eb, false, rm)
+        }
+    }
+}
+
+pub(super) fn div(fmt: Format, a: u64, b: u64, rm: u64) -> FpResult {
+    match (unpack(fmt, a), unpack(fmt, b)) {
+        (x @ Nan { .. }, y) | (x, y @ Nan { .. }) => nan(fmt, is_snan(x) || is_snan(y)),
+        (Inf { .. }, Inf { .. }) | (Num { sig: 0, .. }, Num { sig: 0, .. }) => nan(fmt, true),
+        (Inf { negative: na }, Num { negative: nb, .. }) => (fmt.inf(na != nb), 0),
+        (Num { negative: na, .. }, Inf { negative: nb }) => (fmt.zero(na != nb), 0),
+        (Num { negative: na, .. }, Num { negative: nb, sig: 0, .. }) => (fmt.inf(na != nb), FFLAG_DZ),
+        (Num { negative: na, sig: 0, .. }, Num { negative: nb, .. }) => (fmt.zero(na != nb), 0),
+        (Num { negative: na, sig: siga, exp: ea }, Num { negative: nb, sig: sigb, exp: eb }) => {
+            // 被除数左移到第126位，商至少有73位，余数记为粘滞位
+            let shift = 126 - msb(siga);
+            let num = siga << shift;
+            round_pack(fmt, na != nb, num / sigb, ea - shift - eb, !num.is_multiple_of(sigb), rm)
+        }
+    }
+}
+
+pub(super) fn sqrt(fmt: Format, a: u64, rm: u64) -> FpResult {
+    match unpack(fmt, a) {
+        x @ Nan { .. } => nan(fmt, is_snan(x)),
+        Inf { negative: false } => (fmt.inf(false), 0),
+        // -0 的平方根为 -0
+        Num { sig: 0, .. } => (a, 0),
+        Inf { negative: true } | Num { negative: true, .. } => nan(fmt, true),
+        Num { negative: false, sig, exp } => {
+            // 左移到第124或125位并使指数为偶数，整数平方根约有62位，余数记为粘滞位
+            let shift = 124 - msb(sig);
+            let (sig, exp) = (sig << shift, exp - shift);
+            let (sig, exp) = if exp % 2 == 0 { (sig, exp) } else { (sig << 1, exp - 1) };
+            let root = sig.isqrt();
+            round_pack(fmt, false, root, exp / 2, root * root != sig, rm)
+        }
+    }
+}
+
+/// 融合乘加 a * b + c，只舍入一次；0 * ∞ 即使加数为 quiet NaN 也置 NV
+pub(super) fn fma(fmt: Format, a: u64, b: u64, c: u64, rm: u64) -> FpResult {
+    let (a, b, c) = (unpack(fmt, a), unpack(fmt, b), unpack(fmt, c));
+    let invalid_product = matches!(
+        (a, b),
+        (Inf { .. }, Num { sig: 0, .. }) | (Num { sig: 0, .. }, Inf { .. })
+    );
+    if matches!(a, Nan { .. }) || matches!(b, Nan { .. }) || matches!(c, Nan { .. }) || invalid_product {
+        return nan(fmt, is_snan(a) || is_snan(b) || is_snan(c) || invalid_product);
+    }
+    let product_negative = match (a, b) {
+        (Inf { negative: na } | Num { negative: na, .. }, Inf { negative: nb } | Num { negative: nb, .. }) => {
+            na != nb
+        }
+        _ => unreachable!(),
+    };
+    match (a, b, c) {
+        (Inf { .. }, _, _) | (_, Inf { .. }, _) => match c {
+            Inf { negative } if negative != product_negative => nan(fmt, true),
+            _ => (fmt.inf(product_negative), 0),
+        },
+        (_, _, Inf { negative }) => (fmt.inf(negative), 0),
+        (Num { sig: siga, exp: ea, .. }, Num { sig: sigb, exp: eb, .. }, Num { negative, sig, exp }) => add_num(
+            fmt,
+            (product_negative, siga * sigb, ea + eb),
+            (negative, sig, exp),
+            rm,
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// 整数转浮点，value 为绝对值
+pub(super) fn from_int(fmt: Format, negative: bool, value: u64, rm: u64) -> FpResult {
+    if value == 0 {
+        return (fmt.zero(false), 0);
+    }
+    round_pack(fmt, negative, value as u128, 0, false, rm)
+}
+
+/// 浮点格式之间的转换
+pub(super) fn convert(from: Format, to: Format, a: u64, rm: u64) -> FpResult {
+    match unpack(from, a) {
+        x @ Nan { .. } => nan(to, is_snan(x)),
+        Inf { negative } => (to.inf(negative), 0),
+        Num { negative, sig: 0, .. } => (to.zero(negative), 0),
+        Num { negative, sig, exp } => round_pack(to, negative, sig, exp, false, rm),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RNE: u64 = 0;
+
+    type BinOp = fn(Format, u64, u64, u64) -> FpResult;
+
+    fn f32_op(op: BinOp, a: f32, b: f32, rm: u64) -> (f32, u64) {
+        let (bits, flags) = op(F32, a.to_bits() as u64, b.to_bits() as u64, rm);
+        (f32::from_bits(bits as u32), flags)
+    }
+
+    #[test]
+    fn test_rne_matches_host() {
+        let values = [1.0f64, -3.5, 0.1, 1e300, -7e-310, 2.5e-308, 123456.789, f64::MAX, f64::MIN_POSITIVE];
+        for &a in &values {
+            for &b in &values {
+                let ops: [(BinOp, f64); 4] = [(add, a + b), (sub, a - b), (mul, a * b), (div, a / b)];
+                for (op, want) in ops {
+                    let (bits, _) = op(F64, a.to_bits(), b.to_bits(), RNE);
+                    assert_eq!(f64::from_bits(bits), want, "{a:e} {b:e}");
+                }
+            }
+            let (bits, _) = sqrt(F64, a.abs().to_bits(), RNE);
+            assert_eq!(f64::from_bits(bits), a.abs().sqrt(), "{a:e}");
+            let (bits, _) = fma(F64, a.to_bits(), 3.0f64.to_bits(), 0.5f64.to_bits(), RNE);
+            assert_eq!(f64::from_bits(bits), a.mul_add(3.0, 0.5), "{a:e}");
+        }
+    }
+
+    #[test]
+    fn test_directed_rounding() {
+        let third = |rm| f32_op(div, 1.0, 3.0, rm).0;
+        assert_eq!(third(RNE), 1.0 / 3.0);
+        assert_eq!(third(RM_RDN), third(RM_RUP).next_down());
+        assert_eq!(third(RM_RTZ), third(RM_RDN));
+        assert_eq!(f32_op(div, -1.0, 3.0, RM_RDN).0, -third(RM_RUP));
+
+        // 1 + 2^-24 恰好位于两个可表示值中间
+        let tiny = f32::EPSILON / 2.0;
+        assert_eq!(f32_op(add, 1.0, tiny, RNE), (1.0, FFLAG_NX));
+        assert_eq!(f32_op(add, 1.0, tiny, RM_RMM).0, 1.0f32.next_up());
+        assert_eq!(f32_op(add, 1.0, tiny, RM_RUP).0, 1.0f32.next_up());
+        assert_eq!(f32_op(sub, 1.0, 1.0, RM_RDN).0.to_bits(), (-0.0f32).to_bits());
+    }
+
+    #[test]
+    fn test_overflow_and_underflow_flags() {
+        assert_eq!(f32_op(mul, f32::MAX, 2.0, RNE), (f32::INFINITY, FFLAG_OF | FFLAG_NX));
+        assert_eq!(f32_op(mul, f32::MAX, 2.0, RM_RTZ), (f32::MAX, FFLAG_OF | FFLAG_NX));
+        assert_eq!(f32_op(mul, -f32::MAX, 2.0, RM_RUP), (-f32::MAX, FFLAG_OF | FFLAG_NX));
+
+        // 非正规结果：不精确时置 UF，精确时不置
+        let min_sub = f32::from_bits(1);
+        assert_eq!(f32_op(mul, min_sub, 0.5, RNE), (0.0, FFLAG_UF | FFLAG_NX));
+        assert_eq!(f32_op(mul, min_sub, 0.5, RM_RUP), (min_sub, FFLAG_UF | FFLAG_NX));
+        assert_eq!(f32_op(mul, min_sub, 2.0, RNE), (f32::from_bits(2), 0));
+
+        // 舍入后恰为最小正规数：舍入后检测，不算下溢
+        let below_min = f32::MIN_POSITIVE.next_down();
+        assert_eq!(f32_op(mul, below_min, 1.0 + f32::EPSILON, RM_RUP), (f32::MIN_POSITIVE, FFLAG_NX));
+    }
+
+    #[test]
+    fn test_invalid_operations() {
+        assert_eq!(f32_op(sub, f32::INFINITY, f32::INFINITY, RNE).1, FFLAG_NV);
+        assert_eq!(f32_op(div, 0.0, 0.0, RNE).1, FFLAG_NV);
+        assert_eq!(f32_op(div, 1.0, -0.0, RNE), (f32::NEG_INFINITY, FFLAG_DZ));
+        assert_eq!(sqrt(F32, (-1.0f32).to_bits() as u64, RNE), (F32.canonical_nan(), FFLAG_NV));
+        let qnan = F32.canonical_nan();
+        let inf = f32::INFINITY.to_bits() as u64;
+        assert_eq!(fma(F32, inf, 0, qnan, RNE), (qnan, FFLAG_NV));
+        assert_eq!(fma(F32, qnan, 0, 0, RNE), (qnan, 0));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(convert(F64, F32, 0.1f64.to_bits(), RNE), ((0.1f32).to_bits() as u64, FFLAG_NX));
+        let (down, _) = convert(F64, F32, 0.1f64.to_bits(), RM_RDN);
+        let (up, _) = convert(F64, F32, 0.1f64.to_bits(), RM_RUP);
+        assert_eq!(f32::from_bits(down as u32).next_up(), f32::from_bits(up as u32));
+        assert_eq!(convert(F32, F64, 1.5f32.to_bits() as u64, RNE), (1.5f64.to_bits(), 0));
+
+        assert_eq!(from_int(F32, false, (1 << 24) + 1, RNE), (16777216.0f32.to_bits() as u64, FFLAG_NX));
+        assert_eq!(from_int(F32, false, (1 << 24) + 1, RM_RUP).0, 16777218.0f32.to_bits() as u64);
+        assert_eq!(from_int(F64, true, 42, RNE), ((-42.0f64).to_bits(), 0));
+    }
+}
@@ -0,0 +1,44 @@
+//! 未实现指令的集中登记表
+//!
+//! 硬件移植初期部分指令尚未实现。登记在此处的指令只有在配置
+//! `inst_set.stub_instructions` 中列出时才会被当作空操作执行（并记录日志），
+//! 否则仍按无法解码的指令处理
+
+use crate::emulator::Emulator;
+
+use super::Instruction;
+use super::insts::*;
+
+pub const STUBS: &[Instruction] = &[
+    Instruction {
+        mask: MASK_FENCE_I,
+        identifier: MATCH_FENCE_I,
+        name: "fence.i",
+        execute: |_emu: &mut Emulator, _inst: u32, pc: u64| {
+            tracing::debug!("PC {:#x}: fence.i 作为空操作执行", pc);
+            Ok(())
+        },
+    },
+    Instruction {
+        mask: MASK_SFENCE_VMA,
+        identifier: MATCH_SFENCE_VMA,
+        name: "sfence.vma",
+        execute: |_emu: &mut Emulator, _inst: u32, pc: u64| {
+            tracing::debug!("PC {:#x}: sfence.vma 作为空操作执行", pc);
+            Ok(())
+        },
+    },
+];
+
+/// 将配置中允许的桩指令加入指令集，未登记的名称会被忽略并给出警告
+pub fn apply_stubs(instructions_set: &mut Vec<&'static Instruction>, allowed: &[String]) {
+    for name in allowed {
+        match STUBS.iter().find(|stub| stub.name == name) {
+            Some(stub) => {
+                tracing::info!("指令 {} 将作为空操作执行", name);
+                instructions_set.push(stub);
+            }
+            None => tracing::warn!("未登记的桩指令: {}", name),
+        }
+    }
+}
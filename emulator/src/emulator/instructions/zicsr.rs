@@ -0,0 +1,260 @@
+//! Zicsr 扩展：CSR 读写指令
+//!
+//! 读取未写入过的 CSR 返回 0 而不是报错，固件常常会探测 CSR 是否存在；
+//! 写入只读 CSR（地址高两位为 0b11，如 cycle/time/instret）产生非法指令异常；
+//! RV32 下 cycleh/timeh/instreth 等读取计数器的高32位，写 mcycleh/minstreth 修改高32位
+
+use anyhow::Result;
+
+use crate::emulator::{Emulator, Exception::IllegalInstruction};
+use crate::utils::bit_utils::BitSlice;
+
+use super::Instruction;
+use super::insts::*;
+
+struct FormatCsr {
+    rd: u64,
+    /// 寄存器形式中为 rs1，立即数形式中为 5 位无符号立即数 zimm
+    rs1: u64,
+    csr: u16,
+}
+
+#[inline(always)]
+fn parse_format_csr(inst: u32) -> FormatCsr {
+    FormatCsr {
+        rd: inst.bit_range(7..12),
+        rs1: inst.bit_range(15..20),
+        csr: inst.bit_range(20..32) as u16,
+    }
+}
+
+/// CSR 地址高两位为 0b11 表示只读
+#[inline(always)]
+fn is_read_only(csr: u16) -> bool {
+    csr >> 10 == 0b11
+}
+
+/// 按规范顺序访问 CSR：need_read 为 false 时不读 CSR 也不写 rd；
+/// update 由旧值计算新值，返回 None 时不写 CSR
+#[inline(always)]
+fn csr_access(
+    emu: &mut Emulator,
+    inst: u32,
+    pc: u64,
+    c: &FormatCsr,
+    need_read: bool,
+    update: impl FnOnce(u64) -> Option<u64>,
+) -> Result<()> {
+    let old = if need_read { emu.csr_or_zero(c.csr) } else { 0 };
+    if let Some(value) = update(old) {
+        if is_read_only(c.csr) {
+            emu.execption = Some(IllegalInstruction {
+                instruction: inst,
+                addr: pc,
+            });
+            return Ok(());
+        }
+        emu.write_csr(c.csr, value)?;
+        emu.instret_written = matches!(c.csr, CSR_MINSTRET | CSR_MINSTRETH);
+        emu.cycles_written = matches!(c.csr, CSR_MCYCLE | CSR_MCYCLEH);
+    }
+    if need_read {
+        emu.set_reg(c.rd, old)?;
+    }
+    Ok(())
+}
+
+pub const RV_ZICSR: &[Instruction] = &[
+    Instruction {
+        mask: MASK_CSRRW,
+        identifier: MATCH_CSRRW,
+        name: "csrrw",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            // 先读 rs1，rd 与 rs1 相同时写回的是 CSR 旧值
+            let src = emu.get_reg(c.rs1)?;
+            // rd 为 x0 时不读 CSR，避免读操作的副作用
+            csr_access(emu, inst, pc, &c, c.rd != 0, |_| Some(src))
+        },
+    },
+    Instruction {
+        mask: MASK_CSRRS,
+        identifier: MATCH_CSRRS,
+        name: "csrrs",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            let src = emu.get_reg(c.rs1)?;
+            // rs1 为 x0 时只读不写
+            csr_access(emu, inst, pc, &c, true, |old| {
+                (c.rs1 != 0).then_some(old | src)
+            })
+        },
+    },
+    Instruction {
+        mask: MASK_CSRRC,
+        identifier: MATCH_CSRRC,
+        name: "csrrc",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            let src = emu.get_reg(c.rs1)?;
+            csr_access(emu, inst, pc, &c, true, |old| {
+                (c.rs1 != 0).then_some(old & !src)
+            })
+        },
+    },
+    Instruction {
+        mask: MASK_CSRRWI,
+        identifier: MATCH_CSRRWI,
+        name: "csrrwi",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            let zimm = c.rs1;
+            csr_access(emu, inst, pc, &c, c.rd != 0, |_| Some(zimm))
+        },
+    },
+    Instruction {
+        mask: MASK_CSRRSI,
+        identifier: MATCH_CSRRSI,
+        name: "csrrsi",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            let zimm = c.rs1;
+            csr_access(emu, inst, pc, &c, true, |old| {
+                (zimm != 0).then_some(old | zimm)
+            })
+        },
+    },
+    Instruction {
+        mask: MASK_CSRRCI,
+        identifier: MATCH_CSRRCI,
+        name: "csrrci",
+        execute: |emu: &mut Emulator, inst: u32, pc: u64| {
+            let c = parse_format_csr(inst);
+            let zimm = c.rs1;
+            csr_access(emu, inst, pc, &c, true, |old| {
+                (zimm != 0).then_some(old & !zimm)
+            })
+        },
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::const_values::Xlen;
+    use crate::emulator::instructions::insts::{
+        CAUSE_ILLEGAL_INSTRUCTION, CSR_MSCRATCH, CSR_MTVEC,
+    };
+    use crate::test_utils::{TEST_BASE, emu_from, load_program, test_config};
+
+    #[test]
+    fn test_mscratch_round_trip() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.zicsr = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x34051073, // csrw mscratch, a0
+                0x340025f3, // csrr a1, mscratch
+                0x34061673, // csrrw a2, mscratch, a2
+                0x340026f3, // csrr a3, mscratch
+                0x3401e773, // csrrsi a4, mscratch, 3
+                0x3400f7f3, // csrrci a5, mscratch, 1
+                0x34002873, // csrr a6, mscratch
+                0x7c0028f3, // csrr a7, 0x7c0     未写入过的 CSR 读出 0
+            ],
+        );
+        emu.set_reg(10, 0x1234).unwrap();
+        emu.set_reg(12, 0x10).unwrap();
+        emu.set_reg(17, 0xdead).unwrap();
+        emu.steps(8).unwrap();
+
+        assert_eq!(emu.get_reg(11).unwrap(), 0x1234);
+        // rd 与 rs1 相同：rd 得到旧值，CSR 得到原来的 rs1
+        assert_eq!(emu.get_reg(12).unwrap(), 0x1234);
+        assert_eq!(emu.get_reg(13).unwrap(), 0x10);
+        assert_eq!(emu.get_reg(14).unwrap(), 0x10);
+        assert_eq!(emu.get_reg(15).unwrap(), 0x13);
+        assert_eq!(emu.get_reg(16).unwrap(), 0x12);
+        assert_eq!(emu.get_reg(17).unwrap(), 0);
+        assert_eq!(emu.state.get_csr(CSR_MSCRATCH).unwrap(), 0x12);
+    }
+
+    #[test]
+    fn test_counter_csrs() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.zicsr = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x00100593, // li a1, 1
+                0x00200613, // li a2, 2
+                0x00c586b3, // add a3, a1, a2
+                0xc0202773, // rdinstret a4
+                0xc00027f3, // rdcycle a5
+                0xb0261073, // csrw minstret, a2
+                0xc0202873, // rdinstret a6
+                0xc0001073, // csrw cycle, zero   只读 CSR
+            ],
+        );
+        emu.steps(3).unwrap();
+        assert_eq!(emu.instret(), 3);
+        // rdinstret 读到的是此前已退休的指令数
+        emu.steps(1).unwrap();
+        assert_eq!(emu.get_reg(14).unwrap(), 3);
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(15).unwrap(), 4);
+        // 写入 minstret 的指令本身不计入，随后读到写入的值
+        assert_eq!(emu.get_reg(16).unwrap(), 2);
+        assert_eq!(emu.instret(), 3);
+
+        emu.state.set_csr(CSR_MTVEC, TEST_BASE + 0x40).unwrap();
+        emu.steps(1).unwrap();
+        assert_eq!(emu.trap_state().mcause, CAUSE_ILLEGAL_INSTRUCTION as u64);
+        assert_eq!(emu.instret(), 3);
+    }
+
+    #[test]
+    fn test_mcycle_write_is_read_back() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.zicsr = true;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x06400613, // li a2, 100
+                0xb0061073, // csrw mcycle, a2
+                0xb00026f3, // csrr a3, mcycle
+            ],
+        );
+        emu.steps(3).unwrap();
+        // 写入 mcycle 的指令本身不计入周期数，随后读到写入的值
+        assert_eq!(emu.get_reg(13).unwrap(), 100);
+        assert_eq!(emu.cycles(), 101);
+    }
+
+    #[test]
+    fn test_rv32_counter_high_halves() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.zicsr = true;
+        config.inst_set.xlen = Xlen::X32;
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x00500613, // li a2, 5
+                0xb8061073, // csrw mcycleh, a2
+                0xc80026f3, // csrr a3, cycleh
+                0xb0002773, // csrr a4, mcycle
+                0xb8261073, // csrw minstreth, a2
+            ],
+        );
+        emu.steps(5).unwrap();
+        // 写 mcycleh 只改高32位，低32位仍是此前的周期数
+        assert_eq!(emu.get_reg(13).unwrap(), 5);
+        assert_eq!(emu.get_reg(14).unwrap(), 2);
+        assert_eq!(emu.cycles(), 5 << 32 | 4);
+        assert_eq!(emu.instret(), 5 << 32 | 4);
+    }
+}
@@ -1,13 +1,16 @@
 //! 内存管理模块
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use thiserror::Error;
-use mmio_trait::{MmioDevice, DeviceError};
+use mmio_trait::{AssertionRequest, MmioDevice, DeviceError, HostRequest};
 
-use crate::const_values::EmuConfig;
+use crate::const_values::{EmuConfig, Endianness, MemoryFill};
+use super::debug_points::{WatchKind, Watchpoint};
+use super::fetch_cache::FetchCache;
+use super::instructions::is_compressed;
 
 /// 内存错误类型
 #[derive(Debug, Error)]
@@ -16,10 +19,20 @@ pub enum MemoryError {
     OutOfBounds { addr: u64, size: usize },
     #[error("内存对齐错误: 地址 {addr:#x}, 对齐要求 {alignment}")]
     Misaligned { addr: u64, alignment: usize },
-    #[error("MMIO 区域重叠: 地址 {addr:#x}")]
-    MmioOverlap { addr: u64 },
+    #[error("MMIO 区域重叠: 地址 {addr:#x} 与 '{conflict}' [{conflict_base:#x}, {conflict_end:#x}) 冲突")]
+    MmioOverlap { addr: u64, conflict: String, conflict_base: u64, conflict_end: u64 },
+    #[error("访问跨越区域边界: 地址 {addr:#x}, 大小 {size}, 边界 {boundary:#x}")]
+    Straddle { addr: u64, size: usize, boundary: u64 },
+    #[error("区域不完全位于主内存中: 地址 {addr:#x}, 大小 {size}")]
+    NotRam { addr: u64, size: usize },
+    #[error("写入只读区域: 地址 {addr:#x}, 大小 {size}")]
+    ReadOnly { addr: u64, size: usize },
+    #[error("栈溢出: 写入地址 {addr:#x} 位于栈保护区 [{guard_start:#x}, {guard_end:#x})")]
+    StackOverflow { addr: u64, guard_start: u64, guard_end: u64 },
     #[error("设备错误: {0}")]
     Device(#[from] DeviceError),
+    #[error("未映射的设备: {0}")]
+    UnknownDevice(String),
 }
 
 /// MMIO 区域
@@ -28,6 +41,50 @@ pub struct MmioRegion {
     pub size: u64,
     pub device: Arc<Mutex<dyn MmioDevice>>,
     pub name: String,
+    /// 设备寄存器字节序，大端设备的多字节访问会被透明地字节翻转
+    pub endianness: Endianness,
+    /// 连接到的 PLIC 中断源号，None 表示设备的中断直接作为 mip 位
+    pub irq_source: Option<u32>,
+    /// 每次访问额外计入的周期数
+    pub latency: u64,
+    /// 是否允许从该区域取指（RAM/ROM 设备）
+    pub executable: bool,
+}
+
+impl MmioRegion {
+    /// 读取设备寄存器，返回小端字节序的数据
+    #[inline(always)]
+    fn read(&self, offset: u64, size: usize) -> Result<Vec<u8>, MemoryError> {
+        let mut res = self.device.lock().unwrap().read(offset, size)?;
+        if self.endianness == Endianness::Big {
+            res.reverse();
+        }
+        Ok(res)
+    }
+
+    /// 写入设备寄存器，data 为小端字节序；返回设备因此发出的请求
+    #[inline(always)]
+    fn write(&self, offset: u64, data: &[u8]) -> Result<DeviceRequests, MemoryError> {
+        let mut device = self.device.lock().unwrap();
+        if self.endianness == Endianness::Big {
+            let swapped: Vec<u8> = data.iter().rev().copied().collect();
+            device.write(offset, &swapped)?;
+        } else {
+            device.write(offset, data)?;
+        }
+        Ok(DeviceRequests {
+            exit_code: device.take_exit_code(),
+            host_request: device.take_host_request(),
+            assertion: device.take_assertion_failure(),
+        })
+    }
+}
+
+/// 设备因一次写入发出的请求
+struct DeviceRequests {
+    exit_code: Option<u8>,
+    host_request: Option<HostRequest>,
+    assertion: Option<AssertionRequest>,
 }
 
 impl std::fmt::Debug for MmioRegion {
@@ -36,10 +93,91 @@ impl std::fmt::Debug for MmioRegion {
             .field("base", &format_args!("{:#x}", self.base))
             .field("size", &format_args!("{:#x}", self.size))
             .field("name", &self.name)
+            .field("endianness", &self.endianness)
             .finish()
     }
 }
 
+/// 一次读写（由内存记录，供模拟器的 MMIO 日志与访存追踪器使用）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MmioAccess {
+    pub addr: u64,
+    pub size: usize,
+    pub is_write: bool,
+    /// 读出或写入的值（小端拼接）
+    pub value: u64,
+}
+
+impl MmioAccess {
+    fn new(addr: u64, data: &[u8], is_write: bool) -> Self {
+        let mut bytes = [0u8; 8];
+        let len = data.len().min(8);
+        bytes[..len].copy_from_slice(&data[..len]);
+        Self {
+            addr,
+            size: data.len(),
+            is_write,
+            value: u64::from_le_bytes(bytes),
+        }
+    }
+}
+
+/// MMIO 日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MmioRecord {
+    /// 发起访问的指令地址
+    pub pc: u64,
+    pub device: String,
+    /// 相对于设备基址的偏移
+    pub offset: u64,
+    pub size: usize,
+    pub is_write: bool,
+    pub value: u64,
+}
+
+/// 带名称标记的主内存区域，用于调试时追踪对特定分配的访问
+#[derive(Debug, Clone)]
+pub struct TaggedRange {
+    pub name: String,
+    pub base: u64,
+    pub size: u64,
+}
+
+/// 一次落在标记区域内的访问（tag 为标记区域的下标）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TagHit {
+    pub tag: usize,
+    pub addr: u64,
+    pub size: usize,
+    pub is_write: bool,
+}
+
+/// 标记区域访问日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagRecord {
+    /// 发起访问的指令地址
+    pub pc: u64,
+    pub tag: String,
+    pub addr: u64,
+    pub size: usize,
+    pub is_write: bool,
+}
+
+/// 只读区域的用途，决定写入时报告的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProtectKind {
+    ReadOnly,
+    StackGuard,
+}
+
+/// 主内存中的只读区域
+#[derive(Debug, Clone, Copy)]
+struct ProtectedRegion {
+    base: u64,
+    size: u64,
+    kind: ProtectKind,
+}
+
 /// 内存管理结构
 #[derive(Debug)]
 pub struct Memory {
@@ -55,8 +193,43 @@ pub struct Memory {
     mmio_regions: Vec<MmioRegion>,
     /// is last mmio
     is_last_mmio: RefCell<bool>,
+    /// 取指缓存（可选）
+    fetch_cache: Option<RefCell<FetchCache>>,
+    /// 主内存中的只读区域（含栈保护区）
+    protected_regions: Vec<ProtectedRegion>,
+    /// 设备发出、尚未被模拟器取走的退出请求
+    exit_request: Cell<Option<u8>>,
+    /// 设备发出、尚未被模拟器取走的宿主请求
+    host_request: Cell<Option<HostRequest>>,
+    /// 设备报告、尚未被模拟器取走的断言失败
+    assertion_failure: Cell<Option<AssertionRequest>>,
+    /// 最近一次 MMIO 访问
+    last_mmio_access: Cell<Option<MmioAccess>>,
+    /// 标记区域
+    tagged_ranges: Vec<TaggedRange>,
+    /// 尚未被取走的标记区域访问最多保留的条数，0 表示不记录（默认关闭，避免影响访存性能）
+    tag_hit_limit: usize,
+    /// 尚未被模拟器取走的标记区域访问
+    tag_hits: RefCell<Vec<TagHit>>,
+    /// 观察点（与模拟器的断点/观察点表同步）
+    watchpoints: Vec<Watchpoint>,
+    /// 尚未被模拟器取走的第一次观察点命中：(命中的地址, 是否为写)
+    watch_hit: Cell<Option<(u64, bool)>>,
+    /// 是否记录每次读写（默认关闭，供访存追踪器使用）
+    access_tracking: bool,
+    /// 尚未被取走的读写记录
+    accesses: RefCell<Vec<MmioAccess>>,
+    /// lr 建立的保留集（按 RESERVATION_GRANULE 对齐的地址），写入该范围的主内存会将其清除
+    reservation: Option<u64>,
+    /// 每次读写主内存额外计入的周期数
+    mem_latency: u64,
+    /// 尚未被模拟器取走的访存延迟周期数
+    access_latency: Cell<u64>,
 }
 
+/// 保留集的大小
+const RESERVATION_GRANULE: u64 = 8;
+
 impl Memory {
     /// 使用主配置和设备配置创建内存实例
     pub fn new(config: Rc<EmuConfig>, device_file: &crate::const_values::DeviceFile) -> Result<Self, MemoryError> {
@@ -64,23 +237,227 @@ impl Memory {
         if !size.is_power_of_two() {
             return Err(MemoryError::Misaligned { addr: 0, alignment: 2 });
         }
-        Ok(Self {
-            data: vec![0; size],
+        let fetch_cache = match config.others.fetch_cache_size {
+            0 => None,
+            n => Some(RefCell::new(FetchCache::new(n))),
+        };
+        let stack_guard = config.memory.stack_guard;
+        // 模拟器每步都会取走访问，超出访问日志容量的部分没有意义
+        let tag_hit_limit = config.debug.tag_log_size;
+        let data = match config.memory.fill {
+            MemoryFill::Zero => vec![0; size],
+            MemoryFill::Poison => vec![MemoryFill::POISON_BYTE; size],
+            MemoryFill::Incrementing => (0..size).map(|i| i as u8).collect(),
+        };
+        let mut memory = Self {
+            data,
             config,
             memory_base: device_file.memory.memory_base,
             memory_size: device_file.memory.memory_size * 1024 * 1024,
             mmio_regions: Vec::new(),
             is_last_mmio: RefCell::new(false),
-        })
+            fetch_cache,
+            protected_regions: Vec::new(),
+            exit_request: Cell::new(None),
+            host_request: Cell::new(None),
+            assertion_failure: Cell::new(None),
+            last_mmio_access: Cell::new(None),
+            tagged_ranges: Vec::new(),
+            tag_hit_limit,
+            tag_hits: RefCell::new(Vec::new()),
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+            access_tracking: false,
+            accesses: RefCell::new(Vec::new()),
+            reservation: None,
+            mem_latency: device_file.memory.latency,
+            access_latency: Cell::new(0),
+        };
+        if let Some(guard) = stack_guard {
+            memory.set_stack_guard(guard.stack_base, guard.guard_size);
+        }
+        Ok(memory)
+    }
+
+    /// 将主内存中的 [base, base + size) 设为只读
+    pub fn add_readonly_region(&mut self, base: u64, size: u64) {
+        self.protected_regions.push(ProtectedRegion { base, size, kind: ProtectKind::ReadOnly });
+    }
+
+    /// 在栈底 stack_base 下方设置大小为 guard_size 的只读保护区
+    pub fn set_stack_guard(&mut self, stack_base: u64, guard_size: u64) {
+        let base = stack_base.saturating_sub(guard_size);
+        tracing::info!("启用栈保护区: [{:#x}, {:#x})", base, stack_base);
+        self.protected_regions.retain(|r| r.kind != ProtectKind::StackGuard);
+        self.protected_regions.push(ProtectedRegion {
+            base,
+            size: stack_base - base,
+            kind: ProtectKind::StackGuard,
+        });
+    }
+
+    /// 将主内存中的 [base, base + size) 标记为 name
+    pub fn add_tagged_range(&mut self, name: impl Into<String>, base: u64, size: u64) {
+        self.tagged_ranges.push(TaggedRange { name: name.into(), base, size });
+    }
+
+    /// 全部标记区域
+    pub fn tagged_ranges(&self) -> &[TaggedRange] {
+        &self.tagged_ranges
+    }
+
+    /// 开启对标记区域访问的记录，未被取走的访问最多保留 limit 条（超出后不再记录），0 表示关闭
+    pub fn set_tag_tracking(&mut self, limit: usize) {
+        self.tag_hit_limit = limit;
+        self.tag_hits.borrow_mut().clear();
+    }
+
+    /// 取走尚未处理的标记区域访问
+    pub fn take_tag_hits(&self) -> Vec<TagHit> {
+        std::mem::take(&mut *self.tag_hits.borrow_mut())
+    }
+
+    /// 设置需要检查的观察点
+    pub(super) fn set_watchpoints(&mut self, watchpoints: Vec<Watchpoint>) {
+        self.watchpoints = watchpoints;
+        self.watch_hit.set(None);
+    }
+
+    /// 取走尚未处理的观察点命中：(命中的地址, 是否为写)
+    #[inline(always)]
+    pub fn take_watch_hit(&self) -> Option<(u64, bool)> {
+        self.watch_hit.take()
+    }
+
+    /// 开启/关闭对每次读写的记录；开启期间按宽度读写的快速路径也经由 read/write 记录
+    pub fn set_access_tracking(&mut self, enabled: bool) {
+        self.access_tracking = enabled;
+        self.accesses.borrow_mut().clear();
+    }
+
+    /// 是否正在记录读写
+    #[inline(always)]
+    pub fn is_access_tracking(&self) -> bool {
+        self.access_tracking
+    }
+
+    /// 按发生顺序取走尚未处理的读写记录
+    pub fn take_accesses(&self) -> Vec<MmioAccess> {
+        std::mem::take(&mut *self.accesses.borrow_mut())
+    }
+
+    #[cold]
+    fn record_access(&self, addr: u64, data: &[u8], is_write: bool) {
+        self.accesses.borrow_mut().push(MmioAccess::new(addr, data, is_write));
+    }
+
+    /// 记录落在标记区域或观察点内的主内存访问
+    #[inline(always)]
+    fn note_tagged(&self, addr: u64, size: usize, is_write: bool) {
+        if self.tag_hit_limit > 0 {
+            self.record_tag_hit(addr, size, is_write);
+        }
+        self.note_watched(addr, size, is_write);
+    }
+
+    /// 记录落在观察点内的访问
+    #[inline(always)]
+    fn note_watched(&self, addr: u64, size: usize, is_write: bool) {
+        if !self.watchpoints.is_empty() {
+            self.record_watch_hit(addr, size, is_write);
+        }
+    }
+
+    /// 只保留第一次命中，报告的地址是访问范围内第一个被观察的字节
+    #[cold]
+    fn record_watch_hit(&self, addr: u64, size: usize, is_write: bool) {
+        if self.watch_hit.get().is_some() {
+            return;
+        }
+        let end = addr.saturating_add(size as u64);
+        let hit = self.watchpoints.iter().find(|watch| {
+            let kind_matches = match watch.kind {
+                WatchKind::Write => is_write,
+                WatchKind::Read => !is_write,
+                WatchKind::ReadWrite => true,
+            };
+            kind_matches && addr < watch.addr.saturating_add(watch.len) && end > watch.addr
+        });
+        if let Some(watch) = hit {
+            self.watch_hit.set(Some((addr.max(watch.addr), is_write)));
+        }
+    }
+
+    /// 累计访存延迟
+    #[inline(always)]
+    fn add_latency(&self, latency: u64) {
+        if latency != 0 {
+            self.access_latency.set(self.access_latency.get() + latency);
+        }
+    }
+
+    /// 取走累计的访存延迟周期数
+    #[inline(always)]
+    pub fn take_access_latency(&self) -> u64 {
+        self.access_latency.take()
+    }
+
+    #[cold]
+    fn record_tag_hit(&self, addr: u64, size: usize, is_write: bool) {
+        let end = addr.saturating_add(size as u64);
+        let mut hits = self.tag_hits.borrow_mut();
+        for (tag, range) in self.tagged_ranges.iter().enumerate() {
+            if hits.len() >= self.tag_hit_limit {
+                break;
+            }
+            if addr < range.base + range.size && end > range.base {
+                hits.push(TagHit { tag, addr, size, is_write });
+            }
+        }
     }
 
-    /// 映射 MMIO 设备
+    /// 检查主内存写入是否落在只读区域内
+    #[inline(always)]
+    pub(crate) fn check_writable(&self, addr: u64, size: usize) -> Result<(), MemoryError> {
+        if self.protected_regions.is_empty() {
+            return Ok(());
+        }
+        let end = addr.saturating_add(size as u64);
+        for region in &self.protected_regions {
+            let region_end = region.base + region.size;
+            if addr < region_end && end > region.base {
+                return Err(match region.kind {
+                    ProtectKind::ReadOnly => MemoryError::ReadOnly { addr, size },
+                    ProtectKind::StackGuard => MemoryError::StackOverflow {
+                        addr,
+                        guard_start: region.base,
+                        guard_end: region_end,
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 映射 MMIO 设备（小端）
     pub fn map_mmio(
         &mut self,
         base: u64,
         size: u64,
         device: Arc<Mutex<dyn MmioDevice>>,
         name: String,
+    ) -> Result<(), MemoryError> {
+        self.map_mmio_with_endianness(base, size, device, name, Endianness::Little)
+    }
+
+    /// 以指定字节序映射 MMIO 设备
+    pub fn map_mmio_with_endianness(
+        &mut self,
+        base: u64,
+        size: u64,
+        device: Arc<Mutex<dyn MmioDevice>>,
+        name: String,
+        endianness: Endianness,
     ) -> Result<(), MemoryError> {
         let new_end = base + size;
 
@@ -89,12 +466,23 @@ impl Memory {
             let region_end = region.base + region.size;
 
             if base < region_end && new_end > region.base {
-                return Err(MemoryError::MmioOverlap { addr: base });
+                return Err(MemoryError::MmioOverlap {
+                    addr: base,
+                    conflict: region.name.clone(),
+                    conflict_base: region.base,
+                    conflict_end: region_end,
+                });
             }
         }
 
-        if base < (self.memory_base + self.memory_size as u64) && new_end > self.memory_base {
-            return Err(MemoryError::MmioOverlap { addr: base });
+        let memory_end = self.memory_base + self.memory_size as u64;
+        if base < memory_end && new_end > self.memory_base {
+            return Err(MemoryError::MmioOverlap {
+                addr: base,
+                conflict: "主内存".to_string(),
+                conflict_base: self.memory_base,
+                conflict_end: memory_end,
+            });
         }
 
         self.mmio_regions.push(MmioRegion {
@@ -102,11 +490,133 @@ impl Memory {
             size,
             device,
             name,
+            endianness,
+            irq_source: None,
+            latency: 0,
+            executable: false,
         });
 
         Ok(())
     }
 
+    /// 将名为 name 的设备的中断连接到 PLIC 的中断源 source
+    pub fn route_irq(&mut self, name: &str, source: u32) -> Result<(), MemoryError> {
+        let region = self
+            .mmio_regions
+            .iter_mut()
+            .find(|region| region.name == name)
+            .ok_or_else(|| MemoryError::UnknownDevice(name.to_string()))?;
+        region.irq_source = Some(source);
+        Ok(())
+    }
+
+    /// 设置名为 name 的设备每次访问额外计入的周期数
+    pub fn set_device_latency(&mut self, name: &str, latency: u64) -> Result<(), MemoryError> {
+        let region = self
+            .mmio_regions
+            .iter_mut()
+            .find(|region| region.name == name)
+            .ok_or_else(|| MemoryError::UnknownDevice(name.to_string()))?;
+        region.latency = latency;
+        Ok(())
+    }
+
+    /// 允许从名为 name 的设备区域取指
+    pub fn set_device_executable(&mut self, name: &str) -> Result<(), MemoryError> {
+        let region = self
+            .mmio_regions
+            .iter_mut()
+            .find(|region| region.name == name)
+            .ok_or_else(|| MemoryError::UnknownDevice(name.to_string()))?;
+        region.executable = true;
+        Ok(())
+    }
+
+    /// addr 是否位于允许取指的 MMIO 区域中
+    pub fn is_exec_mmio(&self, addr: u64) -> bool {
+        self.find_mmio_region(addr).is_some_and(|region| region.executable)
+    }
+
+    /// 将主内存整体搬移到 new_base，保留原有数据
+    ///
+    /// 新区间不得与已映射的 MMIO 区域重叠；只读区域与标记区域随主内存一起平移，lr 保留集被清除
+    pub fn set_base(&mut self, new_base: u64) -> Result<(), MemoryError> {
+        let size = self.memory_size as u64;
+        let new_end = new_base
+            .checked_add(size)
+            .ok_or(MemoryError::OutOfBounds { addr: new_base, size: self.memory_size })?;
+        if let Some(region) = self
+            .mmio_regions
+            .iter()
+            .find(|region| new_base < region.base + region.size && new_end > region.base)
+        {
+            return Err(MemoryError::MmioOverlap {
+                addr: new_base,
+                conflict: region.name.clone(),
+                conflict_base: region.base,
+                conflict_end: region.base + region.size,
+            });
+        }
+
+        let old_base = self.memory_base;
+        for region in &mut self.protected_regions {
+            region.base = region.base - old_base + new_base;
+        }
+        for range in &mut self.tagged_ranges {
+            range.base = range.base - old_base + new_base;
+        }
+        self.reservation = None;
+        self.memory_base = new_base;
+        if let Some(cache) = &self.fetch_cache {
+            cache.borrow_mut().clear();
+        }
+        tracing::info!("主内存基址由 {:#x} 搬移到 {:#x}", old_base, new_base);
+        Ok(())
+    }
+
+    /// 驱动所有 MMIO 设备前进 cycles 个周期，并收集设备因此发出的退出请求
+    pub fn tick_devices(&self, cycles: u64) {
+        for region in &self.mmio_regions {
+            let mut device = region.device.lock().unwrap();
+            device.tick(cycles);
+            if let Some(code) = device.take_exit_code() {
+                self.exit_request.set(Some(code));
+            }
+        }
+    }
+
+    /// 汇总所有 MMIO 设备当前挂起的中断，第 n 位对应中断号 n
+    ///
+    /// 连接到 PLIC 的设备先汇总为中断源电平交给中断控制器（未实现 `set_irq_sources` 的设备忽略），
+    /// 其余设备（包括 PLIC 本身）的 `irq_pending` 直接作为 mip 中的位号
+    pub fn device_irq_lines(&self) -> u64 {
+        let mut sources = 0u64;
+        for region in &self.mmio_regions {
+            if let Some(source) = region.irq_source
+                && source < 64
+                && region.device.lock().unwrap().irq_pending().is_some()
+            {
+                sources |= 1 << source;
+            }
+        }
+        let mut lines = 0;
+        for region in self.mmio_regions.iter().filter(|region| region.irq_source.is_none()) {
+            let mut device = region.device.lock().unwrap();
+            device.set_irq_sources(sources);
+            if let Some(irq) = device.irq_pending().filter(|&irq| irq < 64) {
+                lines |= 1 << irq;
+            }
+        }
+        lines
+    }
+
+    /// 冻结/恢复所有 MMIO 设备的时间
+    pub fn set_devices_time_frozen(&self, frozen: bool) {
+        for region in &self.mmio_regions {
+            region.device.lock().unwrap().set_time_frozen(frozen);
+        }
+    }
+
     /// 排序 MMIO 区域
     pub fn sort_mmio_regions(&mut self) {
         self.mmio_regions.sort_by_key(|region| region.base);
@@ -132,6 +642,12 @@ impl Memory {
             }).ok().map(|index| &self.mmio_regions[index])
     }
 
+    /// 返回覆盖指定地址的 MMIO 区域 (名称, 基址, 大小)
+    pub fn mmio_region_at(&self, addr: u64) -> Option<(&str, u64, u64)> {
+        self.find_mmio_region(addr)
+            .map(|region| (region.name.as_str(), region.base, region.size))
+    }
+
     #[inline(always)]
     pub fn is_mem_region(&self, addr: u64) -> bool {
         addr >= self.memory_base && addr < self.memory_base + self.memory_size as u64
@@ -182,16 +698,43 @@ impl Memory {
         Ok(real_addr)
     }
 
+    /// 从主内存开始但越过主内存末尾的访问
+    #[cold]
+    fn ram_straddle(&self, addr: u64, size: usize) -> MemoryError {
+        MemoryError::Straddle { addr, size, boundary: self.memory_base + self.memory_size as u64 }
+    }
+
+    /// 检查 MMIO 访问是否越过所在区域的末尾，返回区域内的偏移
+    #[inline(always)]
+    fn mmio_offset(region: &MmioRegion, addr: u64, size: usize) -> Result<u64, MemoryError> {
+        let boundary = region.base + region.size;
+        if addr.saturating_add(size as u64) > boundary {
+            return Err(MemoryError::Straddle { addr, size, boundary });
+        }
+        Ok(addr - region.base)
+    }
+
     /// 读取内存
     #[inline(always)]
     pub fn read(&self, addr: u64, size: usize) -> Result<Vec<u8>, MemoryError> {
+        let res = self.read_untracked(addr, size)?;
+        if self.access_tracking {
+            self.record_access(addr, &res, false);
+        }
+        Ok(res)
+    }
+
+    #[inline(always)]
+    fn read_untracked(&self, addr: u64, size: usize) -> Result<Vec<u8>, MemoryError> {
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, size, false);
+            self.add_latency(self.mem_latency);
             // 普通内存访问 - 根据长度选择优化路径
             match size {
                 1 => {
                     // 字节访问
                     if !self.is_mem_region_range(addr, 1) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 1 });
+                        return Err(self.ram_straddle(addr, 1));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = unsafe { self.read_byte_unsafe(real_addr) };
@@ -200,7 +743,7 @@ impl Memory {
                 2 => {
                     // 半字访问
                     if !self.is_mem_region_range(addr, 2) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 2 });
+                        return Err(self.ram_straddle(addr, 2));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = unsafe { self.read_halfword_unsafe(real_addr) };
@@ -209,7 +752,7 @@ impl Memory {
                 4 => {
                     // 字访问
                     if !self.is_mem_region_range(addr, 4) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 4 });
+                        return Err(self.ram_straddle(addr, 4));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = unsafe { self.read_word_unsafe(real_addr) };
@@ -218,7 +761,7 @@ impl Memory {
                 8 => {
                     // 双字访问
                     if !self.is_mem_region_range(addr, 8) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 8 });
+                        return Err(self.ram_straddle(addr, 8));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = unsafe { self.read_doubleword_unsafe(real_addr) };
@@ -226,6 +769,9 @@ impl Memory {
                 }
                 _ => {
                     // 非标准长度，使用传统方法
+                    if !self.is_mem_region_range(addr, size) {
+                        return Err(self.ram_straddle(addr, size));
+                    }
                     let real_addr = self.translate_address(addr, size, 1)?;
                     let start = real_addr as usize;
                     return Ok(self.data[start..start + size].to_vec());
@@ -233,16 +779,44 @@ impl Memory {
             }
         }
 
-        // 检查是否为 MMIO 访问
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            let res = device.read(offset, size)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(res);
+        // MMIO访问
+        self.read_mmio(addr, size)
+    }
+
+    /// 主内存基地址
+    #[inline(always)]
+    pub fn memory_base(&self) -> u64 {
+        self.memory_base
+    }
+
+    /// 整个主内存的内容
+    #[inline(always)]
+    pub fn ram(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 用 data 整体覆盖主内存（长度须与主内存一致），清空取指缓存与保留集
+    pub fn restore_ram(&mut self, data: &[u8]) -> Result<(), MemoryError> {
+        if data.len() != self.data.len() {
+            return Err(MemoryError::OutOfBounds { addr: self.memory_base, size: data.len() });
         }
+        self.data.copy_from_slice(data);
+        if let Some(cache) = &self.fetch_cache {
+            cache.borrow_mut().clear();
+        }
+        self.reservation = None;
+        Ok(())
+    }
 
-        Err(MemoryError::OutOfBounds { addr, size })
+    /// 直接借用主内存中 [addr, addr + len) 的数据，不发生拷贝
+    /// 仅支持主内存，区域涉及 MMIO 或未映射地址时返回错误
+    #[inline(always)]
+    pub fn as_slice(&self, addr: u64, len: usize) -> Result<&[u8], MemoryError> {
+        if !self.is_mem_region_range(addr, len) {
+            return Err(MemoryError::NotRam { addr, size: len });
+        }
+        let start = addr.wrapping_sub(self.memory_base) as usize;
+        Ok(&self.data[start..start + len])
     }
 
     /// 快速读取u32指令（unsafe版本，仅用于取指）
@@ -258,6 +832,95 @@ impl Memory {
         }
     }
 
+    /// 从主内存取指，启用取指缓存时优先查缓存
+    /// PC 不完全位于主内存中时返回 None，由调用者走慢速路径
+    #[inline(always)]
+    pub fn fetch_word(&self, pc: u64) -> Option<u32> {
+        if let Some(cache) = &self.fetch_cache
+            && let Some(word) = cache.borrow().lookup(pc)
+        {
+            return Some(word);
+        }
+        if !self.is_mem_region_range(pc, 4) {
+            return None;
+        }
+        // 上面已检查4字节读取不会越界
+        let word = unsafe { self.read_u32_fast(pc) };
+        if let Some(cache) = &self.fetch_cache {
+            cache.borrow_mut().insert(pc, word);
+        }
+        Some(word)
+    }
+
+    /// 取指：先走 `fetch_word` 的快速路径，否则先取低半字，仅当是32位指令时再取高半字，
+    /// 因此位于主内存末尾的压缩指令不会越界；取不到完整指令时返回 None
+    #[inline(always)]
+    pub fn fetch_inst(&self, pc: u64) -> Option<u32> {
+        if let Some(word) = self.fetch_word(pc) {
+            return Some(word);
+        }
+        self.fetch_inst_slow(pc)
+    }
+
+    #[cold]
+    fn fetch_inst_slow(&self, pc: u64) -> Option<u32> {
+        let low = self.fetch_halfword(pc)?;
+        if is_compressed(low) {
+            return Some(low);
+        }
+        let high = self.fetch_halfword(pc.wrapping_add(2))?;
+        Some(low | high << 16)
+    }
+
+    /// 读取2字节指令片段，零扩展为 u32；既不在主内存中也不在可取指的 MMIO 区域中时返回 None
+    fn fetch_halfword(&self, addr: u64) -> Option<u32> {
+        if self.is_mem_region_range(addr, 2) {
+            let real_addr = addr.wrapping_sub(self.memory_base) as usize;
+            return Some(unsafe { self.read_halfword_unsafe(real_addr) } as u32);
+        }
+        let region = self.find_mmio_region(addr).filter(|region| region.executable)?;
+        // 指令按16位片段取出，每个片段与 CPU 的半字读取一样经 MmioRegion::read 按区域的字节序转换为小端
+        let bytes = region.read(addr - region.base, 2).ok()?;
+        Some(u16::from_le_bytes(bytes.try_into().ok()?) as u32)
+    }
+
+    /// 使取指缓存中与写入区间重叠的条目失效
+    #[inline(always)]
+    fn invalidate_fetch_cache(&self, addr: u64, len: usize) {
+        if let Some(cache) = &self.fetch_cache {
+            cache.borrow_mut().invalidate(addr, len);
+        }
+    }
+
+    /// 主内存写入后的处理：失效取指缓存，并清除被覆盖的保留集
+    #[inline(always)]
+    fn after_ram_write(&mut self, addr: u64, len: usize) {
+        self.invalidate_fetch_cache(addr, len);
+        if let Some(reserved) = self.reservation
+            && addr < reserved + RESERVATION_GRANULE
+            && reserved < addr.saturating_add(len as u64)
+        {
+            self.reservation = None;
+        }
+    }
+
+    /// 为 lr 建立包含 addr 的保留集，替换之前的保留集
+    #[inline(always)]
+    pub fn reserve(&mut self, addr: u64) {
+        self.reservation = Some(addr & !(RESERVATION_GRANULE - 1));
+    }
+
+    /// 使保留集失效
+    pub fn clear_reservation(&mut self) {
+        self.reservation = None;
+    }
+
+    /// 取走保留集，返回 addr 是否仍被保留；sc 无论成功与否都会使保留集失效
+    #[inline(always)]
+    pub fn take_reservation(&mut self, addr: u64) -> bool {
+        self.reservation.take() == Some(addr & !(RESERVATION_GRANULE - 1))
+    }
+
     /// 快速读取字节（unsafe版本）
     #[inline(always)]
     unsafe fn read_byte_unsafe(&self, real_addr: usize) -> u8 {
@@ -327,13 +990,25 @@ impl Memory {
     /// 写入内存
     #[inline(always)]
     pub fn write(&mut self, addr: u64, data: &[u8]) -> Result<(), MemoryError> {
+        self.write_untracked(addr, data)?;
+        if self.access_tracking {
+            self.record_access(addr, data, true);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn write_untracked(&mut self, addr: u64, data: &[u8]) -> Result<(), MemoryError> {
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, data.len(), true);
+            self.add_latency(self.mem_latency);
+            self.check_writable(addr, data.len())?;
             // 普通内存访问 - 根据长度选择优化路径
             match data.len() {
                 1 => {
                     // 字节访问
                     if !self.is_mem_region_range(addr, 1) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 1 });
+                        return Err(self.ram_straddle(addr, 1));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     unsafe { self.write_byte_unsafe(real_addr, data[0]); }
@@ -341,7 +1016,7 @@ impl Memory {
                 2 => {
                     // 半字访问
                     if !self.is_mem_region_range(addr, 2) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 2 });
+                        return Err(self.ram_straddle(addr, 2));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = u16::from_le_bytes([data[0], data[1]]);
@@ -350,7 +1025,7 @@ impl Memory {
                 4 => {
                     // 字访问
                     if !self.is_mem_region_range(addr, 4) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 4 });
+                        return Err(self.ram_straddle(addr, 4));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
@@ -359,7 +1034,7 @@ impl Memory {
                 8 => {
                     // 双字访问
                     if !self.is_mem_region_range(addr, 8) {
-                        return Err(MemoryError::OutOfBounds { addr, size: 8 });
+                        return Err(self.ram_straddle(addr, 8));
                     }
                     let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
                     let value = u64::from_le_bytes([
@@ -370,24 +1045,81 @@ impl Memory {
                 }
                 _ => {
                     // 非标准长度，使用传统方法
+                    if !self.is_mem_region_range(addr, data.len()) {
+                        return Err(self.ram_straddle(addr, data.len()));
+                    }
                     let real_addr = self.translate_address(addr, data.len(), 1)?;
                     let start = real_addr as usize;
                     self.data[start..start + data.len()].copy_from_slice(data);
                 }
             }
+            self.after_ram_write(addr, data.len());
             return Ok(())
         }
 
-        // 检查是否为 MMIO 访问
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            device.write(offset, data)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(());
+        // MMIO访问
+        self.write_mmio(addr, data)
+    }
+
+    /// 读取 MMIO 设备并记录本次访问
+    #[inline(always)]
+    fn read_mmio(&self, addr: u64, size: usize) -> Result<Vec<u8>, MemoryError> {
+        let Some(region) = self.find_mmio_region(addr) else {
+            return Err(MemoryError::OutOfBounds { addr, size });
+        };
+        let res = region.read(Self::mmio_offset(region, addr, size)?, size)?;
+        self.add_latency(region.latency);
+        self.note_watched(addr, size, false);
+        *self.is_last_mmio.borrow_mut() = true;
+        self.last_mmio_access.set(Some(MmioAccess::new(addr, &res, false)));
+        Ok(res)
+    }
+
+    /// 写入 MMIO 设备，记录本次访问及设备发出的退出请求、宿主请求与断言失败
+    #[inline(always)]
+    fn write_mmio(&self, addr: u64, data: &[u8]) -> Result<(), MemoryError> {
+        let Some(region) = self.find_mmio_region(addr) else {
+            return Err(MemoryError::OutOfBounds { addr, size: data.len() });
+        };
+        let requests = region.write(Self::mmio_offset(region, addr, data.len())?, data)?;
+        self.add_latency(region.latency);
+        self.note_watched(addr, data.len(), true);
+        if let Some(code) = requests.exit_code {
+            self.exit_request.set(Some(code));
+        }
+        if requests.host_request.is_some() {
+            self.host_request.set(requests.host_request);
+        }
+        if requests.assertion.is_some() {
+            self.assertion_failure.set(requests.assertion);
         }
+        *self.is_last_mmio.borrow_mut() = true;
+        self.last_mmio_access.set(Some(MmioAccess::new(addr, data, true)));
+        Ok(())
+    }
+
+    /// 取走最近一次 MMIO 访问
+    #[inline(always)]
+    pub fn take_mmio_access(&self) -> Option<MmioAccess> {
+        self.last_mmio_access.take()
+    }
+
+    /// 取走设备发出的退出请求
+    #[inline(always)]
+    pub fn take_exit_request(&self) -> Option<u8> {
+        self.exit_request.take()
+    }
+
+    /// 取走设备发出的宿主请求
+    #[inline(always)]
+    pub fn take_host_request(&self) -> Option<HostRequest> {
+        self.host_request.take()
+    }
 
-        Err(MemoryError::OutOfBounds { addr, size: data.len() })
+    /// 取走设备报告的断言失败
+    #[inline(always)]
+    pub fn take_assertion_failure(&self) -> Option<AssertionRequest> {
+        self.assertion_failure.take()
     }
 
     #[inline(always)]
@@ -400,200 +1132,188 @@ impl Memory {
     /// 读取字节
     #[inline(always)]
     pub fn read_byte(&self, addr: u64) -> Result<u8, MemoryError> {
+        if self.access_tracking {
+            return self.read(addr, 1).map(|bytes| bytes[0]);
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 1, false);
+            self.add_latency(self.mem_latency);
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 1) {
-                return Err(MemoryError::OutOfBounds { addr, size: 1 });
+                return Err(self.ram_straddle(addr, 1));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             return Ok(unsafe { self.read_byte_unsafe(real_addr) });
         }
 
-        // MMIO访问 - 通过通用read方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            let res = device.read(offset, 1)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(res[0]);
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 1 })
+        // MMIO访问
+        let res = self.read_mmio(addr, 1)?;
+        Ok(res[0])
     }
 
     /// 读取半字
     #[inline(always)]
     pub fn read_halfword(&self, addr: u64) -> Result<u16, MemoryError> {
+        if self.access_tracking {
+            return self.read(addr, 2).map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()));
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 2, false);
+            self.add_latency(self.mem_latency);
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 2) {
-                return Err(MemoryError::OutOfBounds { addr, size: 2 });
+                return Err(self.ram_straddle(addr, 2));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             return Ok(unsafe { self.read_halfword_unsafe(real_addr) });
         }
 
-        // MMIO访问 - 通过通用read方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            let res = device.read(offset, 2)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(u16::from_le_bytes([res[0], res[1]]));
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 2 })
+        // MMIO访问
+        let res = self.read_mmio(addr, 2)?;
+        Ok(u16::from_le_bytes([res[0], res[1]]))
     }
 
     /// 读取字
     #[inline(always)]
     pub fn read_word(&self, addr: u64) -> Result<u32, MemoryError> {
+        if self.access_tracking {
+            return self.read(addr, 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 4, false);
+            self.add_latency(self.mem_latency);
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 4) {
-                return Err(MemoryError::OutOfBounds { addr, size: 4 });
+                return Err(self.ram_straddle(addr, 4));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             return Ok(unsafe { self.read_word_unsafe(real_addr) });
         }
 
-        // MMIO访问 - 通过通用read方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            let res = device.read(offset, 4)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(u32::from_le_bytes([res[0], res[1], res[2], res[3]]));
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 4 })
+        // MMIO访问
+        let res = self.read_mmio(addr, 4)?;
+        Ok(u32::from_le_bytes([res[0], res[1], res[2], res[3]]))
     }
 
     /// 读取双字
     #[inline(always)]
     pub fn read_doubleword(&self, addr: u64) -> Result<u64, MemoryError> {
+        if self.access_tracking {
+            return self.read(addr, 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 8, false);
+            self.add_latency(self.mem_latency);
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 8) {
-                return Err(MemoryError::OutOfBounds { addr, size: 8 });
+                return Err(self.ram_straddle(addr, 8));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             return Ok(unsafe { self.read_doubleword_unsafe(real_addr) });
         }
 
-        // MMIO访问 - 通过通用read方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            let res = device.read(offset, 8)?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(u64::from_le_bytes([
-                res[0], res[1], res[2], res[3],
-                res[4], res[5], res[6], res[7],
-            ]));
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 8 })
+        // MMIO访问
+        let res = self.read_mmio(addr, 8)?;
+        Ok(u64::from_le_bytes([
+            res[0], res[1], res[2], res[3],
+            res[4], res[5], res[6], res[7],
+        ]))
     }
 
     /// 写入字节
     #[inline(always)]
     pub fn write_byte(&mut self, addr: u64, value: u8) -> Result<(), MemoryError> {
+        if self.access_tracking {
+            return self.write(addr, &value.to_le_bytes());
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 1, true);
+            self.add_latency(self.mem_latency);
+            self.check_writable(addr, 1)?;
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 1) {
-                return Err(MemoryError::OutOfBounds { addr, size: 1 });
+                return Err(self.ram_straddle(addr, 1));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             unsafe { self.write_byte_unsafe(real_addr, value); }
+            self.after_ram_write(addr, 1);
             return Ok(());
         }
 
-        // MMIO访问 - 通过通用write方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            device.write(offset, &[value])?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(());
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 1 })
+        // MMIO访问
+        self.write_mmio(addr, &[value])
     }
 
     /// 写入半字
     #[inline(always)]
     pub fn write_halfword(&mut self, addr: u64, value: u16) -> Result<(), MemoryError> {
+        if self.access_tracking {
+            return self.write(addr, &value.to_le_bytes());
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 2, true);
+            self.add_latency(self.mem_latency);
+            self.check_writable(addr, 2)?;
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 2) {
-                return Err(MemoryError::OutOfBounds { addr, size: 2 });
+                return Err(self.ram_straddle(addr, 2));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             unsafe { self.write_halfword_unsafe(real_addr, value); }
+            self.after_ram_write(addr, 2);
             return Ok(());
         }
 
-        // MMIO访问 - 通过通用write方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            device.write(offset, &value.to_le_bytes())?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(());
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 2 })
+        // MMIO访问
+        self.write_mmio(addr, &value.to_le_bytes())
     }
 
     /// 写入字
     #[inline(always)]
     pub fn write_word(&mut self, addr: u64, value: u32) -> Result<(), MemoryError> {
+        if self.access_tracking {
+            return self.write(addr, &value.to_le_bytes());
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 4, true);
+            self.add_latency(self.mem_latency);
+            self.check_writable(addr, 4)?;
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 4) {
-                return Err(MemoryError::OutOfBounds { addr, size: 4 });
+                return Err(self.ram_straddle(addr, 4));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             unsafe { self.write_word_unsafe(real_addr, value); }
+            self.after_ram_write(addr, 4);
             return Ok(());
         }
 
-        // MMIO访问 - 通过通用write方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            device.write(offset, &value.to_le_bytes())?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(());
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 4 })
+        // MMIO访问
+        self.write_mmio(addr, &value.to_le_bytes())
     }
 
     /// 写入双字
     #[inline(always)]
     pub fn write_doubleword(&mut self, addr: u64, value: u64) -> Result<(), MemoryError> {
+        if self.access_tracking {
+            return self.write(addr, &value.to_le_bytes());
+        }
         if self.is_mem_region(addr) {
+            self.note_tagged(addr, 8, true);
+            self.add_latency(self.mem_latency);
+            self.check_writable(addr, 8)?;
             // 主内存访问 - 直接使用unsafe版本
             if !self.is_mem_region_range(addr, 8) {
-                return Err(MemoryError::OutOfBounds { addr, size: 8 });
+                return Err(self.ram_straddle(addr, 8));
             }
             let real_addr = (addr.wrapping_sub(self.memory_base)) as usize;
             unsafe { self.write_doubleword_unsafe(real_addr, value); }
+            self.after_ram_write(addr, 8);
             return Ok(());
         }
 
-        // MMIO访问 - 通过通用write方法
-        if let Some(region) = self.find_mmio_region(addr) {
-            let offset = addr - region.base;
-            let mut device = region.device.lock().unwrap();
-            device.write(offset, &value.to_le_bytes())?;
-            *self.is_last_mmio.borrow_mut() = true;
-            return Ok(());
-        }
-
-        Err(MemoryError::OutOfBounds { addr, size: 8 })
+        // MMIO访问
+        self.write_mmio(addr, &value.to_le_bytes())
     }
 }
 
@@ -601,7 +1321,7 @@ impl Memory {
 mod tests {
     use super::*;
     use std::rc::Rc;
-    use crate::const_values::{EmuConfig, MemoryConfig, InstSetConfig, DebugConfig, OthersConfig};
+    use crate::const_values::EmuConfig;
 
     // 模拟 UART 设备
     struct MockUart {
@@ -629,35 +1349,32 @@ mod tests {
         }
     }
 
-    fn create_test_config() -> (Rc<EmuConfig>, crate::const_values::DeviceFile) {
-        let config = Rc::new(EmuConfig {
-            memory: MemoryConfig {
-                boot_pc: 0x8000_0000,
-            },
-            inst_set: InstSetConfig {
-                m_ext: false,
-                a_ext: false,
-                c_ext: false,
-            },
-            debug: DebugConfig {
-                event_list_size: 64,
-                #[cfg(feature = "tracer")]
-                instruction_tracer_list_size: 64,
-            },
-            others: OthersConfig {
-                decoder_cache_size: 1024,
-            },
-        });
+    // 模拟寄存器按偏移存储的设备
+    struct MockRegs {
+        regs: [u8; 16],
+    }
 
-        let device_file = crate::const_values::DeviceFile {
-            memory: crate::const_values::DeviceFileMemory {
-                memory_base: 0x8000_0000,
-                memory_size: 128,
-            },
-            devices: Vec::new(),
-        };
+    impl mmio_trait::MmioDevice for MockRegs {
+        fn read(&mut self, offset: u64, size: usize) -> Result<Vec<u8>, mmio_trait::DeviceError> {
+            let offset = offset as usize;
+            Ok(self.regs[offset..offset + size].to_vec())
+        }
+
+        fn write(&mut self, offset: u64, data: &[u8]) -> Result<(), mmio_trait::DeviceError> {
+            let offset = offset as usize;
+            self.regs[offset..offset + data.len()].copy_from_slice(data);
+            Ok(())
+        }
 
-        (config, device_file)
+        fn name(&self) -> &str {
+            "mock_regs"
+        }
+    }
+
+    fn create_test_config() -> (Rc<EmuConfig>, crate::const_values::DeviceFile) {
+        let (config, mut device_file) = crate::test_utils::test_config();
+        device_file.memory.memory_size = 128;
+        (Rc::new(config), device_file)
     }
 
     #[test]
@@ -692,7 +1409,75 @@ mod tests {
 
         let uart2 = Arc::new(Mutex::new(MockUart::new()));
         let result = memory.map_mmio(0x1000_0050, 0x100, uart2, "uart2".to_string());
-        assert!(matches!(result, Err(MemoryError::MmioOverlap { .. })));
+        let err = result.unwrap_err();
+        assert!(matches!(
+            &err,
+            MemoryError::MmioOverlap { addr: 0x1000_0050, conflict, conflict_base: 0x1000_0000, conflict_end: 0x1000_0100 }
+                if conflict == "uart1"
+        ));
+        assert!(err.to_string().contains("'uart1' [0x10000000, 0x10000100)"));
+
+        // 与主内存重叠时同样给出主内存的范围
+        let uart3 = Arc::new(Mutex::new(MockUart::new()));
+        let err = memory.map_mmio(0x8000_0000, 0x100, uart3, "uart3".to_string()).unwrap_err();
+        assert!(matches!(err, MemoryError::MmioOverlap { conflict_base: 0x8000_0000, .. }));
+    }
+
+    #[test]
+    fn test_tag_hits_are_bounded() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+        memory.add_tagged_range("buf", 0x8000_0000, 0x100);
+        memory.set_tag_tracking(2);
+        for i in 0..8 {
+            memory.write_doubleword(0x8000_0000 + i * 8, i).unwrap();
+        }
+        // 没有被取走的访问不会无限增长
+        assert_eq!(memory.take_tag_hits().len(), 2);
+
+        memory.set_tag_tracking(0);
+        memory.write_doubleword(0x8000_0000, 1).unwrap();
+        assert!(memory.take_tag_hits().is_empty());
+    }
+
+    #[test]
+    fn test_set_base_keeps_data() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+        memory.write_doubleword(0x8000_0010, 0xdead_beef_cafe_f00d).unwrap();
+
+        memory.set_base(0x4000_0000).unwrap();
+        assert_eq!(memory.memory_base(), 0x4000_0000);
+        assert_eq!(memory.read_doubleword(0x4000_0010).unwrap(), 0xdead_beef_cafe_f00d);
+        assert!(memory.read_doubleword(0x8000_0010).is_err());
+    }
+
+    #[test]
+    fn test_set_base_rejects_mmio_overlap() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+        let uart = Arc::new(Mutex::new(MockUart::new()));
+        memory.map_mmio(0x1000_0000, 0x100, uart, "test_uart".to_string()).unwrap();
+
+        let result = memory.set_base(0x0c00_0000);
+        assert!(matches!(
+            result,
+            Err(MemoryError::MmioOverlap { addr: 0x0c00_0000, conflict_base: 0x1000_0000, .. })
+        ));
+        assert_eq!(memory.memory_base(), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_fill_pattern() {
+        let (mut config, device_file) = crate::test_utils::test_config();
+        config.memory.fill = MemoryFill::Poison;
+        let memory = Memory::new(Rc::new(config), &device_file).unwrap();
+        assert_eq!(memory.read_word(0x8000_1234).unwrap(), 0xAAAA_AAAA);
+
+        let (mut config, device_file) = crate::test_utils::test_config();
+        config.memory.fill = MemoryFill::Incrementing;
+        let memory = Memory::new(Rc::new(config), &device_file).unwrap();
+        assert_eq!(memory.read_word(0x8000_0104).unwrap(), 0x0706_0504);
     }
 
     #[test]
@@ -717,6 +1502,82 @@ mod tests {
         assert_eq!(data, 0x01); // MockUart 返回 0x01
     }
 
+    #[test]
+    fn test_access_straddling_region_boundary() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+        let ram_end = 0x8000_0000 + 128 * 1024 * 1024;
+        let regs = Arc::new(Mutex::new(MockRegs { regs: [0; 16] }));
+        memory.map_mmio(ram_end, 16, regs.clone(), "regs".to_string()).unwrap();
+
+        // 从主内存末尾前4字节开始的8字节访问跨入了 MMIO 区域
+        let err = memory.read(ram_end - 4, 8).unwrap_err();
+        assert!(matches!(err, MemoryError::Straddle { addr, size: 8, boundary } if addr == ram_end - 4 && boundary == ram_end));
+        let err = memory.write(ram_end - 4, &[0xff; 8]).unwrap_err();
+        assert!(matches!(err, MemoryError::Straddle { boundary, .. } if boundary == ram_end));
+        assert!(matches!(memory.read_doubleword(ram_end - 4), Err(MemoryError::Straddle { .. })));
+        assert!(matches!(memory.write_doubleword(ram_end - 4, 0), Err(MemoryError::Straddle { .. })));
+        assert_eq!(memory.read(ram_end - 4, 4).unwrap(), vec![0; 4]);
+        assert_eq!(regs.lock().unwrap().regs, [0; 16]);
+
+        // 越过 MMIO 区域末尾的访问不会传给设备
+        let err = memory.read(ram_end + 12, 8).unwrap_err();
+        assert!(matches!(err, MemoryError::Straddle { boundary, .. } if boundary == ram_end + 16));
+        assert!(memory.write(ram_end + 12, &[0xff; 8]).is_err());
+        assert!(matches!(memory.write_doubleword(ram_end + 12, 0), Err(MemoryError::Straddle { .. })));
+        assert_eq!(regs.lock().unwrap().regs, [0; 16]);
+    }
+
+    #[test]
+    fn test_mmio_big_endian_device() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+
+        let dev = Arc::new(Mutex::new(MockRegs { regs: [0; 16] }));
+        memory
+            .map_mmio_with_endianness(0x1000_0000, 0x10, dev.clone(), "be_regs".to_string(), Endianness::Big)
+            .unwrap();
+
+        // 设备中按大端存储
+        memory.write_word(0x1000_0004, 0x1122_3344).unwrap();
+        assert_eq!(dev.lock().unwrap().regs[4..8], [0x11, 0x22, 0x33, 0x44]);
+
+        // CPU 读回的值与写入一致
+        assert_eq!(memory.read_word(0x1000_0004).unwrap(), 0x1122_3344);
+        assert_eq!(memory.read(0x1000_0004, 4).unwrap(), vec![0x44, 0x33, 0x22, 0x11]);
+
+        // 单字节访问不受影响
+        memory.write_byte(0x1000_0000, 0xab).unwrap();
+        assert_eq!(dev.lock().unwrap().regs[0], 0xab);
+        assert_eq!(memory.read_byte(0x1000_0000).unwrap(), 0xab);
+
+        // 取指的每个16位片段按区域的字节序读取，与半字读取看到的值一致
+        memory.set_device_executable("be_regs").unwrap();
+        memory.write_halfword(0x1000_0008, 0x0513).unwrap(); // addi a0, a0, 1 的低半字
+        memory.write_halfword(0x1000_000a, 0x0015).unwrap();
+        assert_eq!(dev.lock().unwrap().regs[8..12], [0x05, 0x13, 0x00, 0x15]);
+        assert_eq!(memory.fetch_inst(0x1000_0008), Some(0x0015_0513));
+        memory.write_halfword(0x1000_000c, 0x4585).unwrap(); // c.li a1, 1
+        assert_eq!(memory.fetch_inst(0x1000_000c), Some(0x4585));
+    }
+
+    #[test]
+    fn test_as_slice() {
+        let (config, device_file) = create_test_config();
+        let mut memory = Memory::new(config, &device_file).unwrap();
+        let uart = Arc::new(Mutex::new(MockUart::new()));
+        memory.map_mmio(0x1000_0000, 0x100, uart, "test_uart".to_string()).unwrap();
+
+        memory.write_word(0x8000_0100, 0xdead_beef).unwrap();
+        assert_eq!(memory.as_slice(0x8000_0100, 4).unwrap(), &[0xef, 0xbe, 0xad, 0xde]);
+
+        // MMIO 与越界区域不可借用
+        assert!(matches!(memory.as_slice(0x1000_0000, 4), Err(MemoryError::NotRam { .. })));
+        let end = 0x8000_0000 + 128 * 1024 * 1024;
+        assert!(memory.as_slice(end - 4, 4).is_ok());
+        assert!(matches!(memory.as_slice(end - 4, 8), Err(MemoryError::NotRam { .. })));
+    }
+
     #[test]
     fn test_regular_memory_access() {
         let (config, device_file) = create_test_config();
@@ -9,26 +9,50 @@ pub mod gdb;
 #[cfg(feature = "tracer")] // 条件编译追踪器模块
 pub mod tracer;
 
+mod call_graph;
+mod debug_points;
 mod device_manager;
+mod fetch_cache;
+mod handle;
+mod histogram;
+mod hostcall;
 mod memory;
+pub mod pc_trace;
+mod snapshot;
+mod syscall;
+mod trap;
 
+use std::ops::Range;
 use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::emulator::instructions::is_compressed;
-use crate::utils::disasm_riscv64_instruction;
+use crate::utils::{SymbolTable, disasm_riscv64_instruction};
 use crate::{const_values, utils::ringbuf::RingBuffer};
 use anyhow::{Context, Result};
-pub use exception::Exception;
+pub use call_graph::CallGraph;
+pub use debug_points::{WatchKind, Watchpoint};
+pub use exception::{Exception, PrivilegeLevel};
 
 #[cfg(feature = "gdb")] // 条件编译 GDB 模块
-pub use gdb::EmuGdbEventLoop;
-pub use memory::{Memory, MemoryError};
+pub use gdb::{EmuGdbEventLoop, EmuGdbEventLoop32, Rv32Target};
+pub use handle::{EmulatorCommand, EmulatorHandle, EmulatorStatus};
+pub use histogram::{InstFormat, OpcodeHistogram, inst_format};
+pub use self::hostcall::{AssertionFailure, Hostcall, HostcallResult};
+pub use memory::{Memory, MemoryError, MmioAccess, MmioRecord, TagHit, TagRecord, TaggedRange};
 
 #[cfg(feature = "difftest")]
 use rv64emu::rv64core::{bus::DeviceType, cpu_core::CpuCore};
 pub use state::State;
-pub use state::{Event, ExecMode, ExecState};
+pub use state::{Event, ExecMode, ExecState, StepOutcome, StopReason};
+pub use snapshot::{ArchState, MemoryRange, Snapshot, SnapshotDiff};
+pub use syscall::{
+    RiscvTestResult, SyscallAction, SyscallContext, SyscallHandler, SyscallOutcome,
+};
+pub use trap::{TRAP_LOOP_LIMIT, TrapError, TrapRecord, TrapStateView};
+
+/// 模糊测试状态中寄存器与 PC 部分的长度
+pub const FUZZ_STATE_HEADER_SIZE: usize = 33 * 8;
 
 /// 模拟器结构体
 pub struct Emulator {
@@ -38,12 +62,74 @@ pub struct Emulator {
     exec_mode: ExecMode,
     event: Event,
     execption: Option<Exception>,
+    /// 当前特权级
+    privilege: PrivilegeLevel,
+    /// PC 轨迹记录/比对（可选）
+    pc_trace: Option<pc_trace::PcTrace>,
+    /// 陷入日志（可选）
+    trap_log: Option<RingBuffer<TrapRecord>>,
+    /// MMIO 访问日志（可选），记录 (pc, 访问)
+    mmio_log: Option<RingBuffer<(u64, MmioAccess)>>,
+    /// 标记区域访问日志（可选），记录 (pc, 访问)
+    tag_log: Option<RingBuffer<(u64, TagHit)>>,
+    /// 调用图（可选）
+    call_graph: Option<call_graph::CallGraph>,
+    /// 按指令格式统计的执行条数（可选）
+    format_histogram: Option<std::collections::HashMap<InstFormat, u64>>,
+    /// 按指令名统计的执行条数（可选）
+    opcode_histogram: Option<histogram::OpcodeHistogram>,
+    /// 设备时间是否被冻结
+    time_frozen: bool,
+    /// 客户程序的退出码（程序结束后有效）
+    exit_code: Option<u8>,
+    /// 尚未交给设备的周期数
+    pending_ticks: u64,
+    /// 周期计数：每条指令计1个周期，另加访存延迟
+    cycles: u64,
+    /// 已退休的指令数
+    instret: u64,
+    /// 当前指令写入了 minstret，该指令不再计入退休数
+    instret_written: bool,
+    /// 当前指令写入了 mcycle，该指令不再计入周期数
+    cycles_written: bool,
+    /// 上次同步到 mip 的设备中断线
+    device_irqs: u64,
+    /// 本步在指令边界响应的中断（mcause）
+    last_interrupt: Option<u64>,
+    /// 本步执行的指令 (原始编码, 指令名)，本步没有执行指令（断点、取指陷入等）时为 None
+    last_inst: Option<(u32, &'static str)>,
+    /// 最近一次陷入跳转到的处理程序地址
+    last_trap_target: Option<u64>,
+    /// 处理程序入口连续触发异常的次数，用于检测陷入循环
+    trap_repeats: u32,
+    /// 自定义系统调用处理函数（可选）
+    syscall_handler: Option<syscall::SyscallHandler>,
+    /// brk 系统调用维护的 program break
+    program_break: u64,
+    /// reset 后恢复的 program break：最近加载的镜像的末尾
+    reset_break: u64,
+    /// 最近加载的 ELF 中的函数符号
+    symbols: SymbolTable,
+    /// 可执行的地址范围，来自 ELF 的代码节或扁平二进制镜像
+    exec_regions: Vec<Range<u64>>,
+    /// 是否在取指前检查 PC 位于可执行范围内
+    exec_check: bool,
+    /// 是否按 riscv-tests 约定解读 exit 调用
+    riscv_test_mode: bool,
+    /// riscv-tests 模式下报告的测试结果
+    riscv_test_result: Option<syscall::RiscvTestResult>,
+    /// 客户程序通过 hostcall 设备发出的宿主调用结果
+    hostcall_results: Vec<hostcall::HostcallResult>,
+    /// 客户程序通过 assert 设备报告的最近一次断言失败
+    last_assertion_failure: Option<hostcall::AssertionFailure>,
     event_list: RingBuffer<Event>,
     decoder: instructions::InstDecoder,
-    #[allow(unused)]
     config: Rc<const_values::EmuConfig>, // 模拟器配置
-    #[cfg(feature = "gdb")] // 条件编译 GDB 相关
-    gdb_data: gdb::GdbData,
+    /// 断点与观察点
+    debug_points: debug_points::DebugPoints,
+    /// GDB 每次 continue 最多连续执行的指令数，超过后暂停并交还控制权
+    #[cfg(feature = "gdb")]
+    gdb_continue_quantum: Option<usize>,
     #[cfg(feature = "difftest")] // 条件编译 DiffTest 相关
     ref_emu: rv64emu::rv64core::cpu_core::CpuCore,
 }
@@ -59,7 +145,7 @@ impl Emulator {
             prj_base.join(&args.config)
         };
         // 解析主配置
-        let emu_config = Rc::new(const_values::EmuConfig::new(&config_path)?);
+        let emu_config = const_values::EmuConfig::new(&config_path)?;
 
         // 解析设备配置文件（相对于主配置文件目录）
         let arg_device_path = PathBuf::from(&args.device_config);
@@ -70,8 +156,19 @@ impl Emulator {
         };
         let device_file = const_values::DeviceFile::new(&device_path)?;
 
+        Self::from_config(emu_config, &device_file)
+    }
+
+    /// 使用已解析的主配置和设备配置创建模拟器实例
+    pub fn from_config(
+        emu_config: const_values::EmuConfig,
+        device_file: &const_values::DeviceFile,
+    ) -> Result<Self> {
+        emu_config.validate(device_file)?;
+        let emu_config = Rc::new(emu_config);
+
         // 使用主配置和设备配置创建状态
-        let state = State::new(emu_config.clone(), &device_file)?;
+        let state = State::new(emu_config.clone(), device_file)?;
         let exec_mode = if cfg!(feature = "gdb") {
             ExecMode::Continue // 如果启用了GDB，默认执行模式为连续执行
         } else {
@@ -114,43 +211,250 @@ impl Emulator {
             ref_emu = in_core;
         }
 
+        Self::check_exec_addr(&state, emu_config.memory.boot_pc, "boot_pc")?;
+
         Ok(Self {
             state,
             exec_state: ExecState::Idle,
             exec_mode,
             event: Event::None,
             execption: None,
+            privilege: PrivilegeLevel::Machine,
+            pc_trace: None,
+            trap_log: match emu_config.debug.trap_log_size {
+                0 => None,
+                n => Some(RingBuffer::new(n)),
+            },
+            mmio_log: match emu_config.debug.mmio_log_size {
+                0 => None,
+                n => Some(RingBuffer::new(n)),
+            },
+            tag_log: match emu_config.debug.tag_log_size {
+                0 => None,
+                n => Some(RingBuffer::new(n)),
+            },
+            call_graph: None,
+            format_histogram: None,
+            opcode_histogram: None,
+            time_frozen: false,
+            exit_code: None,
+            pending_ticks: 0,
+            cycles: 0,
+            instret: 0,
+            instret_written: false,
+            cycles_written: false,
+            device_irqs: 0,
+            last_interrupt: None,
+            last_inst: None,
+            last_trap_target: None,
+            trap_repeats: 0,
+            syscall_handler: None,
+            program_break: device_file.memory.memory_base,
+            reset_break: device_file.memory.memory_base,
+            symbols: SymbolTable::default(),
+            exec_regions: Vec::new(),
+            exec_check: false,
+            riscv_test_mode: false,
+            riscv_test_result: None,
+            hostcall_results: Vec::new(),
+            last_assertion_failure: None,
             event_list: RingBuffer::new(emu_config.debug.event_list_size),
-            decoder: instructions::InstDecoder::new(emu_config.clone()),
+            decoder: instructions::InstDecoder::new(emu_config.clone())?,
             config: emu_config,
-            #[cfg(feature = "gdb")] // 条件编译 GDB 相关
-            gdb_data: gdb::GdbData::new(),
+            debug_points: debug_points::DebugPoints::default(),
+            #[cfg(feature = "gdb")]
+            gdb_continue_quantum: None,
             #[cfg(feature = "difftest")] // 条件编译 DiffTest 相关
             ref_emu,
         })
     }
 
+    /// 检查启动地址是否可以取指：落在不可取指的 MMIO 区域内时报错，
+    /// 既不在主内存也不在 RAM/ROM 设备中时给出警告
+    fn check_exec_addr(state: &State, addr: u64, what: &str) -> Result<()> {
+        if let Some((name, base, size)) = state.memory.mmio_region_at(addr)
+            && !state.memory.is_exec_mmio(addr)
+        {
+            return Err(anyhow::anyhow!(
+                "{} {:#x} 位于 MMIO 设备 '{}' 的区域 [{:#x}, {:#x}) 内，无法从该处取指",
+                what,
+                addr,
+                name,
+                base,
+                base + size
+            ));
+        }
+        if !state.memory.is_mem_region(addr) && !state.memory.is_exec_mmio(addr) {
+            tracing::warn!("{} {:#x} 不在主内存范围内", what, addr);
+        }
+        Ok(())
+    }
+
     /// 加载ELF文件
     pub fn load_elf(&mut self, path: &str) -> Result<()> {
         use crate::utils::load_elf;
 
-        // 使用工具模块加载ELF
-        load_elf(&mut self.state, path)
+        // 使用工具模块加载ELF，镜像末尾作为初始 program break
+        let image = load_elf(&mut self.state, path)
             .with_context(|| format!("无法从 '{}' 加载ELF文件", path))?;
+        self.program_break = image.end;
+        self.reset_break = image.end;
+        self.symbols = image.symbols;
+        self.exec_regions = image.text_ranges;
+        Self::check_exec_addr(&self.state, self.state.get_npc(), "ELF 入口地址")?;
+
+        Ok(())
+    }
+
+    /// 最近加载的 ELF 中的函数符号，未加载 ELF 时为空
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// 开启可执行范围检查：取指前 PC 不在任何可执行范围内、也不在允许取指的 RAM/ROM 设备中时投递取指访问错误，
+    /// 使跳入数据区的错误在跳转目标处立即陷入，而不是执行一段垃圾指令后才暴露
+    pub fn enable_exec_check(&mut self) {
+        self.exec_check = true;
+    }
+
+    /// 追加一段可执行范围
+    pub fn add_exec_region(&mut self, range: Range<u64>) {
+        self.exec_regions.push(range);
+    }
+
+    /// 当前的可执行范围
+    pub fn exec_regions(&self) -> &[Range<u64>] {
+        &self.exec_regions
+    }
+
+    /// 加载扁平二进制镜像（如 `objcopy -O binary` 的输出）：从主内存基址开始写入，并将 PC 设为基址
+    pub fn load_flat_binary(&mut self, path: &str) -> Result<()> {
+        self.load_binary(path, self.state.memory.memory_base())
+    }
+
+    /// 将扁平二进制镜像原样写入 addr 处，并将 PC 设为 addr，类似 QEMU 的 `-kernel`
+    pub fn load_binary(&mut self, path: &str, addr: u64) -> Result<()> {
+        let data = std::fs::read(path).with_context(|| format!("无法读取二进制镜像 '{}'", path))?;
+        Self::check_exec_addr(&self.state, addr, "二进制镜像入口")?;
+        self.state
+            .write_memory(addr, &data)
+            .with_context(|| format!("无法将二进制镜像 '{}' 写入 {:#x}", path, addr))?;
+        self.state.set_npc(addr);
+        self.state.sync_pc();
+        self.program_break = addr + data.len() as u64;
+        self.reset_break = self.program_break;
+        // 扁平镜像不区分代码与数据，整个镜像都视为可执行
+        self.exec_regions = vec![addr..addr + data.len() as u64];
+        tracing::info!(path, addr = format_args!("{:#x}", addr), size = data.len(), "已加载二进制镜像");
 
         Ok(())
     }
 
+    /// 复位架构状态以重新运行已加载的程序，比重新创建模拟器开销小
+    ///
+    /// 通用/浮点寄存器清零、CSR 恢复初始值、回到 M 模式，PC 回到配置的 boot_pc，
+    /// 解除设备时间冻结，并清空事件、退出码、周期与指令计数；DiffTest 下参考模型的 PC、寄存器与 CSR
+    /// 同步到复位后的状态，PC 轨迹与追踪器的影子调用栈等每次运行的状态从头开始。
+    /// 内存内容、断点与观察点以及已开启的日志和统计保持不变，MMIO 设备状态不会复位
+    pub fn reset(&mut self) {
+        self.state.reset(self.config.memory.boot_pc);
+        self.state.memory.clear_reservation();
+        self.privilege = PrivilegeLevel::Machine;
+        self.exec_state = ExecState::Idle;
+        self.event = Event::None;
+        self.execption = None;
+        self.exit_code = None;
+        self.pending_ticks = 0;
+        self.cycles = 0;
+        self.instret = 0;
+        self.instret_written = false;
+        self.cycles_written = false;
+        self.device_irqs = 0;
+        self.last_interrupt = None;
+        self.last_inst = None;
+        self.last_trap_target = None;
+        self.trap_repeats = 0;
+        self.program_break = self.reset_break;
+        self.riscv_test_result = None;
+        self.hostcall_results.clear();
+        self.last_assertion_failure = None;
+        self.event_list = RingBuffer::new(self.event_list.capacity());
+        self.debug_points.clear_resume();
+        self.freeze_time(false);
+        if let Some(trace) = &mut self.pc_trace {
+            trace.start(&self.state.registers, &self.state.fregs);
+        }
+        #[cfg(feature = "tracer")] // 条件编译追踪器相关
+        tracer::global_reset();
+
+        #[cfg(feature = "difftest")] // 条件编译 DiffTest 相关
+        {
+            use crate::difftest::Difftest;
+            self.ref_emu.set_pc(self.state.get_npc());
+            self.ref_emu.set_regs(&self.self_state().reg);
+            for (&csr, &value) in &self.state.csrs {
+                self.ref_emu.set_csr(csr, value);
+            }
+        }
+    }
+
+    /// 取指：取指范围不完全落在主内存中（进入 MMIO 区域或未映射的空洞），
+    /// 或开启可执行范围检查而 PC 不在可执行范围内时投递取指访问错误，返回 None 表示已经陷入
+    #[inline(always)]
+    fn fetch_or_trap(&mut self, pc: u64) -> Result<Option<u32>> {
+        if self.exec_check
+            && !self.exec_regions.iter().any(|range| range.contains(&pc))
+            && !self.state.memory.is_exec_mmio(pc)
+        {
+            self.deliver_exception(Exception::InstructionFault { addr: pc }, pc)?;
+            return Ok(None);
+        }
+        if let Some(word) = self.state.memory.fetch_inst(pc) {
+            return Ok(Some(word));
+        }
+        self.deliver_exception(Exception::InstructionFault { addr: pc }, pc)?;
+        Ok(None)
+    }
+
     #[inline(always)]
     fn step_internal(&mut self) -> Result<()> {
         // 获取PC和指令
         let (pc, instruction) = {
             self.state.sync_pc();
+            self.last_inst = None;
+            // 在指令边界响应中断，随后直接从处理程序入口取指
+            self.last_interrupt = self.pending_interrupt();
+            if let Some(cause) = self.last_interrupt {
+                let epc = self.state.get_pc();
+                self.take_trap(cause, 0, epc);
+                self.state.sync_pc();
+            }
             let pc = self.state.get_pc();
-            let instruction = self
-                .state
-                .fetch_instruction(pc)
-                .with_context(|| format!("无法从PC {:#x} 处读取指令", pc))?;
+            if self.debug_points.check_breakpoint(pc) {
+                // 停在断点处，本步不执行指令
+                self.event = Event::Break;
+                return Ok(());
+            }
+            if self.tag_log.is_some() {
+                // 丢弃指令之外（如加载程序）产生的访问
+                self.state.memory.take_tag_hits();
+            }
+            self.state.memory.take_watch_hit();
+            if self.state.memory.is_access_tracking() {
+                self.state.memory.take_accesses();
+            }
+            if let Some(trace) = &mut self.pc_trace {
+                trace.on_pc(pc)?;
+            }
+            let Some(instruction) = self
+                .fetch_or_trap(pc)
+                .with_context(|| format!("无法从PC {:#x} 处读取指令", pc))?
+            else {
+                // 取指失败已陷入，本步不再执行指令
+                return Ok(());
+            };
+            // 取指不计入访存延迟
+            self.state.memory.take_access_latency();
             (pc, instruction)
         };
 
@@ -165,14 +469,26 @@ impl Emulator {
         //         pc, instruction, instruction_msg, self.state
         //     )
         // })?;
-        let inst = self.decoder.fast_path(instruction).with_context(|| {
-            let instruction_msg =
-                disasm_riscv64_instruction(instruction, pc).unwrap_or("未知指令".to_string());
-            format!(
-                "无法解码PC {:#010x} 处的指令 {:#010x} ({}), cpu状态:\n{}",
-                pc, instruction, instruction_msg, self.state
-            )
-        })?;
+        let inst = match self.decoder.fast_path(instruction) {
+            Ok(inst) => *inst,
+            Err(_) => {
+                // 无法解码的指令作为非法指令异常投递
+                let raw = if is_compressed(instruction) { instruction & 0xFFFF } else { instruction };
+                let exception = Exception::IllegalInstruction { instruction: raw, addr: pc };
+                return self.deliver_exception(exception, pc).with_context(|| {
+                    let instruction_msg =
+                        disasm_riscv64_instruction(instruction, pc).unwrap_or("未知指令".to_string());
+                    format!(
+                        "无法解码PC {:#010x} 处的指令 {:#010x} ({}), cpu状态:\n{}",
+                        pc, raw, instruction_msg, self.state
+                    )
+                });
+            }
+        };
+
+        self.last_inst = Some((instruction, inst.name));
+        self.record_format(instruction);
+        self.record_opcode(inst.name);
 
         if is_compressed(instruction) {
             // 如果是压缩指令，PC需要加2
@@ -182,22 +498,110 @@ impl Emulator {
             self.state.set_npc(pc + 4);
         }
 
-        (inst.execute)(self, instruction, pc).with_context(|| {
-            let instruction_msg =
-                disasm_riscv64_instruction(instruction, pc).unwrap_or("未知指令".to_string());
-            format!(
-                "无法执行PC {:#010x} 处的指令 {:#010x} ({}), cpu状态:\n{}",
-                pc, instruction, instruction_msg, self.state
-            )
-        })?;
+        #[cfg(feature = "inst-spans")] // 条件编译指令分类 span
+        let _span = tracing::trace_span!(
+            "inst",
+            class = instructions::opcode_class(instruction),
+            name = inst.name
+        )
+        .entered();
 
-        if let Event::Halted(x) = self.event {
+        if let Err(err) = (inst.execute)(self, instruction, pc) {
+            // 访存越界作为访问错误异常投递，其余错误直接上报
+            let Some(exception) = self.memory_fault(&err, instruction) else {
+                let instruction_msg =
+                    disasm_riscv64_instruction(instruction, pc).unwrap_or("未知指令".to_string());
+                return Err(err.context(format!(
+                    "无法执行PC {:#010x} 处的指令 {:#010x} ({}), cpu状态:\n{}",
+                    pc, instruction, instruction_msg, self.state
+                )));
+            };
+            self.execption = Some(exception);
+        }
+
+        let mmio_access = self.state.memory.take_mmio_access();
+        if let Some(log) = &mut self.mmio_log
+            && let Some(access) = mmio_access
+        {
+            log.push_overwrite((pc, access));
+        }
+        if let Some(log) = &mut self.tag_log {
+            for hit in self.state.memory.take_tag_hits() {
+                tracing::debug!(
+                    "PC {:#x} {} 标记区域 {}: 地址 {:#x}, 大小 {}",
+                    pc,
+                    if hit.is_write { "写入" } else { "读取" },
+                    self.state.memory.tagged_ranges()[hit.tag].name,
+                    hit.addr,
+                    hit.size
+                );
+                log.push_overwrite((pc, hit));
+            }
+        }
+
+        if let Some((addr, is_write)) = self.state.memory.take_watch_hit()
+            && self.event == Event::None
+        {
+            self.event = if is_write {
+                Event::WatchWrite(addr)
+            } else {
+                Event::WatchRead(addr)
+            };
+        }
+
+        if let Some(graph) = &mut self.call_graph
+            && self.execption.is_none()
+        {
+            graph.on_inst(pc, instruction, self.state.get_npc());
+        }
+        if let Some(trace) = &mut self.pc_trace {
+            trace.on_inst(pc, self.state.get_regs(), &self.state.fregs)?;
+        }
+
+        // 投递执行过程中产生的同步异常，产生异常的指令不计入退休数；
+        // 写入 minstret 的指令也不计入，使随后读到的正是写入的值
+        let instret_written = std::mem::take(&mut self.instret_written);
+        if let Some(exception) = self.execption.take() {
+            self.deliver_exception(exception, pc)?;
+        } else if !instret_written {
+            self.instret += 1;
+        }
+
+        // 写入 mcycle 的指令同样不计入周期数
+        let access_latency = self.state.memory.take_access_latency();
+        if !std::mem::take(&mut self.cycles_written) {
+            self.cycles += 1 + access_latency;
+        }
+        // 冻结设备时间时不驱动设备，watchdog 等按周期计时的设备在单步调试时保持不变
+        if !self.time_frozen {
+            self.pending_ticks += 1;
+        }
+        if !self.time_frozen && self.pending_ticks >= self.config.others.device_tick_interval {
+            self.state.memory.tick_devices(self.pending_ticks);
+            self.pending_ticks = 0;
+            self.poll_device_irqs();
+        } else if mmio_access.is_some() {
+            // 访问设备寄存器（如 PLIC 的 claim/complete）可能改变中断线，不能等到下次 tick
+            self.poll_device_irqs();
+        }
+
+        if let Some(code) = self.state.memory.take_exit_request() {
+            self.event = Event::Exited(code);
+        }
+        if let Some(request) = self.state.memory.take_host_request() {
+            self.handle_host_request(pc, request)?;
+        }
+        if let Some(request) = self.state.memory.take_assertion_failure() {
+            self.handle_assertion_failure(pc, request);
+        }
+
+        if let Event::Halted(x) | Event::Exited(x) = self.event {
             use colored::Colorize;
             self.exec_state = ExecState::End; // 结束执行状态
+            self.exit_code = Some(x);
             if x != 0 {
                 tracing::error!("程序不正确退出，退出码：{x}");
                 println!("{}", "HIT AT BAD TRAP".red());
-                return Err(anyhow::anyhow!("程序不正确退出，退出码：{x}"));
             } else {
                 println!("{}", "HIT AT GOOD TRAP".green());
             }
@@ -212,6 +616,8 @@ impl Emulator {
     pub fn step(&mut self) -> Result<()> {
         self.exec_state = ExecState::Running;
         self.event = Event::None; // 重置事件
+        // 单步时每步都检查设备中断，使调试器能够单步进入中断处理程序
+        self.poll_device_irqs();
 
         self.step_internal()?;
 
@@ -223,7 +629,7 @@ impl Emulator {
 
         #[cfg(feature = "difftest")] // 条件编译 DiffTest 相关
         match self.event {
-            Event::Halted(_) => (),
+            Event::Halted(_) | Event::Exited(_) => (),
             _ => {
                 if !self.state.memory.is_last_mmio() {
                     use crate::difftest::Difftest;
@@ -231,14 +637,9 @@ impl Emulator {
 
                     Difftest::step(&mut self.ref_emu);
                     let ref_state = self.ref_emu.self_state();
-                    if ref_state != self.self_state() {
-                        use anyhow::anyhow;
-
-                        return Err(anyhow!(
-                            "Failed in difftest check, ref state: {}, self state: {}",
-                            ref_state,
-                            self.state
-                        ));
+                    let self_state = self.self_state();
+                    if ref_state != self_state {
+                        return Err(self.difftest_failure(&ref_state, &self_state));
                     }
                 } else {
                     // 跳过检测，直接同步状态
@@ -256,7 +657,64 @@ impl Emulator {
         Ok(())
     }
 
-    /// 运行模拟器
+    /// 执行单步指令，并返回本步的结果
+    pub fn step_outcome(&mut self) -> Result<StepOutcome> {
+        self.step()?;
+        Ok(if self.exec_state == ExecState::End {
+            StepOutcome::Halted
+        } else if self.event != Event::None {
+            StepOutcome::Event(self.event)
+        } else if let Some(cause) = self.last_interrupt {
+            StepOutcome::Interrupt(cause)
+        } else {
+            StepOutcome::Continued
+        })
+    }
+
+    /// 连续单步，直到某条指令使控制流发生非顺序变化（分支跳转、跳转指令、陷入、中断），最多执行 max 条
+    ///
+    /// 遇到非顺序变化时返回 `StepOutcome::Branch`（响应中断时为 `StepOutcome::Interrupt`），
+    /// 此时 pc 是改变控制流的那条指令，npc 是其目标；执行满 max 条仍未遇到时返回 `StepOutcome::Continued`，
+    /// 产生事件或程序结束时返回该步的结果
+    pub fn step_to_next_branch(&mut self, max: usize) -> Result<StepOutcome> {
+        for _ in 0..max {
+            let outcome = self.step_outcome()?;
+            if outcome != StepOutcome::Continued {
+                return Ok(outcome);
+            }
+            // 取指陷入时本步没有执行指令，npc 已指向处理程序
+            let sequential = self.last_inst.is_some_and(|(raw, _)| {
+                self.state.get_npc() == self.state.get_pc() + if is_compressed(raw) { 2 } else { 4 }
+            });
+            if !sequential {
+                return Ok(StepOutcome::Branch);
+            }
+        }
+        Ok(StepOutcome::Continued)
+    }
+
+    /// 连续单步，每步之后对执行后的状态求值 predicate，成立时停止，最多执行 max_steps 条
+    ///
+    /// 程序结束或产生事件（断点、观察点等）时也会停止；predicate 看到的 npc 是下一条要执行的指令
+    pub fn run_until(
+        &mut self,
+        max_steps: usize,
+        mut predicate: impl FnMut(&State) -> bool,
+    ) -> Result<StopReason> {
+        for _ in 0..max_steps {
+            match self.step_outcome()? {
+                StepOutcome::Halted => return Ok(StopReason::Halted),
+                StepOutcome::Event(event) => return Ok(StopReason::Event(event)),
+                StepOutcome::Continued | StepOutcome::Interrupt(_) | StepOutcome::Branch => (),
+            }
+            if predicate(&self.state) {
+                return Ok(StopReason::Predicate);
+            }
+        }
+        Ok(StopReason::MaxSteps)
+    }
+
+    /// 运行模拟器，最多执行 n 条指令；产生事件（断点、观察点等）或范围单步模式下离开范围时提前停止
     pub fn steps(&mut self, n: usize) -> Result<()> {
         self.exec_state = ExecState::Running;
         for _ in 0..n {
@@ -272,21 +730,16 @@ impl Emulator {
 
             #[cfg(feature = "difftest")] // 条件编译 DiffTest 相关
             match self.event {
-                Event::Halted(_) => (),
+                Event::Halted(_) | Event::Exited(_) => (),
                 _ => {
                     if !self.state.memory.is_last_mmio() {
                         use crate::difftest::Difftest;
 
                         Difftest::step(&mut self.ref_emu);
                         let ref_state = self.ref_emu.self_state();
-                        if ref_state != self.self_state() {
-                            use anyhow::anyhow;
-
-                            return Err(anyhow!(
-                                "Failed in difftest check, ref state: {}, self state: {}",
-                                ref_state,
-                                self.state
-                            ));
+                        let self_state = self.self_state();
+                        if ref_state != self_state {
+                            return Err(self.difftest_failure(&ref_state, &self_state));
                         }
                     } else {
                         // 跳过检测，直接同步状态
@@ -298,7 +751,12 @@ impl Emulator {
                 }
             }
 
-            if self.exec_state == ExecState::End {
+            if self.exec_state == ExecState::End || self.event != Event::None {
+                break;
+            }
+            if let ExecMode::RangeStep(start, end) = self.exec_mode
+                && !(start..end).contains(&self.state.get_npc())
+            {
                 break;
             }
         }
@@ -308,6 +766,32 @@ impl Emulator {
         Ok(())
     }
 
+    /// 构造 difftest 失败的错误：先给出出错指令与不一致的寄存器，完整状态仅在 debug 级别输出
+    #[cfg(feature = "difftest")]
+    fn difftest_failure(
+        &self,
+        ref_state: &crate::difftest::DiffState,
+        dut_state: &crate::difftest::DiffState,
+    ) -> anyhow::Error {
+        let pc = self.state.get_pc();
+        let inst_msg = match self.state.fetch_instruction(pc) {
+            Ok(inst) => format!(
+                "{:#010x} ({})",
+                inst,
+                disasm_riscv64_instruction(inst, pc).unwrap_or("未知指令".to_string())
+            ),
+            Err(_) => "<无法读取>".to_string(),
+        };
+        tracing::debug!("参考模型状态:\n{}", ref_state);
+        tracing::debug!("被测模型状态:\n{}", self.state);
+        anyhow::anyhow!(
+            "difftest 检查失败：执行 PC {:#010x} 处的指令 {} 后出现差异:\n{}",
+            pc,
+            inst_msg,
+            ref_state.mismatch_report(dut_state)
+        )
+    }
+
     /// 获取处理器状态引用
     #[inline(always)]
     pub fn get_state_ref(&self) -> &State {
@@ -324,16 +808,66 @@ impl Emulator {
         self.exec_mode
     }
 
+    /// 设置执行模式
+    ///
+    /// `step` 总是只执行一条指令，不受执行模式影响；`steps(n)` 最多执行 n 条指令，
+    /// 在 [`ExecMode::RangeStep`]`(start, end)` 下一旦下一条指令的地址离开 [start, end) 即提前返回。
+    /// 其余模式只作为调用方自身运行循环的提示（如 GDB 事件循环据此决定执行的步数）
+    #[inline(always)]
+    pub fn set_exec_mode(&mut self, mode: ExecMode) {
+        self.exec_mode = mode;
+    }
+
     #[inline(always)]
     pub fn read_memory(&self, addr: u64, size: usize) -> Result<Vec<u8>> {
         self.state.read_memory(addr, size)
     }
 
+    /// 零拷贝地查看主内存 [addr, addr + len)，仅支持 RAM，不能用于 MMIO
+    ///
+    /// 返回的切片借用了整个模拟器，持有期间无法执行指令或写内存；
+    /// 需要观察后续修改时，应在修改后重新获取视图
+    #[inline(always)]
+    pub fn memory_view(&self, addr: u64, len: usize) -> Result<&[u8]> {
+        Ok(self.state.memory.as_slice(addr, len)?)
+    }
+
+    /// 以 `hexdump -C` 格式转储主内存中的 [addr, addr + len)
+    pub fn hexdump(&self, addr: u64, len: usize) -> Result<String> {
+        Ok(crate::utils::hexdump::hexdump(addr, self.memory_view(addr, len)?))
+    }
+
     #[inline(always)]
     pub fn write_memory(&mut self, addr: u64, data: &[u8]) -> Result<()> {
         self.state.write_memory(addr, data)
     }
 
+    /// 作为一个整体应用一组内存补丁 (地址, 数据)
+    ///
+    /// 先检查所有补丁都完全落在主内存中且不触及只读区域，全部合法后才依次写入；
+    /// 任一补丁不合法时不写入任何数据，错误信息给出该补丁的序号。补丁不能写入 MMIO 区域
+    pub fn apply_patches(&mut self, patches: &[(u64, Vec<u8>)]) -> Result<()> {
+        for (index, (addr, data)) in patches.iter().enumerate() {
+            let checked = if self.state.memory.is_mem_region_range(*addr, data.len()) {
+                self.state.memory.check_writable(*addr, data.len())
+            } else {
+                Err(MemoryError::OutOfBounds { addr: *addr, size: data.len() })
+            };
+            checked.with_context(|| {
+                format!(
+                    "补丁 #{} (地址 {:#x}, 大小 {}) 无效，未应用任何补丁",
+                    index,
+                    addr,
+                    data.len()
+                )
+            })?;
+        }
+        for (addr, data) in patches {
+            self.state.write_memory(*addr, data)?;
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     pub fn get_reg(&self, reg: u64) -> Result<u64> {
         self.state.get_reg(reg)
@@ -344,21 +878,77 @@ impl Emulator {
         self.state.set_reg(reg, value)
     }
 
+    /// 直接写入 CSR，不经过指令的权限检查与副作用
+    pub fn set_csr(&mut self, csr: u16, value: u64) -> Result<()> {
+        self.state.set_csr(csr, value)
+    }
+
+    // PC 模型：
+    // 每一步开始时先 sync_pc（pc = npc），从 pc 取指后将 npc 设为顺序的下一条指令地址，
+    // 跳转/分支/陷入等再覆盖 npc。因此一步执行结束后，pc 是刚执行完的指令地址，
+    // npc 是下一步将要执行的地址，直到下一步开始时才同步到 pc
+
+    /// 当前（最近一次执行的）指令地址
     #[inline(always)]
     pub fn get_pc(&self) -> u64 {
         self.state.get_pc()
     }
 
+    /// 下一步将要执行的指令地址
+    #[inline(always)]
+    pub fn get_npc(&self) -> u64 {
+        self.state.get_npc()
+    }
+
+    /// 设置下一步将要执行的指令地址；若需要 get_pc 立即反映新值，再调用 sync_pc
     #[inline(always)]
     pub fn set_npc(&mut self, pc: u64) {
         self.state.set_npc(pc)
     }
 
+    /// 令 pc = npc
     #[inline(always)]
     pub fn sync_pc(&mut self) {
         self.state.sync_pc()
     }
 
+    /// 取出当前 PC 处的指令而不执行，返回指令字及其是否为压缩指令
+    ///
+    /// 压缩指令只有低16位有效，高16位清零
+    pub fn current_instruction_raw(&self) -> Result<(u32, bool)> {
+        let pc = self.state.get_pc();
+        let word = self
+            .state
+            .fetch_instruction(pc)
+            .with_context(|| format!("无法从PC {:#x} 处读取指令", pc))?;
+        if is_compressed(word) {
+            Ok((word & 0xFFFF, true))
+        } else {
+            Ok((word, false))
+        }
+    }
+
+    /// 当前 PC 处指令的名称；只读查询，不经过译码缓存，不影响缓存统计
+    pub fn current_instruction_name(&self) -> Result<&'static str> {
+        let (word, _) = self.current_instruction_raw()?;
+        Ok(self.decoder.slow_path(word)?.name)
+    }
+
+    /// 译码缓存的命中率（命中次数 / 查找次数），禁用缓存时返回 0.0
+    pub fn get_hit_rate(&self) -> f64 {
+        self.decoder.hit_rate()
+    }
+
+    /// 译码缓存的累计 (命中次数, 未命中次数)，用于按负载调整 decoder_cache_size
+    pub fn decode_cache_stats(&self) -> (u64, u64) {
+        self.decoder.cache_stats()
+    }
+
+    /// 清空译码缓存，之后每条指令首次译码都不命中；累计次数不清零
+    pub fn clear_decode_cache(&mut self) {
+        self.decoder.clear_cache();
+    }
+
     #[inline(always)]
     pub fn get_regs(&self) -> &[u64; 32] {
         self.state.get_regs()
@@ -377,8 +967,1598 @@ impl Emulator {
         self.event
     }
 
+    /// 冻结/恢复设备时间，单步调试时冻结可避免计时器在两步之间跳变
+    pub fn freeze_time(&mut self, frozen: bool) {
+        if self.time_frozen != frozen {
+            tracing::info!("{}设备时间", if frozen { "冻结" } else { "恢复" });
+            self.state.memory.set_devices_time_frozen(frozen);
+            self.time_frozen = frozen;
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_time_frozen(&self) -> bool {
+        self.time_frozen
+    }
+
+    /// 从字节序列设置整个机器状态，便于模糊测试从任意状态开始执行
+    ///
+    /// 布局（小端）：32个通用寄存器各8字节，随后8字节的 PC，剩余部分作为内存镜像写入 boot_pc 处。
+    /// x0 的值被忽略
+    pub fn load_fuzz_state(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() < FUZZ_STATE_HEADER_SIZE {
+            return Err(anyhow::anyhow!(
+                "模糊测试状态长度不足: {} 字节，至少需要 {} 字节",
+                data.len(),
+                FUZZ_STATE_HEADER_SIZE
+            ));
+        }
+        let (header, image) = data.split_at(FUZZ_STATE_HEADER_SIZE);
+        let boot_pc = self.config.memory.boot_pc;
+        if !self.state.memory.is_mem_region_range(boot_pc, image.len()) {
+            return Err(anyhow::anyhow!(
+                "内存镜像过大: {} 字节无法从 {:#x} 处放入主内存",
+                image.len(),
+                boot_pc
+            ));
+        }
+
+        let mut words = header
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+        for reg in 0..32 {
+            let value = words.next().unwrap();
+            self.state.set_reg(reg, value)?;
+        }
+        let pc = words.next().unwrap();
+        self.state.write_memory(boot_pc, image)?;
+        self.state.set_npc(pc);
+        self.state.sync_pc();
+        Ok(())
+    }
+
+    /// 设置 PC 轨迹的记录或比对，以当前的寄存器作为求写集的起始状态
+    pub fn set_pc_trace(&mut self, mut trace: pc_trace::PcTrace) {
+        trace.start(self.state.get_regs(), &self.state.fregs);
+        self.pc_trace = Some(trace);
+    }
+
+    /// 结束 PC 轨迹的记录或比对
+    pub fn finish_pc_trace(&mut self) -> Result<()> {
+        match self.pc_trace.take() {
+            Some(mut trace) => trace.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// 开启/关闭逐条指令的读写记录，供访存追踪器取走
+    pub fn set_access_tracking(&mut self, enabled: bool) {
+        self.state.memory.set_access_tracking(enabled);
+    }
+
+    /// 将主内存搬移到 new_base；adjust_pc 为真时，位于主内存内的 pc/npc 随之平移
+    ///
+    /// 位于主内存内的可执行范围、program break、断点与观察点总是随之平移
+    pub fn relocate_memory(&mut self, new_base: u64, adjust_pc: bool) -> Result<()> {
+        let old_base = self.state.memory.memory_base();
+        self.state.memory.set_base(new_base)?;
+        let size = self.state.memory.ram().len() as u64;
+        let relocate = |addr: u64| match addr.wrapping_sub(old_base) {
+            offset if offset < size => new_base + offset,
+            _ => addr,
+        };
+        if adjust_pc {
+            self.state.pc = relocate(self.state.pc);
+            self.state.npc = relocate(self.state.npc);
+        }
+        for range in &mut self.exec_regions {
+            let start = relocate(range.start);
+            *range = start..start + (range.end - range.start);
+        }
+        // program break 可以恰好位于主内存末尾
+        let relocate_break = |addr: u64| match addr.wrapping_sub(old_base) {
+            offset if offset <= size => new_base + offset,
+            _ => addr,
+        };
+        self.program_break = relocate_break(self.program_break);
+        self.reset_break = relocate_break(self.reset_break);
+        self.debug_points.relocate(relocate);
+        self.sync_watchpoints();
+        Ok(())
+    }
+
+    /// 将主内存中的 [base, base + size) 标记为 name，配合 tag_log_size 记录对它的访问
+    pub fn tag_memory(&mut self, name: impl Into<String>, base: u64, size: u64) {
+        self.state.memory.add_tagged_range(name, base, size);
+    }
+
+    /// 最近对标记区域的访问，按时间从旧到新排列
+    pub fn tag_log(&self) -> Vec<TagRecord> {
+        let Some(log) = &self.tag_log else {
+            return Vec::new();
+        };
+        let ranges = self.state.memory.tagged_ranges();
+        log.iter()
+            .map(|&(pc, hit)| TagRecord {
+                pc,
+                tag: ranges[hit.tag].name.clone(),
+                addr: hit.addr,
+                size: hit.size,
+                is_write: hit.is_write,
+            })
+            .collect()
+    }
+
+    /// 最近的 MMIO 访问，按时间从旧到新排列
+    pub fn mmio_log(&self) -> Vec<MmioRecord> {
+        let Some(log) = &self.mmio_log else {
+            return Vec::new();
+        };
+        log.iter()
+            .map(|&(pc, access)| {
+                let (device, base) = self
+                    .state
+                    .memory
+                    .mmio_region_at(access.addr)
+                    .map(|(name, base, _)| (name.to_string(), base))
+                    .unwrap_or_default();
+                MmioRecord {
+                    pc,
+                    device,
+                    offset: access.addr - base,
+                    size: access.size,
+                    is_write: access.is_write,
+                    value: access.value,
+                }
+            })
+            .collect()
+    }
+
+    /// 获取客户程序的退出码，程序尚未结束时返回 None
+    #[inline(always)]
+    pub fn exit_code(&self) -> Option<u8> {
+        self.exit_code
+    }
+
+    /// 周期计数（即 mcycle）：每条指令计1个周期，另加访问主内存与设备寄存器的延迟
+    #[inline(always)]
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// 已退休的指令数（即 minstret），产生异常的指令不计入
+    #[inline(always)]
+    pub fn instret(&self) -> u64 {
+        self.instret
+    }
+
     #[cfg(feature = "difftest")]
     pub fn get_ref_mut(&mut self) -> &mut CpuCore {
         &mut self.ref_emu
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bit_utils::BitSlice;
+    use crate::test_utils::{emu_from, emu_with_program, load_program, test_config, test_device};
+
+    fn emu_with_fetch_cache(program: &[u32]) -> Emulator {
+        let (mut config, device_file) = test_config();
+        config.others.fetch_cache_size = 64;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, program);
+        emu
+    }
+
+    #[test]
+    fn test_decode_cache_stats_and_clear() {
+        let mut emu = emu_with_program(&[
+            0x00a00593, // li a1, 10
+            0xfff58593, // loop: addi a1, a1, -1
+            0xfe059ee3, // bnez a1, loop
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.steps(17).unwrap();
+        // 3 条不同的指令各未命中一次，循环中其余译码均命中
+        assert_eq!(emu.decode_cache_stats(), (14, 3));
+
+        emu.clear_decode_cache();
+        emu.steps(1).unwrap();
+        assert_eq!(emu.decode_cache_stats(), (14, 4));
+        // bnez 清空后首次译码未命中，再次执行的 addi 命中
+        emu.steps(2).unwrap();
+        assert_eq!(emu.decode_cache_stats(), (15, 5));
+    }
+
+    #[test]
+    fn test_fetch_cache_loop() {
+        let mut emu = emu_with_fetch_cache(&[
+            0x00500293, // li t0, 5
+            0x00358593, // addi a1, a1, 3
+            0xfff28293, // addi t0, t0, -1
+            0xfe029ce3, // bnez t0, -8
+            0x00100073, // ebreak
+        ]);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.get_reg(11).unwrap(), 15);
+    }
+
+    #[test]
+    fn test_self_modifying_code_without_fetch_cache() {
+        // 默认配置不启用取指缓存
+        let mut emu = emu_with_program(&[
+            0x00000317, // auipc t1, 0
+            0x01832383, // lw t2, 24(t1)
+            0x00732623, // sw t2, 12(t1)
+            0x00000013, // nop                <- 被改写为 addi a1, a1, 10
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+            0x00a58593, // addi a1, a1, 10
+        ]);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.get_reg(11).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_stack_overflow_hits_guard() {
+        let (mut config, device_file) = test_config();
+        config.memory.stack_guard = Some(const_values::StackGuardConfig {
+            stack_base: crate::test_utils::TEST_BASE + 0x1000,
+            guard_size: 0x100,
+        });
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &[
+            0x00001117, // auipc sp, 1        sp = 栈底
+            0x01010113, // addi sp, sp, 16    只留16字节的栈
+            0xff810113, // addi sp, sp, -8
+            0x00013023, // sd zero, 0(sp)
+            0xff9ff06f, // j -8
+        ]);
+        let err = emu.steps(100).unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("栈溢出"), "{msg}");
+        assert_eq!(emu.get_reg(2).unwrap(), crate::test_utils::TEST_BASE + 0x1000 - 8);
+    }
+
+    #[test]
+    fn test_undecodable_instruction_traps() {
+        use crate::emulator::instructions::insts::{CAUSE_ILLEGAL_INSTRUCTION, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00000013, // nop
+            0x0000000b, // custom-0，无法解码
+            0x00000513, // li a0, 0           <- mtvec
+            0x00100073, // ebreak
+        ]);
+        emu.state.set_csr(CSR_MTVEC, base + 8).unwrap();
+
+        emu.steps(2).unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mepc, base + 4);
+        assert_eq!(view.mcause, CAUSE_ILLEGAL_INSTRUCTION as u64);
+        assert_eq!(view.mtval, 0x0000000b);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_trap_state_after_misaligned_jump() {
+        use crate::emulator::instructions::insts::{CAUSE_MISALIGNED_FETCH, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00000013, // nop
+            0x0020006f, // j +2               跳转目标未对齐
+            0x00000013, // nop
+            0x00000013, // nop
+            0x00000513, // li a0, 0           <- mtvec
+            0x00100073, // ebreak
+        ]);
+        emu.state.set_csr(CSR_MTVEC, base + 16).unwrap();
+
+        emu.steps(2).unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.privilege, PrivilegeLevel::Machine);
+        assert_eq!(view.mtvec, base + 16);
+        assert_eq!(view.mepc, base + 4);
+        assert_eq!(view.mcause, CAUSE_MISALIGNED_FETCH as u64);
+        assert_eq!(view.mtval, base + 6);
+        // MPP 记录陷入前的 M 模式
+        assert_eq!(view.mstatus.bit_range(trap::MSTATUS_MPP), PrivilegeLevel::Machine as u64);
+
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_freeze_time_holds_timer() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("timer0", "timer", 0x1000_0100, 0x100));
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &[
+            0x100002b7, // lui t0, 0x10000
+            0x10028293, // addi t0, t0, 0x100
+            0x0002b583, // ld a1, 0(t0)
+            0x00000013, // nop
+            0x0002b603, // ld a2, 0(t0)
+        ]);
+
+        emu.freeze_time(true);
+        for _ in 0..5 {
+            emu.step().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_ne!(emu.get_reg(11).unwrap(), 0);
+        assert_eq!(emu.get_reg(11).unwrap(), emu.get_reg(12).unwrap());
+    }
+
+    #[test]
+    fn test_memory_view_aliases_ram() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x00a00313, // li t1, 10
+            0x10628023, // sb t1, 256(t0)
+        ]);
+        let view = emu.memory_view(base, 8).unwrap();
+        assert_eq!(view.as_ptr(), emu.state.memory.as_slice(base, 8).unwrap().as_ptr());
+        assert_eq!(view[0..4], 0x00000297u32.to_le_bytes());
+
+        assert_eq!(emu.memory_view(base + 256, 1).unwrap(), &[0]);
+        emu.steps(3).unwrap();
+        assert_eq!(emu.memory_view(base + 256, 1).unwrap(), &[10]);
+    }
+
+    /// 构造一个 mtvec 指向 nop 处理程序、全局中断已开启的模拟器
+    fn emu_with_interrupts_enabled(mie: u64, mip: u64) -> Emulator {
+        use crate::emulator::instructions::insts::{CSR_MIE, CSR_MIP, CSR_MSTATUS, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[0x00000013; 16]);
+        emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << trap::MSTATUS_MIE).unwrap();
+        emu.state.set_csr(CSR_MIE, mie).unwrap();
+        emu.state.set_csr(CSR_MIP, mip).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_interrupt_priority_external_before_timer() {
+        use crate::emulator::instructions::insts::{CSR_MIP, CSR_MSTATUS};
+        use trap::{IRQ_M_EXT, IRQ_M_TIMER, INTERRUPT_BIT};
+
+        let base = crate::test_utils::TEST_BASE;
+        let both = (1 << IRQ_M_EXT) | (1 << IRQ_M_TIMER);
+        let mut emu = emu_with_interrupts_enabled(both, both);
+
+        emu.step().unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, INTERRUPT_BIT | IRQ_M_EXT);
+        assert_eq!(view.mepc, base);
+        // 进入处理程序后 MIE 被清零、MPIE 保存了原值，不会被定时器中断打断
+        assert!(!view.mstatus.bit(trap::MSTATUS_MIE));
+        assert!(view.mstatus.bit(trap::MSTATUS_MPIE));
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mcause, INTERRUPT_BIT | IRQ_M_EXT);
+
+        // 处理程序清除外部中断并重新开中断后，嵌套响应定时器中断
+        emu.state.set_csr(CSR_MIP, 1 << IRQ_M_TIMER).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, view.mstatus | (1 << trap::MSTATUS_MIE)).unwrap();
+        let handler_pc = emu.state.get_npc();
+        emu.step().unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, INTERRUPT_BIT | IRQ_M_TIMER);
+        assert_eq!(view.mepc, handler_pc);
+    }
+
+    #[test]
+    fn test_interrupt_priority_order() {
+        use trap::{INTERRUPT_BIT, IRQ_M_SOFT, IRQ_M_TIMER, IRQ_S_EXT};
+
+        // 软件中断优先于定时器中断
+        let pending = (1 << IRQ_M_SOFT) | (1 << IRQ_M_TIMER) | (1 << IRQ_S_EXT);
+        let mut emu = emu_with_interrupts_enabled(pending, pending);
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mcause, INTERRUPT_BIT | IRQ_M_SOFT);
+
+        // 未使能的中断不会被响应
+        let mut emu = emu_with_interrupts_enabled(1 << IRQ_M_TIMER, 1 << IRQ_M_SOFT);
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mcause, 0);
+        assert_eq!(emu.get_pc(), crate::test_utils::TEST_BASE);
+    }
+
+    #[test]
+    fn test_rv32_interrupt_cause_uses_bit_31() {
+        use crate::emulator::instructions::insts::{CSR_MIE, CSR_MIP, CSR_MSTATUS, CSR_MTVEC};
+        use trap::IRQ_M_TIMER;
+
+        let base = crate::test_utils::TEST_BASE;
+        let (mut config, device_file) = test_config();
+        config.inst_set.xlen = const_values::Xlen::X32;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &[0x00000013; 16]);
+        // 向量模式：中断跳转到 base + 4 * 中断号
+        emu.state.set_csr(CSR_MTVEC, base | 1).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << trap::MSTATUS_MIE).unwrap();
+        emu.state.set_csr(CSR_MIE, 1 << IRQ_M_TIMER).unwrap();
+        emu.state.set_csr(CSR_MIP, 1 << IRQ_M_TIMER).unwrap();
+
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mcause, 1 << 31 | IRQ_M_TIMER);
+        assert_eq!(emu.get_pc(), base + 4 * IRQ_M_TIMER);
+    }
+
+    #[test]
+    fn test_rv32_wraparound() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.xlen = const_values::Xlen::X32;
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &[
+            0x80000537, // lui a0, 0x80000
+            0xfff50513, // addi a0, a0, -1    a0 = 0x7fffffff
+            0x00150513, // addi a0, a0, 1     a0 = 0x80000000（32位溢出）
+            0x00455593, // srli a1, a0, 4     逻辑右移按32位进行
+            0xfff00613, // li a2, -1
+            0x00160613, // addi a2, a2, 1     0xffffffff + 1 = 0
+            0x02b506b3, // mul a3, a0, a1     高位被截断
+            0x00000297, // auipc t0, 0
+            0x10a2a023, // sw a0, 256(t0)     地址按32位计算
+            0x1002a703, // lw a4, 256(t0)
+            0x1002b783, // ld a5, 256(t0)     RV32 中不存在
+        ]);
+        emu.steps(10).unwrap();
+        let reg = |r| emu.get_reg(r).unwrap();
+        assert_eq!(reg(10) as u32, 0x8000_0000);
+        assert_eq!(reg(10), 0xffff_ffff_8000_0000);
+        assert_eq!(reg(11), 0x0800_0000);
+        assert_eq!(reg(12), 0);
+        assert_eq!(reg(13), 0);
+        assert_eq!(reg(14) as u32, 0x8000_0000);
+        assert_eq!(reg(5) as u32, 0x8000_001c);
+        assert!(emu.step().is_err());
+    }
+
+    #[test]
+    fn test_boot_pc_inside_mmio_region_is_rejected() {
+        let (mut config, mut device_file) = test_config();
+        config.memory.boot_pc = 0x1000_0104;
+        device_file.devices.push(test_device("timer0", "timer", 0x1000_0100, 0x100));
+        let err = Emulator::from_config(config, &device_file).err().unwrap();
+        let msg = err.to_string();
+        assert!(msg.contains("boot_pc") && msg.contains("timer0"), "{msg}");
+    }
+
+    #[test]
+    fn test_missing_device_config_uses_builtin_layout() {
+        use clap::Parser;
+        let args = crate::Args::parse_from(["emulator", "-d", "/nonexistent/dolphin-device.toml"]);
+        let emu = Emulator::new(&args).unwrap();
+        let memory = &emu.get_state_ref().memory;
+        assert_eq!(memory.mmio_region_at(0x1000_0000).unwrap().0, "uart0");
+        assert_eq!(memory.mmio_region_at(0x1000_0100).unwrap().0, "timer0");
+        assert_eq!(memory.memory_base(), 0x8000_0000);
+    }
+
+    #[test]
+    fn test_boot_pc_and_memory_base_from_config() {
+        let config_at = |base| {
+            let (mut config, mut device_file) = test_config();
+            config.memory.boot_pc = base;
+            device_file.memory.memory_base = base;
+            (config, device_file)
+        };
+
+        let (config, device_file) = config_at(0x2000_0000);
+        let state = State::new(Rc::new(config), &device_file).unwrap();
+        assert_eq!(state.get_pc(), 0x2000_0000);
+        assert_eq!(state.get_npc(), 0x2000_0000);
+        assert_eq!(state.memory.memory_base(), 0x2000_0000);
+
+        // 从主内存基址开始执行
+        let (config, device_file) = config_at(0x2000_0000);
+        let mut emu = emu_from(config, &device_file);
+        emu.write_memory(0x2000_0000, &0x00100073u32.to_le_bytes()).unwrap(); // ebreak
+        emu.step().unwrap();
+        assert_eq!(emu.get_pc(), 0x2000_0000);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_halt_device_write_exits() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("halt0", "halt", 0x1000_0200, 0x4));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x2002a023, // sw zero, 0x200(t0)
+                0x0000006f, // j .
+            ],
+        );
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(0));
+        assert_eq!(emu.get_cur_event(), Event::Exited(0));
+        assert_eq!(emu.get_pc(), crate::test_utils::TEST_BASE + 4);
+    }
+
+    #[test]
+    fn test_mmio_log_records_device_accesses() {
+        let (mut config, mut device_file) = test_config();
+        config.debug.mmio_log_size = 8;
+        for (name, device_type, base) in [
+            ("uart0", "uart", 0x1000_0000),
+            ("timer0", "timer", 0x1000_0100),
+        ] {
+            device_file.devices.push(test_device(name, device_type, base, 0x100));
+        }
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x04100313, // li t1, 'A'
+                0x00628023, // sb t1, 0(t0)
+                0x1002a383, // lw t2, 0x100(t0)
+            ],
+        );
+        emu.steps(4).unwrap();
+
+        let log = emu.mmio_log();
+        assert_eq!(log.len(), 2);
+        let base = crate::test_utils::TEST_BASE;
+        assert_eq!(
+            log[0],
+            MmioRecord {
+                pc: base + 8,
+                device: "uart0".to_string(),
+                offset: 0,
+                size: 1,
+                is_write: true,
+                value: 0x41,
+            }
+        );
+        assert_eq!(log[1].pc, base + 12);
+        assert_eq!(log[1].device, "timer0");
+        assert_eq!((log[1].offset, log[1].size, log[1].is_write), (0, 4, false));
+        assert_eq!(log[1].value, emu.get_reg(7).unwrap() & 0xffff_ffff);
+    }
+
+    #[test]
+    fn test_relocate_memory_moves_pc() {
+        let mut emu = emu_with_program(&[
+            0x00100513, // li a0, 1
+            0x00100073, // ebreak
+        ]);
+        emu.relocate_memory(0x4000_0000, true).unwrap();
+        assert_eq!(emu.get_pc(), 0x4000_0000);
+        assert_eq!(emu.read_memory(0x4000_0000, 4).unwrap(), 0x00100513u32.to_le_bytes());
+
+        emu.steps(2).unwrap();
+        assert_eq!(emu.get_reg(10).unwrap(), 1);
+        assert_eq!(emu.exit_code(), Some(1));
+    }
+
+    #[test]
+    fn test_relocate_memory_moves_exec_regions_and_debug_points() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00100513, // li a0, 1
+            0x00100073, // ebreak
+        ]);
+        emu.add_exec_region(base..base + 8);
+        emu.enable_exec_check();
+        emu.add_breakpoint(base + 4);
+        emu.add_watchpoint(base + 0x100, 1, WatchKind::Write);
+        emu.tag_memory("buf", base + 0x100, 0x10);
+
+        emu.relocate_memory(0x4000_0000, true).unwrap();
+        assert_eq!(emu.exec_regions(), &[0x4000_0000..0x4000_0008]);
+        assert_eq!(emu.list_breakpoints(), vec![0x4000_0004]);
+        assert_eq!(emu.list_watchpoints(), vec![(0x4000_0100, WatchKind::Write)]);
+        assert_eq!(emu.state.memory.tagged_ranges()[0].base, 0x4000_0100);
+
+        // 取指落在搬移后的可执行范围内，不会陷入
+        emu.remove_breakpoint(0x4000_0004);
+        emu.steps(2).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(1));
+    }
+
+    /// 统计新建的 "inst" span 数量
+    #[derive(Default)]
+    struct InstSpanCounter(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    impl tracing::Subscriber for InstSpanCounter {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            if attrs.metadata().name() == "inst" {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+            tracing::span::Id::from_u64(1)
+        }
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, _: &tracing::Event<'_>) {}
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn test_inst_spans_follow_feature() {
+        let counter = InstSpanCounter::default();
+        let count = counter.0.clone();
+        let mut emu = emu_with_program(&[
+            0x00100513, // li a0, 1
+            0x00a50533, // add a0, a0, a0
+        ]);
+        tracing::subscriber::with_default(counter, || emu.steps(2).unwrap());
+
+        let spans = count.load(std::sync::atomic::Ordering::Relaxed);
+        if cfg!(feature = "inst-spans") {
+            assert_eq!(spans, 2);
+        } else {
+            assert_eq!(spans, 0);
+        }
+    }
+
+    #[test]
+    fn test_watchdog_timeout_halts() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("wdt0", "watchdog", 0x1000_0300, 0x10));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x7d000313, // li t1, 2000
+                0x3062a023, // sw t1, 0x300(t0)
+                0x0000006f, // j .
+            ],
+        );
+        emu.steps(10_000).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(watchdog::TIMEOUT_EXIT_CODE));
+        assert_eq!(emu.get_pc(), crate::test_utils::TEST_BASE + 12);
+    }
+
+    #[test]
+    fn test_device_tick_follows_steps() {
+        let (mut config, mut device_file) = test_config();
+        config.others.device_tick_interval = 1;
+        device_file.devices.push(test_device("wdt0", "watchdog", 0x1000_0300, 0x10));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x06400313, // li t1, 100
+                0x3062a023, // sw t1, 0x300(t0)
+                0x00000013, // nop
+                0x00000013, // nop
+                0x00000013, // nop
+            ],
+        );
+        let count = |emu: &Emulator| {
+            u32::from_le_bytes(emu.read_memory(0x1000_0308, 4).unwrap().try_into().unwrap())
+        };
+        // 写入超时的那条指令结束时已驱动一个周期，此后每步驱动一个周期
+        emu.steps(3).unwrap();
+        assert_eq!(count(&emu), 99);
+        for remaining in [98, 97, 96] {
+            emu.steps(1).unwrap();
+            assert_eq!(count(&emu), remaining);
+        }
+    }
+
+    #[test]
+    fn test_frozen_time_stops_device_ticks() {
+        let (mut config, mut device_file) = test_config();
+        config.others.device_tick_interval = 1;
+        device_file.devices.push(test_device("wdt0", "watchdog", 0x1000_0300, 0x10));
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x00300313, // li t1, 3
+                0x3062a023, // sw t1, 0x300(t0)
+                0x00000013, // nop
+                0x00000013, // nop
+                0x00000013, // nop
+                0x00000013, // nop
+            ],
+        );
+        emu.steps(3).unwrap();
+        emu.freeze_time(true);
+        // 冻结期间 watchdog 不倒计时，不会超时结束模拟
+        emu.steps(3).unwrap();
+        assert_ne!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.read_memory(0x1000_0308, 4).unwrap(), 2u32.to_le_bytes());
+
+        emu.freeze_time(false);
+        emu.steps(2).unwrap();
+        assert_eq!(emu.exit_code(), Some(watchdog::TIMEOUT_EXIT_CODE));
+    }
+
+    #[test]
+    fn test_region_latency_advances_mcycle() {
+        let (mut config, mut device_file) = test_config();
+        config.inst_set.zicsr = true;
+        device_file.memory.latency = 2;
+        device_file.devices.push(const_values::DeviceConfig {
+            latency: 50,
+            ..test_device("slow0", "hostcall", 0x1000_0400, 0x20)
+        });
+        let mut emu = emu_from(config, &device_file);
+        load_program(
+            &mut emu,
+            &[
+                0x100002b7, // lui t0, 0x10000
+                0x40028293, // addi t0, t0, 0x400
+                0xb0002473, // csrr s0, mcycle
+                0x0002b503, // ld a0, 0(t0)       慢速设备
+                0xb00024f3, // csrr s1, mcycle
+                0x00000317, // auipc t1, 0
+                0x04033583, // ld a1, 64(t1)      主内存
+                0xb0002973, // csrr s2, mcycle
+                0x00100073, // ebreak
+            ],
+        );
+        emu.steps(100).unwrap();
+
+        let (s0, s1, s2) = (emu.get_reg(8).unwrap(), emu.get_reg(9).unwrap(), emu.get_reg(18).unwrap());
+        assert_eq!(s0, 2);
+        // csrr + ld，ld 另计设备延迟
+        assert_eq!(s1 - s0, 2 + 50);
+        // csrr + auipc + ld，ld 另计主内存延迟
+        assert_eq!(s2 - s1, 3 + 2);
+        assert_eq!(emu.cycles(), s2 + 2);
+    }
+
+    #[test]
+    fn test_step_delivers_timer_interrupt() {
+        use crate::emulator::instructions::insts::{CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use trap::{INTERRUPT_BIT, IRQ_M_TIMER};
+
+        let base = crate::test_utils::TEST_BASE;
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("timer0", "timer", 0x1000_0100, 0x100));
+        let mut emu = emu_from(config, &device_file);
+        let mut program = vec![
+            0x100002b7, // lui t0, 0x10000
+            0x1002b303, // ld t1, 0x100(t0)   读当前计数
+            0x03230313, // addi t1, t1, 50
+            0x1062b823, // sd t1, 0x110(t0)   50us 后到期
+            0x0000006f, // j .
+        ];
+        program.resize(16, 0x00000013); // 处理程序：nop
+        load_program(&mut emu, &program);
+        emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << trap::MSTATUS_MIE).unwrap();
+        emu.state.set_csr(CSR_MIE, 1 << IRQ_M_TIMER).unwrap();
+
+        for _ in 0..4 {
+            assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Continued);
+        }
+        // 单步直到定时器到期，单步过程中不调用 steps
+        let outcome = (0..1_000_000)
+            .map(|_| emu.step_outcome().unwrap())
+            .find(|outcome| *outcome != StepOutcome::Continued);
+        assert_eq!(outcome, Some(StepOutcome::Interrupt(INTERRUPT_BIT | IRQ_M_TIMER)));
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, INTERRUPT_BIT | IRQ_M_TIMER);
+        assert_eq!(view.mepc, base + 16);
+        assert!(view.mip.bit(IRQ_M_TIMER as usize));
+        // 本步执行的是处理程序的第一条指令
+        assert_eq!(emu.get_pc(), base + 0x20);
+    }
+
+    #[test]
+    fn test_apply_patches_is_all_or_nothing() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[]);
+        let patches = vec![(base, vec![0xAA; 4]), (0x1000, vec![0xBB; 4])];
+
+        let err = emu.apply_patches(&patches).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("#1") && msg.contains("0x1000"), "{msg}");
+        assert_eq!(emu.read_memory(base, 4).unwrap(), vec![0; 4]);
+
+        emu.apply_patches(&patches[..1]).unwrap();
+        assert_eq!(emu.read_memory(base, 4).unwrap(), vec![0xAA; 4]);
+    }
+
+    #[test]
+    fn test_device_irq_routed_through_plic() {
+        use crate::emulator::instructions::insts::{CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use std::sync::{Arc, Mutex};
+        use trap::{INTERRUPT_BIT, IRQ_M_EXT};
+
+        /// 中断线恒为有效的设备
+        struct IrqLine;
+
+        impl mmio_trait::MmioDevice for IrqLine {
+            fn read(&mut self, _offset: u64, size: usize) -> Result<Vec<u8>, mmio_trait::DeviceError> {
+                Ok(vec![0; size])
+            }
+            fn write(&mut self, _offset: u64, _data: &[u8]) -> Result<(), mmio_trait::DeviceError> {
+                Ok(())
+            }
+            fn irq_pending(&self) -> Option<u32> {
+                Some(0)
+            }
+        }
+
+        let base = crate::test_utils::TEST_BASE;
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("plic0", "plic", 0x0c00_0000, 0x400_0000));
+        let mut emu = emu_from(config, &device_file);
+        emu.state
+            .memory
+            .map_mmio(0x1000_0000, 0x10, Arc::new(Mutex::new(IrqLine)), "irq0".to_string())
+            .unwrap();
+        emu.state.memory.route_irq("irq0", 3).unwrap();
+        load_program(
+            &mut emu,
+            &[
+                0x00000013, // nop
+                0x00000013, // nop
+                0x0042a503, // 处理程序: lw a0, 4(t0)   claim
+                0x00a2a223, // sw a0, 4(t0)             complete
+            ],
+        );
+        emu.set_reg(5, 0x0c20_0000).unwrap();
+        emu.state.set_csr(CSR_MTVEC, base + 8).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << trap::MSTATUS_MIE).unwrap();
+        emu.state.set_csr(CSR_MIE, 1 << IRQ_M_EXT).unwrap();
+
+        // 优先级为0或未使能时中断源被屏蔽
+        emu.step().unwrap();
+        assert_eq!(emu.trap_state().mcause, 0);
+
+        emu.write_memory(0x0c00_0000 + 4 * 3, &1u32.to_le_bytes()).unwrap();
+        emu.write_memory(0x0c00_2000, &(1u32 << 3).to_le_bytes()).unwrap();
+        assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Interrupt(INTERRUPT_BIT | IRQ_M_EXT));
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, INTERRUPT_BIT | IRQ_M_EXT);
+        assert_eq!(view.mepc, base + 4);
+        assert_eq!(emu.get_reg(10).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_irq_line_repolled_after_mmio_ack() {
+        use crate::emulator::instructions::insts::{CSR_MIE, CSR_MSTATUS, CSR_MTVEC};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{Arc, Mutex};
+        use trap::IRQ_M_EXT;
+
+        /// 直接驱动 MEIP 的设备，写入任意值即撤销中断
+        struct AckLine(Arc<AtomicBool>);
+
+        impl mmio_trait::MmioDevice for AckLine {
+            fn read(&mut self, _offset: u64, size: usize) -> Result<Vec<u8>, mmio_trait::DeviceError> {
+                Ok(vec![0; size])
+            }
+            fn write(&mut self, _offset: u64, _data: &[u8]) -> Result<(), mmio_trait::DeviceError> {
+                self.0.store(false, Ordering::Relaxed);
+                Ok(())
+            }
+            fn irq_pending(&self) -> Option<u32> {
+                self.0.load(Ordering::Relaxed).then_some(IRQ_M_EXT as u32)
+            }
+        }
+
+        let base = crate::test_utils::TEST_BASE;
+        let (mut config, device_file) = test_config();
+        config.inst_set.privileged = true;
+        let mut emu = emu_from(config, &device_file);
+        let line = Arc::new(AtomicBool::new(true));
+        emu.state
+            .memory
+            .map_mmio(0x1000_0000, 0x10, Arc::new(Mutex::new(AckLine(line.clone()))), "ack0".to_string())
+            .unwrap();
+        load_program(
+            &mut emu,
+            &[
+                0x00000513, // li a0, 0
+                0x00100073, // ebreak
+                0x0002a023, // 处理程序: sw zero, 0(t0)   撤销中断
+                0x00158593, // addi a1, a1, 1
+                0x30200073, // mret
+            ],
+        );
+        emu.set_reg(5, 0x1000_0000).unwrap();
+        emu.state.set_csr(CSR_MTVEC, base + 8).unwrap();
+        emu.state.set_csr(CSR_MSTATUS, 1 << trap::MSTATUS_MIE).unwrap();
+        emu.state.set_csr(CSR_MIE, 1 << IRQ_M_EXT).unwrap();
+
+        // 批量执行不会每步轮询中断线，撤销后 MEIP 必须立即清除，否则 mret 后会再次进入处理程序
+        emu.step().unwrap();
+        emu.steps(20).unwrap();
+        assert!(!line.load(Ordering::Relaxed));
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_range_step_stops_at_range_boundary() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00158593, // addi a1, a1, 1
+            0x00158593, // addi a1, a1, 1
+            0x00158593, // addi a1, a1, 1
+            0x00158593, // addi a1, a1, 1
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.set_exec_mode(ExecMode::RangeStep(base, base + 12));
+        assert_eq!(emu.get_exec_mode(), ExecMode::RangeStep(base, base + 12));
+
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::Idle);
+        assert_eq!(emu.get_reg(11).unwrap(), 3);
+        assert_eq!(emu.state.get_npc(), base + 12);
+
+        // 恢复连续执行后运行到结束
+        emu.set_exec_mode(ExecMode::Continue);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.get_reg(11).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_npc_points_at_branch_target_before_sync() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00000663, // beqz zero, 12
+            0x00158593, // addi a1, a1, 1
+            0x00000013, // nop
+            0x00160613, // addi a2, a2, 1
+            0x00100073, // ebreak
+        ]);
+        emu.steps(1).unwrap();
+        assert_eq!(emu.get_pc(), base);
+        assert_eq!(emu.get_npc(), base + 12);
+
+        // 嵌入方改写 npc 后，下一步从新地址执行
+        emu.set_npc(base + 4);
+        emu.steps(1).unwrap();
+        assert_eq!(emu.get_pc(), base + 4);
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+        assert_eq!(emu.get_npc(), base + 8);
+    }
+
+    #[test]
+    fn test_step_to_next_branch_stops_after_taken_branch() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00158593, // addi a1, a1, 1
+            0x00058663, // beqz a1, 12 (不跳转)
+            0x00158593, // addi a1, a1, 1
+            0x00059463, // bnez a1, 8 (跳转)
+            0x00160613, // addi a2, a2, 1
+            0x00100073, // ebreak
+        ]);
+        // 达到上限时停在顺序执行的位置
+        assert_eq!(emu.step_to_next_branch(1).unwrap(), StepOutcome::Continued);
+        assert_eq!(emu.get_pc(), base);
+
+        assert_eq!(emu.step_to_next_branch(100).unwrap(), StepOutcome::Branch);
+        assert_eq!(emu.get_pc(), base + 12);
+        assert_eq!(emu.get_npc(), base + 20);
+        assert_eq!(emu.get_reg(11).unwrap(), 2);
+        assert_eq!(emu.get_reg(12).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_run_until_stops_on_pc() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00300513, // li a0, 3
+            0x00158593, // loop: addi a1, a1, 1
+            0xfff50513, // addi a0, a0, -1
+            0xfe051ce3, // bnez a0, loop
+            0x00760613, // addi a2, a2, 7
+            0x00100073, // ebreak
+        ]);
+        let reason = emu.run_until(100, |state| state.get_npc() == base + 16).unwrap();
+        assert_eq!(reason, StopReason::Predicate);
+        assert_eq!(emu.get_pc(), base + 12);
+        assert_eq!(emu.get_reg(11).unwrap(), 3);
+        assert_eq!(emu.get_reg(12).unwrap(), 0);
+
+        // 条件始终不成立时运行到程序结束
+        assert_eq!(emu.run_until(100, |_| false).unwrap(), StopReason::Halted);
+        assert_eq!(emu.get_reg(12).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_run_until_stops_on_max_steps() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00158593, // loop: addi a1, a1, 1
+            0xffdff06f, // j loop
+        ]);
+        let reason = emu.run_until(10, |state| state.get_reg(11).unwrap() == 100).unwrap();
+        assert_eq!(reason, StopReason::MaxSteps);
+        assert_eq!(emu.get_reg(11).unwrap(), 5);
+        assert_eq!(emu.get_pc(), base + 4);
+        assert_eq!(emu.get_exec_state(), ExecState::Idle);
+    }
+
+    #[test]
+    fn test_fetch_compressed_instruction_at_memory_end() {
+        let (config, device_file) = test_config();
+        let memory_end =
+            crate::test_utils::TEST_BASE + (device_file.memory.memory_size * 1024 * 1024) as u64;
+        let mut emu = emu_from(config, &device_file);
+
+        // c.nop 位于主内存最后2字节
+        emu.write_memory(memory_end - 2, &[0x01, 0x00]).unwrap();
+        assert_eq!(emu.state.fetch_instruction(memory_end - 2).unwrap(), 0x0001);
+
+        // 32位指令的高半字越界时仍然报错
+        emu.write_memory(memory_end - 2, &[0x13, 0x00]).unwrap();
+        let err = emu.state.fetch_instruction(memory_end - 2).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<state::StateError>(),
+            Some(state::StateError::InvalidInstructionBytes(pc)) if *pc == memory_end - 2
+        ));
+    }
+
+    #[test]
+    fn test_load_store_out_of_bounds_raise_access_faults() {
+        use crate::emulator::instructions::insts::{CAUSE_LOAD_ACCESS, CAUSE_STORE_ACCESS, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut program = vec![
+            0x00003503, // ld a0, 0(zero)     地址0未映射
+        ];
+        program.resize(8, 0x00000013);
+        program.push(0x00a03423); // sd a0, 8(zero)     处理程序
+        let mut emu = emu_with_program(&program);
+        emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+
+        emu.step().unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_LOAD_ACCESS as u64);
+        assert_eq!(view.mepc, base);
+        assert_eq!(view.mtval, 0);
+        assert_eq!(emu.state.get_npc(), base + 0x20);
+
+        emu.step().unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_STORE_ACCESS as u64);
+        assert_eq!(view.mepc, base + 0x20);
+        assert_eq!(view.mtval, 8);
+
+        // 未设置 mtvec 时越界访问仍作为错误返回
+        let mut emu = emu_with_program(&program);
+        assert!(emu.step().is_err());
+    }
+
+    #[test]
+    fn test_zero_and_high_address_accesses_fault_cleanly() {
+        use crate::emulator::instructions::insts::{CAUSE_LOAD_ACCESS, CAUSE_STORE_ACCESS, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let cases = [
+            (0x00003503, 0, CAUSE_LOAD_ACCESS),                      // ld a0, 0(zero)
+            (0xff803503, 0xffff_ffff_ffff_fff8, CAUSE_LOAD_ACCESS),  // ld a0, -8(zero)
+            (0xfff00503, u64::MAX, CAUSE_LOAD_ACCESS),               // lb a0, -1(zero)
+            (0xfea03c23, 0xffff_ffff_ffff_fff8, CAUSE_STORE_ACCESS), // sd a0, -8(zero)
+        ];
+        for (inst, addr, cause) in cases {
+            // 裸机模式（未设置 mtvec）：以携带故障地址的内存错误结束本步
+            let mut emu = emu_with_program(&[inst]);
+            let err = emu.step().unwrap_err();
+            let fault = err.chain().find_map(|e| e.downcast_ref::<MemoryError>());
+            assert!(
+                matches!(fault, Some(MemoryError::OutOfBounds { addr: a, .. }) if *a == addr),
+                "{inst:#010x}: {err:?}"
+            );
+
+            // 操作系统模式：作为访问错误投递，mtval 为故障地址
+            let mut emu = emu_with_program(&[inst]);
+            emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+            emu.step().unwrap();
+            let view = emu.trap_state();
+            assert_eq!(view.mcause, cause as u64, "{inst:#010x}");
+            assert_eq!(view.mepc, base);
+            assert_eq!(view.mtval, addr);
+            assert_eq!(emu.state.get_npc(), base + 0x20);
+        }
+    }
+
+    #[test]
+    fn test_unmapped_mtvec_reports_trap_loop() {
+        use crate::emulator::instructions::insts::{CAUSE_FETCH_ACCESS, CSR_MTVEC};
+
+        let mut emu = emu_with_program(&[
+            0x00003503, // ld a0, 0(zero)     地址0未映射
+        ]);
+        emu.state.set_csr(CSR_MTVEC, 0x1000).unwrap();
+
+        let err = emu.steps(1000).unwrap_err();
+        match err.downcast_ref::<TrapError>() {
+            Some(TrapError::TrapLoop { target, cause, count }) => {
+                assert_eq!(*target, 0x1000);
+                assert_eq!(*cause, CAUSE_FETCH_ACCESS as u64);
+                assert_eq!(*count, TRAP_LOOP_LIMIT);
+            }
+            None => panic!("应报告陷入循环: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_jump_into_data_faults_at_target() {
+        use crate::emulator::instructions::insts::{CAUSE_FETCH_ACCESS, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut program = vec![
+            0x00000297, // auipc t0, 0
+            0x04028293, // addi t0, t0, 0x40
+            0x00028067, // jr t0              跳转到数据区
+        ];
+        program.resize(8, 0x00000013);
+        program.extend([
+            0x00000513, // li a0, 0           处理程序
+            0x00100073, // ebreak
+        ]);
+        program.resize(16, 0);
+        program.push(0x00100513); // 数据区：内容恰好是合法指令 li a0, 1
+        let mut emu = emu_with_program(&program);
+        emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+        emu.add_exec_region(base..base + 0x40);
+        emu.enable_exec_check();
+
+        emu.steps(3).unwrap();
+        assert_eq!(emu.state.get_npc(), base + 0x40);
+        emu.steps(1).unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_FETCH_ACCESS as u64);
+        assert_eq!(view.mepc, base + 0x40);
+        assert_eq!(view.mtval, base + 0x40);
+        assert_eq!(emu.get_reg(10).unwrap(), 0);
+
+        emu.steps(10).unwrap();
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_fetch_from_mmio_raises_access_fault() {
+        use crate::emulator::instructions::insts::{CAUSE_FETCH_ACCESS, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("timer0", "timer", 0x1000_0100, 0x100));
+        let mut emu = emu_from(config, &device_file);
+        let mut program = vec![
+            0x100002b7, // lui t0, 0x10000
+            0x10028293, // addi t0, t0, 0x100
+            0x00028067, // jr t0              跳转到定时器的 MMIO 基址
+        ];
+        program.resize(8, 0x00000013);
+        program.extend([
+            0x00000513, // li a0, 0           处理程序
+            0x00100073, // ebreak
+        ]);
+        load_program(&mut emu, &program);
+        emu.state.set_csr(CSR_MTVEC, base + 0x20).unwrap();
+
+        emu.steps(4).unwrap();
+        let view = emu.trap_state();
+        assert_eq!(view.mcause, CAUSE_FETCH_ACCESS as u64);
+        assert_eq!(view.mepc, 0x1000_0100);
+        assert_eq!(view.mtval, 0x1000_0100);
+        assert_eq!(emu.state.get_npc(), base + 0x20);
+
+        emu.steps(10).unwrap();
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_step_outcome_reaches_halted() {
+        let mut emu = emu_with_program(&[
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Continued);
+        assert_eq!(emu.step_outcome().unwrap(), StepOutcome::Halted);
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_c_ext_is_rejected_cleanly() {
+        let (mut config, device_file) = test_config();
+        config.inst_set.c_ext = true;
+        config.inst_set.xlen = const_values::Xlen::X32;
+        let err = Emulator::from_config(config, &device_file).err().unwrap();
+        assert!(err.to_string().contains("xlen = 32 时暂不支持 C 扩展"), "{err}");
+    }
+
+    #[test]
+    fn test_current_instruction_raw_reports_compressed_width() {
+        let base = crate::test_utils::TEST_BASE;
+        let (mut config, device_file) = test_config();
+        config.inst_set.c_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        let program: [&[u8]; 3] = [
+            &0x00150513u32.to_le_bytes(), // +0   addi a0, a0, 1
+            &0x4585u16.to_le_bytes(),     // +4   c.li a1, 1
+            &0x0589u16.to_le_bytes(),     // +6   c.addi a1, 2
+        ];
+        emu.write_memory(base, &program.concat()).unwrap();
+
+        assert_eq!(emu.current_instruction_raw().unwrap(), (0x00150513, false));
+        assert_eq!(emu.current_instruction_name().unwrap(), "addi");
+
+        emu.set_npc(base + 4);
+        emu.sync_pc();
+        // 取指读入4字节，高16位属于下一条指令，必须被清除
+        assert_eq!(emu.current_instruction_raw().unwrap(), (0x4585, true));
+        assert_eq!(emu.current_instruction_name().unwrap(), "c.li");
+        assert_eq!(emu.get_reg(11).unwrap(), 0);
+        // 查询不经过译码缓存
+        assert_eq!(emu.decode_cache_stats(), (0, 0));
+    }
+
+    #[test]
+    fn test_load_flat_binary_runs_from_memory_base() {
+        let (config, device_file) = test_config();
+        let mut emu = emu_from(config, &device_file);
+        // 先将 PC 移走，确认由加载函数重新设置
+        emu.set_npc(crate::test_utils::TEST_BASE + 0x100);
+        emu.sync_pc();
+
+        let program: [u32; 3] = [
+            0x00700593, // li a1, 7
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ];
+        let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+        let path = std::env::temp_dir().join(format!("dolphin-flat-{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        emu.load_flat_binary(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(emu.get_pc(), crate::test_utils::TEST_BASE);
+
+        emu.step().unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 7);
+        emu.steps(10).unwrap();
+        assert_eq!(emu.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_load_binary_at_address() {
+        let addr = crate::test_utils::TEST_BASE + 0x1000;
+        let (config, device_file) = test_config();
+        let mut emu = emu_from(config, &device_file);
+
+        let program: [u32; 3] = [
+            0x00900593, // li a1, 9
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ];
+        let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+        let path = std::env::temp_dir().join(format!("dolphin-bin-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        emu.load_binary(path.to_str().unwrap(), addr).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(emu.get_pc(), addr);
+        assert_eq!(emu.state.read_memory(addr, bytes.len()).unwrap(), bytes);
+
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 9);
+        assert_eq!(emu.exit_code(), Some(0));
+
+        // 写入范围超出主内存时报错
+        let path = std::env::temp_dir().join(format!("dolphin-bin-oob-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let res = emu.load_binary(path.to_str().unwrap(), 0x10);
+        std::fs::remove_file(&path).ok();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_load_binary_rejects_mmio_entry() {
+        let (config, mut device_file) = test_config();
+        device_file.devices.push(test_device("host0", "hostcall", 0x1000_0400, 0x20));
+        let mut emu = emu_from(config, &device_file);
+        let path = std::env::temp_dir().join(format!("dolphin-bin-mmio-{}.bin", std::process::id()));
+        std::fs::write(&path, 0x00000013u32.to_le_bytes()).unwrap();
+
+        let err = emu.load_binary(path.to_str().unwrap(), 0x1000_0400).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains("二进制镜像入口"), "{err}");
+    }
+
+    #[test]
+    fn test_reset_reruns_program() {
+        let mut emu = emu_with_program(&[
+            0x00500513, // li a0, 5
+            0x00a585b3, // loop: add a1, a1, a0
+            0xfff50513, // addi a0, a0, -1
+            0xfe051ce3, // bnez a0, loop
+            0x00000297, // auipc t0, 0
+            0x04b2b023, // sd a1, 64(t0)
+            0x0402b603, // ld a2, 64(t0)
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        let first = emu.arch_state();
+        assert_eq!(first.registers[12], 15);
+        assert_eq!(emu.instret(), 21);
+
+        emu.freeze_time(true);
+        emu.reset();
+        assert_eq!(emu.get_exec_state(), ExecState::Idle);
+        assert_eq!(emu.get_pc(), crate::test_utils::TEST_BASE);
+        assert!(!emu.is_time_frozen());
+        assert_eq!(emu.exit_code(), None);
+        assert_eq!(emu.instret(), 0);
+        assert_eq!(emu.get_reg(11).unwrap(), 0);
+
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        assert_eq!(emu.exit_code(), Some(0));
+        assert_eq!(emu.instret(), 21);
+        assert_eq!(emu.arch_state(), first);
+    }
+
+    #[test]
+    fn test_state_display_steps_over_compressed_instructions() {
+        let base = crate::test_utils::TEST_BASE;
+        let (mut config, device_file) = test_config();
+        config.inst_set.c_ext = true;
+        let mut emu = emu_from(config, &device_file);
+        let program: [&[u8]; 10] = [
+            &0x00150513u32.to_le_bytes(), // +0   addi a0, a0, 1
+            &0x4585u16.to_le_bytes(),     // +4   c.li a1, 1
+            &0x0589u16.to_le_bytes(),     // +6   c.addi a1, 2
+            &0x00150513u32.to_le_bytes(), // +8   addi a0, a0, 1
+            &0x862eu16.to_le_bytes(),     // +12  c.mv a2, a1
+            &0x862eu16.to_le_bytes(),     // +14  c.mv a2, a1   <- PC
+            &0x00150513u32.to_le_bytes(), // +16  addi a0, a0, 1
+            &0x4585u16.to_le_bytes(),     // +20  c.li a1, 1
+            &0x0589u16.to_le_bytes(),     // +22  c.addi a1, 2
+            &0x00150513u32.to_le_bytes(), // +24  addi a0, a0, 1
+        ];
+        emu.write_memory(base, &program.concat()).unwrap();
+        emu.set_npc(base + 14);
+        emu.sync_pc();
+
+        let text = emu.get_state_ref().to_string();
+        let lines: Vec<&str> = text
+            .lines()
+            .skip_while(|line| *line != "Memory around PC:")
+            .skip(1)
+            .take(9)
+            .collect();
+        let expected = [
+            (4, "4585    "),
+            (6, "0589    "),
+            (8, "00150513"),
+            (12, "862e    "),
+            (14, "862e    "),
+            (16, "00150513"),
+            (20, "4585    "),
+            (22, "0589    "),
+            (24, "00150513"),
+        ];
+        assert_eq!(lines.len(), expected.len());
+        for (line, (offset, raw)) in lines.iter().zip(expected) {
+            let prefix = format!("  0x{:016x}: {}", base + offset, raw);
+            assert!(line.starts_with(&prefix), "{:?} 应以 {:?} 开头", line, prefix);
+            assert_eq!(line.ends_with("<-- PC"), offset == 14);
+        }
+    }
+
+    #[test]
+    fn test_hexdump_reads_guest_memory() {
+        let mut emu = emu_with_program(&[]);
+        let base = crate::test_utils::TEST_BASE;
+        emu.write_memory(base, b"RISC-V\x01\x7f").unwrap();
+        assert_eq!(
+            emu.hexdump(base, 8).unwrap(),
+            "0000000080000000  52 49 53 43 2d 56 01 7f                           |RISC-V..|\n"
+        );
+        assert!(emu.hexdump(0x1000, 16).is_err());
+    }
+
+    #[test]
+    fn test_tag_log_records_access_pc() {
+        let (mut config, device_file) = test_config();
+        config.debug.tag_log_size = 8;
+        let mut emu = emu_from(config, &device_file);
+        let base = crate::test_utils::TEST_BASE;
+        emu.tag_memory("heap", base + 0x2000, 0x10);
+        // 指令之外的访问不计入日志
+        emu.write_memory(base + 0x2000, &42u64.to_le_bytes()).unwrap();
+        load_program(
+            &mut emu,
+            &[
+                0x00002297, // auipc t0, 0x2
+                0x0002b303, // ld t1, 0(t0)
+                0x0062b423, // sd t1, 8(t0)
+                0x0102b383, // ld t2, 16(t0)
+            ],
+        );
+        emu.steps(4).unwrap();
+
+        let log = emu.tag_log();
+        assert_eq!(
+            log,
+            vec![
+                TagRecord {
+                    pc: base + 4,
+                    tag: "heap".to_string(),
+                    addr: base + 0x2000,
+                    size: 8,
+                    is_write: false,
+                },
+                TagRecord {
+                    pc: base + 8,
+                    tag: "heap".to_string(),
+                    addr: base + 0x2008,
+                    size: 8,
+                    is_write: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        use crate::emulator::instructions::insts::CSR_MSCRATCH;
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x12300313, // li t1, 0x123
+            0x10629023, // sh t1, 256(t0)
+            0x10628223, // sb t1, 260(t0)
+        ]);
+        let before = emu.snapshot();
+        emu.steps(4).unwrap();
+        emu.state.set_csr(CSR_MSCRATCH, 7).unwrap();
+        emu.state.fregs[3] = 1.5f64.to_bits();
+        let after = emu.snapshot();
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.registers, vec![(5, 0, base), (6, 0, 0x123)]);
+        assert_eq!(diff.fregs, vec![(3, 0, 1.5f64.to_bits())]);
+        assert_eq!(diff.pc, Some((base, base + 12)));
+        assert_eq!(diff.csrs, vec![(CSR_MSCRATCH, 0, 7)]);
+        assert_eq!(
+            diff.memory,
+            vec![
+                MemoryRange { addr: base + 256, len: 2 },
+                MemoryRange { addr: base + 260, len: 1 },
+            ]
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_load_fuzz_state() {
+        let base = crate::test_utils::TEST_BASE;
+        let mut data = Vec::new();
+        for reg in 0..32u64 {
+            data.extend_from_slice(&(reg * 0x100).to_le_bytes());
+        }
+        data.extend_from_slice(&base.to_le_bytes());
+        for inst in [
+            0x00158593u32, // addi a1, a1, 1
+            0xffdff06f,    // j -4
+        ] {
+            data.extend_from_slice(&inst.to_le_bytes());
+        }
+
+        let mut emu = emu_with_program(&[]);
+        emu.load_fuzz_state(&data).unwrap();
+        assert_eq!(emu.get_reg(0).unwrap(), 0);
+        assert_eq!(emu.get_reg(11).unwrap(), 0xb00);
+        assert_eq!(emu.get_reg(31).unwrap(), 0x1f00);
+        assert_eq!(emu.get_pc(), base);
+
+        emu.steps(10).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 0xb00 + 5);
+
+        // 长度校验
+        assert!(emu.load_fuzz_state(&data[..FUZZ_STATE_HEADER_SIZE - 1]).is_err());
+        let mut huge = data[..FUZZ_STATE_HEADER_SIZE].to_vec();
+        huge.resize(FUZZ_STATE_HEADER_SIZE + 2 * 1024 * 1024, 0);
+        assert!(emu.load_fuzz_state(&huge).is_err());
+    }
+
+    #[test]
+    fn test_trap_log_records_refaulting_handler() {
+        use crate::emulator::instructions::insts::{CAUSE_MISALIGNED_FETCH, CSR_MTVEC};
+
+        let base = crate::test_utils::TEST_BASE;
+        let mut emu = emu_with_program(&[
+            0x0020006f, // j +2               跳转目标未对齐
+            0x0020006f, // j +2               <- mtvec：处理程序自身再次触发异常
+        ]);
+        emu.state.set_csr(CSR_MTVEC, base + 4).unwrap();
+        emu.steps(5).unwrap();
+
+        let log = emu.trap_log();
+        assert_eq!(log.len(), 5);
+        assert_eq!(
+            log[0],
+            TrapRecord { pc: base, cause: CAUSE_MISALIGNED_FETCH as u64, tval: base + 2, target: base + 4 }
+        );
+        for record in &log[1..] {
+            assert_eq!(
+                *record,
+                TrapRecord { pc: base + 4, cause: CAUSE_MISALIGNED_FETCH as u64, tval: base + 6, target: base + 4 }
+            );
+        }
+    }
+
+    #[test]
+    fn test_stub_instructions() {
+        let program = [
+            0x0000100f, // fence.i
+            0x0ff0000f, // fence
+            0x00158593, // addi a1, a1, 1
+            0x10500073, // wfi
+        ];
+        let (mut config, device_file) = test_config();
+        config.inst_set.stub_instructions = vec!["fence.i".to_string()];
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &program);
+
+        // fence 已实现，无需列出
+        emu.steps(3).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), 1);
+        // 未启用特权指令时 wfi 不能作为桩指令执行
+        assert!(emu.step().is_err());
+
+        // 未被允许时 fence.i 无法执行
+        let (mut config, device_file) = test_config();
+        config.inst_set.stub_instructions.clear();
+        let mut emu = emu_from(config, &device_file);
+        load_program(&mut emu, &program);
+        assert!(emu.step().is_err());
+    }
+
+    #[test]
+    fn test_fetch_cache_self_modifying_code() {
+        let mut emu = emu_with_fetch_cache(&[
+            0x00000317, // auipc t1, 0
+            0x02832383, // lw t2, 40(t1)      t2 = 替换用的指令
+            0x00200293, // li t0, 2
+            0x00158593, // addi a1, a1, 1     <- 被改写为 addi a1, a1, 10
+            0x00732623, // sw t2, 12(t1)
+            0xfff28293, // addi t0, t0, -1
+            0xfe029ae3, // bnez t0, -12
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+            0x00000013, // nop
+            0x00a58593, // addi a1, a1, 10
+        ]);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_exec_state(), ExecState::End);
+        // 第一次执行原指令 (+1)，第二次执行改写后的指令 (+10)
+        assert_eq!(emu.get_reg(11).unwrap(), 11);
+    }
+}
@@ -0,0 +1,330 @@
+//! PC 轨迹记录与比对
+//! 记录一次运行执行过的 PC 序列，并在之后的运行中逐条比对，报告第一个分叉点。
+//! 相比与 rv64emu 的 difftest，这种方式只需要一次已知正确的运行结果。
+//! 记录时可为每条指令附带其写入的寄存器（写集）的哈希，
+//! PC 相同但计算结果不同的改动也能在第一条出错的指令处发现
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Seek, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+
+/// 轨迹中的一条记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u64,
+    /// 写集的哈希，轨迹不带写集列时为 None
+    pub hash: Option<u64>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hash {
+            Some(hash) => write!(f, "{:#x} {:016x}", self.pc, hash),
+            None => write!(f, "{:#x}", self.pc),
+        }
+    }
+}
+
+/// 计算写集的哈希（FNV-1a），写集为按编号排列的 (编号, 新值)，浮点寄存器编号为 32..64
+///
+/// 不依赖标准库的随机化哈希，同一写集在不同运行与不同构建之间哈希一致
+pub fn writeset_hash(before: &[u64; 32], after: &[u64; 32], fbefore: &[u64; 32], fafter: &[u64; 32]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET;
+    let mut mix = |value: u64| {
+        for byte in value.to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    };
+    let changes = before.iter().chain(fbefore).zip(after.iter().chain(fafter)).enumerate();
+    for (reg, (old, new)) in changes {
+        if old != new {
+            mix(reg as u64);
+            mix(*new);
+        }
+    }
+    hash
+}
+
+/// 从文件读取轨迹：每行一个十六进制 PC，可带以空白分隔的十六进制写集哈希
+pub fn read_trace(path: impl AsRef<Path>) -> Result<Vec<TraceEntry>> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("无法打开轨迹文件 '{}'", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .map(|(line_no, line)| {
+            let line = line?;
+            let invalid = || anyhow!("轨迹文件第 {} 行无效: '{}'", line_no + 1, line);
+            let parse = |field: &str| u64::from_str_radix(field.trim_start_matches("0x"), 16).map_err(|_| invalid());
+            let mut fields = line.split_whitespace();
+            let pc = parse(fields.next().ok_or_else(&invalid)?)?;
+            let hash = fields.next().map(parse).transpose()?;
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+            Ok(TraceEntry { pc, hash })
+        })
+        .collect()
+}
+
+/// 运行时的 PC 轨迹处理
+pub struct PcTrace {
+    mode: TraceMode,
+    /// 是否带写集列：带写集列时在指令执行后处理，否则在执行前处理
+    writeset: bool,
+    /// 上一条指令执行后的通用与浮点寄存器，用于求写集
+    regs: [u64; 32],
+    fregs: [u64; 32],
+}
+
+enum TraceMode {
+    /// 将执行的 PC 写入文件
+    Record(BufWriter<File>),
+    /// 与已记录的轨迹逐条比对，index 为下一条待比对记录的下标（从0开始，报告时从1开始计数）
+    Compare { expected: Vec<TraceEntry>, index: usize },
+}
+
+impl PcTrace {
+    /// 记录轨迹，writeset 为 true 时每条指令附带写集哈希
+    pub fn record(path: impl AsRef<Path>, writeset: bool) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("无法创建轨迹文件 '{}'", path.display()))?;
+        Ok(Self::new(TraceMode::Record(BufWriter::new(file)), writeset))
+    }
+
+    /// 与记录的轨迹比对，是否比较写集由轨迹文件是否带写集列决定
+    pub fn compare(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let expected = read_trace(path)?;
+        let writeset = expected.first().is_some_and(|entry| entry.hash.is_some());
+        if expected.iter().any(|entry| entry.hash.is_some() != writeset) {
+            return Err(anyhow!("轨迹文件 '{}' 中部分行缺少写集哈希", path.display()));
+        }
+        Ok(Self::new(TraceMode::Compare { expected, index: 0 }, writeset))
+    }
+
+    fn new(mode: TraceMode, writeset: bool) -> Self {
+        Self {
+            mode,
+            writeset,
+            regs: [0; 32],
+            fregs: [0; 32],
+        }
+    }
+
+    /// 开始一次运行，以 regs/fregs 作为第一条指令执行前的寄存器状态；
+    /// 比对模式下从参考轨迹开头重新比对，记录模式下丢弃已记录的内容
+    pub(super) fn start(&mut self, regs: &[u64; 32], fregs: &[u64; 32]) {
+        self.regs = *regs;
+        self.fregs = *fregs;
+        match &mut self.mode {
+            TraceMode::Record(writer) => {
+                let truncate = writer.flush().and_then(|_| {
+                    let file = writer.get_mut();
+                    file.set_len(0)?;
+                    file.rewind().map(|_| ())
+                });
+                if let Err(e) = truncate {
+                    tracing::warn!("无法清空轨迹文件: {}", e);
+                }
+            }
+            TraceMode::Compare { index, .. } => *index = 0,
+        }
+    }
+
+    /// 运行结束时调用：记录模式下刷新文件，比对模式下检查参考轨迹是否已全部匹配
+    pub fn finish(&mut self) -> Result<()> {
+        match &mut self.mode {
+            TraceMode::Record(writer) => writer.flush().context("写入轨迹文件失败"),
+            TraceMode::Compare { expected, index } if *index < expected.len() => Err(anyhow!(
+                "PC 轨迹在第 {} 条指令处分叉: 期望 {}, 实际运行已结束",
+                *index + 1,
+                expected[*index]
+            )),
+            TraceMode::Compare { .. } => Ok(()),
+        }
+    }
+
+    /// 处理即将执行的指令的 PC，比对模式下发现分叉时返回错误；带写集列时不做处理
+    #[inline(always)]
+    pub fn on_pc(&mut self, pc: u64) -> Result<()> {
+        if self.writeset {
+            return Ok(());
+        }
+        self.on_entry(TraceEntry { pc, hash: None })
+    }
+
+    /// 处理一条已执行的指令，regs/fregs 为其执行后的寄存器；只在带写集列时处理
+    #[inline(always)]
+    pub fn on_inst(&mut self, pc: u64, regs: &[u64; 32], fregs: &[u64; 32]) -> Result<()> {
+        if !self.writeset {
+            return Ok(());
+        }
+        let hash = writeset_hash(&self.regs, regs, &self.fregs, fregs);
+        self.regs = *regs;
+        self.fregs = *fregs;
+        self.on_entry(TraceEntry { pc, hash: Some(hash) })
+    }
+
+    fn on_entry(&mut self, actual: TraceEntry) -> Result<()> {
+        match &mut self.mode {
+            TraceMode::Record(writer) => {
+                writeln!(writer, "{}", actual).context("写入轨迹文件失败")?;
+            }
+            TraceMode::Compare { expected, index } => {
+                match expected.get(*index) {
+                    Some(&want) if want == actual => (),
+                    Some(&want) => {
+                        return Err(anyhow!(
+                            "PC 轨迹在第 {} 条指令处分叉: 期望 {}, 实际 {}",
+                            *index + 1,
+                            want,
+                            actual
+                        ));
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "PC 轨迹在第 {} 条指令处分叉: 参考轨迹已结束, 实际 {}",
+                            *index + 1,
+                            actual
+                        ));
+                    }
+                }
+                *index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::emu_with_program;
+
+    #[test]
+    fn test_record_and_compare() {
+        let path = std::env::temp_dir().join(format!("dolphin-pc-trace-{}.txt", std::process::id()));
+
+        // 参考运行：a1 = 3 时循环3次
+        let mut good = emu_with_program(&[
+            0x00300593, // li a1, 3
+            0xfff58593, // addi a1, a1, -1
+            0xfe059ee3, // bnez a1, -4
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        good.set_pc_trace(PcTrace::record(&path, false).unwrap());
+        good.steps(100).unwrap();
+        good.finish_pc_trace().unwrap();
+        let trace = read_trace(&path).unwrap();
+        assert_eq!(trace.len(), 9);
+
+        // 可疑运行：a1 = 2，少循环一次，在第6条指令处分叉
+        let mut bad = emu_with_program(&[
+            0x00200593, // li a1, 2
+            0xfff58593, // addi a1, a1, -1
+            0xfe059ee3, // bnez a1, -4
+            0x00000513, // li a0, 0
+            0x00100073, // ebreak
+        ]);
+        bad.set_pc_trace(PcTrace::compare(&path).unwrap());
+        let err = bad.steps(100).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains("第 6 条指令"), "{err}");
+    }
+
+    #[test]
+    fn test_compare_restarts_after_reset() {
+        let path = std::env::temp_dir().join(format!("dolphin-pc-trace-reset-{}.txt", std::process::id()));
+
+        let mut emu = emu_with_program(&PROGRAM);
+        emu.set_pc_trace(PcTrace::record(&path, false).unwrap());
+        emu.steps(100).unwrap();
+        // 复位后重新记录，轨迹文件只保留最后一次运行
+        emu.reset();
+        emu.steps(100).unwrap();
+        emu.finish_pc_trace().unwrap();
+        assert_eq!(read_trace(&path).unwrap().len(), 9);
+
+        // 复位后从参考轨迹开头重新比对
+        emu.reset();
+        emu.set_pc_trace(PcTrace::compare(&path).unwrap());
+        emu.steps(100).unwrap();
+        emu.reset();
+        let result = emu.steps(100).and_then(|_| emu.finish_pc_trace());
+        std::fs::remove_file(&path).ok();
+        result.unwrap();
+    }
+
+    const PROGRAM: [u32; 5] = [
+        0x00300593, // li a1, 3
+        0xfff58593, // addi a1, a1, -1
+        0xfe059ee3, // bnez a1, -4
+        0x00000513, // li a0, 0
+        0x00100073, // ebreak
+    ];
+
+    #[test]
+    fn test_writeset_hash_ignores_unchanged_registers() {
+        let zero = [0; 32];
+        let before = [7; 32];
+        let mut after = before;
+        assert_eq!(
+            writeset_hash(&before, &after, &zero, &zero),
+            writeset_hash(&zero, &zero, &zero, &zero)
+        );
+        after[10] = 1;
+        let mut other = [0; 32];
+        other[10] = 1;
+        assert_eq!(
+            writeset_hash(&before, &after, &zero, &zero),
+            writeset_hash(&zero, &other, &zero, &zero)
+        );
+        other[10] = 2;
+        assert_ne!(
+            writeset_hash(&before, &after, &zero, &zero),
+            writeset_hash(&zero, &other, &zero, &zero)
+        );
+        // 浮点寄存器与同编号的通用寄存器不会混淆
+        let mut fother = [0; 32];
+        fother[10] = 1;
+        assert_ne!(
+            writeset_hash(&before, &after, &zero, &zero),
+            writeset_hash(&zero, &zero, &zero, &fother)
+        );
+    }
+
+    #[test]
+    fn test_writeset_record_and_compare() {
+        let path = std::env::temp_dir().join(format!("dolphin-writeset-trace-{}.txt", std::process::id()));
+
+        let mut golden = emu_with_program(&PROGRAM);
+        golden.set_pc_trace(PcTrace::record(&path, true).unwrap());
+        golden.steps(100).unwrap();
+        golden.finish_pc_trace().unwrap();
+        let trace = read_trace(&path).unwrap();
+        assert_eq!(trace.len(), 9);
+        assert!(trace.iter().all(|entry| entry.hash.is_some()));
+
+        // 完全相同的运行
+        let mut same = emu_with_program(&PROGRAM);
+        same.set_pc_trace(PcTrace::compare(&path).unwrap());
+        same.steps(100).unwrap();
+        same.finish_pc_trace().unwrap();
+
+        // 只改动 li a0 的立即数：PC 序列不变，第8条指令的写集不同
+        let mut patched_program = PROGRAM;
+        patched_program[3] = 0x00100513; // li a0, 1
+        let mut patched = emu_with_program(&patched_program);
+        patched.set_pc_trace(PcTrace::compare(&path).unwrap());
+        let err = patched.steps(100).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains("第 8 条指令"), "{err}");
+    }
+}
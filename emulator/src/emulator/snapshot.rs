@@ -0,0 +1,269 @@
+//! 快照模块
+//! 保存寄存器、CSR、计数器与主内存内容，支持从快照恢复以及比较两个快照之间的差异
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{Emulator, Event, ExecState, PrivilegeLevel};
+use crate::utils::RegFile;
+
+/// 内存比较时的分块大小，先整块比较以快速跳过未修改的区域
+const DIFF_CHUNK_SIZE: usize = 4096;
+
+/// 模拟器架构状态与主内存的快照（不包含 MMIO 设备状态），可序列化后写入磁盘
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub registers: [u64; 32],
+    pub fregs: [u64; 32],
+    pub pc: u64,
+    pub npc: u64,
+    pub privilege: PrivilegeLevel,
+    pub csrs: BTreeMap<u16, u64>,
+    /// 周期计数（mcycle），旧快照中缺省为0
+    #[serde(default)]
+    pub cycles: u64,
+    /// 已退休的指令数（minstret），旧快照中缺省为0
+    #[serde(default)]
+    pub instret: u64,
+    /// 主内存基地址
+    pub memory_base: u64,
+    /// 主内存内容
+    pub memory: Vec<u8>,
+}
+
+/// 不含内存的架构状态，便于测试中直接比较两个模拟器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchState {
+    pub registers: [u64; 32],
+    pub fregs: [u64; 32],
+    pub pc: u64,
+    pub npc: u64,
+    pub privilege: PrivilegeLevel,
+    pub csrs: BTreeMap<u16, u64>,
+}
+
+/// 一段连续的被修改内存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRange {
+    pub addr: u64,
+    pub len: usize,
+}
+
+/// 两个快照之间的差异，各项均为 (旧值, 新值)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// (寄存器编号, 旧值, 新值)
+    pub registers: Vec<(usize, u64, u64)>,
+    /// (浮点寄存器编号, 旧值, 新值)
+    pub fregs: Vec<(usize, u64, u64)>,
+    pub pc: Option<(u64, u64)>,
+    /// (CSR 地址, 旧值, 新值)，不存在的 CSR 视为0
+    pub csrs: Vec<(u16, u64, u64)>,
+    /// 合并后的被修改内存区间，按地址升序
+    pub memory: Vec<MemoryRange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+            && self.fregs.is_empty()
+            && self.pc.is_none() && self.csrs.is_empty() && self.memory.is_empty()
+    }
+}
+
+impl Snapshot {
+    /// 比较 self（旧）与 other（新）之间的差异
+    pub fn diff(&self, other: &Snapshot) -> SnapshotDiff {
+        let pc = (self.pc != other.pc).then_some((self.pc, other.pc));
+
+        let mut csrs: Vec<(u16, u64, u64)> = self
+            .csrs
+            .keys()
+            .chain(other.csrs.keys())
+            .map(|&csr| {
+                let old = self.csrs.get(&csr).copied().unwrap_or(0);
+                let new = other.csrs.get(&csr).copied().unwrap_or(0);
+                (csr, old, new)
+            })
+            .filter(|(_, old, new)| old != new)
+            .collect();
+        csrs.sort_unstable();
+        csrs.dedup();
+
+        SnapshotDiff {
+            registers: self.registers.diff(&other.registers),
+            fregs: self.fregs.diff(&other.fregs),
+            pc,
+            csrs,
+            memory: diff_memory(self.memory_base, &self.memory, &other.memory),
+        }
+    }
+}
+
+/// 比较两段内存，返回合并后的差异区间；长度不同时多出的部分视为被修改
+fn diff_memory(base: u64, old: &[u8], new: &[u8]) -> Vec<MemoryRange> {
+    let mut ranges: Vec<MemoryRange> = Vec::new();
+    let mut push = |offset: usize, len: usize| {
+        let addr = base + offset as u64;
+        match ranges.last_mut() {
+            Some(last) if last.addr + last.len as u64 == addr => last.len += len,
+            _ => ranges.push(MemoryRange { addr, len }),
+        }
+    };
+
+    let common = old.len().min(new.len());
+    for start in (0..common).step_by(DIFF_CHUNK_SIZE) {
+        let end = (start + DIFF_CHUNK_SIZE).min(common);
+        if old[start..end] == new[start..end] {
+            continue;
+        }
+        for i in start..end {
+            if old[i] != new[i] {
+                push(i, 1);
+            }
+        }
+    }
+    let longer = old.len().max(new.len());
+    if longer > common {
+        push(common, longer - common);
+    }
+    ranges
+}
+
+impl Emulator {
+    /// 当前的架构状态（寄存器、pc/npc、特权级与 CSR），不复制内存
+    pub fn arch_state(&self) -> ArchState {
+        ArchState {
+            registers: self.state.registers,
+            fregs: self.state.fregs,
+            pc: self.state.pc,
+            npc: self.state.npc,
+            privilege: self.privilege,
+            csrs: self.state.csrs.iter().map(|(&k, &v)| (k, v)).collect(),
+        }
+    }
+
+    /// 保存当前状态的快照
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.state.registers,
+            fregs: self.state.fregs,
+            pc: self.state.pc,
+            npc: self.state.npc,
+            privilege: self.privilege,
+            csrs: self.state.csrs.iter().map(|(&k, &v)| (k, v)).collect(),
+            cycles: self.cycles,
+            instret: self.instret,
+            memory_base: self.state.memory.memory_base(),
+            memory: self.state.memory.ram().to_vec(),
+        }
+    }
+
+    /// 恢复到快照保存时的状态；快照的主内存基址与大小须与当前一致
+    ///
+    /// MMIO 设备状态不受影响；恢复后模拟器处于空闲状态，可以继续执行
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        let base = self.state.memory.memory_base();
+        let size = self.state.memory.ram().len();
+        if snapshot.memory_base != base || snapshot.memory.len() != size {
+            anyhow::bail!(
+                "快照的主内存 [{:#x}, +{:#x}) 与当前主内存 [{:#x}, +{:#x}) 不一致",
+                snapshot.memory_base,
+                snapshot.memory.len(),
+                base,
+                size
+            );
+        }
+        self.state.memory.restore_ram(&snapshot.memory)?;
+        self.state.registers = snapshot.registers;
+        self.state.fregs = snapshot.fregs;
+        self.state.pc = snapshot.pc;
+        self.state.npc = snapshot.npc;
+        self.state.csrs = snapshot.csrs.iter().map(|(&k, &v)| (k, v)).collect();
+        self.privilege = snapshot.privilege;
+        self.cycles = snapshot.cycles;
+        self.instret = snapshot.instret;
+        self.exec_state = ExecState::Idle;
+        self.event = Event::None;
+        self.execption = None;
+        self.exit_code = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::emu_with_program;
+
+    #[test]
+    fn test_arch_state_lockstep() {
+        let program = [
+            0x00500513, // li a0, 5
+            0x00a585b3, // loop: add a1, a1, a0
+            0xfff50513, // addi a0, a0, -1
+            0xfe051ce3, // bnez a0, loop
+            0x00100073, // ebreak
+        ];
+        let mut a = emu_with_program(&program);
+        let mut b = emu_with_program(&program);
+        assert_eq!(a.arch_state(), b.arch_state());
+        while a.get_exec_state() != crate::emulator::ExecState::End {
+            a.step().unwrap();
+            b.step().unwrap();
+            assert_eq!(a.arch_state(), b.arch_state());
+        }
+
+        b.set_reg(7, 1).unwrap();
+        assert_ne!(a.arch_state(), b.arch_state());
+    }
+
+    #[test]
+    fn test_restore_snapshot() {
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x00a585b3, // loop: add a1, a1, a0
+            0x00150513, // addi a0, a0, 1
+            0x08a2b023, // sd a0, 128(t0)
+            0x00000013, // nop
+            0xff1ff06f, // j loop
+        ]);
+        emu.steps(3).unwrap();
+        let saved = emu.snapshot();
+
+        emu.steps(100).unwrap();
+        assert!(!saved.diff(&emu.snapshot()).is_empty());
+
+        emu.restore(&saved).unwrap();
+        assert_eq!(emu.snapshot(), saved);
+        assert_eq!((emu.cycles(), emu.instret()), (3, 3));
+        // 恢复后继续执行与从保存点执行的结果一致
+        emu.steps(100).unwrap();
+        let after = emu.snapshot();
+        emu.restore(&saved).unwrap();
+        emu.steps(100).unwrap();
+        assert_eq!(emu.snapshot(), after);
+    }
+
+    #[test]
+    fn test_diff_memory_coalesces_ranges() {
+        let old = vec![0u8; 3 * DIFF_CHUNK_SIZE];
+        let mut new = old.clone();
+        new[10..14].fill(1);
+        new[20] = 2;
+        // 跨越分块边界的连续修改合并为一个区间
+        new[DIFF_CHUNK_SIZE - 2..DIFF_CHUNK_SIZE + 2].fill(3);
+
+        assert_eq!(
+            diff_memory(0x1000, &old, &new),
+            vec![
+                MemoryRange { addr: 0x1000 + 10, len: 4 },
+                MemoryRange { addr: 0x1000 + 20, len: 1 },
+                MemoryRange { addr: 0x1000 + DIFF_CHUNK_SIZE as u64 - 2, len: 4 },
+            ]
+        );
+        assert!(diff_memory(0, &old, &old).is_empty());
+    }
+}
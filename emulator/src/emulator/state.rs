@@ -1,7 +1,12 @@
 //! CPU状态管理
 
+use super::instructions::insts::{CSR_FCSR, CSR_FFLAGS, CSR_FRM};
+use super::instructions::is_compressed;
 use super::memory::{Memory, MemoryError};
-use crate::{const_values::EmuConfig, utils::disasm::RiscvDisassembler};
+use crate::{
+    const_values::{EmuConfig, Xlen},
+    utils::{disasm::RiscvDisassembler, reg_file::get_register_alias},
+};
 use anyhow::Result;
 use std::{fmt, rc::Rc};
 use thiserror::Error;
@@ -42,16 +47,48 @@ pub enum Event {
     #[default]
     None,
     Halted(u8),
+    /// 客户程序通过 halt 设备请求退出，携带退出码
+    Exited(u8),
     Break,
     WatchWrite(u64),
     WatchRead(u64),
 }
 
+/// 单步执行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// 正常执行，没有产生事件
+    Continued,
+    /// 产生了事件但程序仍在运行（断点、观察点等）
+    Event(Event),
+    /// 在指令边界响应了中断（参数为 mcause），本步执行的是处理程序的第一条指令
+    Interrupt(u64),
+    /// 程序已结束（ebreak 或 halt 设备），退出码见 `Emulator::exit_code`
+    Halted,
+    /// 本步指令使控制流发生非顺序变化（分支跳转、跳转指令、陷入），只由 `Emulator::step_to_next_branch` 返回
+    Branch,
+}
+
+/// `Emulator::run_until` 停止的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// 停止条件成立
+    Predicate,
+    /// 执行满最大步数
+    MaxSteps,
+    /// 产生了事件（断点、观察点等）
+    Event(Event),
+    /// 程序已结束，退出码见 `Emulator::exit_code`
+    Halted,
+}
+
 /// CPU状态
 #[derive(Debug)]
 pub struct State {
     // 通用寄存器
     pub registers: [u64; 32],
+    // 浮点寄存器（单精度值以 NaN-boxing 形式存放）
+    pub fregs: [u64; 32],
     // 程序计数器
     pub pc: u64,
     // npc
@@ -60,6 +97,8 @@ pub struct State {
     pub csrs: rustc_hash::FxHashMap<u16, u64>,
     // 内存
     pub memory: Memory,
+    // 寄存器宽度
+    pub xlen: Xlen,
     // 设置
     pub config: Rc<EmuConfig>,
 }
@@ -76,14 +115,34 @@ impl State {
 
         Ok(Self {
             registers: [0; 32],
+            fregs: [0; 32],
             pc: config.memory.boot_pc,
             npc: config.memory.boot_pc,
-            csrs: rustc_hash::FxHashMap::default(),
+            csrs: Self::initial_csrs(&config),
             memory,
+            xlen: config.inst_set.xlen,
             config
         })
     }
 
+    /// 复位时的 CSR 初始值
+    fn initial_csrs(config: &EmuConfig) -> rustc_hash::FxHashMap<u16, u64> {
+        let mut csrs = rustc_hash::FxHashMap::default();
+        if config.inst_set.f_ext {
+            csrs.insert(CSR_FCSR, 0);
+        }
+        csrs
+    }
+
+    /// 寄存器清零、CSR 恢复初始值，并从 pc 处重新开始执行；内存不变
+    pub fn reset(&mut self, pc: u64) {
+        self.registers = [0; 32];
+        self.fregs = [0; 32];
+        self.csrs = Self::initial_csrs(&self.config);
+        self.pc = pc;
+        self.npc = pc;
+    }
+
     /// 读取内存
     #[inline(always)]
     pub fn read_memory(&self, addr: u64, size: usize) -> Result<Vec<u8>> {
@@ -96,23 +155,12 @@ impl State {
         Ok(self.memory.write(addr, data)?)
     }
 
-    /// 取指令
+    /// 取指令，取不到完整指令时返回 `InvalidInstructionBytes`
     #[inline(always)]
     pub fn fetch_instruction(&self, pc: u64) -> Result<u32> {
-        // 快速路径：如果PC在主内存区域，使用unsafe快速读取
-        if self.memory.is_mem_region_range(pc, 4) {
-            // 安全检查：确保4字节读取不会越界
-            unsafe {
-                return Ok(self.memory.read_u32_fast(pc));
-            }
-        }
-        
-        // 慢速路径：可能涉及MMIO或边界情况
-        let bytes = self.read_memory(pc, 4)?;
-        Ok(bytes
-            .try_into()
-            .map(u32::from_le_bytes)
-            .map_err(|_| StateError::InvalidInstructionBytes(pc))?)
+        self.memory
+            .fetch_inst(pc)
+            .ok_or_else(|| StateError::InvalidInstructionBytes(pc).into())
     }
 
     #[inline(always)]
@@ -141,8 +189,11 @@ impl State {
             return Err(StateError::InvalidRegister(reg).into());
         }
         if reg != 0 {
-            // x0不可写
-            self.registers[reg] = value;
+            // x0不可写；RV32 的寄存器值以符号扩展形式保存
+            self.registers[reg] = match self.xlen {
+                Xlen::X32 => value as i32 as u64,
+                Xlen::X64 => value,
+            };
         }
         Ok(())
     }
@@ -170,58 +221,72 @@ impl State {
     }
 
     /// 获取CSR值
+    ///
+    /// fflags 与 frm 是 fcsr 的字段视图，统一存放在 fcsr 中
     #[inline(always)]
     pub fn get_csr(&self, csr: u16) -> Result<u64> {
+        let (key, shift, mask) = match csr {
+            CSR_FFLAGS => (CSR_FCSR, 0, 0x1F),
+            CSR_FRM => (CSR_FCSR, 5, 0x7),
+            _ => (csr, 0, u64::MAX),
+        };
         self.csrs
-            .get(&csr)
-            .copied()
+            .get(&key)
+            .map(|value| (value >> shift) & mask)
             .ok_or_else(|| StateError::InvalidCsr(csr).into())
     }
 
     /// 设置CSR值
     #[inline(always)]
     pub fn set_csr(&mut self, csr: u16, value: u64) -> Result<()> {
-        self.csrs.insert(csr, value);
+        let (key, value) = match csr {
+            CSR_FCSR => (CSR_FCSR, value & 0xFF),
+            CSR_FFLAGS | CSR_FRM => {
+                let (shift, mask) = if csr == CSR_FFLAGS { (0, 0x1F) } else { (5, 0x7) };
+                let fcsr = self.csrs.get(&CSR_FCSR).copied().unwrap_or(0);
+                (CSR_FCSR, (fcsr & !(mask << shift)) | ((value & mask) << shift))
+            }
+            _ => (csr, value),
+        };
+        self.csrs.insert(key, value);
         Ok(())
     }
 }
 
-/// RISC-V寄存器别名
-fn get_register_alias(reg: usize) -> &'static str {
-    match reg {
-        0 => "zero",   // Hard-wired zero
-        1 => "ra",     // Return address
-        2 => "sp",     // Stack pointer
-        3 => "gp",     // Global pointer
-        4 => "tp",     // Thread pointer
-        5 => "t0",     // Temporary
-        6 => "t1",     // Temporary
-        7 => "t2",     // Temporary
-        8 => "s0/fp",  // Saved register/frame pointer
-        9 => "s1",     // Saved register
-        10 => "a0",    // Function argument/return value
-        11 => "a1",    // Function argument/return value
-        12 => "a2",    // Function argument
-        13 => "a3",    // Function argument
-        14 => "a4",    // Function argument
-        15 => "a5",    // Function argument
-        16 => "a6",    // Function argument
-        17 => "a7",    // Function argument
-        18 => "s2",    // Saved register
-        19 => "s3",    // Saved register
-        20 => "s4",    // Saved register
-        21 => "s5",    // Saved register
-        22 => "s6",    // Saved register
-        23 => "s7",    // Saved register
-        24 => "s8",    // Saved register
-        25 => "s9",    // Saved register
-        26 => "s10",   // Saved register
-        27 => "s11",   // Saved register
-        28 => "t3",    // Temporary
-        29 => "t4",    // Temporary
-        30 => "t5",    // Temporary
-        31 => "t6",    // Temporary
-        _ => "unknown",
+impl State {
+    /// 读取 addr 处的指令：压缩指令只取低16位，读取失败时返回 None
+    fn inst_at(&self, addr: u64) -> Option<u32> {
+        let inst = self.memory.fetch_inst(addr)?;
+        Some(if is_compressed(inst) { inst & 0xffff } else { inst })
+    }
+
+    /// addr 处指令的字节数，无法读取时按4字节计
+    fn inst_len_at(&self, addr: u64) -> u64 {
+        match self.inst_at(addr) {
+            Some(inst) if is_compressed(inst) => 2,
+            _ => 4,
+        }
+    }
+
+    /// pc 之前最多 count 条指令的地址
+    ///
+    /// 变长指令无法向前解码，因此从 pc - 4 * count 开始按2字节尝试起点，
+    /// 取第一个能够顺序解码并恰好落在 pc 上的指令序列
+    fn preceding_inst_addrs(&self, pc: u64, count: usize) -> Vec<u64> {
+        let window = 4 * count as u64;
+        for start in (pc.saturating_sub(window)..pc).step_by(2) {
+            let mut addrs = vec![];
+            let mut addr = start;
+            while addr < pc {
+                addrs.push(addr);
+                addr += self.inst_len_at(addr);
+            }
+            if addr == pc {
+                let skip = addrs.len().saturating_sub(count);
+                return addrs.split_off(skip);
+            }
+        }
+        vec![]
     }
 }
 
@@ -250,40 +315,35 @@ impl fmt::Display for State {
             }
         };
 
-        // 显示PC前后各4条指令（共9条）
-        let start_offset = 4 * 4; // 4条指令 * 4字节
-        let instruction_count = 9;
-        let start_addr = self.pc.saturating_sub(start_offset);
-
-        for i in 0..instruction_count {
-            let addr = start_addr + (i * 4) as u64;
-
-            // 检查是否越界
-            match self.read_memory(addr, 4) {
-                Ok(bytes) => {
-                    if bytes.len() == 4 {
-                        let instruction = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-
-                        // 标记当前PC
-                        let marker = if addr == self.pc { " <-- PC" } else { "" };
-
-                        // 反汇编指令
-                        match disasm.disasm_instruction(instruction, addr) {
-                            Ok(disasm_text) => {
-                                writeln!(f, "  0x{:016x}: {:08x}    {}{}",
-                                        addr, instruction, disasm_text, marker)?;
-                            }
-                            Err(_) => {
-                                writeln!(f, "  0x{:016x}: {:08x}    <invalid>{}",
-                                        addr, instruction, marker)?;
-                            }
-                        }
-                    } else {
-                        writeln!(f, "  0x{:016x}: <partial read>", addr)?;
-                    }
+        // 显示PC前后各4条指令（共9条），按 is_compressed 区分 2/4 字节宽度
+        let mut addrs = self.preceding_inst_addrs(self.pc, 4);
+        let mut addr = self.pc;
+        for _ in 0..5 {
+            addrs.push(addr);
+            addr += self.inst_len_at(addr);
+        }
+
+        for addr in addrs {
+            // 标记当前PC
+            let marker = if addr == self.pc { " <-- PC" } else { "" };
+
+            let Some(instruction) = self.inst_at(addr) else {
+                writeln!(f, "  0x{:016x}: <memory error>", addr)?;
+                continue;
+            };
+            let raw = if is_compressed(instruction) {
+                format!("{:04x}    ", instruction)
+            } else {
+                format!("{:08x}", instruction)
+            };
+
+            // 反汇编指令
+            match disasm.disasm_instruction(instruction, addr) {
+                Ok(disasm_text) => {
+                    writeln!(f, "  0x{:016x}: {}    {}{}", addr, raw, disasm_text, marker)?;
                 }
                 Err(_) => {
-                    writeln!(f, "  0x{:016x}: <memory error>", addr)?;
+                    writeln!(f, "  0x{:016x}: {}    <invalid>{}", addr, raw, marker)?;
                 }
             }
         }
@@ -0,0 +1,299 @@
+//! 系统调用模块
+//! 未设置 mtvec 时，ecall 按 Linux RISC-V 约定（a7 为调用号，a0-a5 为参数，返回值写回 a0）
+//! 由模拟器直接处理；嵌入方可以注册处理函数拦截任意调用号。
+//! riscv-tests 模式下 exit 调用按 riscv-tests 的约定解读为测试结果，无论是否设置了 mtvec
+
+use anyhow::Result;
+use std::io::Write;
+
+use super::instructions::insts::CSR_MTVEC;
+use super::{Emulator, Event, Exception, State};
+
+pub const SYS_WRITE: u64 = 64;
+pub const SYS_EXIT: u64 = 93;
+pub const SYS_EXIT_GROUP: u64 = 94;
+pub const SYS_BRK: u64 = 214;
+
+const EBADF: i64 = 9;
+const ENOSYS: i64 = 38;
+
+/// 一次系统调用的上下文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyscallContext {
+    /// 调用号（a7）
+    pub number: u64,
+    /// 参数（a0-a5）
+    pub args: [u64; 6],
+    /// ecall 指令的地址
+    pub pc: u64,
+}
+
+/// riscv-tests 的测试结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiscvTestResult {
+    Pass,
+    /// 失败的测试编号（即 gp 的值）
+    Fail(u64),
+    /// a0 非零但最低位为0，不符合 riscv-tests 的约定，保存 a0 的原始值
+    Malformed(u64),
+}
+
+/// 内置系统调用要求调用者执行的后续动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallAction {
+    /// 将返回值写回 a0 后继续执行
+    Continue,
+    /// 客户程序请求以给定退出码结束
+    Exit(i32),
+}
+
+/// 内置系统调用的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyscallOutcome {
+    /// 返回值（a0）
+    pub ret: u64,
+    pub action: SyscallAction,
+}
+
+/// 自定义系统调用处理函数：返回 Some(ret) 表示已处理并将 ret 写入 a0，返回 None 交给内置实现
+pub type SyscallHandler = Box<dyn FnMut(&SyscallContext, &mut State) -> Option<u64>>;
+
+impl Emulator {
+    /// 注册自定义系统调用处理函数，它先于内置的 exit/write/brk 实现被调用
+    pub fn set_syscall_handler(&mut self, handler: SyscallHandler) {
+        self.syscall_handler = Some(handler);
+    }
+
+    /// 启用 riscv-tests 模式：ecall(a7 = 93) 报告测试结果并结束运行，a0 为0表示通过，
+    /// 否则 a0 = (gp << 1) | 1，gp 为失败的测试编号；a0 为非零偶数时报告为格式错误的结果
+    pub fn enable_riscv_test_mode(&mut self) {
+        self.riscv_test_mode = true;
+    }
+
+    /// riscv-tests 模式下客户程序报告的测试结果，尚未报告时返回 None
+    pub fn riscv_test_result(&self) -> Option<RiscvTestResult> {
+        self.riscv_test_result
+    }
+
+    /// 执行 ecall：设置了 mtvec 时陷入客户程序自己的处理程序，否则作为系统调用处理
+    pub(super) fn exec_ecall(&mut self, pc: u64) -> Result<()> {
+        if self.riscv_test_mode && self.state.get_reg(17)? == SYS_EXIT {
+            let a0 = self.state.get_reg(10)?;
+            let result = match a0 {
+                0 => RiscvTestResult::Pass,
+                a0 if a0 & 1 == 1 => RiscvTestResult::Fail(a0 >> 1),
+                a0 => RiscvTestResult::Malformed(a0),
+            };
+            match result {
+                RiscvTestResult::Pass => tracing::info!("riscv-tests 通过"),
+                RiscvTestResult::Fail(test) => tracing::error!("riscv-tests 失败: 测试编号 {}", test),
+                RiscvTestResult::Malformed(a0) => tracing::error!("riscv-tests 退出值格式错误: a0 = {:#x}", a0),
+            }
+            self.riscv_test_result = Some(result);
+            self.event = Event::Exited((result != RiscvTestResult::Pass) as u8);
+            return Ok(());
+        }
+        if self.csr_or_zero(CSR_MTVEC) != 0 {
+            self.execption = Some(Exception::EnvironmentCall);
+            return Ok(());
+        }
+
+        let mut args = [0; 6];
+        for (i, arg) in args.iter_mut().enumerate() {
+            *arg = self.state.get_reg(10 + i as u64)?;
+        }
+        let ctx = SyscallContext {
+            number: self.state.get_reg(17)?,
+            args,
+            pc,
+        };
+
+        if let Some(handler) = &mut self.syscall_handler
+            && let Some(ret) = handler(&ctx, &mut self.state)
+        {
+            return self.state.set_reg(10, ret);
+        }
+
+        let outcome = self.handle_syscall(&ctx)?;
+        match outcome.action {
+            SyscallAction::Continue => self.state.set_reg(10, outcome.ret),
+            SyscallAction::Exit(code) => {
+                tracing::info!("客户程序调用 exit({})", code);
+                self.event = Event::Exited(code as u8);
+                Ok(())
+            }
+        }
+    }
+
+    /// 执行内置的系统调用实现，只产生调用本身的副作用（如输出、移动 program break），
+    /// 不写回 a0 也不结束运行，由调用者根据返回的动作决定
+    pub fn handle_syscall(&mut self, ctx: &SyscallContext) -> Result<SyscallOutcome> {
+        let ret = match ctx.number {
+            SYS_EXIT | SYS_EXIT_GROUP => {
+                return Ok(SyscallOutcome {
+                    ret: ctx.args[0],
+                    action: SyscallAction::Exit(ctx.args[0] as i32),
+                });
+            }
+            SYS_WRITE => self.sys_write(ctx.args[0], ctx.args[1], ctx.args[2])?,
+            SYS_BRK => self.sys_brk(ctx.args[0]),
+            number => {
+                tracing::warn!("PC {:#x} 处调用了未实现的系统调用 {}", ctx.pc, number);
+                -ENOSYS as u64
+            }
+        };
+        Ok(SyscallOutcome {
+            ret,
+            action: SyscallAction::Continue,
+        })
+    }
+
+    /// write(fd, buf, len)：仅支持标准输出与标准错误
+    fn sys_write(&mut self, fd: u64, buf: u64, len: u64) -> Result<u64> {
+        let data = self.state.read_memory(buf, len as usize)?;
+        let written = match fd {
+            1 => std::io::stdout().write_all(&data),
+            2 => std::io::stderr().write_all(&data),
+            _ => return Ok(-EBADF as u64),
+        };
+        Ok(match written {
+            Ok(()) => len,
+            Err(_) => -EBADF as u64,
+        })
+    }
+
+    /// brk(addr)：addr 位于主内存中时移动 program break，总是返回当前的 program break
+    fn sys_brk(&mut self, addr: u64) -> u64 {
+        if addr != 0 && self.state.memory.is_mem_region(addr) {
+            self.program_break = addr;
+        }
+        self.program_break
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::ExecState;
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_custom_syscall_handler() {
+        let mut emu = emu_with_program(&[
+            0x00700513, // li a0, 7
+            0x00800593, // li a1, 8
+            0x40000893, // li a7, 1024
+            0x00000073, // ecall
+            0x00050613, // mv a2, a0
+            0x05d00893, // li a7, 93
+            0x00000073, // ecall              exit(a0)
+        ]);
+        let seen = Rc::new(RefCell::new(None));
+        let seen_in_handler = seen.clone();
+        emu.set_syscall_handler(Box::new(move |ctx, _state| {
+            if ctx.number != 1024 {
+                return None;
+            }
+            *seen_in_handler.borrow_mut() = Some(*ctx);
+            Some(ctx.args[0] * ctx.args[1])
+        }));
+
+        emu.steps(100).unwrap();
+        let ctx = seen.borrow().expect("处理函数未被调用");
+        assert_eq!(ctx.args[..2], [7, 8]);
+        assert_eq!(ctx.pc, TEST_BASE + 12);
+        assert_eq!(emu.get_reg(12).unwrap(), 56);
+        // exit 未被拦截，由内置实现处理
+        assert_eq!(emu.exit_code(), Some(56));
+    }
+
+    #[test]
+    fn test_builtin_brk_and_unknown_syscall() {
+        let mut emu = emu_with_program(&[
+            0x0d600893, // li a7, 214
+            0x00000513, // li a0, 0
+            0x00000073, // ecall              brk(0)
+            0x00050593, // mv a1, a0
+            0x40000893, // li a7, 1024
+            0x00000073, // ecall              未实现
+            0x00100073, // ebreak
+        ]);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.get_reg(11).unwrap(), TEST_BASE);
+        assert_eq!(emu.exit_code(), Some(-ENOSYS as u8));
+    }
+
+    #[test]
+    fn test_handle_syscall_reports_exit_without_stopping() {
+        let mut emu = emu_with_program(&[]);
+        let ctx = |number, a0| SyscallContext {
+            number,
+            args: [a0, 0, 0, 0, 0, 0],
+            pc: TEST_BASE,
+        };
+
+        let outcome = emu.handle_syscall(&ctx(SYS_EXIT, -3i64 as u64)).unwrap();
+        assert_eq!(outcome.action, SyscallAction::Exit(-3));
+        assert_eq!(emu.exit_code(), None);
+        assert_eq!(emu.get_exec_state(), ExecState::Idle);
+
+        let outcome = emu.handle_syscall(&ctx(SYS_BRK, 0)).unwrap();
+        assert_eq!(
+            outcome,
+            SyscallOutcome {
+                ret: TEST_BASE,
+                action: SyscallAction::Continue
+            }
+        );
+    }
+
+    #[test]
+    fn test_riscv_test_mode_reports_pass_and_fail() {
+        use crate::emulator::instructions::insts::CSR_MTVEC;
+
+        let fail = [
+            0x00500193, // li gp, 5
+            0x05d00893, // li a7, 93
+            0x00119513, // slli a0, gp, 1
+            0x00156513, // ori a0, a0, 1
+            0x00000073, // ecall
+        ];
+        let mut emu = emu_with_program(&fail);
+        // riscv-tests 会设置自己的 mtvec，测试模式下 exit 调用不经过它
+        emu.state.set_csr(CSR_MTVEC, TEST_BASE + 0x100).unwrap();
+        emu.enable_riscv_test_mode();
+        emu.steps(100).unwrap();
+        assert_eq!(emu.riscv_test_result(), Some(RiscvTestResult::Fail(5)));
+        assert_eq!(emu.exit_code(), Some(1));
+
+        let mut emu = emu_with_program(&[
+            0x00100193, // li gp, 1
+            0x05d00893, // li a7, 93
+            0x00000513, // li a0, 0
+            0x00000073, // ecall
+        ]);
+        emu.enable_riscv_test_mode();
+        emu.steps(100).unwrap();
+        assert_eq!(emu.riscv_test_result(), Some(RiscvTestResult::Pass));
+        assert_eq!(emu.exit_code(), Some(0));
+
+        // a0 为非零偶数不是合法的失败编码
+        let mut emu = emu_with_program(&[
+            0x05d00893, // li a7, 93
+            0x00400513, // li a0, 4
+            0x00000073, // ecall
+        ]);
+        emu.enable_riscv_test_mode();
+        emu.steps(100).unwrap();
+        assert_eq!(emu.riscv_test_result(), Some(RiscvTestResult::Malformed(4)));
+        assert_eq!(emu.exit_code(), Some(1));
+
+        // 未启用测试模式时按普通 exit 处理
+        let mut emu = emu_with_program(&fail);
+        emu.steps(100).unwrap();
+        assert_eq!(emu.riscv_test_result(), None);
+        assert_eq!(emu.exit_code(), Some(11));
+    }
+}
@@ -0,0 +1,189 @@
+use super::super::Emulator;
+use crate::emulator::call_graph::{Transfer, classify};
+use crate::emulator::tracer::TracerTrace;
+use crate::utils::ringbuf::RingBuffer;
+
+/// 函数调用或返回
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FTraceKind {
+    /// 调用入口地址为 target 的函数
+    Call {
+        target: u64,
+    },
+    Return,
+}
+
+/// 一次函数调用或返回的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FTraceEntry {
+    /// 跳转指令的地址
+    pub pc: u64,
+    /// 发生时的调用深度，用于缩进
+    pub depth: usize,
+    /// 被调用或返回的函数名
+    pub name: String,
+    pub kind: FTraceKind,
+}
+
+/// 函数追踪器：根据 ELF 符号表记录函数的调用与返回
+///
+/// jal/jalr 的目标恰好是已知函数入口时视为调用，按链接寄存器约定识别返回
+pub struct FTracer {
+    entries: RingBuffer<FTraceEntry>,
+    /// 影子调用栈，目标不是已知函数入口的调用记为 None，其返回不输出
+    stack: Vec<Option<String>>,
+}
+
+impl FTracer {
+    /// 创建函数追踪器，capacity 为保留的最近记录数
+    pub fn new(capacity: usize) -> Self {
+        FTracer {
+            entries: RingBuffer::new(capacity),
+            stack: Vec::new(),
+        }
+    }
+
+    /// 按从旧到新的顺序遍历保留的记录
+    pub fn entries(&self) -> impl Iterator<Item = &FTraceEntry> {
+        self.entries.iter()
+    }
+}
+
+impl TracerTrace for FTracer {
+    fn name(&self) -> &'static str {
+        "FTracer"
+    }
+
+    fn trace(&mut self, emulator: &Emulator) {
+        // 使用本步实际执行的指令，本步陷入（中断或取指异常）而没有执行指令时不记录
+        let Some((inst, _)) = emulator.last_inst else {
+            return;
+        };
+        let pc = emulator.state.get_pc();
+        match classify(inst) {
+            Some(Transfer::Call) => {
+                let target = emulator.state.get_npc();
+                let name = emulator
+                    .symbols()
+                    .function_at(target)
+                    .map(|func| func.name.clone());
+                if let Some(name) = &name {
+                    self.entries.push_overwrite(FTraceEntry {
+                        pc,
+                        depth: self.stack.len(),
+                        name: name.clone(),
+                        kind: FTraceKind::Call { target },
+                    });
+                }
+                self.stack.push(name);
+            }
+            Some(Transfer::Return) => {
+                if let Some(Some(name)) = self.stack.pop() {
+                    self.entries.push_overwrite(FTraceEntry {
+                        pc,
+                        depth: self.stack.len(),
+                        name,
+                        kind: FTraceKind::Return,
+                    });
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// 复位后重新运行时调用栈从空开始
+    fn reset(&mut self) {
+        self.stack.clear();
+    }
+
+    /// 按调用深度缩进打印调用轨迹
+    fn get_instructions_log(&mut self) -> String {
+        let mut log = String::new();
+        for entry in self.entries.iter() {
+            let indent = "  ".repeat(entry.depth);
+            log += &match entry.kind {
+                FTraceKind::Call { target } => {
+                    format!(
+                        "{:#010x}: {}call [{}@{:#010x}]\n",
+                        entry.pc, indent, entry.name, target
+                    )
+                }
+                FTraceKind::Return => {
+                    format!("{:#010x}: {}ret [{}]\n", entry.pc, indent, entry.name)
+                }
+            };
+        }
+        log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::ExecState;
+    use crate::test_utils::{TEST_BASE, build_test_elf_with_symbols, emu_from, emu_with_program, test_config};
+
+    #[test]
+    fn test_nested_call_trace() {
+        let elf = build_test_elf_with_symbols(
+            TEST_BASE,
+            &[
+                0x010000ef, // main: jal ra, f
+                0x00100073, // ebreak
+                0x00000013, // nop
+                0x00000013, // nop
+                0x00008413, // f: mv s0, ra
+                0x00c000ef, // jal ra, g
+                0x00040093, // mv ra, s0
+                0x00008067, // ret
+                0x00150513, // g: addi a0, a0, 1
+                0x00008067, // ret
+            ],
+            &[
+                ("main", TEST_BASE, 16),
+                ("f", TEST_BASE + 16, 16),
+                ("g", TEST_BASE + 32, 8),
+            ],
+        );
+        let path = std::env::temp_dir().join(format!("dolphin-ftrace-{}.elf", std::process::id()));
+        std::fs::write(&path, &elf).unwrap();
+        let (config, device_file) = test_config();
+        let mut emu = emu_from(config, &device_file);
+        let res = emu.load_elf(path.to_str().unwrap());
+        std::fs::remove_file(&path).ok();
+        res.unwrap();
+
+        let mut tracer = FTracer::new(16);
+        while emu.get_exec_state() != ExecState::End {
+            emu.step().unwrap();
+            tracer.trace(&emu);
+        }
+
+        let nesting: Vec<_> = tracer
+            .entries()
+            .map(|entry| (entry.name.as_str(), entry.depth))
+            .collect();
+        assert_eq!(nesting, vec![("f", 0), ("g", 1), ("g", 1), ("f", 0)]);
+        assert_eq!(
+            tracer.get_instructions_log(),
+            "0x80000000: call [f@0x80000010]\n\
+             0x80000014:   call [g@0x80000020]\n\
+             0x80000024:   ret [g]\n\
+             0x8000001c: ret [f]\n"
+        );
+    }
+
+    #[test]
+    fn test_reset_clears_call_stack() {
+        let mut emu = emu_with_program(&[
+            0x008000ef, // jal ra, 8
+        ]);
+        let mut tracer = FTracer::new(16);
+        emu.step().unwrap();
+        tracer.trace(&emu);
+        assert_eq!(tracer.stack.len(), 1);
+
+        tracer.reset();
+        assert!(tracer.stack.is_empty());
+    }
+}
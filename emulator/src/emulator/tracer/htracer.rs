@@ -0,0 +1,84 @@
+use super::super::Emulator;
+use crate::emulator::OpcodeHistogram;
+use crate::emulator::tracer::TracerTrace;
+
+/// 指令频率追踪器：按助记符统计每条指令的执行次数
+#[derive(Debug, Default)]
+pub struct HTracer {
+    histogram: OpcodeHistogram,
+}
+
+impl HTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次执行
+    pub fn record(&mut self, name: &'static str) {
+        self.histogram.record(name);
+    }
+
+    /// 按执行次数降序排列的直方图，次数相同时按助记符排序
+    pub fn histogram(&self) -> Vec<(&'static str, u64)> {
+        self.histogram.sorted()
+    }
+}
+
+impl TracerTrace for HTracer {
+    fn name(&self) -> &'static str {
+        "HTracer"
+    }
+
+    fn trace(&mut self, emulator: &Emulator) {
+        // 使用执行时已译码的指令名，本步没有执行指令时不计数
+        if let Some((_, name)) = emulator.last_inst {
+            self.record(name);
+        }
+    }
+
+    /// 打印指令频率直方图
+    fn get_instructions_log(&mut self) -> String {
+        let total: u64 = self.histogram.counts().values().sum();
+        let mut log = String::new();
+        for (name, count) in self.histogram() {
+            log += &format!(
+                "{:<12} {:>10}  {:>6.2}%\n",
+                name,
+                count,
+                count as f64 * 100.0 / total as f64
+            );
+        }
+        log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::ExecState;
+    use crate::test_utils::emu_with_program;
+
+    #[test]
+    fn test_instruction_histogram() {
+        let mut emu = emu_with_program(&[
+            0x00300513, // li a0, 3
+            0x00158593, // loop: addi a1, a1, 1
+            0x00b60633, // add a2, a2, a1
+            0xfff50513, // addi a0, a0, -1
+            0xfe051ae3, // bnez a0, loop
+            0x00100073, // ebreak
+        ]);
+        let mut tracer = HTracer::new();
+        while emu.get_exec_state() != ExecState::End {
+            emu.step().unwrap();
+            tracer.trace(&emu);
+        }
+
+        assert_eq!(
+            tracer.histogram(),
+            vec![("addi", 7), ("add", 3), ("bne", 3), ("ebreak", 1)]
+        );
+        let log = tracer.get_instructions_log();
+        assert!(log.starts_with("addi"), "{log}");
+    }
+}
@@ -1,33 +1,163 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use super::super::Emulator;
-use crate::const_values::INSTRUCTION_TRACER_LIST_SIZE;
 use crate::emulator::tracer::TracerTrace;
 use crate::utils::disasm_riscv64_with_details;
 use crate::utils::ringbuf::RingBuffer;
 
+/// 溢出文件中每条记录的字节数：pc (8) + code (4)，小端
+const SPILL_RECORD_SIZE: usize = 12;
+
 /// 指令和地址结构体
-#[derive(Debug, Clone, Copy, Default)]
-struct Instruction {
-    pc: u64,
-    code: u32,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u64,
+    pub code: u32,
+}
+
+impl TraceEntry {
+    fn to_bytes(self) -> [u8; SPILL_RECORD_SIZE] {
+        let mut bytes = [0; SPILL_RECORD_SIZE];
+        bytes[..8].copy_from_slice(&self.pc.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.code.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; SPILL_RECORD_SIZE]) -> Self {
+        TraceEntry {
+            pc: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            code: u32::from_le_bytes(bytes[8..].try_into().unwrap()),
+        }
+    }
+}
+
+/// 溢出文件：环形缓冲区即将覆盖的旧记录按顺序追加到临时文件中
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: usize,
+}
+
+impl SpillFile {
+    fn create() -> io::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "dolphin-itrace-{}-{}.bin",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(SpillFile {
+            path,
+            writer,
+            count: 0,
+        })
+    }
+
+    fn append(&mut self, entry: TraceEntry) -> io::Result<()> {
+        self.writer.write_all(&entry.to_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
 }
 
 /// 指令追踪器
+///
+/// 默认只保留最近的若干条记录；启用溢出后，缓冲区满时被挤出的旧记录写入临时文件，
+/// 从而以有限的内存保留完整的指令轨迹
 pub struct ITracer {
-    instructions: RingBuffer<Instruction>,
+    instructions: RingBuffer<TraceEntry>,
+    spill: Option<SpillFile>,
 }
 
 impl ITracer {
-    /// 创建新的指令追踪器
-    pub fn new() -> Self {
+    /// 创建新的指令追踪器，capacity 为内存中保留的记录数
+    pub fn new(capacity: usize) -> Self {
         ITracer {
-            instructions: RingBuffer::new(INSTRUCTION_TRACER_LIST_SIZE),
+            instructions: RingBuffer::new(capacity),
+            spill: None,
+        }
+    }
+
+    /// 创建缓冲区满时将旧记录溢出到临时文件的指令追踪器
+    pub fn with_spill(capacity: usize) -> io::Result<Self> {
+        Ok(ITracer {
+            instructions: RingBuffer::new(capacity),
+            spill: Some(SpillFile::create()?),
+        })
+    }
+
+    /// 记录一条指令
+    pub fn record(&mut self, entry: TraceEntry) {
+        if self.instructions.is_full()
+            && let Some(spill) = &mut self.spill
+        {
+            let oldest = self.instructions.pop().unwrap();
+            if let Err(e) = spill.append(oldest) {
+                tracing::warn!("指令轨迹写入溢出文件失败，停止溢出: {}", e);
+                self.spill = None;
+            }
         }
+        self.instructions.push_overwrite(entry);
+    }
+
+    /// 已保留的记录总数（溢出文件 + 内存）
+    pub fn len(&self) -> usize {
+        self.spill.as_ref().map_or(0, |spill| spill.count) + self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 按从旧到新的顺序遍历完整轨迹：先读溢出文件，再读内存中的记录
+    pub fn entries(&mut self) -> io::Result<TraceEntries> {
+        let reader = match &mut self.spill {
+            Some(spill) => {
+                spill.writer.flush()?;
+                Some(BufReader::new(File::open(&spill.path)?))
+            }
+            None => None,
+        };
+        Ok(TraceEntries {
+            reader,
+            memory: self.instructions.iter().copied().collect::<Vec<_>>().into_iter(),
+        })
     }
 }
 
-impl Default for ITracer {
-    fn default() -> Self {
-        Self::new()
+/// [`ITracer::entries`] 返回的迭代器
+pub struct TraceEntries {
+    reader: Option<BufReader<File>>,
+    memory: std::vec::IntoIter<TraceEntry>,
+}
+
+impl Iterator for TraceEntries {
+    type Item = TraceEntry;
+
+    fn next(&mut self) -> Option<TraceEntry> {
+        if let Some(reader) = &mut self.reader {
+            let mut bytes = [0; SPILL_RECORD_SIZE];
+            match reader.read_exact(&mut bytes) {
+                Ok(()) => return Some(TraceEntry::from_bytes(&bytes)),
+                Err(e) => {
+                    if e.kind() != io::ErrorKind::UnexpectedEof {
+                        tracing::warn!("读取指令轨迹溢出文件失败: {}", e);
+                    }
+                    self.reader = None;
+                }
+            }
+        }
+        self.memory.next()
     }
 }
 
@@ -41,7 +171,7 @@ impl TracerTrace for ITracer {
     fn trace(&mut self, emulator: &Emulator) {
         let pc = emulator.state.get_pc();
         if let Ok(instruction) = emulator.state.fetch_instruction(pc) {
-            self.instructions.push_overwrite(Instruction {
+            self.record(TraceEntry {
                 pc,
                 code: instruction,
             });
@@ -51,23 +181,56 @@ impl TracerTrace for ITracer {
     /// 打印所有追踪的指令(带反汇编)
     fn get_instructions_log(&mut self) -> String {
         let mut log = String::new();
-        let mut temp = Vec::new();
-        while let Ok(inst) = self.instructions.pop() {
-            temp.push(inst);
-        }
+        let entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(e) => return format!("<无法读取指令轨迹: {}>\n", e),
+        };
 
-        for inst in &temp {
+        for inst in entries {
             if let Ok(disasm) = disasm_riscv64_with_details(inst.code, inst.pc) {
                 log += &format!("{:08x}: {:08x}  {}\n", inst.pc, inst.code, disasm);
             } else {
                 log += &format!("{:08x}: {:08x}  <invalid>\n", inst.pc, inst.code);
             }
         }
+        log
+    }
+}
 
-        // 重新放回ringbuf
-        for inst in temp {
-            self.instructions.push_overwrite(inst);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: u64) -> TraceEntry {
+        TraceEntry {
+            pc: 0x8000_0000 + 4 * i,
+            code: i as u32,
         }
-        log
+    }
+
+    #[test]
+    fn test_spill_keeps_full_trace() {
+        let mut tracer = ITracer::with_spill(4).unwrap();
+        for i in 0..10 {
+            tracer.record(entry(i));
+        }
+        assert_eq!(tracer.len(), 10);
+        let trace: Vec<_> = tracer.entries().unwrap().collect();
+        assert_eq!(trace, (0..10).map(entry).collect::<Vec<_>>());
+
+        // 读取后继续记录，轨迹依然完整有序
+        tracer.record(entry(10));
+        let trace: Vec<_> = tracer.entries().unwrap().collect();
+        assert_eq!(trace, (0..11).map(entry).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_without_spill_keeps_last_entries() {
+        let mut tracer = ITracer::new(4);
+        for i in 0..10 {
+            tracer.record(entry(i));
+        }
+        let trace: Vec<_> = tracer.entries().unwrap().collect();
+        assert_eq!(trace, (6..10).map(entry).collect::<Vec<_>>());
     }
 }
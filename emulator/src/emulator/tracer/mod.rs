@@ -1,6 +1,12 @@
+mod ftracer;
+mod htracer;
 mod itracer;
+mod mtracer;
 
-pub use itracer::ITracer;
+pub use ftracer::{FTraceEntry, FTraceKind, FTracer};
+pub use htracer::HTracer;
+pub use itracer::{ITracer, TraceEntries, TraceEntry};
+pub use mtracer::MTracer;
 
 use clap::Args;
 use std::sync::{Mutex, OnceLock};
@@ -9,11 +15,11 @@ use super::Emulator;
 
 static GLOBAL_TRACER: OnceLock<Mutex<Option<Tracer>>> = OnceLock::new();
 
-/// 初始化全局追踪器
-pub fn init_global_tracer(args: TracerArgs) {
+/// 初始化全局追踪器，list_size 为指令追踪器在内存中保留的记录数
+pub fn init_global_tracer(args: TracerArgs, list_size: usize) {
     GLOBAL_TRACER.get_or_init(|| {
         let mut tracer = Tracer::new();
-        tracer.add_tracers(args);
+        tracer.add_tracers(args, list_size);
         Mutex::new(Some(tracer))
     });
 }
@@ -23,10 +29,10 @@ pub fn global_trace(emulator: &Emulator) {
     let tracers = GLOBAL_TRACER.get();
     match tracers {
         Some(tracer) => {
-            if let Ok(mut tracer) = tracer.lock() {
-                if let Some(ref mut t) = *tracer {
-                    t.trace(emulator);
-                }
+            if let Ok(mut tracer) = tracer.lock()
+                && let Some(ref mut t) = *tracer
+            {
+                t.trace(emulator);
             }
         }
         None => {
@@ -40,10 +46,10 @@ pub fn global_get_log() -> Option<String> {
     let tracers = GLOBAL_TRACER.get();
     match tracers {
         Some(tracer) => {
-            if let Ok(mut tracer) = tracer.lock() {
-                if let Some(ref mut t) = *tracer {
-                    return Some(t.print_log());
-                }
+            if let Ok(mut tracer) = tracer.lock()
+                && let Some(ref mut t) = *tracer
+            {
+                return Some(t.print_log());
             }
         }
         None => {
@@ -53,6 +59,16 @@ pub fn global_get_log() -> Option<String> {
     None
 }
 
+/// 模拟器复位时调用：清空各追踪器每次运行的状态，未初始化时不做处理
+pub fn global_reset() {
+    if let Some(tracer) = GLOBAL_TRACER.get()
+        && let Ok(mut tracer) = tracer.lock()
+        && let Some(ref mut t) = *tracer
+    {
+        t.reset();
+    }
+}
+
 /// 销毁全局追踪器
 pub fn destroy_global_tracer() {
     if let Some(tracer) = GLOBAL_TRACER.get() {
@@ -69,6 +85,22 @@ pub struct TracerArgs {
     /// 启用指令追踪器
     #[arg(long, default_value_t = false)]
     pub enable_itracer: bool,
+
+    /// 指令追踪缓冲区满时将较旧的记录写入临时文件，以保留完整轨迹
+    #[arg(long, default_value_t = false, requires = "enable_itracer")]
+    pub itrace_spill: bool,
+
+    /// 启用指令频率追踪器，结束时按助记符输出执行次数直方图
+    #[arg(long, default_value_t = false)]
+    pub enable_htracer: bool,
+
+    /// 启用访存追踪器，记录每次读写的地址、大小与值
+    #[arg(long, default_value_t = false)]
+    pub enable_mtracer: bool,
+
+    /// 启用函数追踪器，根据 ELF 符号表输出缩进的函数调用轨迹
+    #[arg(long, default_value_t = false)]
+    pub enable_ftracer: bool,
 }
 
 /// 统一的追踪器入口
@@ -85,6 +117,9 @@ trait TracerTrace: Send + Sync {
 
     /// 打印Log
     fn get_instructions_log(&mut self) -> String;
+
+    /// 清空每次运行的状态，已记录的日志保留
+    fn reset(&mut self) {}
 }
 
 impl Tracer {
@@ -95,9 +130,26 @@ impl Tracer {
         Tracer { tracers }
     }
 
-    pub fn add_tracers(&mut self, args: TracerArgs) {
+    pub fn add_tracers(&mut self, args: TracerArgs, list_size: usize) {
         if args.enable_itracer {
-            self.tracers.push(Box::new(ITracer::new()));
+            let itracer = if args.itrace_spill {
+                ITracer::with_spill(list_size).unwrap_or_else(|e| {
+                    tracing::warn!("无法创建指令轨迹溢出文件，只保留最近 {} 条: {}", list_size, e);
+                    ITracer::new(list_size)
+                })
+            } else {
+                ITracer::new(list_size)
+            };
+            self.tracers.push(Box::new(itracer));
+        }
+        if args.enable_htracer {
+            self.tracers.push(Box::new(HTracer::new()));
+        }
+        if args.enable_mtracer {
+            self.tracers.push(Box::new(MTracer::new(list_size)));
+        }
+        if args.enable_ftracer {
+            self.tracers.push(Box::new(FTracer::new(list_size)));
         }
     }
 
@@ -108,6 +160,13 @@ impl Tracer {
         }
     }
 
+    /// 清空各追踪器每次运行的状态
+    pub fn reset(&mut self) {
+        for tracer in &mut self.tracers {
+            tracer.reset();
+        }
+    }
+
     pub fn print_log(&mut self) -> String {
         let mut log = String::new();
         for tracer in &mut self.tracers {
@@ -0,0 +1,86 @@
+use super::super::Emulator;
+use crate::emulator::MmioAccess;
+use crate::emulator::tracer::TracerTrace;
+use crate::utils::ringbuf::RingBuffer;
+
+/// 访存追踪器：记录每条指令产生的读写地址、大小与值
+///
+/// 需要先通过 [`Emulator::set_access_tracking`] 开启内存的读写记录
+pub struct MTracer {
+    accesses: RingBuffer<MmioAccess>,
+}
+
+impl MTracer {
+    /// 创建访存追踪器，capacity 为保留的最近记录数
+    pub fn new(capacity: usize) -> Self {
+        MTracer {
+            accesses: RingBuffer::new(capacity),
+        }
+    }
+
+    /// 按从旧到新的顺序遍历保留的记录
+    pub fn accesses(&self) -> impl Iterator<Item = &MmioAccess> {
+        self.accesses.iter()
+    }
+}
+
+impl TracerTrace for MTracer {
+    fn name(&self) -> &'static str {
+        "MTracer"
+    }
+
+    fn trace(&mut self, emulator: &Emulator) {
+        for access in emulator.state.memory.take_accesses() {
+            self.accesses.push_overwrite(access);
+        }
+    }
+
+    /// 打印所有追踪的读写
+    fn get_instructions_log(&mut self) -> String {
+        let mut log = String::new();
+        for access in self.accesses.iter() {
+            log += &format!(
+                "[{}] {:#010x} {} = {:#x}\n",
+                if access.is_write { "W" } else { "R" },
+                access.addr,
+                access.size,
+                access.value
+            );
+        }
+        log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, emu_with_program};
+
+    #[test]
+    fn test_store_then_load_traced_in_order() {
+        let mut emu = emu_with_program(&[
+            0x00000297, // auipc t0, 0
+            0x02a00513, // li a0, 42
+            0x04a2b023, // sd a0, 64(t0)
+            0x0402b583, // ld a1, 64(t0)
+            0x00100073, // ebreak
+        ]);
+        emu.set_access_tracking(true);
+        let mut tracer = MTracer::new(16);
+        for _ in 0..5 {
+            emu.step().unwrap();
+            tracer.trace(&emu);
+        }
+
+        let accesses: Vec<_> = tracer.accesses().copied().collect();
+        assert_eq!(accesses.len(), 2);
+        assert!(accesses[0].is_write && !accesses[1].is_write);
+        for access in &accesses {
+            assert_eq!((access.addr, access.size, access.value), (TEST_BASE + 64, 8, 42));
+        }
+        assert_eq!(
+            tracer.get_instructions_log(),
+            "[W] 0x80000040 8 = 0x2a\n[R] 0x80000040 8 = 0x2a\n"
+        );
+    }
+}
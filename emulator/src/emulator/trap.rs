@@ -0,0 +1,260 @@
+//! 陷入处理模块
+//! 负责按优先级选择中断、将中断与同步异常投递到 mtvec，并提供陷入相关 CSR 的整体视图
+
+use anyhow::Result;
+use thiserror::Error;
+
+use super::instructions::insts::*;
+use super::instructions::is_store;
+use super::{Emulator, Exception, MemoryError, PrivilegeLevel};
+use crate::const_values::Xlen;
+use crate::utils::bit_utils::BitSlice;
+
+/// mstatus.MIE
+pub const MSTATUS_MIE: usize = 3;
+/// mstatus.MPIE
+pub const MSTATUS_MPIE: usize = 7;
+/// mstatus.MPP 所在的位区间
+pub const MSTATUS_MPP: std::ops::Range<usize> = 11..13;
+
+/// RV64 下 mcause 中的中断标志位，RV32 下为第31位，见 [`Emulator::interrupt_bit`]
+pub const INTERRUPT_BIT: u64 = 1 << 63;
+
+/// 中断号（同时也是 mip/mie 中的位号）
+pub const IRQ_S_SOFT: u64 = 1;
+pub const IRQ_M_SOFT: u64 = 3;
+pub const IRQ_S_TIMER: u64 = 5;
+pub const IRQ_M_TIMER: u64 = 7;
+pub const IRQ_S_EXT: u64 = 9;
+pub const IRQ_M_EXT: u64 = 11;
+
+/// 特权架构规定的中断优先级，从高到低
+const INTERRUPT_PRIORITY: [u64; 6] = [
+    IRQ_M_EXT,
+    IRQ_M_SOFT,
+    IRQ_M_TIMER,
+    IRQ_S_EXT,
+    IRQ_S_SOFT,
+    IRQ_S_TIMER,
+];
+
+/// 处理程序入口处的指令连续触发异常达到该次数时判定为陷入循环
+pub const TRAP_LOOP_LIMIT: u32 = 16;
+
+#[derive(Debug, Error)]
+pub enum TrapError {
+    #[error("陷入循环: 处理程序入口 {target:#x} 连续 {count} 次触发异常 (mcause={cause:#x})")]
+    TrapLoop { target: u64, cause: u64, count: u32 },
+}
+
+/// 一次陷入的记录
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrapRecord {
+    /// 触发陷入的指令地址（写入 mepc 的值）
+    pub pc: u64,
+    pub cause: u64,
+    pub tval: u64,
+    /// 跳转到的处理程序地址
+    pub target: u64,
+}
+
+/// 当前特权级与陷入相关 CSR 的快照
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrapStateView {
+    pub privilege: PrivilegeLevel,
+    pub mstatus: u64,
+    pub mtvec: u64,
+    pub mepc: u64,
+    pub mcause: u64,
+    pub mtval: u64,
+    pub mie: u64,
+    pub mip: u64,
+}
+
+impl Emulator {
+    /// 读取 CSR，未写入过的 CSR 视为0
+    ///
+    /// 计数器由模拟器维护：mcycle/cycle 与 time 返回周期计数，minstret/instret 返回退休指令数，
+    /// 两者都只由执行过的指令决定，同一程序每次运行读到的值相同；RV32 下 *h 计数器返回其高32位
+    #[inline(always)]
+    pub(crate) fn csr_or_zero(&self, csr: u16) -> u64 {
+        match csr {
+            CSR_MCYCLE | CSR_CYCLE | CSR_TIME => self.cycles,
+            CSR_MINSTRET | CSR_INSTRET => self.instret,
+            CSR_MCYCLEH | CSR_CYCLEH | CSR_TIMEH if self.state.xlen == Xlen::X32 => self.cycles >> 32,
+            CSR_MINSTRETH | CSR_INSTRETH if self.state.xlen == Xlen::X32 => self.instret >> 32,
+            _ => self.state.get_csr(csr).unwrap_or(0),
+        }
+    }
+
+    /// 写入 CSR，写 mcycle/minstret 时修改模拟器的计数器；RV32 下它们只写低32位，
+    /// mcycleh/minstreth 写高32位
+    #[inline(always)]
+    pub(crate) fn write_csr(&mut self, csr: u16, value: u64) -> Result<()> {
+        const LOW: u64 = 0xffff_ffff;
+        let rv32 = self.state.xlen == Xlen::X32;
+        match csr {
+            CSR_MCYCLE if rv32 => self.cycles = (self.cycles & !LOW) | (value & LOW),
+            CSR_MINSTRET if rv32 => self.instret = (self.instret & !LOW) | (value & LOW),
+            CSR_MCYCLEH if rv32 => self.cycles = (self.cycles & LOW) | (value & LOW) << 32,
+            CSR_MINSTRETH if rv32 => self.instret = (self.instret & LOW) | (value & LOW) << 32,
+            CSR_MCYCLE => self.cycles = value,
+            CSR_MINSTRET => self.instret = value,
+            _ => self.state.set_csr(csr, value)?,
+        }
+        Ok(())
+    }
+
+    /// 当前特权级
+    #[inline(always)]
+    pub fn privilege(&self) -> PrivilegeLevel {
+        self.privilege
+    }
+
+    /// 最近发生的陷入，按时间从旧到新排列
+    pub fn trap_log(&self) -> Vec<TrapRecord> {
+        self.trap_log
+            .as_ref()
+            .map(|log| log.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// 一次性获取特权级与陷入相关 CSR
+    pub fn trap_state(&self) -> TrapStateView {
+        TrapStateView {
+            privilege: self.privilege,
+            mstatus: self.csr_or_zero(CSR_MSTATUS),
+            mtvec: self.csr_or_zero(CSR_MTVEC),
+            mepc: self.csr_or_zero(CSR_MEPC),
+            mcause: self.csr_or_zero(CSR_MCAUSE),
+            mtval: self.csr_or_zero(CSR_MTVAL),
+            mie: self.csr_or_zero(CSR_MIE),
+            mip: self.csr_or_zero(CSR_MIP),
+        }
+    }
+
+    /// 选出当前应响应的中断，返回带中断标志位的 mcause
+    ///
+    /// 所有中断均在 M 模式处理（暂不支持 mideleg 委托）：M 模式下受 mstatus.MIE 控制，
+    /// 更低特权级下始终开启。多个中断同时挂起时按优先级选择最高者
+    #[inline(always)]
+    pub(super) fn pending_interrupt(&self) -> Option<u64> {
+        let mip = self.csr_or_zero(CSR_MIP);
+        if mip == 0 {
+            return None;
+        }
+        let pending = mip & self.csr_or_zero(CSR_MIE);
+        if pending == 0 {
+            return None;
+        }
+        let enabled = self.privilege != PrivilegeLevel::Machine
+            || self.csr_or_zero(CSR_MSTATUS).bit(MSTATUS_MIE);
+        if !enabled {
+            return None;
+        }
+        INTERRUPT_PRIORITY
+            .iter()
+            .find(|&&irq| pending.bit(irq as usize))
+            .map(|&irq| self.interrupt_bit() | irq)
+    }
+
+    /// mcause 中的中断标志位，即 mcause 的最高位 (xlen - 1)
+    #[inline(always)]
+    pub(super) fn interrupt_bit(&self) -> u64 {
+        1 << (self.state.xlen.bits() - 1)
+    }
+
+    /// 将设备的中断线同步到 mip：只更新由设备驱动的位，软件写入的其它位保持不变
+    pub(super) fn poll_device_irqs(&mut self) {
+        let lines = self.state.memory.device_irq_lines();
+        if lines == self.device_irqs {
+            return;
+        }
+        let mip = (self.csr_or_zero(CSR_MIP) & !self.device_irqs) | lines;
+        let _ = self.state.set_csr(CSR_MIP, mip);
+        self.device_irqs = lines;
+    }
+
+    /// 将指令执行中的访存越界（包括跨越区域边界的访问）转换为加载/存储访问错误
+    ///
+    /// 未设置 mtvec 时返回 None，越界仍作为模拟器错误上报，以保留完整的出错现场
+    pub(super) fn memory_fault(&self, err: &anyhow::Error, inst: u32) -> Option<Exception> {
+        if self.csr_or_zero(CSR_MTVEC) == 0 {
+            return None;
+        }
+        let addr = err.chain().find_map(|e| match e.downcast_ref::<MemoryError>() {
+            Some(MemoryError::OutOfBounds { addr, .. } | MemoryError::Straddle { addr, .. }) => Some(*addr),
+            _ => None,
+        })?;
+        Some(if is_store(inst) {
+            Exception::StoreAccessFault { addr }
+        } else {
+            Exception::LoadAccessFault { addr }
+        })
+    }
+
+    /// 投递同步异常，epc 为触发异常的指令地址
+    /// 未设置 mtvec 时没有可用的处理程序，直接返回错误
+    pub(super) fn deliver_exception(&mut self, exception: Exception, epc: u64) -> Result<()> {
+        if self.csr_or_zero(CSR_MTVEC) == 0 {
+            return Err(anyhow::Error::new(exception)
+                .context(format!("PC {:#x} 处发生异常，但未设置 mtvec", epc)));
+        }
+        tracing::debug!("PC {:#x} 处发生异常: {}", epc, exception);
+        let cause = exception.cause(self.privilege);
+        // 处理程序入口的指令再次触发异常时，期间没有任何指令提交，继续投递只会原地打转
+        if self.last_trap_target == Some(epc) {
+            self.trap_repeats += 1;
+            if self.trap_repeats >= TRAP_LOOP_LIMIT {
+                return Err(anyhow::Error::new(exception).context(TrapError::TrapLoop {
+                    target: epc,
+                    cause,
+                    count: self.trap_repeats,
+                }));
+            }
+        } else {
+            self.trap_repeats = 0;
+        }
+        self.take_trap(cause, exception.tval(), epc);
+        Ok(())
+    }
+
+    /// 陷入 M 模式：保存现场到 mepc/mcause/mtval/mstatus，并跳转到 mtvec
+    pub(super) fn take_trap(&mut self, cause: u64, tval: u64, epc: u64) {
+        let mut mstatus = self.csr_or_zero(CSR_MSTATUS);
+        let mie = mstatus.bit(MSTATUS_MIE);
+        mstatus.set_bit(MSTATUS_MPIE, mie);
+        mstatus.set_bit(MSTATUS_MIE, false);
+        mstatus.set_bit_range(MSTATUS_MPP, self.privilege as u64);
+
+        let _ = self.state.set_csr(CSR_MSTATUS, mstatus);
+        let _ = self.state.set_csr(CSR_MEPC, epc);
+        let _ = self.state.set_csr(CSR_MCAUSE, cause);
+        let _ = self.state.set_csr(CSR_MTVAL, tval);
+        self.privilege = PrivilegeLevel::Machine;
+
+        // 向量模式下中断跳转到 base + 4 * code，异常始终跳转到 base
+        let mtvec = self.csr_or_zero(CSR_MTVEC);
+        let base = mtvec & !0b11;
+        let interrupt_bit = self.interrupt_bit();
+        let is_interrupt = cause & interrupt_bit != 0;
+        let target = if mtvec & 0b11 == 1 && is_interrupt {
+            base.wrapping_add(4 * (cause & !interrupt_bit))
+        } else {
+            base
+        };
+        self.state.set_npc(target);
+        self.last_trap_target = Some(target);
+
+        tracing::debug!(
+            "陷入: pc={:#x}, cause={:#x}, tval={:#x}, target={:#x}",
+            epc,
+            cause,
+            tval,
+            target
+        );
+        if let Some(log) = &mut self.trap_log {
+            log.push_overwrite(TrapRecord { pc: epc, cause, tval, target });
+        }
+    }
+}
@@ -1,104 +1,260 @@
-//! RISC-V模拟器库
-pub mod const_values;
-pub mod emulator;
-pub mod utils;
-
-#[cfg(feature = "difftest")]
-mod difftest;
-
-use anyhow::Result;
-use clap::Parser;
-use emulator::Emulator;
-use tracing::info;
-
-#[cfg(feature = "tracer")]
-use emulator::tracer::TracerArgs;
-
-// 仅在启用 GDB feature 时导入相关模块
-#[cfg(feature = "gdb")]
-use {
-    emulator::{EmuGdbEventLoop, gdb},
-    gdbstub::{conn::ConnectionExt, stub::GdbStub},
-};
-
-/// RISC-V 模拟器
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-pub struct Args {
-    /// ELF文件路径
-    #[arg(short, long)]
-    pub elf: Option<String>,
-
-    /// GDB端口
-    #[arg(short, long, default_value = "1234")]
-    pub port: u16,
-
-    /// 配置文件地址
-    #[arg(short, long, default_value = "profile/config.toml")]
-    pub config: String,
-
-    /// 设备配置文件路径（相对于主配置文件目录解析）
-    #[arg(short = 'd', long, default_value = "../devices/profile/device.toml")]
-    pub device_config: String,
-
-    /// 追踪器参数
-    #[cfg(feature = "tracer")]
-    #[command(flatten)]
-    pub tracer: TracerArgs,
-}
-
-pub fn build_emu_run_blocking(args: Args) -> Result<()> {
-    // 创建模拟器
-    let mut emu = Emulator::new(&args)?;
-
-    if let Some(elf_path) = &args.elf {
-        info!(path = %elf_path, "加载ELF文件");
-        emu.load_elf(elf_path)?;
-
-        #[cfg(feature = "difftest")]
-        utils::load_elf_diff(emu.get_ref_mut(), elf_path)?;
-    }
-
-    // 初始化全局追踪器
-    #[cfg(feature = "tracer")]
-    emulator::tracer::init_global_tracer(args.tracer);
-
-    #[cfg(feature = "gdb")] // 条件编译 GDB 支持
-    {
-        info!(port = args.port, "启用调试模式");
-        let connection: Box<dyn ConnectionExt<Error = std::io::Error>> =
-            Box::new(gdb::wait_for_tcp(args.port)?);
-
-        let gdb_conn = GdbStub::new(connection);
-
-        match gdb_conn.run_blocking::<EmuGdbEventLoop>(&mut emu) {
-            Ok(_) => info!("GDB调试会话结束"),
-            Err(e) => {
-                tracing::error!("GDB调试会话出错");
-                return Err(e.into());
-            }
-        };
-    }
-    #[cfg(not(feature = "gdb"))] // 如果没有启用 GDB
-    {
-        // 运行模拟器
-        while emu.get_exec_state() != emulator::ExecState::End {
-            // 执行模拟器步骤
-            emu.steps(usize::MAX)?;
-        }
-    }
-
-    #[cfg(feature = "tracer")]
-    {
-        // 打印追踪日志
-        use crate::emulator::tracer::destroy_global_tracer;
-        if let Some(log) = emulator::tracer::global_get_log() {
-            info!("追踪日志:\n{}", log);
-        } else {
-            info!("没有追踪日志");
-        }
-        destroy_global_tracer();
-    }
-
-    Ok(())
-}
+//! RISC-V模拟器库
+pub mod const_values;
+pub mod emulator;
+pub mod prelude;
+pub mod utils;
+
+#[cfg(feature = "difftest")]
+mod difftest;
+#[cfg(test)]
+mod test_utils;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use emulator::Emulator;
+use tracing::info;
+
+#[cfg(feature = "tracer")]
+use emulator::tracer::TracerArgs;
+
+// 仅在启用 GDB feature 时导入相关模块
+#[cfg(feature = "gdb")]
+use {
+    emulator::{EmuGdbEventLoop, EmuGdbEventLoop32, Rv32Target, gdb},
+    gdbstub::{conn::ConnectionExt, stub::GdbStub},
+};
+
+/// RISC-V 模拟器
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// ELF文件路径
+    #[arg(short, long)]
+    pub elf: Option<String>,
+
+    /// 扁平二进制镜像路径，从主内存基址（或 --bin-addr）加载并由此开始执行
+    #[arg(long, conflicts_with = "elf")]
+    pub bin: Option<String>,
+
+    /// 扁平二进制镜像的加载地址，支持十进制或 0x 前缀的十六进制
+    #[arg(long, requires = "bin", value_parser = parse_addr)]
+    pub bin_addr: Option<u64>,
+
+    /// GDB端口
+    #[arg(short, long, default_value = "1234")]
+    pub port: u16,
+
+    /// GDB 监听地址，远程调试时可设为 0.0.0.0
+    #[arg(long, default_value = "127.0.0.1")]
+    pub gdb_addr: String,
+
+    /// 配置文件地址
+    #[arg(short, long, default_value = "profile/config.toml")]
+    pub config: String,
+
+    /// 设备配置文件路径（相对于主配置文件目录解析）
+    #[arg(short = 'd', long, default_value = "../devices/profile/device.toml")]
+    pub device_config: String,
+
+    /// 将执行过的 PC 序列记录到文件
+    #[arg(long, conflicts_with = "trace_compare")]
+    pub trace_record: Option<String>,
+
+    /// 记录 PC 序列时为每条指令附带寄存器写集的哈希，比对时可发现 PC 相同但结果不同的改动
+    #[arg(long, requires = "trace_record")]
+    pub trace_writeset: bool,
+
+    /// 与记录的 PC 序列比对，报告第一个分叉点；轨迹带写集哈希时一并比对
+    #[arg(long)]
+    pub trace_compare: Option<String>,
+
+    /// 运行结束后将调用图以 Graphviz DOT 格式写入文件
+    #[arg(long)]
+    pub call_graph: Option<String>,
+
+    /// 取指前检查 PC 位于可执行范围（ELF 代码节或二进制镜像）内，否则产生取指访问错误
+    #[arg(long)]
+    pub check_exec: bool,
+
+    /// riscv-tests 模式：exit 调用按 riscv-tests 约定报告通过或失败的测试编号
+    #[arg(long)]
+    pub riscv_tests: bool,
+
+    /// GDB 每次 continue 最多连续执行的指令数，超过后暂停并交还控制权（默认不限制）
+    #[cfg(feature = "gdb")]
+    #[arg(long)]
+    pub gdb_continue_quantum: Option<usize>,
+
+    /// 追踪器参数
+    #[cfg(feature = "tracer")]
+    #[command(flatten)]
+    pub tracer: TracerArgs,
+}
+
+/// 解析命令行中的地址，支持十进制或 0x 前缀的十六进制
+fn parse_addr(s: &str) -> Result<u64, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(&hex.replace('_', ""), 16),
+        None => s.replace('_', "").parse(),
+    }
+}
+
+/// 创建并运行模拟器直至程序结束，返回客户程序的退出码
+pub fn build_emu_run_blocking(args: Args) -> Result<i32> {
+    // 创建模拟器
+    let mut emu = Emulator::new(&args)?;
+
+    if let Some(elf_path) = &args.elf {
+        info!(path = %elf_path, "加载ELF文件");
+        emu.load_elf(elf_path)?;
+
+        #[cfg(feature = "difftest")]
+        utils::load_elf_diff(emu.get_ref_mut(), elf_path)?;
+    }
+
+    if let Some(bin_path) = &args.bin {
+        if cfg!(feature = "difftest") {
+            return Err(anyhow::anyhow!("DiffTest 模式暂不支持加载扁平二进制镜像"));
+        }
+
+        info!(path = %bin_path, "加载二进制镜像");
+        match args.bin_addr {
+            Some(addr) => emu.load_binary(bin_path, addr)?,
+            None => emu.load_flat_binary(bin_path)?,
+        }
+    }
+
+    if let Some(path) = &args.trace_record {
+        emu.set_pc_trace(emulator::pc_trace::PcTrace::record(path, args.trace_writeset)?);
+    } else if let Some(path) = &args.trace_compare {
+        emu.set_pc_trace(emulator::pc_trace::PcTrace::compare(path)?);
+    }
+
+    if args.call_graph.is_some() {
+        emu.enable_call_graph();
+    }
+
+    if args.riscv_tests {
+        emu.enable_riscv_test_mode();
+    }
+
+    if args.check_exec {
+        if emu.exec_regions().is_empty() {
+            tracing::warn!("未加载任何可执行范围，所有取指都将产生访问错误");
+        }
+        emu.enable_exec_check();
+    }
+
+    // 初始化全局追踪器，访存追踪器依赖内存记录每次读写
+    #[cfg(feature = "tracer")]
+    if args.tracer.enable_mtracer {
+        emu.set_access_tracking(true);
+    }
+    #[cfg(feature = "tracer")]
+    emulator::tracer::init_global_tracer(
+        args.tracer,
+        emu.get_state_ref().config.debug.instruction_tracer_list_size,
+    );
+
+    #[cfg(feature = "gdb")] // 条件编译 GDB 支持
+    {
+        info!(addr = %args.gdb_addr, port = args.port, "启用调试模式");
+        emu.set_gdb_continue_quantum(args.gdb_continue_quantum);
+        let connection: Box<dyn ConnectionExt<Error = std::io::Error>> =
+            Box::new(gdb::wait_for_tcp(&args.gdb_addr, args.port)?);
+
+        let gdb_conn = GdbStub::new(connection);
+
+        // GDB 目标架构按 XLEN 选择，RV32 以 Riscv32 架构调试
+        let result = if emu.get_state_ref().xlen == const_values::Xlen::X32 {
+            let mut target = Rv32Target(emu);
+            let result = gdb_conn.run_blocking::<EmuGdbEventLoop32>(&mut target);
+            emu = target.0;
+            result
+        } else {
+            gdb_conn.run_blocking::<EmuGdbEventLoop>(&mut emu)
+        };
+        match result {
+            Ok(_) => info!("GDB调试会话结束"),
+            Err(e) => {
+                tracing::error!("GDB调试会话出错");
+                return Err(e.into());
+            }
+        };
+    }
+    #[cfg(not(feature = "gdb"))] // 如果没有启用 GDB
+    {
+        // 运行模拟器
+        while emu.get_exec_state() != emulator::ExecState::End {
+            // 执行模拟器步骤
+            emu.steps(usize::MAX)?;
+        }
+        info!("译码缓存命中率: {:.2}%", emu.get_hit_rate() * 100.0);
+    }
+    emu.finish_pc_trace()?;
+
+    if let Some(path) = &args.call_graph {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("无法创建调用图文件 '{}'", path))?;
+        emu.write_call_graph_dot(file)?;
+        info!(path = %path, "已写出调用图");
+    }
+
+    if args.riscv_tests {
+        match emu.riscv_test_result() {
+            Some(emulator::RiscvTestResult::Pass) => info!("riscv-tests: PASS"),
+            Some(emulator::RiscvTestResult::Fail(test)) => info!("riscv-tests: FAIL (测试编号 {})", test),
+            Some(emulator::RiscvTestResult::Malformed(a0)) => {
+                tracing::warn!("riscv-tests: 退出值格式错误 (a0 = {:#x})", a0)
+            }
+            None => tracing::warn!("riscv-tests: 程序结束但未报告测试结果"),
+        }
+    }
+
+    #[cfg(feature = "tracer")]
+    {
+        // 打印追踪日志
+        use crate::emulator::tracer::destroy_global_tracer;
+        if let Some(log) = emulator::tracer::global_get_log() {
+            info!("追踪日志:\n{}", log);
+        } else {
+            info!("没有追踪日志");
+        }
+        destroy_global_tracer();
+    }
+
+    // 未通过 ebreak 正常结束（如 GDB 会话提前断开）时视为退出码 0
+    Ok(emu.exit_code().map_or(0, i32::from))
+}
+
+#[cfg(all(test, not(feature = "gdb")))]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TEST_BASE, write_test_elf};
+
+    #[test]
+    fn test_run_blocking_returns_guest_exit_code() {
+        let elf = write_test_elf(
+            "exit_code",
+            TEST_BASE,
+            &[
+                0x00300513, // li a0, 3
+                0x00100073, // ebreak
+            ],
+        );
+        let args = Args::parse_from(["emulator", "--elf", elf.to_str().unwrap()]);
+        let code = build_emu_run_blocking(args).unwrap();
+        std::fs::remove_file(&elf).ok();
+        assert_eq!(code, 3);
+    }
+
+    #[test]
+    fn test_bin_addr_argument() {
+        let args = Args::parse_from(["emulator", "--bin", "a.bin", "--bin-addr", "0x8000_1000"]);
+        assert_eq!(args.bin_addr, Some(0x8000_1000));
+        let args = Args::parse_from(["emulator", "--bin", "a.bin", "--bin-addr", "4096"]);
+        assert_eq!(args.bin_addr, Some(4096));
+        assert!(Args::try_parse_from(["emulator", "--bin-addr", "0x1000"]).is_err());
+        assert!(Args::try_parse_from(["emulator", "--bin", "a.bin", "--bin-addr", "0xzz"]).is_err());
+    }
+}
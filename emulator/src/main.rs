@@ -26,5 +26,7 @@ fn main() -> Result<()> {
     info!(version = env!("CARGO_PKG_VERSION"), "启动RISC-V模拟器");
     info!(config_path = args.config, "加载配置文件");
 
-    build_emu_run_blocking(args)
+    // 以客户程序的退出码作为进程退出码，出错时由 Err 返回非零值
+    let code = build_emu_run_blocking(args)?;
+    std::process::exit(code)
 }
@@ -0,0 +1,41 @@
+//! 常用公共类型的集中导出，嵌入方只需 `use emulator::prelude::*;`
+//!
+//! ```
+//! use emulator::prelude::*;
+//!
+//! let config: EmuConfig = toml::from_str(
+//!     r#"
+//!     [memory]
+//!     boot_pc = 0x8000_0000
+//!
+//!     [inst_set]
+//!
+//!     [debug]
+//!     event_list_size = 16
+//!     instruction_tracer_list_size = 16
+//!
+//!     [others]
+//!     decoder_cache_size = 64
+//!     "#,
+//! )
+//! .unwrap();
+//! let mut emu = Emulator::from_config(config, &DeviceFile::builtin()).unwrap();
+//!
+//! // li a1, 7; li a0, 0; ebreak
+//! let program = [0x00700593u32, 0x00000513, 0x00100073];
+//! let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+//! emu.write_memory(0x8000_0000, &bytes).unwrap();
+//!
+//! emu.step().unwrap();
+//! assert_eq!(emu.get_reg(11).unwrap(), 7);
+//! assert_eq!(emu.get_cur_event(), Event::None);
+//! emu.step().unwrap();
+//! emu.step().unwrap();
+//! assert_eq!(emu.get_cur_event(), Event::Halted(0));
+//! assert_eq!(emu.get_exec_state(), ExecState::End);
+//! ```
+
+pub use crate::const_values::{DeviceFile, EmuConfig};
+pub use crate::emulator::{
+    Emulator, Event, Exception, ExecMode, ExecState, MemoryError, State, StepOutcome, StopReason,
+};
@@ -0,0 +1,251 @@
+//! 测试辅助工具：在内存中构造配置与模拟器，避免依赖配置文件
+
+use crate::const_values::{DeviceConfig, DeviceFile, EmuConfig, Endianness};
+use crate::emulator::Emulator;
+
+/// 测试用主配置（未列出的项使用 serde 默认值）
+const TEST_EMU_CONFIG: &str = r#"
+[memory]
+boot_pc = 0x8000_0000
+
+[inst_set]
+m_ext = true
+
+[debug]
+event_list_size = 16
+instruction_tracer_list_size = 16
+
+[others]
+decoder_cache_size = 64
+"#;
+
+/// 测试用设备配置：1MB 主内存，无外设
+const TEST_DEVICE_CONFIG: &str = r#"
+[memory]
+memory_base = 0x8000_0000
+memory_size = 1
+"#;
+
+/// 测试程序的加载地址（与 boot_pc 一致）
+pub const TEST_BASE: u64 = 0x8000_0000;
+
+/// 返回可修改的测试配置
+pub fn test_config() -> (EmuConfig, DeviceFile) {
+    let config = toml::from_str(TEST_EMU_CONFIG).expect("测试主配置无效");
+    let device_file = toml::from_str(TEST_DEVICE_CONFIG).expect("测试设备配置无效");
+    (config, device_file)
+}
+
+/// 构造一个启用的小端设备配置，其余项（中断、延迟、参数）取默认值，需要时用结构体更新语法覆盖
+pub fn test_device(name: &str, device_type: &str, base: u64, size: u64) -> DeviceConfig {
+    DeviceConfig {
+        name: name.to_string(),
+        device_type: device_type.to_string(),
+        base,
+        size,
+        enabled: true,
+        endianness: Endianness::Little,
+        irq: None,
+        latency: 0,
+        params: Default::default(),
+    }
+}
+
+/// 使用给定配置创建模拟器
+pub fn emu_from(config: EmuConfig, device_file: &DeviceFile) -> Emulator {
+    Emulator::from_config(config, device_file).expect("创建测试模拟器失败")
+}
+
+/// 将指令序列写入 boot_pc 处
+pub fn load_program(emu: &mut Emulator, program: &[u32]) {
+    let bytes: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+    emu.write_memory(TEST_BASE, &bytes).expect("写入测试程序失败");
+}
+
+/// 使用默认测试配置创建模拟器并加载程序
+pub fn emu_with_program(program: &[u32]) -> Emulator {
+    let (config, device_file) = test_config();
+    let mut emu = emu_from(config, &device_file);
+    load_program(&mut emu, program);
+    emu
+}
+
+/// 构造仅包含一个 .text 节的最小 RV64 ELF 可执行文件
+pub fn build_test_elf(entry: u64, program: &[u32]) -> Vec<u8> {
+    build_test_elf_with_flags(entry, 0, program)
+}
+
+/// 同 [`build_test_elf`]，可指定 e_flags
+pub fn build_test_elf_with_flags(entry: u64, e_flags: u32, program: &[u32]) -> Vec<u8> {
+    let text: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+    let sections = [TestSection {
+        name: ".text",
+        sh_type: 1,     // SHT_PROGBITS
+        sh_flags: 0x6, // SHF_ALLOC | SHF_EXECINSTR
+        addr: entry,
+        data: text,
+    }];
+    build_test_elf_sections(2, entry, e_flags, &sections)
+}
+
+/// 构造符号表与字符串表的内容，符号均为位于第1节的全局函数；字符串表补齐到8字节
+pub fn build_test_symbols(symbols: &[(&str, u64, u64)]) -> (Vec<u8>, Vec<u8>) {
+    let mut strtab = vec![0u8];
+    // 下标0为空符号
+    let mut symtab = vec![0u8; 24];
+    for &(name, addr, size) in symbols {
+        symtab.extend_from_slice(&(strtab.len() as u32).to_le_bytes()); // st_name
+        symtab.push(0x12); // STB_GLOBAL | STT_FUNC
+        symtab.push(0); // st_other
+        symtab.extend_from_slice(&1u16.to_le_bytes()); // st_shndx
+        symtab.extend_from_slice(&addr.to_le_bytes());
+        symtab.extend_from_slice(&size.to_le_bytes());
+        strtab.extend_from_slice(name.as_bytes());
+        strtab.push(0);
+    }
+    strtab.resize(strtab.len().next_multiple_of(8), 0);
+    (symtab, strtab)
+}
+
+/// 同 [`build_test_elf`]，另带 .symtab/.strtab，symbols 为 (名称, 地址, 大小) 的全局函数符号
+pub fn build_test_elf_with_symbols(entry: u64, program: &[u32], symbols: &[(&str, u64, u64)]) -> Vec<u8> {
+    let (symtab, strtab) = build_test_symbols(symbols);
+    // 补齐到8字节，使紧随其后的 .symtab 在文件中对齐
+    let mut text: Vec<u8> = program.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+    text.resize(text.len().next_multiple_of(8), 0);
+    let sections = [
+        TestSection {
+            name: ".text",
+            sh_type: 1,
+            sh_flags: 0x6,
+            addr: entry,
+            data: text,
+        },
+        TestSection {
+            name: ".symtab",
+            sh_type: 2, // SHT_SYMTAB
+            sh_flags: 0,
+            addr: 0,
+            data: symtab,
+        },
+        TestSection {
+            name: ".strtab",
+            sh_type: 3, // SHT_STRTAB
+            sh_flags: 0,
+            addr: 0,
+            data: strtab,
+        },
+    ];
+    build_test_elf_sections(2, entry, 0, &sections)
+}
+
+/// 测试 ELF 中的一个节
+pub struct TestSection {
+    pub name: &'static str,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+/// 构造包含给定节（外加 NULL 与 .shstrtab）的最小 RV64 ELF，e_type 为 ET_EXEC(2) 或 ET_DYN(3)
+pub fn build_test_elf_sections(e_type: u16, entry: u64, e_flags: u32, sections: &[TestSection]) -> Vec<u8> {
+    const EHDR_SIZE: usize = 64;
+    const SHDR_SIZE: usize = 64;
+
+    // 节名字符串表
+    let mut shstrtab = vec![0u8];
+    let mut name_offsets = Vec::new();
+    for section in sections {
+        name_offsets.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(section.name.as_bytes());
+        shstrtab.push(0);
+    }
+    let shstrtab_name = shstrtab.len() as u32;
+    shstrtab.extend_from_slice(b".shstrtab\0");
+
+    let mut data_offsets = Vec::new();
+    let mut offset = EHDR_SIZE;
+    for section in sections {
+        data_offsets.push(offset);
+        offset += section.data.len();
+    }
+    let shstrtab_off = offset;
+    let shoff = (shstrtab_off + shstrtab.len()).next_multiple_of(8);
+    let shnum = sections.len() + 2;
+
+    let mut elf = Vec::with_capacity(shoff + shnum * SHDR_SIZE);
+    // ELF 头
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&e_type.to_le_bytes());
+    elf.extend_from_slice(&243u16.to_le_bytes()); // EM_RISCV
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+    elf.extend_from_slice(&(shoff as u64).to_le_bytes());
+    elf.extend_from_slice(&e_flags.to_le_bytes());
+    elf.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    elf.extend_from_slice(&56u16.to_le_bytes()); // e_phentsize
+    elf.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+    elf.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+    elf.extend_from_slice(&(shnum as u16).to_le_bytes()); // e_shnum
+    elf.extend_from_slice(&((shnum - 1) as u16).to_le_bytes()); // e_shstrndx
+
+    // 节数据
+    for section in sections {
+        elf.extend_from_slice(&section.data);
+    }
+    elf.extend_from_slice(&shstrtab);
+    elf.resize(shoff, 0);
+
+    // 节头表：NULL、给定的节、.shstrtab
+    let mut header = |name: u32,
+                      kind: u32,
+                      flags: u64,
+                      addr: u64,
+                      offset: usize,
+                      size: usize,
+                      link: u32,
+                      entsize: u64| {
+        elf.extend_from_slice(&name.to_le_bytes());
+        elf.extend_from_slice(&kind.to_le_bytes());
+        elf.extend_from_slice(&flags.to_le_bytes());
+        elf.extend_from_slice(&addr.to_le_bytes());
+        elf.extend_from_slice(&(offset as u64).to_le_bytes());
+        elf.extend_from_slice(&(size as u64).to_le_bytes());
+        elf.extend_from_slice(&link.to_le_bytes()); // sh_link
+        // 符号表的 sh_info 为第一个非局部符号的下标，只有下标0的空符号是局部的
+        elf.extend_from_slice(&(matches!(kind, 2 | 11) as u32).to_le_bytes()); // sh_info
+        elf.extend_from_slice(&4u64.to_le_bytes()); // sh_addralign
+        elf.extend_from_slice(&entsize.to_le_bytes()); // sh_entsize
+    };
+    header(0, 0, 0, 0, 0, 0, 0, 0);
+    for (i, section) in sections.iter().enumerate() {
+        // SHT_RELA 与 SHT_SYMTAB/SHT_DYNSYM 的表项均为 24 字节，符号表关联紧随其后的字符串表
+        let (link, entsize) = match section.sh_type {
+            2 | 11 => (i as u32 + 2, 24),
+            4 => (0, 24),
+            _ => (0, 0),
+        };
+        header(
+            name_offsets[i],
+            section.sh_type,
+            section.sh_flags,
+            section.addr,
+            data_offsets[i],
+            section.data.len(),
+            link,
+            entsize,
+        );
+    }
+    header(shstrtab_name, 3, 0, 0, shstrtab_off, shstrtab.len(), 0, 0); // SHT_STRTAB
+    elf
+}
+
+/// 将测试 ELF 写入临时目录，返回文件路径
+pub fn write_test_elf(name: &str, entry: u64, program: &[u32]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("dolphin-{}-{}.elf", name, std::process::id()));
+    std::fs::write(&path, build_test_elf(entry, program)).expect("写入测试ELF失败");
+    path
+}
@@ -8,6 +8,7 @@ pub trait BitSlice {
 
     /// Get a range of bits [start..end) (0-based, LSB is 0)
     /// Panics if range is out of bounds or start > end
+    /// An empty range (start == end, including `64..64`) yields 0
     fn bit_range(&self, range: std::ops::Range<usize>) -> u64;
 
     /// Set a single bit at position (0-based, LSB is 0)
@@ -15,10 +16,24 @@ pub trait BitSlice {
     fn set_bit(&mut self, pos: usize, value: bool);
 
     /// Set a range of bits [start..end) (0-based, LSB is 0)
-    /// Panics if range is out of bounds or start > end
+    /// Panics if range is out of bounds, start > end, or value does not fit
+    /// An empty range (start == end, including `64..64`) is a no-op
     fn set_bit_range(&mut self, range: std::ops::Range<usize>, value: u64);
 }
 
+/// Mask with the low `bits` bits set; saturates to all ones for `bits >= 64`
+/// so callers never shift by the full type width
+#[inline(always)]
+fn low_mask_u64(bits: usize) -> u64 {
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+/// Mask with the low `bits` bits set; saturates to all ones for `bits >= 32`
+#[inline(always)]
+fn low_mask_u32(bits: usize) -> u32 {
+    if bits >= 32 { u32::MAX } else { (1u32 << bits) - 1 }
+}
+
 impl BitSlice for u64 {
     #[inline(always)]
     fn bit(&self, pos: usize) -> bool {
@@ -35,13 +50,7 @@ impl BitSlice for u64 {
             return 0;
         }
 
-        let mask = if range.end == 64 {
-            u64::MAX
-        } else {
-            (1 << range.end) - 1
-        };
-
-        (self & mask) >> range.start
+        (self & low_mask_u64(range.end)) >> range.start
     }
 
     #[inline(always)]
@@ -60,19 +69,14 @@ impl BitSlice for u64 {
         assert!(range.start <= range.end, "Invalid bit range");
 
         let width = range.end - range.start;
-        let value_mask = if width == 64 {
-            u64::MAX
-        } else {
-            (1 << width) - 1
-        };
+        assert!(value <= low_mask_u64(width), "Value too large for bit range");
 
-        assert!(value <= value_mask, "Value too large for bit range");
+        if width == 0 {
+            return;
+        }
 
-        let mask = if range.end == 64 {
-            u64::MAX
-        } else {
-            (1 << range.end) - 1
-        } ^ ((1 << range.start) - 1);
+        // start < end <= 64，因此这里的移位不会溢出
+        let mask = low_mask_u64(range.end) ^ low_mask_u64(range.start);
 
         *self = (*self & !mask) | ((value << range.start) & mask);
     }
@@ -94,13 +98,7 @@ impl BitSlice for u32 {
             return 0;
         }
 
-        let mask = if range.end == 32 {
-            u32::MAX
-        } else {
-            (1 << range.end) - 1
-        };
-
-        ((self & mask) >> range.start) as u64
+        ((self & low_mask_u32(range.end)) >> range.start) as u64
     }
 
     #[inline(always)]
@@ -119,19 +117,14 @@ impl BitSlice for u32 {
         assert!(range.start <= range.end, "Invalid bit range");
 
         let width = range.end - range.start;
-        let value_mask = if width == 32 {
-            u32::MAX as u64
-        } else {
-            (1u64 << width) - 1
-        };
+        assert!(value <= low_mask_u64(width), "Value too large for bit range");
 
-        assert!(value <= value_mask, "Value too large for bit range");
+        if width == 0 {
+            return;
+        }
 
-        let mask = if range.end == 32 {
-            u32::MAX
-        } else {
-            (1 << range.end) - 1
-        } ^ ((1 << range.start) - 1);
+        // start < end <= 32，因此这里的移位不会溢出
+        let mask = low_mask_u32(range.end) ^ low_mask_u32(range.start);
 
         *self = (*self & !mask) | (((value as u32) << range.start) & mask);
     }
@@ -433,18 +426,73 @@ mod tests {
         assert_eq!(x, 0b10111110 << 8);
     }
 
-    // #[test]
-    // #[should_panic(expected = "Invalid bit range")]
-    // fn test_invalid_range_start_gt_end() {
-    //     let _ = 0u64.bit_range(3..2);
-    // }
+    #[test]
+    #[should_panic(expected = "Invalid bit range")]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_invalid_range_start_gt_end() {
+        let _ = 0u64.bit_range(3..2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid bit range")]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_set_bit_range_start_gt_end() {
+        let mut x = 0u64;
+        x.set_bit_range(3..2, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid bit range")]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_set_bit_range_start_gt_end_u32() {
+        let mut x = 0u32;
+        x.set_bit_range(3..2, 0);
+    }
+
+    #[test]
+    fn test_empty_range_at_bit_64() {
+        let mut x = 0x1234567890abcdefu64;
+        assert_eq!(x.bit_range(64..64), 0);
+        x.set_bit_range(64..64, 0);
+        assert_eq!(x, 0x1234567890abcdef);
+    }
+
+    #[test]
+    fn test_empty_range_at_bit_32_u32() {
+        let mut x = 0x12345678u32;
+        assert_eq!(x.bit_range(32..32), 0);
+        x.set_bit_range(32..32, 0);
+        assert_eq!(x, 0x12345678);
+    }
 
-    // #[test]
-    // #[should_panic(expected = "Invalid bit range")]
-    // fn test_set_bit_range_start_gt_end() {
-    //     let mut x = 0u64;
-    //     x.set_bit_range(3..2, 0);
-    // }
+    #[test]
+    #[should_panic(expected = "Value too large for bit range")]
+    fn test_empty_range_rejects_nonzero_value() {
+        let mut x = 0u64;
+        x.set_bit_range(64..64, 1);
+    }
+
+    #[test]
+    fn test_ranges_touching_bit_64() {
+        let mut x = 0u64;
+        x.set_bit_range(60..64, 0b1011);
+        assert_eq!(x.bit_range(60..64), 0b1011);
+        assert_eq!(x, 0xB000_0000_0000_0000);
+        assert_eq!(x.bit_range(63..64), 1);
+        x.set_bit_range(32..64, 0xFFFF_FFFF);
+        assert_eq!(x, 0xFFFF_FFFF_0000_0000);
+        assert_eq!(x.bit_range(32..64), 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn test_ranges_touching_bit_32_u32() {
+        let mut x = 0u32;
+        x.set_bit_range(28..32, 0b1011);
+        assert_eq!(x.bit_range(28..32), 0b1011);
+        assert_eq!(x, 0xB000_0000);
+        x.set_bit_range(16..32, 0xFFFF);
+        assert_eq!(x, 0xFFFF_0000);
+    }
 
     #[test]
     fn test_sign_extend_32() {
@@ -9,11 +9,12 @@ pub struct RiscvDisassembler {
 }
 
 impl RiscvDisassembler {
-    /// 创建新的RISC-V 64位反汇编器
+    /// 创建新的RISC-V 64位反汇编器（同时支持 C 扩展的压缩指令）
     pub fn new() -> Result<Self> {
         let cs = Capstone::new()
             .riscv()
             .mode(arch::riscv::ArchMode::RiscV64)
+            .extra_mode([arch::riscv::ArchExtraMode::RiscVC].into_iter())
             .detail(true)
             .build()
             .map_err(|e| anyhow!("Failed to create capstone engine: {}", e))?;
@@ -1,17 +1,140 @@
 //! ELF文件加载器
 #[cfg(feature = "difftest")]
 use crate::difftest::Difftest;
+use crate::const_values::InstSetConfig;
 use crate::emulator::State;
 use anyhow::{Context, Result, anyhow};
-use object::{Architecture, Object, ObjectSection, SectionKind};
+use object::elf::{
+    EF_RISCV_FLOAT_ABI, EF_RISCV_FLOAT_ABI_DOUBLE, EF_RISCV_FLOAT_ABI_QUAD,
+    EF_RISCV_FLOAT_ABI_SINGLE, EF_RISCV_RVC, EF_RISCV_RVE,
+};
+use object::{
+    Architecture, FileFlags, Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, SectionKind,
+    SymbolKind,
+};
 #[cfg(feature = "difftest")]
 use rv64emu::rv64core::cpu_core::CpuCore;
 use std::fs;
+use std::ops::Range;
 
-/// 加载ELF文件到模拟器内存
-pub fn load_elf(state: &mut State, path: &str) -> Result<()> {
+/// 检查 ELF e_flags 中的 ABI 要求是否被当前指令集配置满足，返回不满足的项
+pub fn check_elf_flags(e_flags: u32, inst_set: &InstSetConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if e_flags & EF_RISCV_RVC != 0 && !inst_set.c_ext {
+        warnings.push("ELF 需要 C 扩展 (EF_RISCV_RVC)，但当前配置未启用 c_ext".to_string());
+    }
+    // 硬浮点 ABI 需要对应的浮点扩展，Q 扩展未实现
+    let float_abi = match e_flags & EF_RISCV_FLOAT_ABI {
+        EF_RISCV_FLOAT_ABI_SINGLE if !inst_set.f_ext => Some("single (需要 F 扩展)"),
+        EF_RISCV_FLOAT_ABI_DOUBLE if !(inst_set.f_ext && inst_set.d_ext) => Some("double (需要 F/D 扩展)"),
+        EF_RISCV_FLOAT_ABI_QUAD => Some("quad (需要 Q 扩展)"),
+        _ => None,
+    };
+    if let Some(abi) = float_abi {
+        warnings.push(format!("ELF 使用硬浮点 ABI: {}，但当前配置未启用对应的浮点扩展", abi));
+    }
+    if e_flags & EF_RISCV_RVE != 0 {
+        warnings.push("ELF 为 RVE (EF_RISCV_RVE) 程序，模拟器按 RV64I 的32个寄存器执行".to_string());
+    }
+    warnings
+}
+
+/// gzip 文件头魔数
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 读取ELF文件，gzip 压缩的文件会被透明解压
+fn read_elf_file(path: &str) -> Result<Vec<u8>> {
+    let data = fs::read(path).with_context(|| format!("无法读取ELF文件 '{}'", path))?;
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(data);
+    }
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Read;
+        let mut elf_data = Vec::new();
+        flate2::read::GzDecoder::new(&*data)
+            .read_to_end(&mut elf_data)
+            .with_context(|| format!("无法解压 gzip 格式的ELF文件 '{}'", path))?;
+        Ok(elf_data)
+    }
+    #[cfg(not(feature = "gzip"))]
+    Err(anyhow!("ELF文件 '{}' 是 gzip 压缩的，需要启用 gzip 特性才能直接加载", path))
+}
+
+/// 一个函数符号，覆盖 [start, end)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuncSymbol {
+    pub name: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// ELF 符号表中的函数符号，按起始地址升序排列
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    funcs: Vec<FuncSymbol>,
+}
+
+impl SymbolTable {
+    /// 由函数符号构造，按起始地址排序，起始地址相同的只保留第一个
+    pub fn new(mut funcs: Vec<FuncSymbol>) -> Self {
+        funcs.sort_by_key(|func| func.start);
+        funcs.dedup_by_key(|func| func.start);
+        Self { funcs }
+    }
+
+    /// 从 ELF 的 .symtab 收集 STT_FUNC 符号，没有符号表时为空
+    fn from_elf(elf_file: &object::File) -> Self {
+        let funcs = elf_file
+            .symbols()
+            .filter(|symbol| symbol.kind() == SymbolKind::Text)
+            .filter_map(|symbol| {
+                let name = symbol.name().ok().filter(|name| !name.is_empty())?;
+                Some(FuncSymbol {
+                    name: name.to_string(),
+                    start: symbol.address(),
+                    end: symbol.address() + symbol.size(),
+                })
+            })
+            .collect();
+        Self::new(funcs)
+    }
+
+    /// 入口恰好为 addr 的函数
+    pub fn function_at(&self, addr: u64) -> Option<&FuncSymbol> {
+        let index = self.funcs.binary_search_by_key(&addr, |func| func.start).ok()?;
+        Some(&self.funcs[index])
+    }
+
+    /// 包含 addr 的函数
+    pub fn lookup(&self, addr: u64) -> Option<&FuncSymbol> {
+        let index = self.funcs.partition_point(|func| func.start <= addr);
+        self.funcs[..index].last().filter(|func| addr < func.end)
+    }
+
+    pub fn len(&self) -> usize {
+        self.funcs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.funcs.is_empty()
+    }
+}
+
+/// 已加载的 ELF 镜像
+#[derive(Debug)]
+pub struct ElfImage {
+    /// 已加载镜像的结束地址
+    pub end: u64,
+    pub symbols: SymbolTable,
+    /// 代码节覆盖的地址范围
+    pub text_ranges: Vec<Range<u64>>,
+}
+
+/// 加载ELF文件到模拟器内存，返回镜像的结束地址与函数符号
+pub fn load_elf(state: &mut State, path: &str) -> Result<ElfImage> {
     // 读取ELF文件
-    let elf_data = fs::read(path).with_context(|| format!("无法读取ELF文件 '{}'", path))?;
+    let elf_data = read_elf_file(path)?;
     let elf_file =
         object::File::parse(&*elf_data).with_context(|| format!("无法解析ELF文件 '{}'", path))?;
 
@@ -20,8 +143,29 @@ pub fn load_elf(state: &mut State, path: &str) -> Result<()> {
         return Err(anyhow!("不支持的目标架构, 仅支持RISC-V"));
     }
 
+    // 检查 ABI 标志
+    if let FileFlags::Elf { e_flags, .. } = elf_file.flags() {
+        for warning in check_elf_flags(e_flags, &state.config.inst_set) {
+            tracing::warn!("{}", warning);
+        }
+    }
+
     // 遍历所有节并加载到内存
+    let mut image_end = 0;
+    let mut text_ranges = Vec::new();
     for section in elf_file.sections() {
+        let section_name = section.name().unwrap_or("<unknown>").to_string();
+        let addr = section.address();
+
+        // .bss 不占文件空间，主内存可能并非零初始化，需显式清零
+        if section.kind() == SectionKind::UninitializedData {
+            state
+                .write_memory(addr, &vec![0; section.size() as usize])
+                .with_context(|| format!("无法清零节 '{}' ({:#x})", section_name, addr))?;
+            image_end = image_end.max(addr + section.size());
+            continue;
+        }
+
         // 跳过非分配节
         if !matches!(
             section.kind(),
@@ -30,9 +174,6 @@ pub fn load_elf(state: &mut State, path: &str) -> Result<()> {
             continue;
         }
 
-        let section_name = section.name().unwrap_or("<unknown>").to_string();
-        let addr = section.address();
-
         let data = section
             .data()
             .with_context(|| format!("无法读取节 '{}' 的数据", section_name))?;
@@ -49,18 +190,81 @@ pub fn load_elf(state: &mut State, path: &str) -> Result<()> {
         state
             .write_memory(addr, data)
             .with_context(|| format!("无法将节 '{}' 写入地址 {:#x}", section_name, addr))?;
+        image_end = image_end.max(addr + data.len() as u64);
+        if section.kind() == SectionKind::Text {
+            text_ranges.push(addr..addr + data.len() as u64);
+        }
+    }
+
+    // 按链接地址加载，装载偏移为0
+    if let Some(rela) = elf_file.section_by_name(".rela.dyn") {
+        let data = rela.data().context("无法读取节 '.rela.dyn' 的数据")?;
+        apply_relocations(state, &elf_file, data, 0)?;
     }
 
     // 设置程序入口点
     state.set_npc(elf_file.entry());
 
+    Ok(ElfImage {
+        end: image_end,
+        symbols: SymbolTable::from_elf(&elf_file),
+        text_ranges,
+    })
+}
+
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_64: u32 = 2;
+const R_RISCV_RELATIVE: u32 = 3;
+/// Elf64_Rela 表项大小
+const RELA_ENTRY_SIZE: usize = 24;
+
+/// 处理 .rela.dyn 中的 R_RISCV_RELATIVE 与 R_RISCV_64 重定位，bias 为实际装载地址与链接地址之差
+fn apply_relocations(state: &mut State, elf_file: &object::File, rela: &[u8], bias: u64) -> Result<()> {
+    let mut unsupported = 0;
+    for entry in rela.chunks_exact(RELA_ENTRY_SIZE) {
+        let r_offset = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+        let r_info = u64::from_le_bytes(entry[8..16].try_into().unwrap());
+        let r_addend = i64::from_le_bytes(entry[16..24].try_into().unwrap());
+        let r_sym = (r_info >> 32) as usize;
+
+        let value = match r_info as u32 {
+            R_RISCV_NONE => continue,
+            R_RISCV_RELATIVE => bias.wrapping_add_signed(r_addend),
+            R_RISCV_64 => {
+                // 符号0表示没有符号，S 为0；动态重定位的符号下标指向 .dynsym
+                let symbol = match r_sym {
+                    0 => 0,
+                    index => {
+                        let symbol = elf_file
+                            .dynamic_symbol_table()
+                            .context("R_RISCV_64 重定位引用了符号，但 ELF 没有 .dynsym")?
+                            .symbol_by_index(object::SymbolIndex(index))
+                            .with_context(|| format!("R_RISCV_64 重定位引用了无效的动态符号 {}", index))?;
+                        bias.wrapping_add(symbol.address())
+                    }
+                };
+                symbol.wrapping_add_signed(r_addend)
+            }
+            _ => {
+                unsupported += 1;
+                continue;
+            }
+        };
+        let addr = bias.wrapping_add(r_offset);
+        state
+            .write_memory(addr, &value.to_le_bytes())
+            .with_context(|| format!("无法在 {:#x} 处应用重定位", addr))?;
+    }
+    if unsupported > 0 {
+        tracing::warn!("忽略了 {} 个不支持的动态重定位（仅支持 R_RISCV_RELATIVE 与 R_RISCV_64）", unsupported);
+    }
     Ok(())
 }
 
 #[cfg(feature = "difftest")]
 pub fn load_elf_diff(state: &mut CpuCore, path: &str) -> Result<()> {
     // 读取ELF文件
-    let elf_data = fs::read(path).with_context(|| format!("无法读取ELF文件 '{}'", path))?;
+    let elf_data = read_elf_file(path)?;
     let elf_file =
         object::File::parse(&*elf_data).with_context(|| format!("无法解析ELF文件 '{}'", path))?;
 
@@ -97,3 +301,198 @@ pub fn load_elf_diff(state: &mut CpuCore, path: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        TEST_BASE, TestSection, build_test_elf_sections, build_test_elf_with_flags,
+        build_test_elf_with_symbols, build_test_symbols, test_config,
+    };
+    use std::rc::Rc;
+
+    #[test]
+    fn test_hard_float_elf_warns_on_rv64i() {
+        let elf = build_test_elf_with_flags(TEST_BASE, EF_RISCV_FLOAT_ABI_DOUBLE, &[0x00100073]);
+        let file = object::File::parse(&*elf).unwrap();
+        let FileFlags::Elf { e_flags, .. } = file.flags() else {
+            panic!("不是 ELF 文件");
+        };
+
+        let (config, _) = test_config();
+        let warnings = check_elf_flags(e_flags, &config.inst_set);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("硬浮点") && warnings[0].contains("double"));
+
+        // 启用 F/D 扩展后不再警告，单独的 F 扩展不满足 double ABI
+        let (mut config, _) = test_config();
+        config.inst_set.f_ext = true;
+        assert_eq!(check_elf_flags(e_flags, &config.inst_set).len(), 1);
+        config.inst_set.d_ext = true;
+        assert!(check_elf_flags(e_flags, &config.inst_set).is_empty());
+
+        // 仅警告，仍然可以加载
+        let path = std::env::temp_dir().join(format!("dolphin-hard-float-{}.elf", std::process::id()));
+        fs::write(&path, &elf).unwrap();
+        let (config, device_file) = test_config();
+        let mut state = State::new(Rc::new(config), &device_file).unwrap();
+        let res = load_elf(&mut state, path.to_str().unwrap());
+        fs::remove_file(&path).ok();
+        res.unwrap();
+        assert_eq!(state.get_npc(), TEST_BASE);
+    }
+
+    #[test]
+    fn test_function_symbols_loaded() {
+        let program = [0x00100073; 8];
+        let elf = build_test_elf_with_symbols(
+            TEST_BASE,
+            &program,
+            &[("main", TEST_BASE, 16), ("helper", TEST_BASE + 16, 16)],
+        );
+        let path = std::env::temp_dir().join(format!("dolphin-symbols-{}.elf", std::process::id()));
+        fs::write(&path, &elf).unwrap();
+        let (config, device_file) = test_config();
+        let mut state = State::new(Rc::new(config), &device_file).unwrap();
+        let res = load_elf(&mut state, path.to_str().unwrap());
+        fs::remove_file(&path).ok();
+        let image = res.unwrap();
+
+        assert_eq!(image.end, TEST_BASE + 32);
+        assert_eq!(image.symbols.len(), 2);
+        assert_eq!(image.symbols.function_at(TEST_BASE + 16).unwrap().name, "helper");
+        assert_eq!(image.symbols.function_at(TEST_BASE + 20), None);
+        assert_eq!(image.symbols.lookup(TEST_BASE + 12).unwrap().name, "main");
+        assert_eq!(image.symbols.lookup(TEST_BASE + 28).unwrap().name, "helper");
+        assert_eq!(image.symbols.lookup(TEST_BASE + 32), None);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_elf_runs_like_uncompressed() {
+        use crate::emulator::ExecState;
+        use crate::test_utils::{build_test_elf, emu_from};
+        use std::io::Write;
+
+        let elf = build_test_elf(
+            TEST_BASE,
+            &[
+                0x00500513, // li a0, 5
+                0x00a585b3, // loop: add a1, a1, a0
+                0xfff50513, // addi a0, a0, -1
+                0xfe051ce3, // bnez a0, loop
+                0x00100073, // ebreak
+            ],
+        );
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&elf).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let dir = std::env::temp_dir();
+        let plain_path = dir.join(format!("dolphin-plain-{}.elf", std::process::id()));
+        let gzip_path = dir.join(format!("dolphin-gzip-{}.elf.gz", std::process::id()));
+        fs::write(&plain_path, &elf).unwrap();
+        fs::write(&gzip_path, &compressed).unwrap();
+
+        let run = |path: &std::path::Path| {
+            let (config, device_file) = test_config();
+            let mut emu = emu_from(config, &device_file);
+            let res = emu.load_elf(path.to_str().unwrap());
+            fs::remove_file(path).ok();
+            res.unwrap();
+            emu.steps(100).unwrap();
+            assert_eq!(emu.get_exec_state(), ExecState::End);
+            emu.snapshot()
+        };
+        let plain = run(&plain_path);
+        let gzip = run(&gzip_path);
+        assert_eq!(plain.registers[11], 15);
+        assert_eq!(plain, gzip);
+    }
+
+    #[test]
+    fn test_soft_float_elf_has_no_warning() {
+        let (config, _) = test_config();
+        assert!(check_elf_flags(0, &config.inst_set).is_empty());
+        assert_eq!(check_elf_flags(EF_RISCV_RVC, &config.inst_set).len(), 1);
+    }
+
+    #[test]
+    fn test_pie_relative_relocation_applied() {
+        let rela = |offset: u64, r_type: u64, addend: i64| -> Vec<u8> {
+            [offset.to_le_bytes(), r_type.to_le_bytes(), addend.to_le_bytes()].concat()
+        };
+        let sections = [
+            TestSection {
+                name: ".text",
+                sh_type: 1,
+                sh_flags: 0x6,
+                addr: TEST_BASE,
+                data: 0x00100073u32.to_le_bytes().to_vec(),
+            },
+            TestSection {
+                name: ".data",
+                sh_type: 1,
+                sh_flags: 0x3, // SHF_WRITE | SHF_ALLOC
+                addr: TEST_BASE + 0x100,
+                data: vec![0; 16],
+            },
+            TestSection {
+                name: ".rela.dyn",
+                sh_type: 4, // SHT_RELA
+                sh_flags: 0x2,
+                addr: TEST_BASE + 0x200,
+                data: [
+                    rela(TEST_BASE + 0x100, R_RISCV_RELATIVE as u64, 0x40 + TEST_BASE as i64),
+                    rela(TEST_BASE + 0x108, R_RISCV_64 as u64, 0x1234),
+                ]
+                .concat(),
+            },
+        ];
+        let elf = build_test_elf_sections(3, TEST_BASE, 0, &sections); // ET_DYN
+
+        let path = std::env::temp_dir().join(format!("dolphin-pie-{}.elf", std::process::id()));
+        fs::write(&path, &elf).unwrap();
+        let (config, device_file) = test_config();
+        let mut state = State::new(Rc::new(config), &device_file).unwrap();
+        let res = load_elf(&mut state, path.to_str().unwrap());
+        fs::remove_file(&path).ok();
+        res.unwrap();
+
+        let pointer = state.read_memory(TEST_BASE + 0x100, 8).unwrap();
+        assert_eq!(u64::from_le_bytes(pointer.try_into().unwrap()), TEST_BASE + 0x40);
+        let value = state.read_memory(TEST_BASE + 0x108, 8).unwrap();
+        assert_eq!(u64::from_le_bytes(value.try_into().unwrap()), 0x1234);
+    }
+
+    #[test]
+    fn test_symbol_relocation_uses_dynsym_with_bias() {
+        let rela = [
+            (TEST_BASE + 0x100).to_le_bytes(),
+            (1u64 << 32 | R_RISCV_64 as u64).to_le_bytes(),
+            8i64.to_le_bytes(),
+        ]
+        .concat();
+        // 两张符号表中下标1的符号不同，动态重定位必须使用 .dynsym
+        let (dynsym, dynstr) = build_test_symbols(&[("target", TEST_BASE + 0x80, 0)]);
+        let (symtab, strtab) = build_test_symbols(&[("other", TEST_BASE + 0x40, 0)]);
+        let section = |name, sh_type, data| TestSection { name, sh_type, sh_flags: 0, addr: 0, data };
+        let sections = [
+            section(".rela.dyn", 4, rela.clone()), // SHT_RELA
+            section(".dynsym", 11, dynsym),       // SHT_DYNSYM
+            section(".dynstr", 3, dynstr),
+            section(".symtab", 2, symtab),
+            section(".strtab", 3, strtab),
+        ];
+        let elf = build_test_elf_sections(3, TEST_BASE, 0, &sections); // ET_DYN
+        let file = object::File::parse(&*elf).unwrap();
+
+        let (config, device_file) = test_config();
+        let mut state = State::new(Rc::new(config), &device_file).unwrap();
+        let bias = 0x1000;
+        apply_relocations(&mut state, &file, &rela, bias).unwrap();
+
+        let value = state.read_memory(TEST_BASE + 0x100 + bias, 8).unwrap();
+        assert_eq!(u64::from_le_bytes(value.try_into().unwrap()), bias + TEST_BASE + 0x80 + 8);
+    }
+}
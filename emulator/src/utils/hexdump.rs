@@ -0,0 +1,50 @@
+//! 十六进制转储格式化
+
+/// 每行字节数
+const BYTES_PER_LINE: usize = 16;
+
+/// 按 `hexdump -C` 的格式生成转储：地址、16 个十六进制字节（8 字节处额外空格）与 ASCII 栏
+///
+/// 不可打印字符在 ASCII 栏中显示为 `.`，最后一行不足 16 字节时以空格补齐
+pub fn hexdump(base: u64, data: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        out.push_str(&format!("{:016x} ", base + (line * BYTES_PER_LINE) as u64));
+        for i in 0..BYTES_PER_LINE {
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => out.push_str(&format!("{:02x} ", byte)),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str(" |");
+        out.extend(chunk.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push_str("|\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump_format() {
+        let mut data = b"Hello, world!\n\x00\xff".to_vec();
+        data.extend_from_slice(b"ok");
+        assert_eq!(
+            hexdump(0x8000_0000, &data),
+            "0000000080000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n\
+             0000000080000010  6f 6b                                             |ok|\n"
+        );
+        assert!(hexdump(0, &[]).is_empty());
+    }
+}
@@ -3,9 +3,12 @@
 pub mod bit_utils;
 pub mod disasm;
 mod elf;
+pub mod hexdump;
+pub mod reg_file;
 pub mod ringbuf;
 
 pub use disasm::{RiscvDisassembler, disasm_riscv64_instruction, disasm_riscv64_with_details};
-pub use elf::load_elf;
+pub use elf::{ElfImage, FuncSymbol, SymbolTable, load_elf};
+pub use reg_file::RegFile;
 #[cfg(feature = "difftest")]
 pub use elf::load_elf_diff;
@@ -0,0 +1,113 @@
+//! 寄存器堆的批量操作
+
+/// 通用寄存器堆 `[u64; 32]` 的辅助方法
+pub trait RegFile {
+    /// 比较两个寄存器堆，按寄存器编号升序返回不一致的 `(编号, 自身值, 对方值)`
+    fn diff(&self, other: &Self) -> Vec<(usize, u64, u64)>;
+}
+
+impl RegFile for [u64; 32] {
+    fn diff(&self, other: &Self) -> Vec<(usize, u64, u64)> {
+        self.iter()
+            .zip(other.iter())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, (&a, &b))| (i, a, b))
+            .collect()
+    }
+}
+
+/// RISC-V寄存器别名
+pub(crate) fn get_register_alias(reg: usize) -> &'static str {
+    match reg {
+        0 => "zero",   // Hard-wired zero
+        1 => "ra",     // Return address
+        2 => "sp",     // Stack pointer
+        3 => "gp",     // Global pointer
+        4 => "tp",     // Thread pointer
+        5 => "t0",     // Temporary
+        6 => "t1",     // Temporary
+        7 => "t2",     // Temporary
+        8 => "s0/fp",  // Saved register/frame pointer
+        9 => "s1",     // Saved register
+        10 => "a0",    // Function argument/return value
+        11 => "a1",    // Function argument/return value
+        12 => "a2",    // Function argument
+        13 => "a3",    // Function argument
+        14 => "a4",    // Function argument
+        15 => "a5",    // Function argument
+        16 => "a6",    // Function argument
+        17 => "a7",    // Function argument
+        18 => "s2",    // Saved register
+        19 => "s3",    // Saved register
+        20 => "s4",    // Saved register
+        21 => "s5",    // Saved register
+        22 => "s6",    // Saved register
+        23 => "s7",    // Saved register
+        24 => "s8",    // Saved register
+        25 => "s9",    // Saved register
+        26 => "s10",   // Saved register
+        27 => "s11",   // Saved register
+        28 => "t3",    // Temporary
+        29 => "t4",    // Temporary
+        30 => "t5",    // Temporary
+        31 => "t6",    // Temporary
+        _ => "unknown",
+    }
+}
+
+/// 生成参考模型与被测模型之间的简洁差异报告，只列出不一致的 pc 与寄存器
+pub fn mismatch_report(ref_pc: u64, ref_regs: &[u64; 32], dut_pc: u64, dut_regs: &[u64; 32]) -> String {
+    let mut report = String::new();
+    if ref_pc != dut_pc {
+        report.push_str(&format!("pc: ref={:#018x}, dut={:#018x}\n", ref_pc, dut_pc));
+    }
+    for (i, ref_val, dut_val) in ref_regs.diff(dut_regs) {
+        report.push_str(&format!(
+            "x{:02}({}): ref={:#018x}, dut={:#018x}\n",
+            i,
+            get_register_alias(i),
+            ref_val,
+            dut_val
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_changed_registers() {
+        let base = [0u64; 32];
+        let mut other = base;
+        other[1] = 0x8000_0000;
+        other[10] = 3;
+        other[31] = u64::MAX;
+
+        assert_eq!(
+            base.diff(&other),
+            vec![(1, 0, 0x8000_0000), (10, 0, 3), (31, 0, u64::MAX)]
+        );
+        assert_eq!(
+            other.diff(&base),
+            vec![(1, 0x8000_0000, 0), (10, 3, 0), (31, u64::MAX, 0)]
+        );
+        assert!(base.diff(&base).is_empty());
+    }
+
+    #[test]
+    fn test_mismatch_report_names_only_diverging_register() {
+        let ref_regs = [0u64; 32];
+        let mut dut_regs = ref_regs;
+        dut_regs[10] = 1;
+
+        let report = mismatch_report(0x8000_0004, &ref_regs, 0x8000_0004, &dut_regs);
+        assert_eq!(
+            report,
+            "x10(a0): ref=0x0000000000000000, dut=0x0000000000000001\n"
+        );
+        assert!(mismatch_report(0x8000_0004, &ref_regs, 0x8000_0004, &ref_regs).is_empty());
+    }
+}
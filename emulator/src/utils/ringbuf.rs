@@ -58,6 +58,11 @@ impl<T: Copy + Default> RingBuffer<T> {
         }
     }
 
+    /// 按从旧到新的顺序遍历元素，不移除
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.len()).map(move |i| &self.buf[(self.read + i) % self.buf.len()])
+    }
+
     pub fn is_empty(&self) -> bool {
         !self.full && self.read == self.write
     }
@@ -132,6 +137,20 @@ mod tests {
         assert!(rb.is_empty());
     }
 
+    #[test]
+    fn test_iter() {
+        let mut rb = RingBuffer::new(3);
+        assert_eq!(rb.iter().count(), 0);
+        rb.push_overwrite(1);
+        rb.push_overwrite(2);
+        assert_eq!(rb.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+        rb.push_overwrite(3);
+        rb.push_overwrite(4);
+        assert_eq!(rb.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        // 遍历不消耗元素
+        assert_eq!(rb.len(), 3);
+    }
+
     #[test]
     fn test_push_overwrite() {
         let mut rb = RingBuffer::new(3);